@@ -1,16 +1,22 @@
 use anyhow::Result;
-use std::{collections::HashSet, env, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use screenpipe_core::Language;
-use screenpipe_db::DatabaseManager;
+use screenpipe_db::{DatabaseManager, DEFAULT_SPEAKER_MATCH_THRESHOLD};
 
 use crate::{
     core::{
         device::{default_input_device, default_output_device},
         engine::AudioTranscriptionEngine,
     },
-    transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN,
-    vad::{VadEngineEnum, VadSensitivity},
+    transcription::{deepgram::CUSTOM_DEEPGRAM_API_TOKEN, realtime::RealtimeTranscriptionEngine},
+    vad::{VadConfig, VadEngineEnum, VadSensitivity},
 };
 
 use crate::audio_manager::AudioManager;
@@ -23,8 +29,12 @@ pub struct AudioManagerOptions {
     pub deepgram_api_key: Option<String>,
     pub enable_diarization: bool,
     pub enable_realtime: bool,
+    pub realtime_engine: RealtimeTranscriptionEngine,
     pub audio_chunk_duration: Duration,
     pub vad_sensitivity: VadSensitivity,
+    pub vad_config: VadConfig,
+    /// Thresholds for specific devices, keyed by device name
+    pub device_vad_configs: HashMap<String, VadConfig>,
     pub health_check_grace_period: u64,
     pub enabled_devices: HashSet<String>,
     pub use_all_devices: bool,
@@ -32,6 +42,7 @@ pub struct AudioManagerOptions {
     pub deepgram_url: Option<String>,
     pub deepgram_websocket_url: Option<String>,
     pub output_path: Option<PathBuf>,
+    pub speaker_match_threshold: f32,
 }
 
 impl Default for AudioManagerOptions {
@@ -48,14 +59,18 @@ impl Default for AudioManagerOptions {
             deepgram_api_key,
             enable_diarization: true,
             enable_realtime: false,
+            realtime_engine: RealtimeTranscriptionEngine::default(),
             audio_chunk_duration: Duration::from_secs(30),
             vad_sensitivity: VadSensitivity::High,
+            vad_config: VadConfig::default(),
+            device_vad_configs: HashMap::new(),
             health_check_grace_period: 15,
             enabled_devices,
             use_all_devices: false,
             db_path: None,
             deepgram_url,
             deepgram_websocket_url,
+            speaker_match_threshold: DEFAULT_SPEAKER_MATCH_THRESHOLD,
         }
     }
 }
@@ -102,6 +117,11 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn realtime_engine(mut self, realtime_engine: RealtimeTranscriptionEngine) -> Self {
+        self.options.realtime_engine = realtime_engine;
+        self
+    }
+
     pub fn audio_chunk_duration(mut self, audio_chunk_duration: Duration) -> Self {
         self.options.audio_chunk_duration = audio_chunk_duration;
         self
@@ -112,6 +132,16 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn vad_config(mut self, vad_config: VadConfig) -> Self {
+        self.options.vad_config = vad_config;
+        self
+    }
+
+    pub fn device_vad_configs(mut self, device_vad_configs: HashMap<String, VadConfig>) -> Self {
+        self.options.device_vad_configs = device_vad_configs;
+        self
+    }
+
     pub fn health_check_grace_period(mut self, health_check_grace_period: u64) -> Self {
         self.options.health_check_grace_period = health_check_grace_period;
         self
@@ -137,6 +167,11 @@ impl AudioManagerBuilder {
         self
     }
 
+    pub fn speaker_match_threshold(mut self, speaker_match_threshold: f32) -> Self {
+        self.options.speaker_match_threshold = speaker_match_threshold;
+        self
+    }
+
     pub async fn build(&mut self, db: Arc<DatabaseManager>) -> Result<AudioManager> {
         self.validate_options()?;
         let options = &mut self.options;
@@ -170,11 +205,25 @@ impl AudioManagerBuilder {
             return Err(anyhow::anyhow!("Output path is required for audio manager"));
         }
 
+        if !(0.0..=2.0).contains(&self.options.speaker_match_threshold) {
+            return Err(anyhow::anyhow!(
+                "Speaker match threshold must be a cosine distance between 0 and 2"
+            ));
+        }
+
+        self.options.vad_config.validate()?;
+        for (device, vad_config) in &self.options.device_vad_configs {
+            vad_config
+                .validate()
+                .map_err(|e| anyhow::anyhow!("Invalid VAD config for {}: {}", device, e))?;
+        }
+
         if self.options.enable_realtime
+            && self.options.realtime_engine == RealtimeTranscriptionEngine::Deepgram
             && (self.options.deepgram_api_key.is_none() && CUSTOM_DEEPGRAM_API_TOKEN.is_empty())
         {
             return Err(anyhow::anyhow!(
-                "Deepgram API key is required for realtime transcription"
+                "Deepgram API key is required for realtime transcription, use the whisper realtime engine to transcribe locally"
             ));
         }
 
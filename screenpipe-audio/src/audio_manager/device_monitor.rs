@@ -69,7 +69,9 @@ pub async fn start_device_monitor(
                             }
                             Err(e) => {
                                 let e_str = e.to_string();
-                                if e_str.contains("already running") || e_str.contains("not found")
+                                if e_str.contains("already running")
+                                    || e_str.contains("not found")
+                                    || e_str.contains("finished")
                                 {
                                     continue;
                                 }
@@ -2,34 +2,40 @@ use anyhow::{anyhow, Result};
 use dashmap::DashMap;
 use std::{
     collections::HashSet,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{atomic::Ordering, Arc},
 };
 use tokio::{
     join,
-    sync::{Mutex, RwLock},
+    sync::{Mutex, OnceCell, RwLock},
     task::JoinHandle,
 };
 use tracing::{error, info, warn};
 use whisper_rs::WhisperContext;
 
-use screenpipe_db::DatabaseManager;
+use screenpipe_db::{DatabaseManager, Speaker};
 
 use super::{start_device_monitor, stop_device_monitor, AudioManagerOptions};
 use crate::{
     core::{
         device::{parse_audio_device, AudioDevice},
         record_and_transcribe,
+        virtual_device::VirtualSource,
     },
     device::device_manager::DeviceManager,
     segmentation::segmentation_manager::SegmentationManager,
+    speaker::enrollment::compute_enrollment_embeddings,
     transcription::{
         deepgram::streaming::stream_transcription_deepgram,
         handle_new_transcript,
+        realtime::RealtimeTranscriptionEngine,
         stt::process_audio_input,
-        whisper::model::{create_whisper_context_parameters, download_whisper_model},
+        whisper::{
+            model::{create_whisper_context_parameters, download_whisper_model},
+            realtime::{stream_transcription_whisper, WhisperRealtimeContext},
+        },
     },
-    vad::{silero::SileroVad, webrtc::WebRtcVad, VadEngine, VadEngineEnum},
+    vad::DeviceVadEngines,
     AudioInput, TranscriptionResult,
 };
 
@@ -49,7 +55,7 @@ pub struct AudioManager {
     segmentation_manager: Arc<SegmentationManager>,
     status: Arc<RwLock<AudioManagerStatus>>,
     db: Arc<DatabaseManager>,
-    vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>>,
+    vad_engines: Arc<DeviceVadEngines>,
     recording_handles: Arc<RecordingHandlesMap>,
     recording_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     recording_receiver: Arc<crossbeam::channel::Receiver<AudioInput>>,
@@ -58,6 +64,8 @@ pub struct AudioManager {
     transcription_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     recording_receiver_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     stt_model_path: PathBuf,
+    /// Loaded once and shared by batch and realtime transcription
+    whisper_context: Arc<OnceCell<Arc<WhisperContext>>>,
 }
 
 impl AudioManager {
@@ -65,10 +73,11 @@ impl AudioManager {
         let device_manager = DeviceManager::new().await?;
         let segmentation_manager = Arc::new(SegmentationManager::new().await?);
         let status = RwLock::new(AudioManagerStatus::Stopped);
-        let vad_engine: Arc<Mutex<Box<dyn VadEngine + Send>>> = match options.vad_engine {
-            VadEngineEnum::Silero => Arc::new(Mutex::new(Box::new(SileroVad::new().await?))),
-            VadEngineEnum::WebRtc => Arc::new(Mutex::new(Box::new(WebRtcVad::new()))),
-        };
+        let vad_engines = Arc::new(DeviceVadEngines::new(
+            options.vad_engine.clone(),
+            options.vad_config.clone(),
+            options.device_vad_configs.clone(),
+        ));
 
         let (recording_sender, recording_receiver) = crossbeam::channel::bounded(1000);
         let (transcription_sender, transcription_receiver) = crossbeam::channel::bounded(1000);
@@ -84,7 +93,7 @@ impl AudioManager {
             segmentation_manager,
             status: Arc::new(status),
             db,
-            vad_engine,
+            vad_engines,
             recording_sender: Arc::new(recording_sender),
             recording_receiver: Arc::new(recording_receiver),
             transcription_receiver: Arc::new(transcription_receiver),
@@ -93,6 +102,7 @@ impl AudioManager {
             recording_receiver_handle: Arc::new(RwLock::new(None)),
             transcription_receiver_handle: Arc::new(RwLock::new(None)),
             stt_model_path,
+            whisper_context: Arc::new(OnceCell::new()),
         };
 
         Ok(manager)
@@ -237,6 +247,10 @@ impl AudioManager {
     async fn record_device(&self, device: &AudioDevice) -> Result<JoinHandle<Result<()>>> {
         let options = self.options.read().await;
         let stream = self.device_manager.stream(device).unwrap();
+        let receiver = match self.device_manager.take_receiver(device) {
+            Some(receiver) => receiver,
+            None => stream.subscribe().await,
+        };
         let audio_chunk_duration = options.audio_chunk_duration;
         let recording_sender = self.recording_sender.clone();
         let is_running = self.device_manager.is_running_mut(device).unwrap();
@@ -244,24 +258,60 @@ impl AudioManager {
         let deepgram_api_key = options.deepgram_api_key.clone();
         let realtime_enabled = options.enable_realtime;
         let device_clone = device.clone();
+        let device_manager = self.device_manager.clone();
+        let recording_handles = self.recording_handles.clone();
+
+        let whisper_realtime = if realtime_enabled
+            && options.realtime_engine == RealtimeTranscriptionEngine::Whisper
+        {
+            Some(Arc::new(WhisperRealtimeContext {
+                whisper_context: self.whisper_context(&options).await?,
+                embedding_extractor: self.segmentation_manager.embedding_extractor.clone(),
+                db: self.db.clone(),
+                languages: languages.clone(),
+                speaker_match_threshold: options.speaker_match_threshold,
+            }))
+        } else {
+            None
+        };
 
         let recording_handle = tokio::spawn(async move {
-            let record_and_transcribe_handle = tokio::spawn(record_and_transcribe(
-                stream.clone(),
-                audio_chunk_duration,
-                recording_sender.clone(),
-                is_running.clone(),
-            ));
-
-            let realtime_handle = if realtime_enabled {
-                Some(tokio::spawn(stream_transcription_deepgram(
+            let record_stream = stream.clone();
+            let record_is_running = is_running.clone();
+            let ended_device = device_clone.clone();
+            let record_and_transcribe_handle = tokio::spawn(async move {
+                let result = record_and_transcribe(
+                    record_stream.clone(),
+                    receiver,
+                    audio_chunk_duration,
+                    recording_sender,
+                    record_is_running,
+                )
+                .await;
+
+                // a file played once is done, the device monitor must not start it over
+                if result.is_ok()
+                    && record_stream.is_disconnected()
+                    && VirtualSource::is_finite(&ended_device)
+                {
+                    device_manager.finish_device(&ended_device).await;
+                    recording_handles.remove(&ended_device);
+                }
+
+                result
+            });
+
+            let realtime_handle = match (realtime_enabled, whisper_realtime) {
+                (true, Some(context)) => Some(tokio::spawn(stream_transcription_whisper(
+                    stream, context, is_running,
+                ))),
+                (true, None) => Some(tokio::spawn(stream_transcription_deepgram(
                     stream,
                     languages,
                     is_running,
                     deepgram_api_key,
-                )))
-            } else {
-                None
+                ))),
+                (false, _) => None,
             };
 
             let (record_result, realtime_result) = if let Some(handle) = realtime_handle {
@@ -310,22 +360,23 @@ impl AudioManager {
         let languages = options.languages.clone();
         let deepgram_api_key = options.deepgram_api_key.clone();
         let audio_transcription_engine = options.transcription_engine.clone();
-        let vad_engine = self.vad_engine.clone();
+        let vad_engines = self.vad_engines.clone();
         let whisper_receiver = self.recording_receiver.clone();
-        let context_param = create_whisper_context_parameters(audio_transcription_engine.clone())?;
-
-        let quantized_path = self.stt_model_path.clone();
-        let whisper_context = Arc::new(
-            WhisperContext::new_with_params(&quantized_path.to_string_lossy(), context_param)
-                .expect("failed to load model"),
-        );
+        let whisper_context = self.whisper_context(&options).await?;
 
         Ok(tokio::spawn(async move {
             while let Ok(audio) = whisper_receiver.recv() {
                 info!("Received audio from device: {:?}", audio.device.name);
+                let vad_engine = match vad_engines.get(&audio.device.to_string()).await {
+                    Ok(vad_engine) => vad_engine,
+                    Err(e) => {
+                        error!("Error creating vad engine: {:?}", e);
+                        continue;
+                    }
+                };
                 if let Err(e) = process_audio_input(
                     audio.clone(),
-                    vad_engine.clone(),
+                    vad_engine,
                     segmentation_model_path.clone(),
                     embedding_manager.clone(),
                     embedding_extractor.clone(),
@@ -344,17 +395,68 @@ impl AudioManager {
         }))
     }
 
+    async fn whisper_context(&self, options: &AudioManagerOptions) -> Result<Arc<WhisperContext>> {
+        self.whisper_context
+            .get_or_try_init(|| async {
+                let context_param =
+                    create_whisper_context_parameters(options.transcription_engine.clone())?;
+                let context = WhisperContext::new_with_params(
+                    &self.stt_model_path.to_string_lossy(),
+                    context_param,
+                )
+                .map_err(|e| anyhow!("failed to load whisper model: {}", e))?;
+                Ok(Arc::new(context))
+            })
+            .await
+            .cloned()
+    }
+
     async fn start_transcription_receiver_handler(&self) -> Result<JoinHandle<()>> {
         let transcription_receiver = self.transcription_receiver.clone();
         let db = self.db.clone();
-        let transcription_engine = self.options.read().await.transcription_engine.clone();
+        let options = self.options.read().await;
+        let transcription_engine = options.transcription_engine.clone();
+        let speaker_match_threshold = options.speaker_match_threshold;
         Ok(tokio::spawn(handle_new_transcript(
             db,
             transcription_receiver,
             transcription_engine,
+            speaker_match_threshold,
         )))
     }
 
+    /// Enrolls a named speaker from a clean voice sample so future recordings match them.
+    pub async fn enroll_speaker(
+        &self,
+        audio_path: &Path,
+        name: &str,
+        speaker_id: Option<i64>,
+    ) -> Result<Speaker> {
+        let embedding_extractor = self.segmentation_manager.embedding_extractor.clone();
+        let audio_path = audio_path.to_path_buf();
+        let embeddings = tokio::task::spawn_blocking(move || {
+            compute_enrollment_embeddings(&audio_path, embedding_extractor)
+        })
+        .await??;
+
+        if embeddings.is_empty() {
+            return Err(anyhow!("no usable speech found in enrollment sample"));
+        }
+
+        let speaker = self
+            .db
+            .enroll_speaker(name, &embeddings, speaker_id)
+            .await?;
+        info!(
+            "enrolled speaker {} ({}) with {} embeddings",
+            speaker.name,
+            speaker.id,
+            embeddings.len()
+        );
+
+        Ok(speaker)
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         self.stop().await?;
         let rec = self.recording_handles.clone();
@@ -386,6 +488,10 @@ impl AudioManager {
     pub async fn enabled_devices(&self) -> HashSet<String> {
         self.options.read().await.enabled_devices.clone()
     }
+
+    pub async fn options(&self) -> AudioManagerOptions {
+        self.options.read().await.clone()
+    }
 }
 
 impl Drop for AudioManager {
@@ -2,6 +2,7 @@ pub mod device;
 pub mod engine;
 mod run_record_and_transcribe;
 pub mod stream;
+pub mod virtual_device;
 use crate::transcription::deepgram::streaming::stream_transcription_deepgram;
 use crate::AudioInput;
 use anyhow::Result;
@@ -12,6 +13,7 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use stream::AudioStream;
+use tokio::sync::broadcast;
 use tracing::error;
 
 lazy_static! {
@@ -56,8 +58,11 @@ fn is_normal_shutdown(is_running: &Arc<AtomicBool>) -> bool {
     !is_running.load(Ordering::Relaxed)
 }
 
+/// Records from `receiver`, subscribed to `audio_stream` before it started so the first
+/// samples are kept. It is reused when recording restarts after an error
 pub async fn record_and_transcribe(
     audio_stream: Arc<AudioStream>,
+    mut receiver: broadcast::Receiver<Vec<f32>>,
     duration: Duration,
     whisper_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     is_running: Arc<AtomicBool>,
@@ -65,6 +70,7 @@ pub async fn record_and_transcribe(
     while is_running.load(Ordering::Relaxed) {
         match run_record_and_transcribe::run_record_and_transcribe(
             audio_stream.clone(),
+            &mut receiver,
             duration,
             whisper_sender.clone(),
             is_running.clone(),
@@ -7,6 +7,7 @@ use std::{
 };
 
 use anyhow::{anyhow, Result};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
 use crate::{core::update_device_capture_time, AudioInput};
@@ -15,11 +16,11 @@ use super::AudioStream;
 
 pub async fn run_record_and_transcribe(
     audio_stream: Arc<AudioStream>,
+    receiver: &mut broadcast::Receiver<Vec<f32>>,
     duration: Duration,
     whisper_sender: Arc<crossbeam::channel::Sender<AudioInput>>,
     is_running: Arc<AtomicBool>,
 ) -> Result<()> {
-    let mut receiver = audio_stream.subscribe().await;
     let device_name = audio_stream.device.to_string();
 
     info!(
@@ -39,15 +40,18 @@ pub async fn run_record_and_transcribe(
         && !audio_stream.is_disconnected.load(Ordering::Relaxed)
     {
         while collected_audio.len() < max_samples && is_running.load(Ordering::Relaxed) {
-            match receiver.recv().await {
-                Ok(chunk) => {
+            match tokio::time::timeout(Duration::from_secs(1), receiver.recv()).await {
+                Ok(Ok(chunk)) => {
                     collected_audio.extend(chunk);
                     update_device_capture_time(&device_name);
                 }
-                Err(e) => {
+                Ok(Err(e)) => {
                     error!("error receiving audio data: {}", e);
                     return Err(anyhow!("Audio stream error: {}", e));
                 }
+                // flush what was recorded when a source ends, e.g. a virtual file device
+                Err(_) if audio_stream.is_disconnected() => break,
+                Err(_) => {}
             }
         }
 
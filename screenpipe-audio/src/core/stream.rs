@@ -11,6 +11,7 @@ use tracing::{error, warn};
 use crate::utils::audio::audio_to_mono;
 
 use super::device::{get_cpal_device_and_config, AudioDevice};
+use super::virtual_device::{spawn_virtual_source, VirtualSource};
 
 #[derive(Clone)]
 pub struct AudioStream {
@@ -22,7 +23,7 @@ pub struct AudioStream {
     pub is_disconnected: Arc<AtomicBool>,
 }
 
-enum StreamControl {
+pub(crate) enum StreamControl {
     Stop(oneshot::Sender<()>),
 }
 
@@ -31,7 +32,21 @@ impl AudioStream {
         device: Arc<AudioDevice>,
         is_running: Arc<AtomicBool>,
     ) -> Result<Self> {
-        let (tx, _) = broadcast::channel::<Vec<f32>>(1000);
+        let (stream, _) = Self::from_device_subscribed(device, is_running).await?;
+        Ok(stream)
+    }
+
+    /// Like `from_device`, with a receiver subscribed before the device starts sending, so not
+    /// even the first samples are missed. A virtual source starts reading its file right away.
+    pub async fn from_device_subscribed(
+        device: Arc<AudioDevice>,
+        is_running: Arc<AtomicBool>,
+    ) -> Result<(Self, broadcast::Receiver<Vec<f32>>)> {
+        if let Some(source) = VirtualSource::from_device(&device) {
+            return Self::from_virtual_source(device, source?).await;
+        }
+
+        let (tx, receiver) = broadcast::channel::<Vec<f32>>(1000);
         let tx_clone = tx.clone();
         let (cpal_audio_device, config) = get_cpal_device_and_config(&device).await?;
         let channels = config.channels();
@@ -52,14 +67,52 @@ impl AudioStream {
         )
         .await?;
 
-        Ok(AudioStream {
+        let stream = AudioStream {
             device,
             device_config: config,
             transmitter: Arc::new(tx_clone),
             stream_control: stream_control_tx,
             stream_thread: Some(Arc::new(tokio::sync::Mutex::new(Some(stream_thread)))),
             is_disconnected,
-        })
+        };
+        Ok((stream, receiver))
+    }
+
+    async fn from_virtual_source(
+        device: Arc<AudioDevice>,
+        source: VirtualSource,
+    ) -> Result<(Self, broadcast::Receiver<Vec<f32>>)> {
+        let (tx, receiver) = broadcast::channel::<Vec<f32>>(1000);
+        let tx_clone = tx.clone();
+        let is_disconnected = Arc::new(AtomicBool::new(false));
+        let (stream_control_tx, stream_control_rx) = mpsc::channel();
+
+        let sample_rate = spawn_virtual_source(
+            device.clone(),
+            source,
+            tx,
+            stream_control_rx,
+            is_disconnected.clone(),
+        )
+        .await?;
+
+        // virtual sources are downmixed to mono f32 before being sent
+        let config = cpal::SupportedStreamConfig::new(
+            1,
+            cpal::SampleRate(sample_rate),
+            cpal::SupportedBufferSize::Unknown,
+            cpal::SampleFormat::F32,
+        );
+
+        let stream = AudioStream {
+            device,
+            device_config: config,
+            transmitter: Arc::new(tx_clone),
+            stream_control: stream_control_tx,
+            stream_thread: None,
+            is_disconnected,
+        };
+        Ok((stream, receiver))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -112,6 +165,14 @@ impl AudioStream {
     pub async fn stop(&self) -> Result<()> {
         self.is_disconnected.store(true, Ordering::Relaxed);
         let (tx, rx) = oneshot::channel();
+
+        // a virtual source can be blocked on a read for a long time and may already have
+        // ended, it exits on its own once it sees the stop request
+        if VirtualSource::is_virtual(&self.device) {
+            let _ = self.stream_control.send(StreamControl::Stop(tx));
+            return Ok(());
+        }
+
         self.stream_control.send(StreamControl::Stop(tx))?;
         rx.await?;
 
@@ -0,0 +1,622 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read},
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::FormatReader,
+    io::{MediaSource, MediaSourceStream, ReadOnlySource},
+    probe::Hint,
+};
+use tokio::sync::{broadcast, oneshot};
+use tracing::{debug, error, info, warn};
+
+use crate::utils::audio::audio_to_mono;
+
+use super::{device::AudioDevice, stream::StreamControl};
+
+/// Samples sent per broadcast message, roughly what a cpal callback delivers
+const CHUNK_DURATION: Duration = Duration::from_millis(100);
+const UDP_READ_TIMEOUT: Duration = Duration::from_millis(500);
+const DEFAULT_PCM_SAMPLE_RATE: u32 = 16000;
+
+/// Audio source that is not a sound card but is recorded like one.
+///
+/// Virtual devices are regular `AudioDevice`s whose name starts with a scheme:
+/// * `file:/path/to/audio.mp3?loop=true` - replays a file in real time
+/// * `pipe:/path/to/fifo?rate=16000&channels=1&format=s16le` - raw PCM from a named pipe,
+///   `pipe:-` reads stdin
+/// * `rtp://0.0.0.0:5004?codec=pcmu` - RTP stream (pcmu, pcma or l16) received over UDP
+/// * `http://host/stream.wav` - any stream symphonia can decode
+#[derive(Clone, Debug, PartialEq)]
+pub enum VirtualSource {
+    File {
+        path: PathBuf,
+        looping: bool,
+    },
+    Pipe {
+        path: Option<PathBuf>,
+        format: PcmFormat,
+    },
+    Rtp {
+        addr: SocketAddr,
+        codec: RtpCodec,
+        sample_rate: u32,
+        channels: u16,
+    },
+    Http {
+        url: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PcmFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub encoding: PcmEncoding,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PcmEncoding {
+    S16Le,
+    F32Le,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RtpCodec {
+    /// G.711 mu-law
+    Pcmu,
+    /// G.711 A-law
+    Pcma,
+    /// 16 bit big endian linear PCM
+    L16,
+}
+
+impl VirtualSource {
+    /// Returns `None` for devices that should be opened through cpal
+    pub fn from_device(device: &AudioDevice) -> Option<Result<Self>> {
+        Self::parse(&device.name)
+    }
+
+    pub fn is_virtual(device: &AudioDevice) -> bool {
+        Self::from_device(device).is_some()
+    }
+
+    /// A file played once has nothing left to record after it ends, other sources can be
+    /// reopened
+    pub fn is_finite(device: &AudioDevice) -> bool {
+        matches!(
+            Self::from_device(device),
+            Some(Ok(VirtualSource::File { looping: false, .. }))
+        )
+    }
+
+    pub fn parse(name: &str) -> Option<Result<Self>> {
+        let (scheme, rest) = name.split_once(':')?;
+        let source = match scheme.to_lowercase().as_str() {
+            "file" => Self::parse_file(rest),
+            "pipe" => Self::parse_pipe(rest),
+            "rtp" => Self::parse_rtp(rest),
+            "http" | "https" => Ok(VirtualSource::Http {
+                url: name.to_string(),
+            }),
+            _ => return None,
+        };
+        Some(source)
+    }
+
+    fn parse_file(rest: &str) -> Result<Self> {
+        let (path, params) = split_params(rest.trim_start_matches("//"));
+        if path.is_empty() {
+            return Err(anyhow!("file device requires a path"));
+        }
+        let looping = match param(&params, "loop") {
+            Some(value) => value
+                .parse()
+                .map_err(|_| anyhow!("invalid loop value: {}", value))?,
+            None => false,
+        };
+        Ok(VirtualSource::File {
+            path: PathBuf::from(path),
+            looping,
+        })
+    }
+
+    fn parse_pipe(rest: &str) -> Result<Self> {
+        let (path, params) = split_params(rest.trim_start_matches("//"));
+        let path = match path {
+            "" => return Err(anyhow!("pipe device requires a path or '-' for stdin")),
+            "-" => None,
+            path => Some(PathBuf::from(path)),
+        };
+        let encoding = match param(&params, "format") {
+            None | Some("s16le") => PcmEncoding::S16Le,
+            Some("f32le") => PcmEncoding::F32Le,
+            Some(other) => return Err(anyhow!("unsupported pcm format: {}", other)),
+        };
+        Ok(VirtualSource::Pipe {
+            path,
+            format: PcmFormat {
+                sample_rate: parse_param(&params, "rate")?.unwrap_or(DEFAULT_PCM_SAMPLE_RATE),
+                channels: parse_param(&params, "channels")?.unwrap_or(1),
+                encoding,
+            },
+        })
+    }
+
+    fn parse_rtp(rest: &str) -> Result<Self> {
+        let (addr, params) = split_params(rest.trim_start_matches("//"));
+        let addr: SocketAddr = addr
+            .parse()
+            .map_err(|e| anyhow!("invalid rtp address {}: {}", addr, e))?;
+        let codec = match param(&params, "codec") {
+            None | Some("pcmu") => RtpCodec::Pcmu,
+            Some("pcma") => RtpCodec::Pcma,
+            Some("l16") => RtpCodec::L16,
+            Some(other) => return Err(anyhow!("unsupported rtp codec: {}", other)),
+        };
+        // G.711 is always 8khz, L16 has no standard rate for dynamic payload types
+        let default_rate = match codec {
+            RtpCodec::Pcmu | RtpCodec::Pcma => 8000,
+            RtpCodec::L16 => DEFAULT_PCM_SAMPLE_RATE,
+        };
+        Ok(VirtualSource::Rtp {
+            addr,
+            codec,
+            sample_rate: parse_param(&params, "rate")?.unwrap_or(default_rate),
+            channels: parse_param(&params, "channels")?.unwrap_or(1),
+        })
+    }
+}
+
+fn split_params(s: &str) -> (&str, Vec<(&str, &str)>) {
+    match s.split_once('?') {
+        Some((path, query)) => (
+            path,
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .collect(),
+        ),
+        None => (s, Vec::new()),
+    }
+}
+
+fn param<'a>(params: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn parse_param<T: std::str::FromStr>(params: &[(&str, &str)], key: &str) -> Result<Option<T>> {
+    param(params, key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| anyhow!("invalid {} value: {}", key, value))
+        })
+        .transpose()
+}
+
+/// Starts the thread feeding `tx` from the virtual source and returns the sample rate of the
+/// audio it sends, once the source has been opened
+pub(crate) async fn spawn_virtual_source(
+    device: Arc<AudioDevice>,
+    source: VirtualSource,
+    tx: broadcast::Sender<Vec<f32>>,
+    stream_control_rx: mpsc::Receiver<StreamControl>,
+    is_disconnected: Arc<AtomicBool>,
+) -> Result<u32> {
+    let (ready_tx, ready_rx) = oneshot::channel();
+
+    // a plain thread rather than the tokio blocking pool: reads can block indefinitely
+    // (a fifo without writer) and the http client must not live on a runtime thread
+    std::thread::Builder::new()
+        .name(format!("virtual-audio-{}", device.name))
+        .spawn(move || {
+            let reader = VirtualSourceReader {
+                tx,
+                stream_control_rx,
+            };
+            let result = match source {
+                VirtualSource::File { path, looping } => reader.run_file(path, looping, ready_tx),
+                VirtualSource::Pipe { path, format } => reader.run_pipe(path, format, ready_tx),
+                VirtualSource::Rtp {
+                    addr,
+                    codec,
+                    sample_rate,
+                    channels,
+                } => reader.run_rtp(addr, codec, sample_rate, channels, ready_tx),
+                VirtualSource::Http { url } => reader.run_http(url, ready_tx),
+            };
+
+            match result {
+                Ok(()) => info!("virtual audio source {} ended", device),
+                Err(e) => error!("virtual audio source {} failed: {}", device, e),
+            }
+            is_disconnected.store(true, Ordering::Relaxed);
+        })?;
+
+    ready_rx
+        .await
+        .map_err(|_| anyhow!("virtual audio source exited before starting"))?
+}
+
+type ReadySender = oneshot::Sender<Result<u32>>;
+
+struct VirtualSourceReader {
+    tx: broadcast::Sender<Vec<f32>>,
+    stream_control_rx: mpsc::Receiver<StreamControl>,
+}
+
+impl VirtualSourceReader {
+    fn should_stop(&self) -> bool {
+        match self.stream_control_rx.try_recv() {
+            Ok(StreamControl::Stop(response)) => {
+                let _ = response.send(());
+                true
+            }
+            Err(mpsc::TryRecvError::Disconnected) => true,
+            Err(mpsc::TryRecvError::Empty) => false,
+        }
+    }
+
+    fn run_file(&self, path: PathBuf, looping: bool, ready_tx: ReadySender) -> Result<()> {
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let open =
+            |path: &PathBuf| -> Result<Box<dyn MediaSource>> { Ok(Box::new(File::open(path)?)) };
+        let mut ready_tx = Some(ready_tx);
+        let mut pacer = Pacer::new();
+
+        loop {
+            let media = match open(&path) {
+                Ok(media) => media,
+                Err(e) => return fail(ready_tx.take(), e),
+            };
+            let (format, decoder, sample_rate) = match probe(media, &hint) {
+                Ok(probed) => probed,
+                Err(e) => return fail(ready_tx.take(), e),
+            };
+            if let Some(ready_tx) = ready_tx.take() {
+                let _ = ready_tx.send(Ok(sample_rate));
+            }
+
+            if !self.decode(format, decoder, sample_rate, &mut pacer)? || !looping {
+                return Ok(());
+            }
+            debug!("replaying {:?}", path);
+        }
+    }
+
+    fn run_http(&self, url: String, ready_tx: ReadySender) -> Result<()> {
+        let response = match reqwest::blocking::get(&url).and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            Err(e) => return fail(Some(ready_tx), e.into()),
+        };
+
+        let mut hint = Hint::new();
+        if let Some(extension) = url.rsplit('.').next().filter(|e| e.len() <= 4) {
+            hint.with_extension(extension);
+        }
+
+        let (format, decoder, sample_rate) =
+            match probe(Box::new(ReadOnlySource::new(response)), &hint) {
+                Ok(probed) => probed,
+                Err(e) => return fail(Some(ready_tx), e),
+            };
+        let _ = ready_tx.send(Ok(sample_rate));
+
+        // paced too, so a file served over http is not transcribed faster than real time
+        self.decode(format, decoder, sample_rate, &mut Pacer::new())?;
+        Ok(())
+    }
+
+    fn run_pipe(
+        &self,
+        path: Option<PathBuf>,
+        format: PcmFormat,
+        ready_tx: ReadySender,
+    ) -> Result<()> {
+        // opening a fifo blocks until a writer connects, so report ready first
+        let _ = ready_tx.send(Ok(format.sample_rate));
+
+        let mut input: Box<dyn Read> = match &path {
+            Some(path) => Box::new(File::open(path)?),
+            None => Box::new(std::io::stdin()),
+        };
+
+        let bytes_per_sample = match format.encoding {
+            PcmEncoding::S16Le => 2,
+            PcmEncoding::F32Le => 4,
+        };
+        let frame_size = bytes_per_sample * format.channels as usize;
+        let chunk_frames = (format.sample_rate as f32 * CHUNK_DURATION.as_secs_f32()) as usize;
+        let mut buffer = vec![0u8; chunk_frames.max(1) * frame_size];
+        let mut pending = Vec::new();
+
+        loop {
+            if self.should_stop() {
+                return Ok(());
+            }
+
+            let read = match input.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            // keep partial frames for the next read
+            pending.extend_from_slice(&buffer[..read]);
+            let complete = pending.len() - pending.len() % frame_size;
+            let samples: Vec<f32> = match format.encoding {
+                PcmEncoding::S16Le => pending[..complete]
+                    .chunks_exact(2)
+                    .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                    .collect(),
+                PcmEncoding::F32Le => pending[..complete]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            };
+            pending.drain(..complete);
+
+            if !samples.is_empty() {
+                let _ = self.tx.send(audio_to_mono(&samples, format.channels));
+            }
+        }
+    }
+
+    fn run_rtp(
+        &self,
+        addr: SocketAddr,
+        codec: RtpCodec,
+        sample_rate: u32,
+        channels: u16,
+        ready_tx: ReadySender,
+    ) -> Result<()> {
+        let socket = match UdpSocket::bind(addr) {
+            Ok(socket) => socket,
+            Err(e) => return fail(Some(ready_tx), e.into()),
+        };
+        socket.set_read_timeout(Some(UDP_READ_TIMEOUT))?;
+        let _ = ready_tx.send(Ok(sample_rate));
+        info!("listening for rtp audio on {}", addr);
+
+        let mut packet = vec![0u8; 65536];
+        let mut last_sequence: Option<u16> = None;
+
+        loop {
+            if self.should_stop() {
+                return Ok(());
+            }
+
+            let size = match socket.recv(&mut packet) {
+                Ok(size) => size,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let Some((sequence, payload)) = rtp_payload(&packet[..size]) else {
+                debug!("ignoring invalid rtp packet of {} bytes", size);
+                continue;
+            };
+
+            // drop late packets instead of splicing them in out of order
+            if let Some(last) = last_sequence {
+                if sequence.wrapping_sub(last) == 0 || sequence.wrapping_sub(last) > u16::MAX / 2 {
+                    continue;
+                }
+            }
+            last_sequence = Some(sequence);
+
+            let samples: Vec<f32> = match codec {
+                RtpCodec::Pcmu => payload.iter().map(|b| mulaw_to_f32(*b)).collect(),
+                RtpCodec::Pcma => payload.iter().map(|b| alaw_to_f32(*b)).collect(),
+                RtpCodec::L16 => payload
+                    .chunks_exact(2)
+                    .map(|b| i16::from_be_bytes([b[0], b[1]]) as f32 / 32768.0)
+                    .collect(),
+            };
+            let _ = self.tx.send(audio_to_mono(&samples, channels));
+        }
+    }
+
+    /// Decodes until the end of the stream, returns false if stopped before that
+    fn decode(
+        &self,
+        mut format: Box<dyn FormatReader>,
+        mut decoder: Box<dyn Decoder>,
+        sample_rate: u32,
+        pacer: &mut Pacer,
+    ) -> Result<bool> {
+        let track_id = format
+            .default_track()
+            .map(|track| track.id)
+            .ok_or_else(|| anyhow!("no audio track"))?;
+        let mut pending = Vec::new();
+        let chunk_samples = (sample_rate as f32 * CHUNK_DURATION.as_secs_f32()) as usize;
+
+        loop {
+            if self.should_stop() {
+                return Ok(false);
+            }
+
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    break;
+                }
+                Err(SymphoniaError::ResetRequired) => {
+                    decoder.reset();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(e)) => {
+                    warn!("skipping undecodable audio packet: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            pending.extend(audio_to_mono(
+                buffer.samples(),
+                spec.channels.count() as u16,
+            ));
+
+            while pending.len() >= chunk_samples.max(1) {
+                let chunk: Vec<f32> = pending.drain(..chunk_samples.max(1)).collect();
+                pacer.wait(chunk.len(), sample_rate);
+                let _ = self.tx.send(chunk);
+            }
+        }
+
+        if !pending.is_empty() {
+            pacer.wait(pending.len(), sample_rate);
+            let _ = self.tx.send(pending);
+        }
+        Ok(true)
+    }
+}
+
+fn fail(ready_tx: Option<ReadySender>, e: anyhow::Error) -> Result<()> {
+    match ready_tx {
+        // the error is reported to whoever is opening the stream
+        Some(ready_tx) => {
+            let _ = ready_tx.send(Err(e));
+            Ok(())
+        }
+        None => Err(e),
+    }
+}
+
+type Probed = (Box<dyn FormatReader>, Box<dyn Decoder>, u32);
+
+fn probe(media: Box<dyn MediaSource>, hint: &Hint) -> Result<Probed> {
+    let mss = MediaSourceStream::new(media, Default::default());
+    let probed = symphonia::default::get_probe().format(
+        hint,
+        mss,
+        &Default::default(),
+        &Default::default(),
+    )?;
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow!("no supported audio tracks found"))?;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow!("could not determine sample rate"))?;
+    let decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &Default::default())
+        .map_err(|_| anyhow!("unsupported codec"))?;
+
+    Ok((probed.format, decoder, sample_rate))
+}
+
+/// Releases samples at the rate a live device would produce them
+struct Pacer {
+    started: Instant,
+    sent_secs: f64,
+}
+
+impl Pacer {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            sent_secs: 0.0,
+        }
+    }
+
+    fn wait(&mut self, samples: usize, sample_rate: u32) {
+        let due = Duration::from_secs_f64(self.sent_secs);
+        if let Some(remaining) = due.checked_sub(self.started.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+        self.sent_secs += samples as f64 / sample_rate as f64;
+    }
+}
+
+/// Returns the sequence number and payload of an RTP packet
+pub fn rtp_payload(packet: &[u8]) -> Option<(u16, &[u8])> {
+    if packet.len() < 12 || packet[0] >> 6 != 2 {
+        return None;
+    }
+
+    let has_padding = packet[0] & 0x20 != 0;
+    let has_extension = packet[0] & 0x10 != 0;
+    let csrc_count = (packet[0] & 0x0f) as usize;
+    let sequence = u16::from_be_bytes([packet[2], packet[3]]);
+
+    let mut start = 12 + csrc_count * 4;
+    if has_extension {
+        let header = packet.get(start..start + 4)?;
+        start += 4 + u16::from_be_bytes([header[2], header[3]]) as usize * 4;
+    }
+
+    let mut end = packet.len();
+    if has_padding {
+        end = end.checked_sub(packet[end - 1] as usize)?;
+    }
+
+    packet.get(start..end).map(|payload| (sequence, payload))
+}
+
+pub fn mulaw_to_f32(byte: u8) -> f32 {
+    let byte = !byte;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0f) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    let sample = if byte & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    };
+    sample as f32 / 32768.0
+}
+
+pub fn alaw_to_f32(byte: u8) -> f32 {
+    let byte = byte ^ 0x55;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0f) as i16;
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 8
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+    let sample = if byte & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    };
+    sample as f32 / 32768.0
+}
@@ -1,34 +1,61 @@
 use crate::core::{
     device::{list_audio_devices, AudioDevice},
     stream::AudioStream,
+    virtual_device::VirtualSource,
 };
 use anyhow::{anyhow, Result};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
+use tokio::sync::broadcast;
 use tracing::info;
 
 pub struct DeviceManager {
     streams: Arc<DashMap<AudioDevice, Arc<AudioStream>>>,
     states: Arc<DashMap<AudioDevice, Arc<AtomicBool>>>,
+    /// Receivers subscribed when the stream was created, until the recording takes them
+    receivers: Arc<DashMap<AudioDevice, broadcast::Receiver<Vec<f32>>>>,
+    /// Virtual devices whose file was played to the end, not restarted until stopped
+    finished: Arc<DashSet<AudioDevice>>,
 }
 
 impl DeviceManager {
     pub async fn new() -> Result<Self> {
         let streams = Arc::new(DashMap::new());
         let states = Arc::new(DashMap::new());
-
-        Ok(Self { streams, states })
+        let receivers = Arc::new(DashMap::new());
+        let finished = Arc::new(DashSet::new());
+
+        Ok(Self {
+            streams,
+            states,
+            receivers,
+            finished,
+        })
     }
 
     pub async fn devices(&self) -> Vec<AudioDevice> {
-        list_audio_devices().await.unwrap_or_default()
+        let mut devices = list_audio_devices().await.unwrap_or_default();
+
+        // virtual devices exist for as long as they are recording
+        devices.extend(
+            self.streams
+                .iter()
+                .map(|pair| pair.key().clone())
+                .filter(VirtualSource::is_virtual),
+        );
+
+        devices
     }
 
     pub async fn start_device(&self, device: &AudioDevice) -> Result<()> {
-        if !self.devices().await.contains(device) {
+        if self.finished.contains(device) {
+            return Err(anyhow!("device {device} finished"));
+        }
+
+        if !VirtualSource::is_virtual(device) && !self.devices().await.contains(device) {
             return Err(anyhow!("device {device} not found"));
         }
 
@@ -37,18 +64,15 @@ impl DeviceManager {
         }
 
         let is_running = Arc::new(AtomicBool::new(false));
-        let stream =
-            match AudioStream::from_device(Arc::new(device.clone()), is_running.clone()).await {
-                Ok(stream) => stream,
-                Err(e) => {
-                    return Err(e);
-                }
-            };
+        let (stream, receiver) =
+            AudioStream::from_device_subscribed(Arc::new(device.clone()), is_running.clone())
+                .await?;
 
         info!("starting recording for device: {}", device);
 
         self.streams.insert(device.clone(), Arc::new(stream));
         self.states.insert(device.clone(), is_running);
+        self.receivers.insert(device.clone(), receiver);
 
         Ok(())
     }
@@ -57,6 +81,12 @@ impl DeviceManager {
         self.streams.get(device).map(|s| s.value().clone())
     }
 
+    /// Receiver subscribed before the stream started, so the recording misses none of it.
+    /// Only the first caller gets it
+    pub fn take_receiver(&self, device: &AudioDevice) -> Option<broadcast::Receiver<Vec<f32>>> {
+        self.receivers.remove(device).map(|(_, receiver)| receiver)
+    }
+
     pub fn is_running(&self, device: &AudioDevice) -> bool {
         self.states
             .get(device)
@@ -72,11 +102,15 @@ impl DeviceManager {
 
         self.states.clear();
         self.streams.clear();
+        self.receivers.clear();
+        self.finished.clear();
 
         Ok(())
     }
 
     pub async fn stop_device(&self, device: &AudioDevice) -> Result<()> {
+        self.finished.remove(device);
+
         if !self.is_running(device) {
             return Err(anyhow!("Device {} already stopped", device));
         }
@@ -92,10 +126,19 @@ impl DeviceManager {
         }
 
         self.streams.remove(device);
+        self.receivers.remove(device);
 
         Ok(())
     }
 
+    /// Stops a virtual device whose file ended and keeps it from being started again, until
+    /// it is stopped explicitly
+    pub async fn finish_device(&self, device: &AudioDevice) {
+        info!("virtual device {} reached the end of its source", device);
+        let _ = self.stop_device(device).await;
+        self.finished.insert(device.clone());
+    }
+
     pub fn is_running_mut(&self, device: &AudioDevice) -> Option<Arc<AtomicBool>> {
         self.states.get(device).map(|s| s.value().clone())
     }
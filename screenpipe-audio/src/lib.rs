@@ -9,4 +9,5 @@ pub use utils::audio::pcm_decode;
 pub use utils::audio::resample;
 pub mod audio_manager;
 mod device;
+pub mod reprocess;
 mod segmentation;
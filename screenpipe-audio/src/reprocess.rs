@@ -0,0 +1,618 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use screenpipe_core::Language;
+use screenpipe_db::{
+    AudioChunk, DatabaseManager, MigrationCommand, MigrationProgress, MigrationResponse,
+    MigrationStatus, TranscriptionSegment, DEFAULT_SPEAKER_MATCH_THRESHOLD,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc, OnceCell},
+    task::JoinHandle,
+    time,
+};
+use tracing::{debug, error, info, warn};
+use whisper_rs::WhisperContext;
+
+use crate::{
+    core::{device::AudioDevice, engine::AudioTranscriptionEngine},
+    pcm_decode, resample,
+    segmentation::segmentation_manager::SegmentationManager,
+    speaker::prepare_segments,
+    speaker::{embedding::EmbeddingExtractor, embedding_manager::EmbeddingManager},
+    transcription::{
+        deepgram::batch::transcribe_with_deepgram,
+        stt::SAMPLE_RATE,
+        whisper::{
+            batch::process_with_whisper,
+            model::{create_whisper_context_parameters, download_whisper_model},
+        },
+    },
+    vad::{DeviceVadEngines, VadConfig, VadEngineEnum},
+};
+
+/// Configuration of an audio reprocessing job
+#[derive(Clone)]
+pub struct ReprocessConfig {
+    /// Only audio chunks recorded in this range are reprocessed
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub transcription_engine: Arc<AudioTranscriptionEngine>,
+    pub vad_engine: VadEngineEnum,
+    pub vad_config: VadConfig,
+    pub device_vad_configs: HashMap<String, VadConfig>,
+    pub languages: Vec<Language>,
+    pub deepgram_api_key: Option<String>,
+    pub speaker_match_threshold: f32,
+    /// Number of audio chunks fetched from the database at once
+    pub batch_size: i64,
+    /// Delay between chunks to leave resources for live recording
+    pub batch_delay_ms: u64,
+    /// Whether to continue with the next chunk if one fails
+    pub continue_on_error: bool,
+}
+
+impl ReprocessConfig {
+    pub fn new(
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        transcription_engine: AudioTranscriptionEngine,
+    ) -> Self {
+        Self {
+            start_time,
+            end_time,
+            transcription_engine: Arc::new(transcription_engine),
+            vad_engine: VadEngineEnum::Silero,
+            vad_config: VadConfig::default(),
+            device_vad_configs: HashMap::new(),
+            languages: vec![],
+            deepgram_api_key: None,
+            speaker_match_threshold: DEFAULT_SPEAKER_MATCH_THRESHOLD,
+            batch_size: 50,
+            batch_delay_ms: 100,
+            continue_on_error: true,
+        }
+    }
+
+    /// Progress is saved per time range so the same job can be resumed later
+    pub fn job_name(&self) -> String {
+        format!(
+            "reprocess_audio_{}_{}",
+            self.start_time.timestamp(),
+            self.end_time.timestamp()
+        )
+    }
+}
+
+/// Worker that re-runs diarization and transcription on stored audio chunks.
+///
+/// It is controlled with the same commands as the database `MigrationWorker`
+/// and its progress is persisted in `migration_progress`, so a paused or
+/// interrupted job picks up where it left off.
+pub struct ReprocessWorker {
+    db: Arc<DatabaseManager>,
+    config: ReprocessConfig,
+    status: Arc<StdMutex<MigrationStatus>>,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    cmd_rx: mpsc::Receiver<MigrationCommand>,
+    status_tx: mpsc::Sender<MigrationResponse>,
+    worker_handle: Option<JoinHandle<()>>,
+}
+
+impl ReprocessWorker {
+    pub fn new(
+        db: Arc<DatabaseManager>,
+        cmd_rx: mpsc::Receiver<MigrationCommand>,
+        status_tx: mpsc::Sender<MigrationResponse>,
+        config: ReprocessConfig,
+    ) -> Self {
+        Self {
+            db,
+            config,
+            status: Arc::new(StdMutex::new(MigrationStatus::NotStarted)),
+            is_running: Arc::new(AtomicBool::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
+            cmd_rx,
+            status_tx,
+            worker_handle: None,
+        }
+    }
+
+    /// Start the worker to process commands
+    pub fn start(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("audio reprocess worker started");
+            while let Some(cmd) = self.cmd_rx.recv().await {
+                match cmd {
+                    MigrationCommand::Start => self.start_job().await,
+                    MigrationCommand::Pause => self.pause_job().await,
+                    MigrationCommand::Stop => {
+                        self.stop_job().await;
+                        break;
+                    }
+                    MigrationCommand::Status => {
+                        let status = self.status.lock().unwrap().clone();
+                        let _ = self.status_tx.send(MigrationResponse { status }).await;
+                    }
+                }
+            }
+            info!("audio reprocess worker stopped");
+        })
+    }
+
+    async fn start_job(&mut self) {
+        if self.is_running.load(Ordering::SeqCst) {
+            if self.is_paused.load(Ordering::SeqCst) {
+                info!("resuming audio reprocessing");
+                self.request(MigrationCommand::Start).await;
+                self.is_paused.store(false, Ordering::SeqCst);
+                return;
+            }
+            warn!("audio reprocessing is already running");
+            return;
+        }
+
+        // a pause or stop left from an earlier run must not hold the new one
+        self.request(MigrationCommand::Start).await;
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        let db = self.db.clone();
+        let config = self.config.clone();
+        let status = self.status.clone();
+        let is_running = self.is_running.clone();
+        let is_paused = self.is_paused.clone();
+        let status_tx = self.status_tx.clone();
+
+        let handle = tokio::spawn(async move {
+            let result = reprocess_audio_chunks(
+                &db,
+                &config,
+                is_running.clone(),
+                is_paused.clone(),
+                status.clone(),
+                status_tx.clone(),
+            )
+            .await;
+
+            let final_status = match result {
+                Ok((total, duration)) => {
+                    info!(
+                        "audio reprocessing completed: {} chunks in {} seconds",
+                        total, duration
+                    );
+                    MigrationStatus::Completed {
+                        total_records: total,
+                        duration_secs: duration,
+                    }
+                }
+                Err(e) => {
+                    error!("audio reprocessing failed: {}", e);
+                    let progress = db
+                        .get_migration_progress(&config.job_name())
+                        .await
+                        .ok()
+                        .flatten();
+                    MigrationStatus::Failed {
+                        total_records: progress.as_ref().map_or(0, |p| p.total_records),
+                        processed_records: progress.as_ref().map_or(0, |p| p.processed_records),
+                        error: e.to_string(),
+                    }
+                }
+            };
+
+            set_status(&status, &status_tx, final_status);
+            is_running.store(false, Ordering::SeqCst);
+        });
+
+        self.worker_handle = Some(handle);
+    }
+
+    /// Saves the command for the job, so it applies wherever the job runs
+    async fn request(&self, command: MigrationCommand) {
+        if let Err(e) = self
+            .db
+            .set_job_request(&self.config.job_name(), &command)
+            .await
+        {
+            warn!("failed to save reprocess request {:?}: {}", command, e);
+        }
+    }
+
+    async fn pause_job(&self) {
+        if self.is_running.load(Ordering::SeqCst) {
+            info!("pausing audio reprocessing");
+            self.request(MigrationCommand::Pause).await;
+            self.is_paused.store(true, Ordering::SeqCst);
+        } else {
+            warn!("cannot pause audio reprocessing: not running");
+        }
+    }
+
+    async fn stop_job(&self) {
+        if self.is_running.load(Ordering::SeqCst) {
+            info!("stopping audio reprocessing");
+            self.request(MigrationCommand::Stop).await;
+            self.is_running.store(false, Ordering::SeqCst);
+            if let Some(handle) = &self.worker_handle {
+                handle.abort();
+            }
+        } else {
+            warn!("cannot stop audio reprocessing: not running");
+        }
+    }
+}
+
+/// Applies the pause or stop saved for the job, from this process or another one
+async fn apply_job_request(
+    db: &DatabaseManager,
+    job_name: &str,
+    is_running: &AtomicBool,
+    is_paused: &AtomicBool,
+) {
+    match db.get_job_request(job_name).await {
+        Ok(Some(MigrationCommand::Stop)) => is_running.store(false, Ordering::SeqCst),
+        Ok(Some(MigrationCommand::Pause)) => is_paused.store(true, Ordering::SeqCst),
+        Ok(_) => is_paused.store(false, Ordering::SeqCst),
+        Err(e) => warn!("failed to read reprocess requests: {}", e),
+    }
+}
+
+/// Create an audio reprocess worker and return channels to control it
+pub fn create_reprocess_worker(
+    db: Arc<DatabaseManager>,
+    config: ReprocessConfig,
+) -> (
+    mpsc::Sender<MigrationCommand>,
+    mpsc::Receiver<MigrationResponse>,
+    JoinHandle<()>,
+) {
+    let (cmd_tx, cmd_rx) = mpsc::channel(100);
+    let (status_tx, status_rx) = mpsc::channel(100);
+
+    let worker = ReprocessWorker::new(db, cmd_rx, status_tx, config);
+    let handle = worker.start();
+
+    (cmd_tx, status_rx, handle)
+}
+
+/// Progress updates are best effort, a slow reader must not stall the job
+fn set_status(
+    status: &StdMutex<MigrationStatus>,
+    status_tx: &mpsc::Sender<MigrationResponse>,
+    new_status: MigrationStatus,
+) {
+    *status.lock().unwrap() = new_status.clone();
+    let _ = status_tx.try_send(MigrationResponse { status: new_status });
+}
+
+/// Models shared by every reprocessed chunk
+struct ReprocessContext {
+    vad_engines: DeviceVadEngines,
+    segmentation_model_path: PathBuf,
+    embedding_extractor: Arc<StdMutex<EmbeddingExtractor>>,
+    /// Loaded on first use, Deepgram only needs it when a request fails
+    whisper_context: OnceCell<Arc<WhisperContext>>,
+}
+
+impl ReprocessContext {
+    async fn new(config: &ReprocessConfig) -> Result<Self> {
+        let segmentation_manager = SegmentationManager::new().await?;
+        let vad_engines = DeviceVadEngines::new(
+            config.vad_engine.clone(),
+            config.vad_config.clone(),
+            config.device_vad_configs.clone(),
+        );
+
+        let context = Self {
+            vad_engines,
+            segmentation_model_path: segmentation_manager.segmentation_model_path,
+            embedding_extractor: segmentation_manager.embedding_extractor,
+            whisper_context: OnceCell::new(),
+        };
+        // a missing model should fail the job before any chunk is touched
+        if *config.transcription_engine != AudioTranscriptionEngine::Deepgram {
+            context.whisper_context(config).await?;
+        }
+        Ok(context)
+    }
+
+    async fn whisper_context(&self, config: &ReprocessConfig) -> Result<Arc<WhisperContext>> {
+        self.whisper_context
+            .get_or_try_init(|| async {
+                let engine = config.transcription_engine.clone();
+                let context = tokio::task::spawn_blocking(move || -> Result<WhisperContext> {
+                    let model_path = download_whisper_model(engine.clone())?;
+                    let context_param = create_whisper_context_parameters(engine)?;
+                    WhisperContext::new_with_params(&model_path.to_string_lossy(), context_param)
+                        .map_err(|e| anyhow!("failed to load whisper model: {}", e))
+                })
+                .await??;
+                Ok(Arc::new(context))
+            })
+            .await
+            .cloned()
+    }
+
+    /// Transcribes a segment like the live pipeline, Deepgram falls back to Whisper on errors
+    async fn transcribe(
+        &self,
+        config: &ReprocessConfig,
+        samples: &[f32],
+        sample_rate: u32,
+        device: &str,
+    ) -> Result<String> {
+        if *config.transcription_engine == AudioTranscriptionEngine::Deepgram {
+            match transcribe_with_deepgram(
+                &config.deepgram_api_key.clone().unwrap_or_default(),
+                samples,
+                device,
+                sample_rate,
+                config.languages.clone(),
+            )
+            .await
+            {
+                Ok(transcription) => return Ok(transcription),
+                Err(e) => error!(
+                    "device: {}, deepgram transcription failed, falling back to Whisper: {:?}",
+                    device, e
+                ),
+            }
+        }
+        process_with_whisper(
+            samples,
+            config.languages.clone(),
+            self.whisper_context(config).await?,
+        )
+        .await
+    }
+}
+
+async fn reprocess_audio_chunks(
+    db: &DatabaseManager,
+    config: &ReprocessConfig,
+    is_running: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    status: Arc<StdMutex<MigrationStatus>>,
+    status_tx: mpsc::Sender<MigrationResponse>,
+) -> Result<(i64, u64)> {
+    let start = Instant::now();
+    let job_name = config.job_name();
+
+    let total_records = db
+        .count_audio_chunks_in_range(config.start_time, config.end_time)
+        .await?;
+    if total_records == 0 {
+        info!("no audio chunks to reprocess");
+        return Ok((0, 0));
+    }
+
+    // a finished job is started over, an interrupted one is resumed
+    let mut progress = match db.get_migration_progress(&job_name).await? {
+        Some(progress) if !progress.completed => progress,
+        _ => MigrationProgress {
+            last_processed_id: 0,
+            total_records,
+            processed_records: 0,
+            completed: false,
+        },
+    };
+    progress.total_records = total_records;
+
+    info!(
+        "reprocessing audio with {}: total_chunks={}, already_processed={}, last_processed_id={}",
+        config.transcription_engine,
+        total_records,
+        progress.processed_records,
+        progress.last_processed_id
+    );
+
+    set_status(
+        &status,
+        &status_tx,
+        MigrationStatus::Running {
+            total_records,
+            processed_records: progress.processed_records,
+        },
+    );
+
+    let context = ReprocessContext::new(config).await?;
+
+    'batches: while is_running.load(Ordering::SeqCst) {
+        let chunks = db
+            .get_audio_chunks_in_range(
+                config.start_time,
+                config.end_time,
+                progress.last_processed_id,
+                config.batch_size,
+            )
+            .await?;
+
+        if chunks.is_empty() {
+            break;
+        }
+
+        for chunk in chunks {
+            apply_job_request(db, &job_name, &is_running, &is_paused).await;
+            while is_paused.load(Ordering::SeqCst) && is_running.load(Ordering::SeqCst) {
+                set_status(
+                    &status,
+                    &status_tx,
+                    MigrationStatus::Paused {
+                        total_records,
+                        processed_records: progress.processed_records,
+                    },
+                );
+                time::sleep(Duration::from_millis(500)).await;
+                apply_job_request(db, &job_name, &is_running, &is_paused).await;
+            }
+
+            if !is_running.load(Ordering::SeqCst) {
+                break 'batches;
+            }
+
+            match reprocess_audio_chunk(db, config, &context, &chunk).await {
+                Ok(inserted) => debug!(
+                    "reprocessed audio chunk {} into {} transcriptions",
+                    chunk.id, inserted
+                ),
+                Err(e) => {
+                    error!("failed to reprocess audio chunk {}: {}", chunk.id, e);
+                    if !config.continue_on_error {
+                        return Err(anyhow!(
+                            "reprocessing audio chunk {} failed: {}",
+                            chunk.id,
+                            e
+                        ));
+                    }
+                }
+            }
+
+            progress.last_processed_id = chunk.id;
+            progress.processed_records += 1;
+            if let Err(e) = db.update_migration_progress(&job_name, &progress).await {
+                warn!("failed to save reprocess progress: {}", e);
+            }
+
+            set_status(
+                &status,
+                &status_tx,
+                MigrationStatus::Running {
+                    total_records,
+                    processed_records: progress.processed_records,
+                },
+            );
+
+            time::sleep(Duration::from_millis(config.batch_delay_ms)).await;
+        }
+    }
+
+    if is_running.load(Ordering::SeqCst) {
+        progress.completed = true;
+        db.update_migration_progress(&job_name, &progress).await?;
+    }
+
+    Ok((progress.processed_records, start.elapsed().as_secs()))
+}
+
+/// Device name given to reprocessed chunks whose device cannot be found
+const UNKNOWN_DEVICE: &str = "unknown";
+
+/// Device an audio chunk was recorded from, read from its file name
+/// `<device> (input|output)_<timestamp>.mp4`
+pub fn device_from_file_path(file_path: &str) -> Option<screenpipe_db::AudioDevice> {
+    let stem = Path::new(file_path).file_stem()?.to_str()?;
+    let (device, _timestamp) = stem.rsplit_once(")_")?;
+    let device = AudioDevice::from_name(&format!("{})", device)).ok()?;
+    Some(screenpipe_db::AudioDevice {
+        name: device.name,
+        device_type: device.device_type.into(),
+    })
+}
+
+/// Re-segments, re-identifies speakers and re-transcribes one stored audio chunk
+async fn reprocess_audio_chunk(
+    db: &DatabaseManager,
+    config: &ReprocessConfig,
+    context: &ReprocessContext,
+    chunk: &AudioChunk,
+) -> Result<usize> {
+    // chunks are not stored with their device, take it from the original transcriptions or
+    // else from the file name, a chunk without any is still worth transcribing
+    let device = match db.get_audio_chunk_device(chunk.id).await? {
+        Some(device) => device,
+        None => device_from_file_path(&chunk.file_path).unwrap_or_else(|| {
+            warn!(
+                "no device known for audio chunk {}, reprocessing it as {}",
+                chunk.id, UNKNOWN_DEVICE
+            );
+            screenpipe_db::AudioDevice {
+                name: UNKNOWN_DEVICE.to_string(),
+                device_type: screenpipe_db::DeviceType::Input,
+            }
+        }),
+    };
+
+    let file_path = chunk.file_path.clone();
+    let (samples, sample_rate) =
+        tokio::task::spawn_blocking(move || pcm_decode(&file_path)).await??;
+    let samples = if sample_rate != SAMPLE_RATE {
+        resample(&samples, sample_rate, SAMPLE_RATE)?
+    } else {
+        samples
+    };
+
+    let vad_engine = context
+        .vad_engines
+        .get(&AudioDevice::from(device.clone()).to_string())
+        .await?;
+    let (mut segments, speech_ratio_ok) = prepare_segments(
+        &samples,
+        vad_engine,
+        &context.segmentation_model_path,
+        EmbeddingManager::new(usize::MAX),
+        context.embedding_extractor.clone(),
+        &device.name,
+    )
+    .await?;
+
+    let mut transcriptions = Vec::new();
+    if speech_ratio_ok {
+        while let Some(segment) = segments.recv().await {
+            let transcription = context
+                .transcribe(config, &segment.samples, segment.sample_rate, &device.name)
+                .await?;
+
+            if transcription.trim().is_empty() {
+                continue;
+            }
+
+            let speaker = match db
+                .get_speaker_from_embedding_with_threshold(
+                    &segment.embedding,
+                    config.speaker_match_threshold,
+                )
+                .await?
+            {
+                Some(speaker) => speaker,
+                None => db.insert_speaker(&segment.embedding).await?,
+            };
+
+            transcriptions.push(TranscriptionSegment {
+                transcription,
+                speaker_id: Some(speaker.id),
+                start_time: segment.start,
+                end_time: segment.end,
+            });
+        }
+    }
+
+    // never wipe an existing transcript because the new pass heard nothing
+    if transcriptions.is_empty() {
+        warn!(
+            "no speech found when reprocessing audio chunk {}, keeping existing transcriptions",
+            chunk.id
+        );
+        return Ok(0);
+    }
+
+    let inserted = db
+        .replace_audio_transcriptions(
+            chunk.id,
+            &device,
+            &config.transcription_engine.to_string(),
+            &transcriptions,
+        )
+        .await?;
+
+    Ok(inserted)
+}
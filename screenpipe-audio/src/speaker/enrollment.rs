@@ -0,0 +1,60 @@
+use anyhow::{bail, Result};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+use super::embedding::EmbeddingExtractor;
+use crate::utils::audio::{normalize_v2, pcm_decode, resample};
+
+const ENROLLMENT_SAMPLE_RATE: u32 = 16000;
+/// Each window of clean speech becomes one profile embedding.
+const ENROLLMENT_WINDOW_SECS: usize = 3;
+/// Trailing windows shorter than this are ignored, they give noisy embeddings.
+const MIN_ENROLLMENT_WINDOW_SECS: usize = 1;
+
+/// Computes speaker embeddings from a clean voice sample, one per window of audio.
+pub fn compute_enrollment_embeddings(
+    audio_path: &Path,
+    embedding_extractor: Arc<Mutex<EmbeddingExtractor>>,
+) -> Result<Vec<Vec<f32>>> {
+    let (samples, sample_rate) = pcm_decode(audio_path)?;
+    let samples = if sample_rate != ENROLLMENT_SAMPLE_RATE {
+        resample(&samples, sample_rate, ENROLLMENT_SAMPLE_RATE)?
+    } else {
+        samples
+    };
+
+    let min_samples = MIN_ENROLLMENT_WINDOW_SECS * ENROLLMENT_SAMPLE_RATE as usize;
+    if samples.len() < min_samples {
+        bail!(
+            "enrollment sample is too short, at least {} second(s) of speech is required",
+            MIN_ENROLLMENT_WINDOW_SECS
+        );
+    }
+
+    let samples = normalize_v2(&samples);
+    let window = ENROLLMENT_WINDOW_SECS * ENROLLMENT_SAMPLE_RATE as usize;
+
+    let mut embeddings = Vec::new();
+    for chunk in samples.chunks(window) {
+        if chunk.len() < min_samples {
+            continue;
+        }
+        let embedding: Vec<f32> = embedding_extractor
+            .lock()
+            .unwrap()
+            .compute(chunk)?
+            .collect();
+        embeddings.push(embedding);
+    }
+
+    debug!(
+        "computed {} enrollment embeddings from {:?}",
+        embeddings.len(),
+        audio_path
+    );
+
+    Ok(embeddings)
+}
@@ -14,6 +14,7 @@ pub fn create_session<P: AsRef<Path>>(path: P) -> Result<Session> {
     Ok(session)
 }
 pub mod embedding_manager;
+pub mod enrollment;
 pub mod models;
 mod prepare_segments;
 pub use prepare_segments::prepare_segments;
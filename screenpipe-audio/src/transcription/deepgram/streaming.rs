@@ -2,8 +2,6 @@ use anyhow::Result;
 use bytes::BufMut;
 use bytes::Bytes;
 use bytes::BytesMut;
-use chrono::DateTime;
-use chrono::Utc;
 use crossbeam::channel::RecvError;
 use deepgram::common::options::Encoding;
 use deepgram::common::stream_response::StreamResponse;
@@ -11,7 +9,6 @@ use futures::channel::mpsc::{self, Receiver as FuturesReceiver};
 use futures::{SinkExt, TryStreamExt};
 use screenpipe_core::Language;
 use screenpipe_events::send_event;
-use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::{atomic::AtomicBool, Arc};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -31,17 +28,7 @@ use crate::core::stream::AudioStream;
 use crate::transcription::deepgram::CUSTOM_DEEPGRAM_API_TOKEN;
 use crate::transcription::deepgram::DEEPGRAM_WEBSOCKET_URL;
 
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct RealtimeTranscriptionEvent {
-    pub timestamp: DateTime<Utc>,
-    pub device: String,
-    pub transcription: String,
-    pub is_final: bool,
-    pub is_input: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub speaker: Option<String>,
-}
+pub use crate::transcription::realtime::RealtimeTranscriptionEvent;
 
 /// Starts a Deepgram transcription stream for the given audio stream
 ///
@@ -10,6 +10,7 @@ pub async fn handle_new_transcript(
     db: Arc<DatabaseManager>,
     transcription_receiver: Arc<crossbeam::channel::Receiver<TranscriptionResult>>,
     transcription_engine: Arc<AudioTranscriptionEngine>,
+    speaker_match_threshold: f32,
 ) {
     let mut previous_transcript = "".to_string();
     let mut previous_transcript_id: Option<i64> = None;
@@ -58,6 +59,7 @@ pub async fn handle_new_transcript(
             transcription_engine.clone(),
             processed_previous,
             previous_transcript_id,
+            speaker_match_threshold,
         )
         .await
         {
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Name of the event realtime transcripts are published under
+pub const REALTIME_TRANSCRIPTION_EVENT: &str = "transcription";
+
+/// Engine producing live transcripts while audio is being recorded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RealtimeTranscriptionEngine {
+    #[default]
+    Deepgram,
+    /// Local whisper model run over a sliding window, works offline
+    Whisper,
+}
+
+/// A live transcript for one device.
+///
+/// Interim results (`is_final == false`) are revisions of the segment being spoken and replace
+/// the previous interim result of the same device, until a final result closes the segment.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RealtimeTranscriptionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub transcription: String,
+    pub is_final: bool,
+    pub is_input: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
@@ -50,6 +50,7 @@ pub async fn process_transcription_result(
     audio_transcription_engine: Arc<AudioTranscriptionEngine>,
     previous_transcript: Option<String>,
     previous_transcript_id: Option<i64>,
+    speaker_match_threshold: f32,
 ) -> Result<Option<i64>, anyhow::Error> {
     if result.error.is_some() || result.transcription.is_none() {
         error!(
@@ -59,7 +60,12 @@ pub async fn process_transcription_result(
         return Ok(None);
     }
 
-    let speaker = get_or_create_speaker_from_embedding(db, &result.speaker_embedding).await?;
+    let speaker = get_or_create_speaker_from_embedding(
+        db,
+        &result.speaker_embedding,
+        speaker_match_threshold,
+    )
+    .await?;
 
     info!("Detected speaker: {:?}", speaker);
 
@@ -138,8 +144,11 @@ pub async fn process_transcription_result(
 async fn get_or_create_speaker_from_embedding(
     db: &DatabaseManager,
     embedding: &[f32],
+    threshold: f32,
 ) -> Result<Speaker, anyhow::Error> {
-    let speaker = db.get_speaker_from_embedding(embedding).await?;
+    let speaker = db
+        .get_speaker_from_embedding_with_threshold(embedding, threshold)
+        .await?;
     if let Some(speaker) = speaker {
         Ok(speaker)
     } else {
@@ -11,6 +11,15 @@ pub async fn process_with_whisper(
     audio: &[f32],
     languages: Vec<Language>,
     whisper_context: Arc<WhisperContext>,
+) -> Result<String> {
+    process_with_whisper_blocking(audio, languages, whisper_context)
+}
+
+/// Same as `process_with_whisper`, for callers already on a blocking thread
+pub fn process_with_whisper_blocking(
+    audio: &[f32],
+    languages: Vec<Language>,
+    whisper_context: Arc<WhisperContext>,
 ) -> Result<String> {
     let mut whisper_state = whisper_context
         .create_state()
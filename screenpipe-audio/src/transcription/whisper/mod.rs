@@ -2,3 +2,4 @@ pub mod batch;
 mod detect_language;
 pub use detect_language::detect_language;
 pub mod model;
+pub mod realtime;
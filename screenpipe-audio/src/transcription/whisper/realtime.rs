@@ -0,0 +1,263 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex as StdMutex,
+};
+use std::time::Duration;
+
+use anyhow::Result;
+use screenpipe_core::Language;
+use screenpipe_db::DatabaseManager;
+use screenpipe_events::send_event;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info, warn};
+use whisper_rs::WhisperContext;
+
+use super::batch::process_with_whisper_blocking;
+use crate::core::{device::DeviceType, stream::AudioStream};
+use crate::speaker::embedding::EmbeddingExtractor;
+use crate::transcription::realtime::{RealtimeTranscriptionEvent, REALTIME_TRANSCRIPTION_EVENT};
+use crate::utils::audio::{normalize_v2, resample};
+
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+/// How much new audio triggers another pass over the window
+const STEP: Duration = Duration::from_secs(1);
+/// Segments are finalized at this length even if the speaker has not paused
+const MAX_WINDOW: Duration = Duration::from_secs(10);
+/// Trailing silence ending a segment
+const END_OF_SPEECH_SILENCE: Duration = Duration::from_millis(700);
+/// Same activity threshold as the deepgram stream uses for display audio
+const SILENCE_RMS: f32 = 0.01;
+/// Embeddings of shorter segments are too noisy to identify the speaker
+const MIN_SPEAKER_SEGMENT: Duration = Duration::from_secs(1);
+
+/// Everything the local realtime transcription needs besides the audio
+pub struct WhisperRealtimeContext {
+    pub whisper_context: Arc<WhisperContext>,
+    pub embedding_extractor: Arc<StdMutex<EmbeddingExtractor>>,
+    pub db: Arc<DatabaseManager>,
+    pub languages: Vec<Language>,
+    pub speaker_match_threshold: f32,
+}
+
+/// Window of audio to transcribe, handed out every time enough new audio came in
+#[derive(Debug, PartialEq)]
+pub struct WindowStep {
+    pub audio: Vec<f32>,
+    pub is_final: bool,
+}
+
+/// Grows a window of audio until the speaker pauses or it gets too long.
+///
+/// Each step re-transcribes the whole window, which is what turns an interim transcript into
+/// a better one as more context arrives. Silence-only windows are dropped without a step.
+pub struct SlidingWindow {
+    sample_rate: usize,
+    window: Vec<f32>,
+    pending: usize,
+}
+
+impl SlidingWindow {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as usize,
+            window: Vec::new(),
+            pending: 0,
+        }
+    }
+
+    fn samples(&self, duration: Duration) -> usize {
+        (self.sample_rate as f64 * duration.as_secs_f64()) as usize
+    }
+
+    pub fn push(&mut self, chunk: &[f32]) -> Option<WindowStep> {
+        self.window.extend_from_slice(chunk);
+        self.pending += chunk.len();
+
+        if self.pending < self.samples(STEP) {
+            return None;
+        }
+        self.pending = 0;
+
+        let frame = self.samples(Duration::from_millis(100)).max(1);
+        if !self.window.chunks(frame).any(|f| rms(f) > SILENCE_RMS) {
+            // keep a little audio so a word starting right now is not cut
+            let keep = self.window.len().min(self.samples(END_OF_SPEECH_SILENCE));
+            self.window.drain(..self.window.len() - keep);
+            return None;
+        }
+
+        let silence = self.samples(END_OF_SPEECH_SILENCE);
+        let trailing_silence = self.window.len() > silence
+            && rms(&self.window[self.window.len() - silence..]) < SILENCE_RMS;
+        let is_final = trailing_silence || self.window.len() >= self.samples(MAX_WINDOW);
+
+        let audio = if is_final {
+            std::mem::take(&mut self.window)
+        } else {
+            self.window.clone()
+        };
+
+        Some(WindowStep { audio, is_final })
+    }
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Transcribes an audio stream with the local whisper model, publishing interim and final
+/// transcripts as they are produced
+pub async fn stream_transcription_whisper(
+    stream: Arc<AudioStream>,
+    context: Arc<WhisperRealtimeContext>,
+    is_running: Arc<AtomicBool>,
+) -> Result<()> {
+    let device = stream.device.clone();
+    let sample_rate = stream.device_config.sample_rate().0;
+    let is_input = device.device_type == DeviceType::Input;
+    let mut receiver = stream.subscribe().await;
+    let mut window = SlidingWindow::new(sample_rate);
+    let mut last_interim = String::new();
+
+    info!("starting whisper realtime transcription for {}", device);
+
+    while is_running.load(Ordering::Relaxed) {
+        let chunk = match tokio::time::timeout(Duration::from_secs(1), receiver.recv()).await {
+            Ok(Ok(chunk)) => chunk,
+            // transcription fell behind, carry on with the most recent audio
+            Ok(Err(RecvError::Lagged(skipped))) => {
+                warn!(
+                    "realtime transcription for {} skipped {} chunks",
+                    device, skipped
+                );
+                continue;
+            }
+            Ok(Err(RecvError::Closed)) => break,
+            Err(_) if stream.is_disconnected() => break,
+            Err(_) => continue,
+        };
+
+        let Some(step) = window.push(&chunk) else {
+            continue;
+        };
+
+        let audio = if sample_rate != WHISPER_SAMPLE_RATE {
+            resample(&step.audio, sample_rate, WHISPER_SAMPLE_RATE)?
+        } else {
+            step.audio
+        };
+
+        // inference and the speaker embedding block for most of a second, they must not hold a
+        // runtime worker
+        let is_final = step.is_final;
+        let languages = context.languages.clone();
+        let whisper_context = context.whisper_context.clone();
+        let embedding_extractor = context.embedding_extractor.clone();
+        let result = tokio::task::spawn_blocking(move || -> Result<(String, Option<Vec<f32>>)> {
+            let transcription = process_with_whisper_blocking(&audio, languages, whisper_context)?
+                .trim()
+                .to_string();
+            let embedding = if is_final && !transcription.is_empty() {
+                speaker_embedding(&audio, &embedding_extractor)
+            } else {
+                None
+            };
+            Ok((transcription, embedding))
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
+        let (transcription, embedding) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                error!(
+                    "realtime whisper transcription failed for {}: {}",
+                    device, e
+                );
+                continue;
+            }
+        };
+
+        if !step.is_final && (transcription.is_empty() || transcription == last_interim) {
+            continue;
+        }
+
+        let speaker = if step.is_final {
+            last_interim.clear();
+            if transcription.is_empty() {
+                continue;
+            }
+            match embedding {
+                Some(embedding) => identify_speaker(&embedding, &context).await,
+                None => None,
+            }
+        } else {
+            last_interim = transcription.clone();
+            None
+        };
+
+        debug!(
+            "realtime transcript for {} (final: {}): {}",
+            device, step.is_final, transcription
+        );
+
+        let _ = send_event(
+            REALTIME_TRANSCRIPTION_EVENT,
+            RealtimeTranscriptionEvent {
+                timestamp: chrono::Utc::now(),
+                device: device.to_string(),
+                transcription,
+                is_final: step.is_final,
+                is_input,
+                speaker,
+            },
+        );
+    }
+
+    info!("stopped whisper realtime transcription for {}", device);
+    Ok(())
+}
+
+/// Speaker embedding of a final segment, computed on the blocking thread of the transcription
+fn speaker_embedding(
+    audio: &[f32],
+    embedding_extractor: &StdMutex<EmbeddingExtractor>,
+) -> Option<Vec<f32>> {
+    let min_samples = (WHISPER_SAMPLE_RATE as f64 * MIN_SPEAKER_SEGMENT.as_secs_f64()) as usize;
+    if audio.len() < min_samples {
+        return None;
+    }
+
+    match embedding_extractor
+        .lock()
+        .unwrap()
+        .compute(&normalize_v2(audio))
+    {
+        Ok(embedding) => Some(embedding.collect()),
+        Err(e) => {
+            debug!("failed to compute realtime speaker embedding: {}", e);
+            None
+        }
+    }
+}
+
+/// Matches the segment against known speakers without creating new ones, the batch pipeline
+/// is the one recording speakers
+async fn identify_speaker(embedding: &[f32], context: &WhisperRealtimeContext) -> Option<String> {
+    match context
+        .db
+        .get_speaker_from_embedding_with_threshold(embedding, context.speaker_match_threshold)
+        .await
+    {
+        Ok(Some(speaker)) if !speaker.name.is_empty() => Some(speaker.name),
+        Ok(Some(speaker)) => Some(speaker.id.to_string()),
+        Ok(None) => None,
+        Err(e) => {
+            debug!("failed to match realtime speaker: {}", e);
+            None
+        }
+    }
+}
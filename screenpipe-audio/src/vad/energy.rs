@@ -0,0 +1,102 @@
+use anyhow;
+use vad_rs::VadStatus;
+
+use super::{FrameHistory, VadConfig, VadEngine, VadSensitivity};
+use crate::utils::audio::{average_noise_spectrum, spectral_subtraction};
+
+/// Frame size `spectral_subtraction` works on, 100ms at 16khz
+const FRAME_SIZE: usize = 1600;
+/// How fast the noise floor follows louder audio, it drops to quieter audio immediately
+const NOISE_FLOOR_RISE: f32 = 0.02;
+/// Noise is over-subtracted so that noise frames score well below the silence threshold
+const OVERSUBTRACTION: f32 = 2.0;
+/// Frames quieter than this are silence whatever the noise floor, about -60 dBFS
+const MIN_SPEECH_POWER: f32 = 1e-6;
+
+/// Voice activity detection from the energy left once the estimated background noise is
+/// removed.
+///
+/// The noise floor tracks the quietest recent frames. Each frame is cleaned with spectral
+/// subtraction against that floor and scored with the share of its energy that survives:
+/// close to 1 for speech well above the noise, close to 0 for the noise itself.
+pub struct EnergyVad {
+    noise_floor: Option<f32>,
+    history: FrameHistory,
+    sensitivity: VadSensitivity,
+    config: VadConfig,
+}
+
+impl EnergyVad {
+    pub fn new() -> Self {
+        Self::with_config(VadConfig::default())
+    }
+
+    pub fn with_config(config: VadConfig) -> Self {
+        Self {
+            noise_floor: None,
+            history: FrameHistory::new(&config),
+            sensitivity: VadSensitivity::Medium,
+            config,
+        }
+    }
+
+    /// Share of the frame energy above the noise floor, between 0 and 1
+    fn score(&mut self, audio_chunk: &[f32]) -> anyhow::Result<f32> {
+        // spectral subtraction works on a single window
+        let frame = &audio_chunk[..audio_chunk.len().min(FRAME_SIZE)];
+        if frame.is_empty() {
+            return Ok(0.0);
+        }
+
+        let power = average_noise_spectrum(frame);
+        let noise_floor = match self.noise_floor {
+            Some(floor) if power < floor => power,
+            Some(floor) => floor + (power - floor) * NOISE_FLOOR_RISE,
+            None => power,
+        };
+        self.noise_floor = Some(noise_floor);
+
+        if power < MIN_SPEECH_POWER {
+            return Ok(0.0);
+        }
+
+        // the fft bins of white noise have a power of window size times its variance
+        let cleaned =
+            spectral_subtraction(frame, OVERSUBTRACTION * noise_floor * FRAME_SIZE as f32)?;
+        // the inverse fft is not normalized, its output is scaled by the window size
+        let cleaned_energy =
+            cleaned.iter().map(|s| s * s).sum::<f32>() / (FRAME_SIZE as f32 * FRAME_SIZE as f32);
+        let energy = power * frame.len() as f32;
+
+        Ok((cleaned_energy / energy).clamp(0.0, 1.0))
+    }
+
+    fn status(&mut self, audio_chunk: &[f32]) -> anyhow::Result<VadStatus> {
+        let score = self.score(audio_chunk)?;
+        Ok(self.history.update(score, &self.config))
+    }
+}
+
+impl Default for EnergyVad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VadEngine for EnergyVad {
+    fn is_voice_segment(&mut self, audio_chunk: &[f32]) -> anyhow::Result<bool> {
+        Ok(self.status(audio_chunk)? == VadStatus::Speech)
+    }
+
+    fn audio_type(&mut self, audio_chunk: &[f32]) -> anyhow::Result<VadStatus> {
+        self.status(audio_chunk)
+    }
+
+    fn set_sensitivity(&mut self, sensitivity: VadSensitivity) {
+        self.sensitivity = sensitivity;
+    }
+
+    fn get_min_speech_ratio(&self) -> f32 {
+        self.config.min_speech_ratio(self.sensitivity)
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use tracing::debug;
+use vad_rs::VadStatus;
+
+use super::{create_vad_engine, VadConfig, VadEngineEnum};
+use crate::utils::audio::{normalize_v2, pcm_decode, resample};
+
+const SAMPLE_RATE: u32 = 16000;
+/// Same frames as `prepare_segments` feeds the engine, 100ms at 16khz
+const FRAME_SIZE: usize = 1600;
+
+/// Frame level confusion counts of a VAD against labels
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct VadScore {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+impl VadScore {
+    fn add(&mut self, predicted: bool, expected: bool) {
+        match (predicted, expected) {
+            (true, true) => self.true_positives += 1,
+            (true, false) => self.false_positives += 1,
+            (false, false) => self.true_negatives += 1,
+            (false, true) => self.false_negatives += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &VadScore) {
+        self.true_positives += other.true_positives;
+        self.false_positives += other.false_positives;
+        self.true_negatives += other.true_negatives;
+        self.false_negatives += other.false_negatives;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.true_positives + self.false_positives + self.true_negatives + self.false_negatives
+    }
+
+    pub fn precision(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> f32 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    pub fn f1(&self) -> f32 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        ratio(self.true_positives + self.true_negatives, self.frames())
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f32 / denominator as f32
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VadFileScore {
+    pub path: PathBuf,
+    pub score: VadScore,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VadEvalReport {
+    pub files: Vec<VadFileScore>,
+    pub total: VadScore,
+}
+
+/// Parses speech regions from an Audacity label file: one `start<TAB>end[<TAB>label]` line
+/// per region, in seconds
+pub fn parse_labels(content: &str) -> Result<Vec<(f64, f64)>> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .enumerate()
+        .map(|(i, line)| {
+            let mut fields = line.split_whitespace();
+            let mut next = || -> Result<f64> {
+                fields
+                    .next()
+                    .ok_or_else(|| anyhow!("label line {} needs a start and an end", i + 1))?
+                    .parse()
+                    .map_err(|e| anyhow!("invalid time on label line {}: {}", i + 1, e))
+            };
+            let (start, end) = (next()?, next()?);
+            if end < start {
+                return Err(anyhow!("label line {} ends before it starts", i + 1));
+            }
+            Ok((start, end))
+        })
+        .collect()
+}
+
+/// Collects labeled wav files: every `.wav` given directly or found in a given directory,
+/// with its labels in a `.txt` file next to it
+pub fn find_labeled_files(paths: &[PathBuf]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| is_wav(p))
+                .collect::<Vec<_>>();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|wav| {
+            let labels = wav.with_extension("txt");
+            if !labels.exists() {
+                return Err(anyhow!("no label file {:?} for {:?}", labels, wav));
+            }
+            Ok((wav, labels))
+        })
+        .collect()
+}
+
+fn is_wav(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case("wav"))
+        .unwrap_or(false)
+}
+
+/// Scores a VAD configuration frame by frame against labeled recordings. Audio goes through
+/// the same resampling, normalization and framing as during recording.
+pub async fn evaluate_vad(
+    engine: VadEngineEnum,
+    config: VadConfig,
+    paths: &[PathBuf],
+) -> Result<VadEvalReport> {
+    config.validate()?;
+    let files = find_labeled_files(paths)?;
+    if files.is_empty() {
+        return Err(anyhow!("no labeled wav files found"));
+    }
+
+    let mut report = VadEvalReport {
+        files: Vec::new(),
+        total: VadScore::default(),
+    };
+
+    for (wav, labels) in files {
+        let regions = parse_labels(&std::fs::read_to_string(&labels)?)
+            .with_context(|| format!("failed to read labels {:?}", labels))?;
+        let (samples, sample_rate) =
+            pcm_decode(&wav).with_context(|| format!("failed to decode {:?}", wav))?;
+        let samples = if sample_rate != SAMPLE_RATE {
+            resample(&samples, sample_rate, SAMPLE_RATE)?
+        } else {
+            samples
+        };
+        let samples = normalize_v2(&samples);
+
+        // a fresh engine per file so history and noise estimates do not carry over
+        let mut vad = create_vad_engine(engine.clone(), config.clone()).await?;
+        let mut score = VadScore::default();
+        for (i, frame) in samples.chunks(FRAME_SIZE).enumerate() {
+            let center = (i * FRAME_SIZE + frame.len() / 2) as f64 / SAMPLE_RATE as f64;
+            let expected = regions
+                .iter()
+                .any(|(start, end)| *start <= center && center < *end);
+            let predicted = vad.audio_type(frame)? == VadStatus::Speech;
+            score.add(predicted, expected);
+        }
+
+        debug!("vad eval {:?}: f1 {:.3}", wav, score.f1());
+        report.total.merge(&score);
+        report.files.push(VadFileScore { path: wav, score });
+    }
+
+    Ok(report)
+}
@@ -1,11 +1,16 @@
+pub mod energy;
+pub mod eval;
 pub mod silero;
 pub mod webrtc;
 
 use anyhow;
+use energy::EnergyVad;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use silero::SileroVad;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
-use std::sync::Once;
+use std::sync::{Arc, Once};
 use tokio::sync::Mutex;
 use vad_rs::VadStatus;
 use webrtc::WebRtcVad;
@@ -28,10 +33,12 @@ impl VadSensitivity {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum VadEngineEnum {
     WebRtc,
     Silero,
+    /// Energy based detection, no model and very little cpu
+    Energy,
 }
 
 pub trait VadEngine: Send {
@@ -46,19 +53,192 @@ const SPEECH_THRESHOLD: f32 = 0.5;
 const SILENCE_THRESHOLD: f32 = 0.35;
 const SPEECH_FRAME_THRESHOLD: usize = 3; // Minimum number of frames above SPEECH_THRESHOLD to consider as speech
 
+/// Tunable thresholds of a VAD engine.
+///
+/// Frame scores are speech probabilities for silero and the share of energy left after noise
+/// removal for the energy engine. WebRtc only uses `min_speech_ratio`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadConfig {
+    /// Score above which a frame counts as speech
+    pub speech_threshold: f32,
+    /// Score below which a frame counts as silence
+    pub silence_threshold: f32,
+    /// Number of recent frames the decision is based on
+    pub frame_history: usize,
+    /// Speech frames needed in the history to report speech
+    pub speech_frame_threshold: usize,
+    /// Share of speech frames a chunk needs to be transcribed, overrides the sensitivity
+    pub min_speech_ratio: Option<f32>,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            speech_threshold: SPEECH_THRESHOLD,
+            silence_threshold: SILENCE_THRESHOLD,
+            frame_history: FRAME_HISTORY,
+            speech_frame_threshold: SPEECH_FRAME_THRESHOLD,
+            min_speech_ratio: None,
+        }
+    }
+}
+
+impl VadConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.silence_threshold > self.speech_threshold {
+            return Err(anyhow::anyhow!(
+                "vad silence threshold must not be above the speech threshold"
+            ));
+        }
+        if self.frame_history == 0 || self.speech_frame_threshold > self.frame_history {
+            return Err(anyhow::anyhow!(
+                "vad speech frame threshold must fit in a non-empty frame history"
+            ));
+        }
+        if let Some(ratio) = self.min_speech_ratio {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(anyhow::anyhow!(
+                    "vad min speech ratio must be between 0 and 1"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn min_speech_ratio(&self, sensitivity: VadSensitivity) -> f32 {
+        self.min_speech_ratio
+            .unwrap_or_else(|| sensitivity.min_speech_ratio())
+    }
+}
+
+/// VAD thresholds as read from a config file: top level keys are the defaults and `devices`
+/// overrides them per device name, e.g. `{"speech_threshold": 0.6, "devices": {"MacBook Pro
+/// Microphone (input)": {"speech_threshold": 0.4}}}`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadConfigFile {
+    #[serde(flatten)]
+    pub default: VadConfig,
+    pub devices: HashMap<String, VadConfig>,
+}
+
+impl VadConfigFile {
+    /// Loads a json config
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let config: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        config.default.validate()?;
+        for (device, device_config) in &config.devices {
+            device_config
+                .validate()
+                .map_err(|e| anyhow::anyhow!("{}: {}", device, e))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Smooths frame scores over the recent history, shared by the score based engines
+struct FrameHistory {
+    scores: VecDeque<f32>,
+}
+
+impl FrameHistory {
+    fn new(config: &VadConfig) -> Self {
+        Self {
+            scores: VecDeque::with_capacity(config.frame_history),
+        }
+    }
+
+    fn update(&mut self, score: f32, config: &VadConfig) -> VadStatus {
+        self.scores.push_back(score);
+        while self.scores.len() > config.frame_history {
+            self.scores.pop_front();
+        }
+
+        let speech_frames = self
+            .scores
+            .iter()
+            .filter(|&&s| s > config.speech_threshold)
+            .count();
+        let silence_frames = self
+            .scores
+            .iter()
+            .filter(|&&s| s < config.silence_threshold)
+            .count();
+
+        if speech_frames >= config.speech_frame_threshold {
+            VadStatus::Speech
+        } else if silence_frames > self.scores.len() / 2 {
+            VadStatus::Silence
+        } else {
+            VadStatus::Unknown
+        }
+    }
+}
+
 lazy_static! {
     static ref MODEL_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
 }
 
 static DOWNLOAD_ONCE: Once = Once::new();
 
-pub async fn create_vad_engine(engine: VadEngineEnum) -> anyhow::Result<Box<dyn VadEngine>> {
+pub async fn create_vad_engine(
+    engine: VadEngineEnum,
+    config: VadConfig,
+) -> anyhow::Result<Box<dyn VadEngine + Send>> {
     match engine {
-        VadEngineEnum::WebRtc => Ok(Box::new(WebRtcVad::new())),
+        VadEngineEnum::WebRtc => Ok(Box::new(WebRtcVad::with_config(config))),
         VadEngineEnum::Silero => {
-            let silero_vad = SileroVad::new().await?;
+            let silero_vad = SileroVad::with_config(config).await?;
             Ok(Box::new(silero_vad))
         }
+        VadEngineEnum::Energy => Ok(Box::new(EnergyVad::with_config(config))),
+    }
+}
+
+pub type SharedVadEngine = Arc<Mutex<Box<dyn VadEngine + Send>>>;
+
+/// One VAD engine per device, so thresholds can differ between devices and the frame
+/// history of one device does not influence another
+pub struct DeviceVadEngines {
+    engine: VadEngineEnum,
+    default_config: VadConfig,
+    device_configs: HashMap<String, VadConfig>,
+    engines: Mutex<HashMap<String, SharedVadEngine>>,
+}
+
+impl DeviceVadEngines {
+    pub fn new(
+        engine: VadEngineEnum,
+        default_config: VadConfig,
+        device_configs: HashMap<String, VadConfig>,
+    ) -> Self {
+        Self {
+            engine,
+            default_config,
+            device_configs,
+            engines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config_for(&self, device: &str) -> &VadConfig {
+        self.device_configs
+            .get(device)
+            .unwrap_or(&self.default_config)
+    }
+
+    pub async fn get(&self, device: &str) -> anyhow::Result<SharedVadEngine> {
+        let mut engines = self.engines.lock().await;
+        if let Some(engine) = engines.get(device) {
+            return Ok(engine.clone());
+        }
+
+        let engine = Arc::new(Mutex::new(
+            create_vad_engine(self.engine.clone(), self.config_for(device).clone()).await?,
+        ));
+        engines.insert(device.to_string(), engine.clone());
+        Ok(engine)
     }
 }
 
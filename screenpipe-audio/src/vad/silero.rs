@@ -1,25 +1,24 @@
 use anyhow;
 use dirs;
-use std::collections::VecDeque;
 use std::path::PathBuf;
 use tracing::debug;
 use vad_rs::{Vad, VadStatus};
 
-use crate::vad::FRAME_HISTORY;
-
-use super::{
-    VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH, SILENCE_THRESHOLD,
-    SPEECH_FRAME_THRESHOLD, SPEECH_THRESHOLD,
-};
+use super::{FrameHistory, VadConfig, VadEngine, VadSensitivity, DOWNLOAD_ONCE, MODEL_PATH};
 
 pub struct SileroVad {
     vad: Vad,
-    prob_history: VecDeque<f32>,
+    prob_history: FrameHistory,
     sensitivity: VadSensitivity,
+    config: VadConfig,
 }
 
 impl SileroVad {
     pub async fn new() -> anyhow::Result<Self> {
+        Self::with_config(VadConfig::default()).await
+    }
+
+    pub async fn with_config(config: VadConfig) -> anyhow::Result<Self> {
         debug!("Initializing SileroVad...");
         let model_path = Self::get_or_download_model().await?;
         debug!("SileroVad Model downloaded to: {:?}", model_path);
@@ -30,8 +29,9 @@ impl SileroVad {
         debug!("SileroVad initialized successfully");
         Ok(Self {
             vad,
-            prob_history: VecDeque::with_capacity(FRAME_HISTORY),
+            prob_history: FrameHistory::new(&config),
             sensitivity: VadSensitivity::Medium,
+            config,
         })
     }
 
@@ -91,29 +91,7 @@ impl SileroVad {
     }
 
     fn update_status(&mut self, prob: f32) -> VadStatus {
-        self.prob_history.push_back(prob);
-        if self.prob_history.len() > FRAME_HISTORY {
-            self.prob_history.pop_front();
-        }
-
-        let speech_frames = self
-            .prob_history
-            .iter()
-            .filter(|&&p| p > SPEECH_THRESHOLD)
-            .count();
-        let silence_frames = self
-            .prob_history
-            .iter()
-            .filter(|&&p| p < SILENCE_THRESHOLD)
-            .count();
-
-        if speech_frames >= SPEECH_FRAME_THRESHOLD {
-            VadStatus::Speech
-        } else if silence_frames > self.prob_history.len() / 2 {
-            VadStatus::Silence
-        } else {
-            VadStatus::Unknown
-        }
+        self.prob_history.update(prob, &self.config)
     }
 
     fn get_threshold(&self) -> f32 {
@@ -175,6 +153,6 @@ impl VadEngine for SileroVad {
     }
 
     fn get_min_speech_ratio(&self) -> f32 {
-        self.sensitivity.min_speech_ratio()
+        self.config.min_speech_ratio(self.sensitivity)
     }
 }
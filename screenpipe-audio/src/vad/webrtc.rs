@@ -1,20 +1,26 @@
 use anyhow;
 use vad_rs::VadStatus;
 
-use super::{VadEngine, VadSensitivity};
+use super::{VadConfig, VadEngine, VadSensitivity};
 
 #[derive(Default)]
 pub struct WebRtcVad {
     vad: webrtc_vad::Vad,
     sensitivity: VadSensitivity,
+    config: VadConfig,
 }
 
 impl WebRtcVad {
     pub fn new() -> Self {
+        Self::with_config(VadConfig::default())
+    }
+
+    pub fn with_config(config: VadConfig) -> Self {
         let vad = webrtc_vad::Vad::new();
         Self {
             vad,
             sensitivity: VadSensitivity::Medium,
+            config,
         }
     }
 }
@@ -67,6 +73,6 @@ impl VadEngine for WebRtcVad {
     }
 
     fn get_min_speech_ratio(&self) -> f32 {
-        self.sensitivity.min_speech_ratio()
+        self.config.min_speech_ratio(self.sensitivity)
     }
 }
@@ -9,13 +9,22 @@ mod tests {
     use screenpipe_audio::core::engine::AudioTranscriptionEngine;
     use screenpipe_audio::core::record_and_transcribe;
     use screenpipe_audio::core::stream::AudioStream;
+    use screenpipe_audio::core::virtual_device::{
+        alaw_to_f32, mulaw_to_f32, rtp_payload, PcmEncoding, PcmFormat, RtpCodec, VirtualSource,
+    };
+    use screenpipe_audio::reprocess::device_from_file_path;
     use screenpipe_audio::speaker::embedding::EmbeddingExtractor;
     use screenpipe_audio::speaker::embedding_manager::EmbeddingManager;
     use screenpipe_audio::speaker::prepare_segments;
     use screenpipe_audio::transcription::whisper::model::{
         create_whisper_context_parameters, download_whisper_model,
     };
-    use screenpipe_audio::vad::{silero::SileroVad, VadEngine};
+    use screenpipe_audio::vad::{
+        energy::EnergyVad,
+        eval::{evaluate_vad, parse_labels},
+        silero::SileroVad,
+        VadConfig, VadConfigFile, VadEngine, VadEngineEnum,
+    };
     use screenpipe_audio::{pcm_decode, stt, AudioInput};
     use screenpipe_core::Language;
     use std::path::{Path, PathBuf};
@@ -25,6 +34,7 @@ mod tests {
     use std::sync::Arc;
     use std::time::{Duration, Instant};
     use tokio::sync::Mutex;
+    use vad_rs::VadStatus;
     use whisper_rs::WhisperContext;
 
     fn setup() {
@@ -51,6 +61,263 @@ mod tests {
         assert_eq!(spec.to_string(), "Test Device (input)");
     }
 
+    #[test]
+    fn test_parse_virtual_source() {
+        let device = parse_audio_device("file:/tmp/meeting.wav?loop=true (input)").unwrap();
+        assert_eq!(
+            VirtualSource::from_device(&device).unwrap().unwrap(),
+            VirtualSource::File {
+                path: PathBuf::from("/tmp/meeting.wav"),
+                looping: true,
+            }
+        );
+        // only a file played once ends for good
+        assert!(!VirtualSource::is_finite(&device));
+        assert!(VirtualSource::is_finite(
+            &parse_audio_device("file:/tmp/meeting.wav (input)").unwrap()
+        ));
+        assert!(!VirtualSource::is_finite(
+            &parse_audio_device("pipe:/tmp/fifo (input)").unwrap()
+        ));
+
+        assert_eq!(
+            VirtualSource::parse("pipe:-?rate=48000&channels=2&format=f32le")
+                .unwrap()
+                .unwrap(),
+            VirtualSource::Pipe {
+                path: None,
+                format: PcmFormat {
+                    sample_rate: 48000,
+                    channels: 2,
+                    encoding: PcmEncoding::F32Le,
+                },
+            }
+        );
+
+        assert_eq!(
+            VirtualSource::parse("rtp://127.0.0.1:5004?codec=pcma")
+                .unwrap()
+                .unwrap(),
+            VirtualSource::Rtp {
+                addr: "127.0.0.1:5004".parse().unwrap(),
+                codec: RtpCodec::Pcma,
+                sample_rate: 8000,
+                channels: 1,
+            }
+        );
+
+        assert!(VirtualSource::parse("rtp://not-an-address")
+            .unwrap()
+            .is_err());
+        assert!(VirtualSource::parse("MacBook Pro Microphone").is_none());
+    }
+
+    #[test]
+    fn test_reprocess_device_from_file_path() {
+        let device =
+            device_from_file_path("/data/MacBook Pro Microphone (input)_2025-01-02_10-20-30.mp4")
+                .unwrap();
+        assert_eq!(device.name, "MacBook Pro Microphone");
+        assert_eq!(device.device_type, screenpipe_db::DeviceType::Input);
+
+        let device = device_from_file_path("Display 1 (output)_2025-01-02_10-20-30.mp4").unwrap();
+        assert_eq!(device.device_type, screenpipe_db::DeviceType::Output);
+
+        assert!(device_from_file_path("/data/imported.mp4").is_none());
+    }
+
+    #[test]
+    fn test_rtp_payload_decoding() {
+        // version 2, one csrc, padding of 2 bytes
+        let mut packet = vec![0xa1, 0x00, 0x01, 0x02, 0, 0, 0, 0, 0, 0, 0, 0];
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        packet.extend_from_slice(&[0xff, 0x7f, 0x00, 0x02]);
+
+        let (sequence, payload) = rtp_payload(&packet).unwrap();
+        assert_eq!(sequence, 0x0102);
+        assert_eq!(payload, &[0xff, 0x7f]);
+        assert!(rtp_payload(&packet[..8]).is_none());
+
+        // 0xff and 0x7f are the two mu-law zeros, 0x00 and 0x80 the extremes
+        assert_eq!(mulaw_to_f32(0xff), 0.0);
+        assert_eq!(mulaw_to_f32(0x7f), 0.0);
+        assert!(mulaw_to_f32(0x00) < -0.98);
+        assert!(mulaw_to_f32(0x80) > 0.98);
+        assert!(alaw_to_f32(0xd5) > 0.0 && alaw_to_f32(0xd5) < 0.001);
+        assert!(alaw_to_f32(0x55) < 0.0 && alaw_to_f32(0x55) > -0.001);
+    }
+
+    #[tokio::test]
+    async fn test_virtual_file_device_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..8000 {
+            let sample = ((i as f32 * 0.05).sin() * 10000.0) as i16;
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let device =
+            parse_audio_device(&format!("file:{} (input)", path.to_string_lossy())).unwrap();
+        // subscribed before the file is read, so no chunk is missed
+        let (stream, mut receiver) =
+            AudioStream::from_device_subscribed(Arc::new(device), Arc::new(AtomicBool::new(true)))
+                .await
+                .unwrap();
+        assert_eq!(stream.device_config.sample_rate().0, 16000);
+
+        let mut received = 0;
+        while received < 8000 {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("virtual device stopped sending audio")
+                .unwrap();
+            received += chunk.len();
+        }
+
+        // half a second of stereo audio downmixed to mono
+        assert_eq!(received, 8000);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(stream.is_disconnected());
+        stream.stop().await.unwrap();
+    }
+
+    /// 3 seconds of low noise then 2 seconds of a loud tone over the same noise, at 16khz
+    fn noise_then_tone() -> Vec<f32> {
+        let mut state = 0x2545f491u32;
+        (0..80000)
+            .map(|i| {
+                // xorshift, enough for deterministic noise
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let noise = (state as f32 / u32::MAX as f32 - 0.5) * 0.02;
+                if i < 48000 {
+                    noise
+                } else {
+                    noise + (i as f32 * 2.0 * std::f32::consts::PI * 440.0 / 16000.0).sin() * 0.3
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_energy_vad_detects_tone_over_noise() {
+        let mut vad = EnergyVad::new();
+        let statuses = noise_then_tone()
+            .chunks(1600)
+            .map(|frame| vad.audio_type(frame).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(statuses[..30].iter().all(|s| *s != VadStatus::Speech));
+        assert!(statuses[35..].iter().all(|s| *s == VadStatus::Speech));
+
+        // a stricter history needs more speech frames before switching
+        let config = VadConfig {
+            speech_frame_threshold: 8,
+            ..Default::default()
+        };
+        let mut vad = EnergyVad::with_config(config);
+        let statuses = noise_then_tone()
+            .chunks(1600)
+            .map(|frame| vad.audio_type(frame).unwrap())
+            .collect::<Vec<_>>();
+        assert!(statuses[33] != VadStatus::Speech);
+        assert!(statuses[38] == VadStatus::Speech);
+    }
+
+    #[test]
+    fn test_vad_config_validation() {
+        assert!(VadConfig::default().validate().is_ok());
+        assert!(VadConfig {
+            speech_threshold: 0.3,
+            silence_threshold: 0.4,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(VadConfig {
+            speech_frame_threshold: 11,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(VadConfig {
+            min_speech_ratio: Some(1.5),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vad.json");
+        std::fs::write(
+            &path,
+            r#"{"speech_threshold": 0.6, "devices": {"Desk Mic (input)": {"frame_history": 20}}}"#,
+        )
+        .unwrap();
+        let config = VadConfigFile::load(&path).unwrap();
+        assert_eq!(config.default.speech_threshold, 0.6);
+        assert_eq!(config.default.frame_history, 10);
+        assert_eq!(config.devices["Desk Mic (input)"].frame_history, 20);
+        assert_eq!(config.devices["Desk Mic (input)"].speech_threshold, 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_vad_eval() {
+        let labels = parse_labels("3.0\t5.0\tspeech\n\n0.5 0.7\n").unwrap();
+        assert_eq!(labels, vec![(3.0, 5.0), (0.5, 0.7)]);
+        assert!(parse_labels("2.0\t1.0").is_err());
+        assert!(parse_labels("2.0").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.path().join("tone.wav"), spec).unwrap();
+        for sample in noise_then_tone() {
+            writer.write_sample((sample * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        std::fs::write(dir.path().join("tone.txt"), "3.0\t5.0\ttone\n").unwrap();
+
+        let report = evaluate_vad(
+            VadEngineEnum::Energy,
+            VadConfig::default(),
+            &[dir.path().to_path_buf()],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.total.frames(), 50);
+        assert_eq!(report.total.false_positives, 0);
+        // only the first frames of the tone are missed while the history fills up
+        assert!(report.total.false_negatives <= 3);
+        assert!(report.total.f1() > 0.9);
+
+        // a wav without labels is an error rather than a silently perfect score
+        std::fs::remove_file(dir.path().join("tone.txt")).unwrap();
+        assert!(evaluate_vad(
+            VadEngineEnum::Energy,
+            VadConfig::default(),
+            &[dir.path().to_path_buf()]
+        )
+        .await
+        .is_err());
+    }
+
     #[tokio::test]
     #[ignore] // Add this if you want to skip this test in regular test runs
     async fn test_record_and_transcribe() {
@@ -65,15 +332,17 @@ mod tests {
         let is_running = Arc::new(AtomicBool::new(true));
         let is_running_clone = Arc::clone(&is_running);
 
-        let audio_stream = AudioStream::from_device(device_spec, is_running_clone)
-            .await
-            .unwrap();
+        let (audio_stream, audio_receiver) =
+            AudioStream::from_device_subscribed(device_spec, is_running_clone)
+                .await
+                .unwrap();
 
         // Act
         let start_time = Instant::now();
         println!("Starting record_and_transcribe");
         let result = record_and_transcribe(
             Arc::new(audio_stream),
+            audio_receiver,
             duration,
             Arc::new(sender),
             is_running,
@@ -125,9 +394,10 @@ mod tests {
         let is_running = Arc::new(AtomicBool::new(true));
         let is_running_clone = Arc::clone(&is_running);
 
-        let audio_stream = AudioStream::from_device(device_spec, is_running_clone.clone())
-            .await
-            .unwrap();
+        let (audio_stream, audio_receiver) =
+            AudioStream::from_device_subscribed(device_spec, is_running_clone.clone())
+                .await
+                .unwrap();
 
         // interrupt in 10 seconds
         tokio::spawn(async move {
@@ -140,6 +410,7 @@ mod tests {
 
         record_and_transcribe(
             Arc::new(audio_stream),
+            audio_receiver,
             duration,
             Arc::new(sender),
             is_running,
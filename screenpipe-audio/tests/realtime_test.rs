@@ -76,3 +76,43 @@ async fn test_realtime_transcription() {
 
     transcription_receiver_handle.await.unwrap();
 }
+
+#[test]
+fn test_whisper_sliding_window() {
+    use screenpipe_audio::transcription::whisper::realtime::SlidingWindow;
+
+    let sample_rate = 16000;
+    let speech: Vec<f32> = (0..sample_rate / 10)
+        .map(|i| (i as f32 * 0.1).sin() * 0.5)
+        .collect();
+    let silence = vec![0.0; sample_rate / 10];
+    let mut window = SlidingWindow::new(sample_rate as u32);
+
+    // silence alone never produces a step
+    for _ in 0..30 {
+        assert!(window.push(&silence).is_none());
+    }
+
+    // one second of speech gives an interim step over the window so far
+    let mut step = None;
+    for _ in 0..10 {
+        step = window.push(&speech).or(step);
+    }
+    let step = step.unwrap();
+    assert!(!step.is_final);
+    assert!(step.audio.len() > sample_rate);
+
+    // a pause finalizes the segment and starts a new window
+    let mut step = None;
+    for _ in 0..10 {
+        step = window.push(&silence).or(step);
+    }
+    let step = step.unwrap();
+    assert!(step.is_final);
+    assert_eq!(step.audio.len(), sample_rate * 27 / 10);
+
+    // continuous speech is cut at the maximum window length
+    let steps: Vec<_> = (0..120).filter_map(|_| window.push(&speech)).collect();
+    assert!(steps.iter().any(|s| s.is_final));
+    assert!(steps.iter().all(|s| s.audio.len() <= sample_rate * 10));
+}
@@ -1,39 +1,476 @@
+//! AT-SPI2 backed accessibility engine for Linux
+//!
+//! Talks to the accessibility bus over D-Bus. Applications expose their widget
+//! tree on that bus when accessibility is enabled (GTK and Qt do so by default
+//! on most desktops, Chromium and Electron need `--force-renderer-accessibility`).
+//! Synthetic mouse and keyboard input goes through the registry's device event
+//! controller, which only works on X11 sessions.
+
 use crate::operator::element::UIElementImpl;
 use crate::operator::platforms::AccessibilityEngine;
 use crate::operator::{AutomationError, Locator, Selector, UIElement, UIElementAttributes};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tracing::{debug, trace};
+use zbus::blocking::Connection;
+use zbus::proxy::{CacheProperties, ProxyDefault};
+use zbus::zvariant::OwnedObjectPath;
+
+const REGISTRY_BUS: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const NULL_PATH: &str = "/org/a11y/atspi/null";
+const DEVICE_EVENT_CONTROLLER_PATH: &str = "/org/a11y/atspi/registry/deviceeventcontroller";
+
+const EDITABLE_TEXT_INTERFACE: &str = "org.a11y.atspi.EditableText";
+const VALUE_INTERFACE: &str = "org.a11y.atspi.Value";
+
+// AtspiStateType bits as returned by GetState
+const STATE_ACTIVE: u32 = 1;
+const STATE_ENABLED: u32 = 8;
+const STATE_FOCUSED: u32 = 12;
+const STATE_SENSITIVE: u32 = 24;
+const STATE_SHOWING: u32 = 25;
+const STATE_VISIBLE: u32 = 30;
+
+// AtspiCoordType::Screen
+const COORD_TYPE_SCREEN: u32 = 0;
+// AtspiKeySynthType
+const KEY_SYM: u32 = 3;
+const KEY_STRING: u32 = 4;
+
+/// Upper bound on the number of elements visited by a single search or text
+/// extraction, browsers easily expose hundreds of thousands of nodes
+const MAX_SEARCH_NODES: usize = 10_000;
+const MAX_SEARCH_DEPTH: usize = 50;
+
+#[zbus::proxy(
+    interface = "org.a11y.Bus",
+    default_service = "org.a11y.Bus",
+    default_path = "/org/a11y/bus",
+    gen_async = false
+)]
+trait Bus {
+    fn get_address(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.Accessible", gen_async = false)]
+trait Accessible {
+    fn get_children(&self) -> zbus::Result<Vec<(String, OwnedObjectPath)>>;
+    fn get_role_name(&self) -> zbus::Result<String>;
+    fn get_state(&self) -> zbus::Result<Vec<u32>>;
+    fn get_attributes(&self) -> zbus::Result<HashMap<String, String>>;
+    fn get_application(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+    fn get_interfaces(&self) -> zbus::Result<Vec<String>>;
+
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn description(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn parent(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+    #[zbus(property)]
+    fn child_count(&self) -> zbus::Result<i32>;
+    #[zbus(property)]
+    fn accessible_id(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.Component", gen_async = false)]
+trait Component {
+    fn get_extents(&self, coord_type: u32) -> zbus::Result<(i32, i32, i32, i32)>;
+    fn grab_focus(&self) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.Action", gen_async = false)]
+trait Action {
+    /// (name, localized name, key binding) of every action
+    fn get_actions(&self) -> zbus::Result<Vec<(String, String, String)>>;
+    fn do_action(&self, index: i32) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.Text", gen_async = false)]
+trait Text {
+    fn get_text(&self, start_offset: i32, end_offset: i32) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn character_count(&self) -> zbus::Result<i32>;
+    #[zbus(property)]
+    fn caret_offset(&self) -> zbus::Result<i32>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.EditableText", gen_async = false)]
+trait EditableText {
+    fn set_text_contents(&self, new_contents: &str) -> zbus::Result<bool>;
+    fn insert_text(&self, position: i32, text: &str, length: i32) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(interface = "org.a11y.atspi.Value", gen_async = false)]
+trait Value {
+    #[zbus(property)]
+    fn current_value(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn set_current_value(&self, value: f64) -> zbus::Result<()>;
+}
 
-pub struct LinuxEngine;
+#[zbus::proxy(
+    interface = "org.a11y.atspi.DeviceEventController",
+    default_service = "org.a11y.atspi.Registry",
+    default_path = "/org/a11y/atspi/registry/deviceeventcontroller",
+    gen_async = false
+)]
+trait DeviceEventController {
+    fn generate_keyboard_event(
+        &self,
+        keycode: i32,
+        keystring: &str,
+        synth_type: u32,
+    ) -> zbus::Result<()>;
+    fn generate_mouse_event(&self, x: i32, y: i32, event_name: &str) -> zbus::Result<()>;
+}
+
+fn dbus_error(e: zbus::Error) -> AutomationError {
+    match e {
+        zbus::Error::MethodError(ref name, _, _)
+            if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied" =>
+        {
+            AutomationError::PermissionDenied(e.to_string())
+        }
+        _ => AutomationError::PlatformError(e.to_string()),
+    }
+}
+
+/// Connect to the accessibility bus, which is separate from the session bus.
+/// `AT_SPI_BUS_ADDRESS` overrides the address, as for the AT-SPI C library.
+fn connect_accessibility_bus() -> Result<Connection, AutomationError> {
+    let address = match std::env::var("AT_SPI_BUS_ADDRESS") {
+        Ok(address) if !address.is_empty() => address,
+        _ => {
+            let session = Connection::session().map_err(|e| {
+                AutomationError::UnsupportedPlatform(format!("no D-Bus session bus: {}", e))
+            })?;
+            BusProxy::new(&session)
+                .and_then(|bus| bus.get_address())
+                .map_err(|e| {
+                    AutomationError::UnsupportedPlatform(format!(
+                        "AT-SPI bus is not available, is at-spi2-core installed? {}",
+                        e
+                    ))
+                })?
+        }
+    };
+
+    zbus::blocking::connection::Builder::address(address.as_str())
+        .and_then(|builder| builder.build())
+        .map_err(|e| {
+            AutomationError::PlatformError(format!(
+                "failed to connect to the AT-SPI bus at {}: {}",
+                address, e
+            ))
+        })
+}
+
+/// AT-SPI role names that correspond to a generic role used in selectors
+pub(crate) fn map_generic_role_to_atspi_roles(role: &str) -> Vec<&'static str> {
+    match role.to_lowercase().as_str() {
+        "window" => vec!["frame", "window"],
+        "button" => vec!["push button", "toggle button", "button", "menu item"],
+        "checkbox" => vec!["check box", "check menu item"],
+        "menu" => vec!["menu"],
+        "menuitem" => vec![
+            "menu item",
+            "check menu item",
+            "radio menu item",
+            "tearoff menu item",
+        ],
+        "menubar" => vec!["menu bar"],
+        "dialog" => vec!["dialog", "alert", "file chooser", "color chooser"],
+        "text" | "textfield" | "input" | "textbox" => vec![
+            "entry",
+            "text",
+            "password text",
+            "editbar",
+            "terminal",
+            "combo box",
+        ],
+        "url" | "urlfield" => vec!["entry", "text"],
+        "list" => vec!["list", "list box", "tree", "tree table"],
+        "listitem" => vec!["list item", "tree item", "table cell"],
+        "combobox" => vec!["combo box"],
+        "tab" => vec!["page tab list"],
+        "tabitem" => vec!["page tab"],
+        "toolbar" => vec!["tool bar"],
+        "link" => vec!["link"],
+        "application" => vec!["application"],
+        _ => vec![],
+    }
+}
+
+/// Generic role reported for an AT-SPI role name, e.g. "push button" -> "button"
+pub(crate) fn atspi_role_to_generic_role(role_name: &str) -> String {
+    match role_name {
+        "frame" | "window" => "window",
+        "push button" | "toggle button" | "button" => "button",
+        "check box" => "checkbox",
+        "menu item" | "check menu item" | "radio menu item" | "tearoff menu item" => "menuitem",
+        "menu bar" => "menubar",
+        "alert" | "file chooser" | "color chooser" => "dialog",
+        "entry" | "text" | "password text" | "editbar" | "terminal" => "textfield",
+        "list box" | "tree" | "tree table" => "list",
+        "list item" | "tree item" | "table cell" => "listitem",
+        "combo box" => "combobox",
+        "page tab list" => "tab",
+        "page tab" => "tabitem",
+        "tool bar" => "toolbar",
+        other => return other.replace(' ', ""),
+    }
+    .to_string()
+}
+
+/// Whether an element with the given AT-SPI role name satisfies a selector role,
+/// which can be generic ("button") or a raw AT-SPI role name ("push button")
+pub(crate) fn role_matches(selector_role: &str, role_name: &str) -> bool {
+    let selector_role = selector_role.to_lowercase();
+    selector_role == role_name
+        || selector_role == atspi_role_to_generic_role(role_name)
+        || map_generic_role_to_atspi_roles(&selector_role).contains(&role_name)
+}
+
+/// Keysym for a named key, single characters are typed as strings instead
+pub(crate) fn key_to_keysym(key: &str) -> Option<i32> {
+    let keysym = match key.to_lowercase().as_str() {
+        "enter" | "return" => 0xff0d,
+        "tab" => 0xff09,
+        "escape" | "esc" => 0xff1b,
+        "backspace" => 0xff08,
+        "delete" | "del" => 0xffff,
+        "home" => 0xff50,
+        "left" | "arrowleft" => 0xff51,
+        "up" | "arrowup" => 0xff52,
+        "right" | "arrowright" => 0xff53,
+        "down" | "arrowdown" => 0xff54,
+        "pageup" => 0xff55,
+        "pagedown" => 0xff56,
+        "end" => 0xff57,
+        "space" => 0x20,
+        other => match other.strip_prefix('f').map(str::parse::<i32>) {
+            Some(Ok(n @ 1..=12)) => 0xffbe + n - 1,
+            _ => return None,
+        },
+    };
+    Some(keysym)
+}
+
+fn has_state(states: &[u32], state: u32) -> bool {
+    states
+        .get((state / 32) as usize)
+        .is_some_and(|word| word & (1 << (state % 32)) != 0)
+}
+
+pub struct LinuxEngine {
+    connection: Connection,
+    use_background_apps: bool,
+    activate_app: bool,
+}
 
 impl LinuxEngine {
     pub fn new(use_background_apps: bool, activate_app: bool) -> Result<Self, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let connection = connect_accessibility_bus()?;
+        let engine = Self {
+            connection,
+            use_background_apps,
+            activate_app,
+        };
+        // fail early when nothing answers on the registry
+        engine.root().child_count()?;
+        Ok(engine)
+    }
+
+    fn root(&self) -> LinuxUIElement {
+        self.element(REGISTRY_BUS.to_string(), ROOT_PATH.try_into().unwrap())
+    }
+
+    fn element(&self, bus: String, path: OwnedObjectPath) -> LinuxUIElement {
+        LinuxUIElement {
+            connection: self.connection.clone(),
+            bus,
+            path,
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        }
+    }
+
+    fn linux_element<'a>(
+        &self,
+        element: &'a UIElement,
+    ) -> Result<&'a LinuxUIElement, AutomationError> {
+        element
+            .as_any()
+            .downcast_ref::<LinuxUIElement>()
+            .ok_or_else(|| {
+                AutomationError::InvalidArgument("element does not come from AT-SPI".to_string())
+            })
+    }
+
+    fn matches(&self, element: &LinuxUIElement, selector: &Selector) -> bool {
+        match selector {
+            Selector::Role { role, name } => {
+                role_matches(role, &element.role_name())
+                    && match name {
+                        Some(name) => element.name().eq_ignore_ascii_case(name),
+                        None => true,
+                    }
+            }
+            Selector::Id(id) => element.id().as_deref() == Some(id.as_str()),
+            Selector::Name(name) => element.name() == *name,
+            Selector::Text(text) => {
+                element.name().contains(text.as_str())
+                    || element
+                        .text_content()
+                        .is_some_and(|t| t.contains(text.as_str()))
+            }
+            Selector::Attributes(attributes) => {
+                let actual = element.atspi_attributes();
+                attributes
+                    .iter()
+                    .all(|(key, value)| actual.get(key) == Some(value))
+            }
+            Selector::Path(_) | Selector::Filter(_) | Selector::Chain(_) => false,
+        }
+    }
+
+    /// Breadth first search below `root`, stops after `limit` matches
+    fn search(
+        &self,
+        selector: &Selector,
+        root: &LinuxUIElement,
+        limit: usize,
+    ) -> Vec<LinuxUIElement> {
+        let mut found = Vec::new();
+        let mut visited = 0;
+        let mut queue: VecDeque<(LinuxUIElement, usize)> = root
+            .child_elements()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| (child, 1))
+            .collect();
+
+        while let Some((element, depth)) = queue.pop_front() {
+            visited += 1;
+            if visited > MAX_SEARCH_NODES {
+                debug!(target: "operator", "Search for {:?} stopped after {} elements", selector, MAX_SEARCH_NODES);
+                break;
+            }
+            if self.matches(&element, selector) {
+                found.push(element.clone());
+                if found.len() >= limit {
+                    break;
+                }
+            }
+            if depth < MAX_SEARCH_DEPTH {
+                match element.child_elements() {
+                    Ok(children) => {
+                        queue.extend(children.into_iter().map(|child| (child, depth + 1)))
+                    }
+                    Err(e) => {
+                        trace!(target: "operator", "Skipping children of {:?}: {}", element, e)
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    fn find(
+        &self,
+        selector: &Selector,
+        root: Option<&UIElement>,
+        limit: usize,
+    ) -> Result<Vec<LinuxUIElement>, AutomationError> {
+        let root = match root {
+            Some(root) => self.linux_element(root)?.clone(),
+            None => self.root(),
+        };
+
+        match selector {
+            Selector::Chain(selectors) => {
+                let mut current = vec![root];
+                for (i, selector) in selectors.iter().enumerate() {
+                    let last = i + 1 == selectors.len();
+                    let mut next = Vec::new();
+                    let mut seen = HashSet::new();
+                    for element in &current {
+                        let remaining = if last { limit - next.len() } else { usize::MAX };
+                        for found in self.find(
+                            selector,
+                            Some(&UIElement::new(Box::new(element.clone()))),
+                            remaining,
+                        )? {
+                            if seen.insert(found.object_id()) {
+                                next.push(found);
+                            }
+                        }
+                        if last && next.len() >= limit {
+                            break;
+                        }
+                    }
+                    current = next;
+                }
+                Ok(current)
+            }
+            Selector::Path(_) | Selector::Filter(_) => Err(AutomationError::UnsupportedOperation(
+                format!("{:?} selectors are not supported on Linux", selector),
+            )),
+            _ => Ok(self.search(selector, &root, limit)),
+        }
     }
 }
 
 impl AccessibilityEngine for LinuxEngine {
     fn get_root_element(&self) -> UIElement {
-        panic!("Linux implementation is not yet available")
+        UIElement::new(Box::new(self.root()))
     }
 
     fn get_focused_element(&self) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
+        for app in self.root().child_elements()? {
+            let windows = match app.child_elements() {
+                Ok(windows) => windows,
+                Err(_) => continue,
+            };
+            for window in windows {
+                if !has_state(&window.states(), STATE_ACTIVE) {
+                    continue;
+                }
+                debug!(target: "operator", "Active window: {:?} of {:?}", window.name(), app.name());
+                let focused = self.find_focused(&window).unwrap_or(window);
+                return Ok(UIElement::new(Box::new(focused)));
+            }
+        }
+        Err(AutomationError::ElementNotFound(
+            "no active window found on the AT-SPI bus".to_string(),
         ))
     }
 
     fn get_applications(&self) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let apps = self.root().child_elements()?;
+        Ok(apps
+            .into_iter()
+            // applications without windows are background services
+            .filter(|app| self.use_background_apps || matches!(app.child_count(), Ok(n) if n > 0))
+            .map(|app| UIElement::new(Box::new(app)))
+            .collect())
     }
 
-    fn get_application_by_name(&self, _name: &str) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn get_application_by_name(&self, name: &str) -> Result<UIElement, AutomationError> {
+        self.root()
+            .child_elements()?
+            .into_iter()
+            .find(|app| app.name().eq_ignore_ascii_case(name))
+            .map(|app| UIElement::new(Box::new(app)))
+            .ok_or_else(|| {
+                AutomationError::ElementNotFound(format!("application '{}' not found", name))
+            })
     }
 
     fn find_element(
@@ -41,161 +478,491 @@ impl AccessibilityEngine for LinuxEngine {
         selector: &Selector,
         root: Option<&UIElement>,
     ) -> Result<UIElement, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.find(selector, root, 1)?
+            .into_iter()
+            .next()
+            .map(|element| UIElement::new(Box::new(element)))
+            .ok_or_else(|| {
+                AutomationError::ElementNotFound(format!("no element matches {:?}", selector))
+            })
     }
 
     fn find_elements(
         &self,
-        _selector: &Selector,
-        _root: Option<&UIElement>,
+        selector: &Selector,
+        root: Option<&UIElement>,
     ) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        Ok(self
+            .find(selector, root, usize::MAX)?
+            .into_iter()
+            .map(|element| UIElement::new(Box::new(element)))
+            .collect())
     }
 }
 
-// Placeholder LinuxUIElement that implements UIElementImpl
-pub struct LinuxUIElement;
+impl LinuxEngine {
+    /// Depth first walk for the element carrying the focused state
+    fn find_focused(&self, element: &LinuxUIElement) -> Option<LinuxUIElement> {
+        let mut stack = vec![(element.clone(), 0)];
+        let mut visited = 0;
+        while let Some((element, depth)) = stack.pop() {
+            visited += 1;
+            if visited > MAX_SEARCH_NODES {
+                return None;
+            }
+            if depth > 0 && has_state(&element.states(), STATE_FOCUSED) {
+                return Some(element);
+            }
+            // only showing subtrees can hold the focus
+            if depth > 0 && !has_state(&element.states(), STATE_SHOWING) {
+                continue;
+            }
+            if depth < MAX_SEARCH_DEPTH {
+                if let Ok(children) = element.child_elements() {
+                    stack.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// An accessible object, identified by the bus name of its application and its object path
+#[derive(Clone)]
+pub struct LinuxUIElement {
+    connection: Connection,
+    bus: String,
+    path: OwnedObjectPath,
+    use_background_apps: bool,
+    activate_app: bool,
+}
 
 impl Debug for LinuxUIElement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("LinuxUIElement").finish()
+        f.debug_struct("LinuxUIElement")
+            .field("bus", &self.bus)
+            .field("path", &self.path.as_str())
+            .finish()
+    }
+}
+
+impl LinuxUIElement {
+    fn proxy<'a, T>(&'a self) -> Result<T, AutomationError>
+    where
+        T: From<zbus::Proxy<'a>> + ProxyDefault,
+    {
+        zbus::blocking::proxy::Builder::<T>::new(&self.connection)
+            .destination(self.bus.as_str())
+            .and_then(|builder| builder.path(self.path.as_ref()))
+            .and_then(|builder| builder.cache_properties(CacheProperties::No).build())
+            .map_err(dbus_error)
+    }
+
+    fn accessible(&self) -> Result<AccessibleProxy<'_>, AutomationError> {
+        self.proxy()
+    }
+
+    fn wrap(&self, (bus, path): (String, OwnedObjectPath)) -> Option<LinuxUIElement> {
+        if path.as_str() == NULL_PATH || bus.is_empty() {
+            return None;
+        }
+        Some(LinuxUIElement {
+            connection: self.connection.clone(),
+            bus,
+            path,
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        })
+    }
+
+    fn child_elements(&self) -> Result<Vec<LinuxUIElement>, AutomationError> {
+        let children = self.accessible()?.get_children().map_err(dbus_error)?;
+        Ok(children
+            .into_iter()
+            .filter_map(|child| self.wrap(child))
+            .collect())
+    }
+
+    fn child_count(&self) -> Result<i32, AutomationError> {
+        self.accessible()?.child_count().map_err(dbus_error)
+    }
+
+    fn role_name(&self) -> String {
+        self.accessible()
+            .and_then(|a| a.get_role_name().map_err(dbus_error))
+            .unwrap_or_default()
+    }
+
+    fn name(&self) -> String {
+        self.accessible()
+            .and_then(|a| a.name().map_err(dbus_error))
+            .unwrap_or_default()
+    }
+
+    fn states(&self) -> Vec<u32> {
+        self.accessible()
+            .and_then(|a| a.get_state().map_err(dbus_error))
+            .unwrap_or_default()
+    }
+
+    fn atspi_attributes(&self) -> HashMap<String, String> {
+        self.accessible()
+            .and_then(|a| a.get_attributes().map_err(dbus_error))
+            .unwrap_or_default()
+    }
+
+    fn interfaces(&self) -> Vec<String> {
+        self.accessible()
+            .and_then(|a| a.get_interfaces().map_err(dbus_error))
+            .unwrap_or_default()
+    }
+
+    /// Full contents of the Text interface, if the element implements it
+    fn text_content(&self) -> Option<String> {
+        let text: TextProxy<'_> = self.proxy().ok()?;
+        let count = text.character_count().ok()?;
+        text.get_text(0, count).ok().filter(|t| !t.is_empty())
+    }
+
+    fn center(&self) -> Result<(i32, i32), AutomationError> {
+        let (x, y, width, height) = self.bounds()?;
+        if width <= 0.0 || height <= 0.0 {
+            return Err(AutomationError::UnsupportedOperation(
+                "element has no on-screen extents".to_string(),
+            ));
+        }
+        Ok(((x + width / 2.0) as i32, (y + height / 2.0) as i32))
+    }
+
+    fn device_event_controller(&self) -> Result<DeviceEventControllerProxy<'_>, AutomationError> {
+        zbus::blocking::proxy::Builder::<DeviceEventControllerProxy>::new(&self.connection)
+            .destination(REGISTRY_BUS)
+            .and_then(|builder| builder.path(DEVICE_EVENT_CONTROLLER_PATH))
+            .and_then(|builder| builder.cache_properties(CacheProperties::No).build())
+            .map_err(dbus_error)
+    }
+
+    /// Synthesize a mouse event ("b1c", "b1d", "b3c", "abs") at the element center
+    fn mouse_event(&self, event_name: &str) -> Result<(), AutomationError> {
+        let (x, y) = self.center()?;
+        debug!(target: "operator", "Mouse event {} at ({}, {})", event_name, x, y);
+        self.device_event_controller()?
+            .generate_mouse_event(x, y, event_name)
+            .map_err(dbus_error)
+    }
+
+    /// Index of the first action whose name is one of `names`
+    fn action_index(&self, names: &[&str]) -> Option<i32> {
+        let action: ActionProxy<'_> = self.proxy().ok()?;
+        let actions = action.get_actions().ok()?;
+        actions
+            .iter()
+            .position(|(name, _, _)| names.iter().any(|n| name.eq_ignore_ascii_case(n)))
+            .map(|i| i as i32)
+    }
+
+    fn do_action(&self, index: i32) -> Result<(), AutomationError> {
+        let action: ActionProxy<'_> = self.proxy()?;
+        if action.do_action(index).map_err(dbus_error)? {
+            Ok(())
+        } else {
+            Err(AutomationError::PlatformError(format!(
+                "action {} was rejected by the application",
+                index
+            )))
+        }
+    }
+
+    fn activate_window(&self) {
+        if !self.activate_app {
+            return;
+        }
+        let mut current = self.clone();
+        // climb to the top-level window, whose parent is the application
+        while let Ok(Some(parent)) = current.parent_element() {
+            if parent.role_name() == "application" {
+                break;
+            }
+            current = parent;
+        }
+        let focused = current
+            .proxy::<ComponentProxy<'_>>()
+            .map(|component| component.grab_focus());
+        debug!(target: "operator", "Activated window {:?}: {:?}", current, focused);
+    }
+
+    fn parent_element(&self) -> Result<Option<LinuxUIElement>, AutomationError> {
+        let parent = self.accessible()?.parent().map_err(dbus_error)?;
+        Ok(self.wrap(parent))
+    }
+
+    fn collect_text(
+        &self,
+        depth: usize,
+        max_depth: usize,
+        budget: &mut usize,
+        parts: &mut Vec<String>,
+    ) {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        let name = self.name();
+        if !name.is_empty() {
+            parts.push(name);
+        }
+        if let Some(text) = self.text_content() {
+            if parts.last() != Some(&text) {
+                parts.push(text);
+            }
+        }
+        if depth >= max_depth {
+            return;
+        }
+        if let Ok(children) = self.child_elements() {
+            for child in children {
+                child.collect_text(depth + 1, max_depth, budget, parts);
+            }
+        }
     }
 }
 
 impl UIElementImpl for LinuxUIElement {
     fn object_id(&self) -> usize {
-        0
+        let mut hasher = DefaultHasher::new();
+        self.bus.hash(&mut hasher);
+        self.path.as_str().hash(&mut hasher);
+        hasher.finish() as usize
     }
 
     fn id(&self) -> Option<String> {
-        None
+        // AccessibleId is only exposed by recent toolkits, fall back to the "id" attribute
+        self.accessible()
+            .ok()
+            .and_then(|a| a.accessible_id().ok())
+            .filter(|id| !id.is_empty())
+            .or_else(|| self.atspi_attributes().remove("id"))
     }
 
     fn role(&self) -> String {
-        "".to_string()
+        atspi_role_to_generic_role(&self.role_name())
     }
 
     fn attributes(&self) -> UIElementAttributes {
+        let role_name = self.role_name();
+        let description = self
+            .accessible()
+            .and_then(|a| a.description().map_err(dbus_error))
+            .ok()
+            .filter(|d| !d.is_empty());
+        let value = self.text_content().or_else(|| {
+            if !self.interfaces().iter().any(|i| i == VALUE_INTERFACE) {
+                return None;
+            }
+            self.proxy::<ValueProxy<'_>>()
+                .ok()
+                .and_then(|v| v.current_value().ok())
+                .map(|v| v.to_string())
+        });
+
+        let mut properties: HashMap<String, Option<serde_json::Value>> = self
+            .atspi_attributes()
+            .into_iter()
+            .map(|(key, value)| (key, Some(serde_json::Value::String(value))))
+            .collect();
+        properties.insert(
+            "AtspiRole".to_string(),
+            Some(serde_json::Value::String(role_name.clone())),
+        );
+
         UIElementAttributes {
-            role: "".to_string(),
-            label: None,
-            value: None,
-            description: None,
-            properties: std::collections::HashMap::new(),
+            role: atspi_role_to_generic_role(&role_name),
+            label: Some(self.name()).filter(|n| !n.is_empty()),
+            value,
+            description,
+            properties,
         }
     }
 
     fn children(&self) -> Result<Vec<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        Ok(self
+            .child_elements()?
+            .into_iter()
+            .map(|child| UIElement::new(Box::new(child)))
+            .collect())
     }
 
     fn parent(&self) -> Result<Option<UIElement>, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        Ok(self
+            .parent_element()?
+            .map(|parent| UIElement::new(Box::new(parent))))
     }
 
     fn bounds(&self) -> Result<(f64, f64, f64, f64), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let component: ComponentProxy<'_> = self.proxy()?;
+        let (x, y, width, height) = component
+            .get_extents(COORD_TYPE_SCREEN)
+            .map_err(dbus_error)?;
+        Ok((x as f64, y as f64, width as f64, height as f64))
     }
 
     fn click(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.activate_window();
+        // prefer the toolkit's own action, it works without a pointer and on Wayland
+        if let Some(index) = self.action_index(&["click", "press", "activate", "jump", "toggle"]) {
+            debug!(target: "operator", "Clicking {:?} through action {}", self, index);
+            return self.do_action(index);
+        }
+        self.mouse_event("b1c")
     }
 
     fn double_click(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.activate_window();
+        self.mouse_event("b1d")
     }
 
     fn right_click(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.activate_window();
+        if let Some(index) = self.action_index(&["showmenu", "show menu", "popup"]) {
+            return self.do_action(index);
+        }
+        self.mouse_event("b3c")
     }
 
     fn hover(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.mouse_event("abs")
     }
 
     fn focus(&self) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        self.activate_window();
+        let component: ComponentProxy<'_> = self.proxy()?;
+        if component.grab_focus().map_err(dbus_error)? {
+            Ok(())
+        } else {
+            Err(AutomationError::PlatformError(
+                "element refused to take the focus".to_string(),
+            ))
+        }
     }
 
-    fn type_text(&self, _text: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn type_text(&self, text: &str) -> Result<(), AutomationError> {
+        if let Err(e) = self.focus() {
+            debug!(target: "operator", "Focus failed, but continuing with type_text: {:?}", e);
+        }
+
+        if self
+            .interfaces()
+            .iter()
+            .any(|i| i == EDITABLE_TEXT_INTERFACE)
+        {
+            let editable: EditableTextProxy<'_> = self.proxy()?;
+            let position = self
+                .proxy::<TextProxy<'_>>()
+                .ok()
+                .and_then(|t| t.caret_offset().ok())
+                .filter(|offset| *offset >= 0)
+                .unwrap_or(0);
+            let length = text.chars().count() as i32;
+            if editable
+                .insert_text(position, text, length)
+                .map_err(dbus_error)?
+            {
+                return Ok(());
+            }
+            debug!(target: "operator", "InsertText was rejected, synthesizing key events");
+        }
+
+        self.device_event_controller()?
+            .generate_keyboard_event(0, text, KEY_STRING)
+            .map_err(dbus_error)
     }
 
-    fn press_key(&self, _key: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn press_key(&self, key: &str) -> Result<(), AutomationError> {
+        let controller = self.device_event_controller()?;
+        if let Some(keysym) = key_to_keysym(key) {
+            return controller
+                .generate_keyboard_event(keysym, "", KEY_SYM)
+                .map_err(dbus_error);
+        }
+        if key.chars().count() == 1 {
+            return controller
+                .generate_keyboard_event(0, key, KEY_STRING)
+                .map_err(dbus_error);
+        }
+        Err(AutomationError::InvalidArgument(format!(
+            "unknown key: {}",
+            key
+        )))
     }
 
     fn get_text(&self, max_depth: usize) -> Result<String, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let mut parts = Vec::new();
+        let mut budget = MAX_SEARCH_NODES;
+        self.collect_text(0, max_depth, &mut budget, &mut parts);
+        Ok(parts.join("\n"))
     }
 
-    fn set_value(&self, _value: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn set_value(&self, value: &str) -> Result<(), AutomationError> {
+        let interfaces = self.interfaces();
+        if interfaces.iter().any(|i| i == EDITABLE_TEXT_INTERFACE) {
+            let editable: EditableTextProxy<'_> = self.proxy()?;
+            if editable.set_text_contents(value).map_err(dbus_error)? {
+                return Ok(());
+            }
+        }
+        if interfaces.iter().any(|i| i == VALUE_INTERFACE) {
+            let number: f64 = value.parse().map_err(|_| {
+                AutomationError::InvalidArgument(format!("'{}' is not a number", value))
+            })?;
+            let proxy: ValueProxy<'_> = self.proxy()?;
+            return proxy.set_current_value(number).map_err(dbus_error);
+        }
+        Err(AutomationError::UnsupportedOperation(format!(
+            "{:?} has no editable value",
+            self
+        )))
     }
 
     fn is_enabled(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let states = self.accessible()?.get_state().map_err(dbus_error)?;
+        Ok(has_state(&states, STATE_ENABLED) || has_state(&states, STATE_SENSITIVE))
     }
 
     fn is_visible(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let states = self.accessible()?.get_state().map_err(dbus_error)?;
+        Ok(has_state(&states, STATE_VISIBLE) && has_state(&states, STATE_SHOWING))
     }
 
     fn is_focused(&self) -> Result<bool, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+        let states = self.accessible()?.get_state().map_err(dbus_error)?;
+        Ok(has_state(&states, STATE_FOCUSED))
     }
 
-    fn perform_action(&self, _action: &str) -> Result<(), AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn perform_action(&self, action: &str) -> Result<(), AutomationError> {
+        match self.action_index(&[action]) {
+            Some(index) => self.do_action(index),
+            None => Err(AutomationError::UnsupportedOperation(format!(
+                "{:?} has no action named '{}'",
+                self, action
+            ))),
+        }
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
 
-    fn create_locator(&self, _selector: Selector) -> Result<Locator, AutomationError> {
-        Err(AutomationError::UnsupportedPlatform(
-            "Linux implementation is not yet available".to_string(),
-        ))
+    fn create_locator(&self, selector: Selector) -> Result<Locator, AutomationError> {
+        let engine = LinuxEngine {
+            connection: self.connection.clone(),
+            use_background_apps: self.use_background_apps,
+            activate_app: self.activate_app,
+        };
+        let root = UIElement::new(Box::new(self.clone()));
+        Ok(Locator::new(Arc::new(engine), selector).within(root))
     }
 
     fn clone_box(&self) -> Box<dyn UIElementImpl> {
-        Box::new(LinuxUIElement)
+        Box::new(self.clone())
     }
 }
@@ -34,7 +34,7 @@ pub trait AccessibilityEngine: Send + Sync {
 }
 
 #[cfg(target_os = "linux")]
-mod linux;
+pub(crate) mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
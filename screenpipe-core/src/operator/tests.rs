@@ -259,4 +259,100 @@ mod tests {
             // println!("children: {:?}", children.len());
         }
     }
+
+    #[cfg(target_os = "linux")]
+    mod linux_tests {
+        use crate::operator::platforms::linux::{
+            atspi_role_to_generic_role, key_to_keysym, map_generic_role_to_atspi_roles,
+            role_matches,
+        };
+        use crate::Desktop;
+
+        use super::*;
+
+        fn setup_tracing() {
+            let filter = EnvFilter::from_default_env()
+                .add_directive(LevelFilter::DEBUG.into())
+                .add_directive("operator=debug".parse().unwrap());
+
+            tracing_subscriber::registry()
+                .with(fmt::layer())
+                .with(filter)
+                .try_init()
+                .unwrap_or_default();
+        }
+
+        #[test]
+        fn test_atspi_role_mapping() {
+            assert_eq!(atspi_role_to_generic_role("push button"), "button");
+            assert_eq!(atspi_role_to_generic_role("frame"), "window");
+            assert_eq!(atspi_role_to_generic_role("password text"), "textfield");
+            assert_eq!(atspi_role_to_generic_role("scroll pane"), "scrollpane");
+
+            assert!(map_generic_role_to_atspi_roles("textfield").contains(&"entry"));
+            assert!(map_generic_role_to_atspi_roles("Window").contains(&"frame"));
+            assert!(map_generic_role_to_atspi_roles("unknown").is_empty());
+
+            assert!(role_matches("button", "push button"));
+            assert!(role_matches("push button", "push button"));
+            assert!(role_matches("scrollpane", "scroll pane"));
+            assert!(role_matches("input", "text"));
+            assert!(!role_matches("button", "label"));
+        }
+
+        #[test]
+        fn test_key_to_keysym() {
+            assert_eq!(key_to_keysym("Enter"), Some(0xff0d));
+            assert_eq!(key_to_keysym("escape"), Some(0xff1b));
+            assert_eq!(key_to_keysym("F1"), Some(0xffbe));
+            assert_eq!(key_to_keysym("f12"), Some(0xffc9));
+            assert_eq!(key_to_keysym("f13"), None);
+            assert_eq!(key_to_keysym("a"), None);
+        }
+
+        // needs a running AT-SPI bus with an accessible application, e.g.
+        // `dbus-run-session -- sh -c "gtk4-demo & cargo test -- --ignored linux_tests"`
+        #[test]
+        #[ignore]
+        fn test_traverse_and_find_elements() {
+            setup_tracing();
+
+            let desktop = match Desktop::new(false, false) {
+                Ok(d) => d,
+                Err(e) => {
+                    println!("Failed to create Desktop automation: {:?}", e);
+                    return;
+                }
+            };
+
+            let apps = desktop.applications().unwrap();
+            assert!(!apps.is_empty(), "no accessible application is running");
+            for app in &apps {
+                println!("App: {:?} ({})", app.attributes().label, app.role());
+            }
+
+            let window = desktop.locator("window").first().unwrap().unwrap();
+            assert_eq!(window.role(), "window");
+            let text = window.text(5).unwrap();
+            println!("Window text: {:?}", text);
+
+            let buttons = window.locator("button").unwrap().all().unwrap();
+            println!("Found {} buttons", buttons.len());
+            for button in buttons.iter().take(5) {
+                println!(
+                    "Button: {:?} at {:?}",
+                    button.attributes().label,
+                    button.bounds()
+                );
+            }
+
+            if let Ok(focused) = desktop.focused_element() {
+                println!(
+                    "Focused: {:?} {:?}",
+                    focused.role(),
+                    focused.attributes().label
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,319 @@
+//! Usage analytics: screen time per app, window and browser domain from the timestamps of the
+//! focused frames of each device, and talk time per speaker from the transcription segments.
+//! Both are rolled up per hour, the hours touched by new or changed recordings are recomputed
+//! in the background and the recent ones also on request.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::{DatabaseManager, HistogramInterval, ScreenTime, TalkTime, UsageBucket, UsageGroup};
+
+/// A frame counts until the next one, at most this many seconds. Longer gaps are the recorder
+/// being stopped or the machine asleep rather than time spent on the frame.
+pub const MAX_FRAME_GAP_SECS: f64 = 60.0;
+
+const HOUR_FORMAT: &str = "%Y-%m-%dT%H:00:00Z";
+
+/// Device, timestamp, app, window and url of a focused frame
+type FocusedFrame = (String, DateTime<Utc>, String, String, Option<String>);
+
+/// Host of a url, lowercased, like the `browser_domain` search facet
+fn url_domain(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or_default().to_lowercase()
+}
+
+/// Rollup bounds of a time range: hours starting in it or containing its start
+fn hour_range(start: DateTime<Utc>, end: DateTime<Utc>) -> (String, String) {
+    (
+        start.format(HOUR_FORMAT).to_string(),
+        end.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    )
+}
+
+impl DatabaseManager {
+    /// Recomputes the rollups of the hours whose recordings changed, starting at the hour of
+    /// `since` when given, and returns how many hours were refreshed. An hour stays marked
+    /// while the last focused frame of a device has no next one yet.
+    pub async fn refresh_usage_rollups(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<usize, sqlx::Error> {
+        let since = since.map(|since| since.format(HOUR_FORMAT).to_string());
+        let hours: Vec<String> = sqlx::query_scalar(
+            "SELECT hour FROM usage_dirty_hours WHERE ?1 IS NULL OR hour >= ?1 ORDER BY hour",
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        let newest: Option<DateTime<Utc>> = sqlx::query_scalar("SELECT MAX(timestamp) FROM frames")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut refreshed = 0;
+        for hour in &hours {
+            let mut tx = self.pool.begin().await?;
+            let claimed = sqlx::query("DELETE FROM usage_dirty_hours WHERE hour = ?1")
+                .bind(hour)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if claimed == 0 {
+                // refreshed meanwhile by another caller
+                tx.commit().await?;
+                continue;
+            }
+            refreshed += 1;
+            sqlx::query("DELETE FROM screen_time_rollups WHERE hour = ?1")
+                .bind(hour)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM talk_time_rollups WHERE hour = ?1")
+                .bind(hour)
+                .execute(&mut *tx)
+                .await?;
+            let Ok(start) = DateTime::parse_from_rfc3339(hour) else {
+                tx.commit().await?;
+                continue;
+            };
+            let start = start.with_timezone(&Utc);
+            let end = start + chrono::Duration::hours(1);
+
+            // one frame is recorded per window, only the focused one is screen time. Frames
+            // recorded before windows were captured separately have no focus flag and stand
+            // for the whole screen.
+            let frames: Vec<FocusedFrame> = sqlx::query_as(
+                r#"
+                SELECT COALESCE(vc.device_name, ''), f.timestamp, COALESCE(f.app_name, ''),
+                    COALESCE(f.window_name, ''), f.browser_url
+                FROM frames f
+                LEFT JOIN video_chunks vc ON vc.id = f.video_chunk_id
+                WHERE f.timestamp >= ?1 AND f.timestamp < ?2 AND COALESCE(f.focused, 1) = 1
+                ORDER BY 1, f.timestamp
+                "#,
+            )
+            .bind(start)
+            .bind(end)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let mut screen_time: HashMap<(String, String, String), (f64, i64)> = HashMap::new();
+            let mut open = false;
+            for device_frames in frames.chunk_by(|a, b| a.0 == b.0) {
+                let device = &device_frames[0].0;
+                let next: Option<DateTime<Utc>> = sqlx::query_scalar(
+                    r#"
+                    SELECT f.timestamp
+                    FROM frames f
+                    LEFT JOIN video_chunks vc ON vc.id = f.video_chunk_id
+                    WHERE f.timestamp >= ?1 AND COALESCE(vc.device_name, '') = ?2
+                        AND COALESCE(f.focused, 1) = 1
+                    ORDER BY f.timestamp
+                    LIMIT 1
+                    "#,
+                )
+                .bind(end)
+                .bind(device)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                let next_timestamps = device_frames
+                    .iter()
+                    .skip(1)
+                    .map(|frame| Some(frame.1))
+                    .chain(std::iter::once(next));
+                for ((_, timestamp, app_name, window_name, browser_url), next) in
+                    device_frames.iter().zip(next_timestamps)
+                {
+                    let seconds = match next {
+                        Some(next) => (next - *timestamp).num_milliseconds() as f64 / 1000.0,
+                        // the device stopped while others kept recording, a later frame
+                        // would be capped to the same time
+                        None if newest.is_some_and(|newest| {
+                            (newest - *timestamp).num_seconds() as f64 > MAX_FRAME_GAP_SECS
+                        }) =>
+                        {
+                            MAX_FRAME_GAP_SECS
+                        }
+                        None => {
+                            open = true;
+                            break;
+                        }
+                    }
+                    .clamp(0.0, MAX_FRAME_GAP_SECS);
+                    let domain = browser_url
+                        .as_deref()
+                        .filter(|url| !url.is_empty())
+                        .map(url_domain)
+                        .unwrap_or_default();
+                    let entry = screen_time
+                        .entry((app_name.clone(), window_name.clone(), domain))
+                        .or_default();
+                    entry.0 += seconds;
+                    entry.1 += 1;
+                }
+            }
+            for ((app_name, window_name, domain), (seconds, frames)) in screen_time {
+                sqlx::query(
+                    "INSERT INTO screen_time_rollups (hour, app_name, window_name, browser_domain, seconds, frames) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .bind(hour)
+                .bind(app_name)
+                .bind(window_name)
+                .bind(domain)
+                .bind(seconds)
+                .bind(frames)
+                .execute(&mut *tx)
+                .await?;
+            }
+            if open {
+                sqlx::query("INSERT OR IGNORE INTO usage_dirty_hours (hour) VALUES (?1)")
+                    .bind(hour)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO talk_time_rollups (hour, speaker_id, seconds, segments)
+                SELECT ?1, speaker_id, SUM(MAX(end_time - start_time, 0)), COUNT(*)
+                FROM audio_transcriptions
+                WHERE timestamp >= ?2 AND timestamp < ?3
+                    AND start_time IS NOT NULL AND end_time IS NOT NULL
+                GROUP BY speaker_id
+                "#,
+            )
+            .bind(hour)
+            .bind(start)
+            .bind(end)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+        Ok(refreshed)
+    }
+
+    /// Screen time in a range grouped by app, window or domain, longest first. The range is
+    /// rounded to whole hours, `app_name` restricts it to one app.
+    pub async fn screen_time(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        group_by: UsageGroup,
+        app_name: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<ScreenTime>, sqlx::Error> {
+        let (value, app, group, filter) = match group_by {
+            UsageGroup::App => ("app_name", "NULL", "app_name", "app_name != ''"),
+            UsageGroup::Window => (
+                "window_name",
+                "app_name",
+                "app_name, window_name",
+                "window_name != ''",
+            ),
+            UsageGroup::Domain => (
+                "browser_domain",
+                "NULL",
+                "browser_domain",
+                "browser_domain != ''",
+            ),
+        };
+        let sql = format!(
+            r#"
+            SELECT {value}, {app}, SUM(seconds) AS total, SUM(frames)
+            FROM screen_time_rollups
+            WHERE hour >= ?1 AND hour < ?2 AND (?3 IS NULL OR app_name = ?3) AND {filter}
+            GROUP BY {group}
+            ORDER BY total DESC
+            LIMIT ?4
+            "#
+        );
+        let (start, end) = hour_range(start, end);
+        let rows: Vec<(String, Option<String>, f64, i64)> = sqlx::query_as(&sql)
+            .bind(start)
+            .bind(end)
+            .bind(app_name)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(value, app_name, seconds, frames)| ScreenTime {
+                value,
+                app_name,
+                seconds,
+                frames,
+            })
+            .collect())
+    }
+
+    /// Screen time in a range per hour or day, oldest first
+    pub async fn screen_time_histogram(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        interval: HistogramInterval,
+        app_name: Option<&str>,
+    ) -> Result<Vec<UsageBucket>, sqlx::Error> {
+        let sql = format!(
+            r#"
+            SELECT strftime('{}', hour) AS bucket, SUM(seconds)
+            FROM screen_time_rollups
+            WHERE hour >= ?1 AND hour < ?2 AND (?3 IS NULL OR app_name = ?3)
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+            interval.strftime_format()
+        );
+        let (start, end) = hour_range(start, end);
+        let rows: Vec<(String, f64)> = sqlx::query_as(&sql)
+            .bind(start)
+            .bind(end)
+            .bind(app_name)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(bucket, seconds)| {
+                let start = DateTime::parse_from_rfc3339(&bucket).ok()?;
+                Some(UsageBucket {
+                    start: start.with_timezone(&Utc),
+                    seconds,
+                })
+            })
+            .collect())
+    }
+
+    /// Talk time per speaker in a range rounded to whole hours, longest first
+    pub async fn talk_time(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<TalkTime>, sqlx::Error> {
+        let (start, end) = hour_range(start, end);
+        let rows: Vec<(Option<i64>, Option<String>, f64, i64)> = sqlx::query_as(
+            r#"
+            SELECT r.speaker_id, s.name, SUM(r.seconds) AS total, SUM(r.segments)
+            FROM talk_time_rollups r
+            LEFT JOIN speakers s ON s.id = r.speaker_id
+            WHERE r.hour >= ?1 AND r.hour < ?2
+            GROUP BY r.speaker_id
+            ORDER BY total DESC
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(speaker_id, speaker_name, seconds, segments)| TalkTime {
+                speaker_id,
+                speaker_name,
+                seconds,
+                segments,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,893 @@
+//! Portable archives of the recordings: a directory with a `manifest.json`, one NDJSON file per
+//! table and the media files the rows point to. Imports verify every checksum before writing,
+//! give the rows new ids and skip the rows already in the database.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteArguments, SqliteConnection, SqliteRow};
+use sqlx::{Column, Row, Sqlite, TypeInfo, ValueRef};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+use crate::{
+    ArchiveContent, ArchiveFilter, ArchiveManifest, ArchiveMediaEntry, ArchiveTableEntry,
+    DatabaseManager, ImportReport, ImportTableReport,
+};
+
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+pub const ARCHIVE_MANIFEST: &str = "manifest.json";
+
+/// Rows read or written per query and per import transaction
+pub(crate) const ARCHIVE_BATCH_SIZE: i64 = 1000;
+/// Key of the JSON object holding a hex encoded blob
+const BLOB_KEY: &str = "$blob";
+/// Key of the id a row sent back to the database it came from has there
+pub(crate) const ORIGIN_KEY: &str = "$origin_id";
+/// Key of the rowid of a row without an id in a sync batch
+pub(crate) const ROWID_KEY: &str = "$rowid";
+
+// ?1 start, ?2 end and ?3 JSON array of app names, NULL when not filtered
+const FRAMES_FILTER: &str = "(?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2) \
+    AND (?3 IS NULL OR app_name IN (SELECT value FROM json_each(?3)))";
+const AUDIO_FILTER: &str = "(?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2)";
+const UI_FILTER: &str = "(?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2) \
+    AND (?3 IS NULL OR app IN (SELECT value FROM json_each(?3)))";
+
+pub(crate) struct ArchiveTable {
+    pub(crate) name: &'static str,
+    /// None for the tags shared by every content
+    pub(crate) content: Option<ArchiveContent>,
+    /// Rows exported, `{frames}`, `{audio}` and `{ui}` stand for the filters above
+    pub(crate) filter: &'static str,
+    /// Whether the rows have an `id` that imports replace
+    pub(crate) has_id: bool,
+    /// Columns holding the id of a row of another table
+    pub(crate) references: &'static [(&'static str, &'static str)],
+    /// Columns identifying a row already in the database
+    pub(crate) unique: &'static [&'static str],
+    /// Column holding the path of a media file
+    pub(crate) media: Option<&'static str>,
+}
+
+/// Tables in import order, parents before the rows referencing them. Full text indexes and
+/// rollups are left out, the triggers fill them as the rows are imported.
+pub(crate) const ARCHIVE_TABLES: &[ArchiveTable] = &[
+    ArchiveTable {
+        name: "tags",
+        content: None,
+        filter: "",
+        has_id: true,
+        references: &[],
+        unique: &["name"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "video_chunks",
+        content: Some(ArchiveContent::Ocr),
+        filter: "id IN (SELECT video_chunk_id FROM frames WHERE {frames})",
+        has_id: true,
+        references: &[],
+        unique: &["file_path"],
+        media: Some("file_path"),
+    },
+    ArchiveTable {
+        name: "frames",
+        content: Some(ArchiveContent::Ocr),
+        filter: "{frames}",
+        has_id: true,
+        references: &[("video_chunk_id", "video_chunks")],
+        unique: &["video_chunk_id", "offset_index", "timestamp"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "ocr_text",
+        content: Some(ArchiveContent::Ocr),
+        filter: "frame_id IN (SELECT id FROM frames WHERE {frames})",
+        has_id: false,
+        references: &[("frame_id", "frames")],
+        unique: &["frame_id"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "ocr_text_embeddings",
+        content: Some(ArchiveContent::Ocr),
+        filter: "frame_id IN (SELECT id FROM frames WHERE {frames})",
+        has_id: true,
+        references: &[("frame_id", "frames")],
+        unique: &["frame_id"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "vision_tags",
+        content: Some(ArchiveContent::Ocr),
+        filter: "vision_id IN (SELECT id FROM frames WHERE {frames})",
+        has_id: false,
+        references: &[("vision_id", "frames"), ("tag_id", "tags")],
+        unique: &["vision_id", "tag_id"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "speakers",
+        content: Some(ArchiveContent::Audio),
+        filter: "id IN (SELECT speaker_id FROM audio_transcriptions WHERE {audio})",
+        has_id: true,
+        references: &[],
+        unique: &[],
+        media: None,
+    },
+    ArchiveTable {
+        name: "speaker_embeddings",
+        content: Some(ArchiveContent::Audio),
+        filter: "speaker_id IN (SELECT speaker_id FROM audio_transcriptions WHERE {audio})",
+        has_id: true,
+        references: &[("speaker_id", "speakers")],
+        unique: &[],
+        media: None,
+    },
+    ArchiveTable {
+        name: "audio_chunks",
+        content: Some(ArchiveContent::Audio),
+        filter: "id IN (SELECT audio_chunk_id FROM audio_transcriptions WHERE {audio})",
+        has_id: true,
+        references: &[],
+        unique: &["file_path"],
+        media: Some("file_path"),
+    },
+    ArchiveTable {
+        name: "audio_transcriptions",
+        content: Some(ArchiveContent::Audio),
+        filter: "{audio}",
+        has_id: true,
+        references: &[
+            ("audio_chunk_id", "audio_chunks"),
+            ("speaker_id", "speakers"),
+        ],
+        unique: &["audio_chunk_id", "offset_index", "transcription"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "audio_tags",
+        content: Some(ArchiveContent::Audio),
+        filter: "audio_chunk_id IN (SELECT audio_chunk_id FROM audio_transcriptions WHERE {audio})",
+        has_id: false,
+        references: &[("audio_chunk_id", "audio_chunks"), ("tag_id", "tags")],
+        unique: &["audio_chunk_id", "tag_id"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "ui_monitoring",
+        content: Some(ArchiveContent::Ui),
+        filter: "{ui}",
+        has_id: true,
+        references: &[],
+        unique: &["timestamp", "app", "window"],
+        media: None,
+    },
+    ArchiveTable {
+        name: "ui_monitoring_tags",
+        content: Some(ArchiveContent::Ui),
+        filter: "ui_monitoring_id IN (SELECT id FROM ui_monitoring WHERE {ui})",
+        has_id: false,
+        references: &[("ui_monitoring_id", "ui_monitoring"), ("tag_id", "tags")],
+        unique: &["ui_monitoring_id", "tag_id"],
+        media: None,
+    },
+];
+
+fn expand_filter(filter: &str) -> String {
+    filter
+        .replace("{frames}", FRAMES_FILTER)
+        .replace("{audio}", AUDIO_FILTER)
+        .replace("{ui}", UI_FILTER)
+}
+
+/// WHERE clause of the rows of a table to export, None when the filter excludes the table
+fn export_condition(table: &ArchiveTable, filter: &ArchiveFilter) -> Option<String> {
+    if let Some(content) = table.content {
+        return filter
+            .includes(content)
+            .then(|| expand_filter(table.filter));
+    }
+    // tags of the exported rows of every included content
+    let links = [
+        (
+            ArchiveContent::Ocr,
+            "id IN (SELECT tag_id FROM vision_tags WHERE vision_id IN (SELECT id FROM frames WHERE {frames}))",
+        ),
+        (
+            ArchiveContent::Audio,
+            "id IN (SELECT tag_id FROM audio_tags WHERE audio_chunk_id IN (SELECT audio_chunk_id FROM audio_transcriptions WHERE {audio}))",
+        ),
+        (
+            ArchiveContent::Ui,
+            "id IN (SELECT tag_id FROM ui_monitoring_tags WHERE ui_monitoring_id IN (SELECT id FROM ui_monitoring WHERE {ui}))",
+        ),
+    ];
+    let conditions: Vec<String> = links
+        .iter()
+        .filter(|(content, _)| filter.includes(*content))
+        .map(|(_, condition)| expand_filter(condition))
+        .collect();
+    (!conditions.is_empty()).then(|| conditions.join(" OR "))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow!("invalid hex blob"))
+        })
+        .collect()
+}
+
+/// Columns of a row as JSON, blobs become `{"$blob": "<hex>"}`
+fn row_to_json(row: &SqliteRow) -> Map<String, Value> {
+    let mut values = Map::new();
+    for (i, column) in row.columns().iter().enumerate() {
+        if column.name() == "archive_rowid" {
+            continue;
+        }
+        let Ok(raw) = row.try_get_raw(i) else {
+            continue;
+        };
+        let value = if raw.is_null() {
+            Value::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" => row
+                    .try_get_unchecked::<i64, _>(i)
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+                "REAL" => row
+                    .try_get_unchecked::<f64, _>(i)
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+                "BLOB" => {
+                    let bytes: Vec<u8> = row.try_get_unchecked(i).unwrap_or_default();
+                    let mut blob = Map::new();
+                    blob.insert(BLOB_KEY.to_string(), Value::String(to_hex(&bytes)));
+                    Value::Object(blob)
+                }
+                _ => row
+                    .try_get_unchecked::<String, _>(i)
+                    .map(Value::String)
+                    .unwrap_or(Value::Null),
+            }
+        };
+        values.insert(column.name().to_string(), value);
+    }
+    values
+}
+
+fn bind_json<'q>(
+    query: sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+) -> Result<sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>> {
+    Ok(match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(value) => query.bind(*value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => query.bind(value),
+            None => query.bind(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => query.bind(value.clone()),
+        Value::Object(object) => match object.get(BLOB_KEY) {
+            Some(Value::String(hex)) => query.bind(from_hex(hex)?),
+            _ => query.bind(value.to_string()),
+        },
+        Value::Array(_) => query.bind(value.to_string()),
+    })
+}
+
+/// Size and SHA-256 of a file, copied to `destination` when given
+pub(crate) fn hash_file(path: &Path, destination: Option<&Path>) -> std::io::Result<(u64, String)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut writer = destination
+        .map(|destination| File::create(destination).map(BufWriter::new))
+        .transpose()?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        if let Some(writer) = writer.as_mut() {
+            writer.write_all(&buf[..read])?;
+        }
+        size += read as u64;
+    }
+    if let Some(mut writer) = writer {
+        writer.flush()?;
+    }
+    Ok((size, to_hex(&hasher.finalize())))
+}
+
+pub(crate) async fn hash_file_async(
+    path: PathBuf,
+    destination: Option<PathBuf>,
+) -> Result<(u64, String)> {
+    Ok(tokio::task::spawn_blocking(move || hash_file(&path, destination.as_deref())).await??)
+}
+
+/// Path of a file of the archive, refusing paths leaving it
+fn archive_path(dir: &Path, relative: &str) -> Result<PathBuf> {
+    let path = Path::new(relative);
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!("invalid path in archive: {}", relative));
+    }
+    Ok(dir.join(path))
+}
+
+/// First path of `dir` named `file_name`, or with a counter before its extension, for which
+/// `available` holds
+fn unique_path(dir: &Path, file_name: &str, mut available: impl FnMut(&Path) -> bool) -> PathBuf {
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (file_name, String::new()),
+    };
+    let mut path = dir.join(file_name);
+    let mut counter = 1;
+    while !available(&path) {
+        path = dir.join(format!("{}_{}{}", stem, counter, extension));
+        counter += 1;
+    }
+    path
+}
+
+/// Where a media file goes in `media_dir`, and whether a file with the same content is already
+/// there. Another file with its name is kept and the new one gets a counter.
+pub(crate) async fn media_destination(
+    media_dir: &Path,
+    file_name: &str,
+    size: u64,
+    sha256: &str,
+) -> Result<(PathBuf, bool)> {
+    let media_dir = media_dir.to_path_buf();
+    let file_name = file_name.to_string();
+    let sha256 = sha256.to_string();
+    Ok(tokio::task::spawn_blocking(move || {
+        let mut existing = false;
+        let destination = unique_path(&media_dir, &file_name, |path| {
+            if !path.exists() {
+                return true;
+            }
+            existing = hash_file(path, None)
+                .map(|(file_size, file_sha256)| file_size == size && file_sha256 == sha256)
+                .unwrap_or(false);
+            existing
+        });
+        (destination, existing)
+    })
+    .await?)
+}
+
+/// Archive columns of a row, with the machine that recorded it filled in
+pub(crate) fn export_row(row: &SqliteRow, database_id: &str) -> Map<String, Value> {
+    let mut values = row_to_json(row);
+    if let Some(machine_id) = values.get_mut("machine_id") {
+        if machine_id.is_null() {
+            *machine_id = Value::String(database_id.to_string());
+        }
+    }
+    values
+}
+
+pub fn read_archive_manifest(dir: &Path) -> Result<ArchiveManifest> {
+    let manifest: ArchiveManifest =
+        serde_json::from_reader(BufReader::new(File::open(dir.join(ARCHIVE_MANIFEST))?))?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "archive format version {} is newer than the supported version {}",
+            manifest.format_version,
+            ARCHIVE_FORMAT_VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+impl DatabaseManager {
+    /// Stable id of this database, generated by its first migration run
+    pub async fn database_id(&self) -> Result<String, sqlx::Error> {
+        sqlx::query_scalar("SELECT database_id FROM database_identity WHERE id = 1")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Writes the rows matching `filter` and their media files to a new archive directory. Every
+    /// table is read in one transaction, so the rows recorded meanwhile are left out of all of
+    /// them and no row points to a missing one.
+    pub async fn export_archive(
+        &self,
+        dir: &Path,
+        filter: &ArchiveFilter,
+    ) -> Result<ArchiveManifest> {
+        if tokio::fs::try_exists(dir).await?
+            && tokio::fs::read_dir(dir)
+                .await?
+                .next_entry()
+                .await?
+                .is_some()
+        {
+            return Err(anyhow!("archive directory {} is not empty", dir.display()));
+        }
+        tokio::fs::create_dir_all(dir.join("tables")).await?;
+        let apps = if filter.app_names.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&filter.app_names)?)
+        };
+
+        let mut manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            created_at: Utc::now(),
+            source_id: self.database_id().await?,
+            filter: filter.clone(),
+            tables: Vec::new(),
+            media: Vec::new(),
+            missing_media: Vec::new(),
+        };
+        let mut tx = self.pool.begin().await?;
+        for table in ARCHIVE_TABLES {
+            let Some(condition) = export_condition(table, filter) else {
+                continue;
+            };
+            let file = format!("tables/{}.ndjson", table.name);
+            let mut output = tokio::fs::File::create(dir.join(&file)).await?;
+            let mut hasher = Sha256::new();
+            let sql = format!(
+                "SELECT rowid AS archive_rowid, * FROM {} WHERE rowid > ?4 AND ({}) ORDER BY rowid LIMIT ?5",
+                table.name, condition
+            );
+            let mut rows = 0;
+            let mut after_rowid = 0;
+            loop {
+                let batch = sqlx::query(&sql)
+                    .bind(filter.start_time)
+                    .bind(filter.end_time)
+                    .bind(&apps)
+                    .bind(after_rowid)
+                    .bind(ARCHIVE_BATCH_SIZE)
+                    .fetch_all(&mut *tx)
+                    .await?;
+                let Some(last) = batch.last() else {
+                    break;
+                };
+                after_rowid = last.try_get("archive_rowid")?;
+                let mut lines = Vec::new();
+                for row in &batch {
+                    let mut values = export_row(row, &manifest.source_id);
+                    if let Some(column) = table.media {
+                        if let Some(Value::String(path)) = values.get(column).cloned() {
+                            match self.export_media(dir, table.name, &path).await? {
+                                Some(entry) => {
+                                    values.insert(column.to_string(), entry.path.clone().into());
+                                    manifest.media.push(entry);
+                                }
+                                None => manifest.missing_media.push(path),
+                            }
+                        }
+                    }
+                    serde_json::to_writer(&mut lines, &values)?;
+                    lines.push(b'\n');
+                    rows += 1;
+                }
+                hasher.update(&lines);
+                output.write_all(&lines).await?;
+                if (batch.len() as i64) < ARCHIVE_BATCH_SIZE {
+                    break;
+                }
+            }
+            output.flush().await?;
+            manifest.tables.push(ArchiveTableEntry {
+                name: table.name.to_string(),
+                file,
+                rows,
+                sha256: to_hex(&hasher.finalize()),
+            });
+        }
+        tx.commit().await?;
+
+        tokio::fs::write(
+            dir.join(ARCHIVE_MANIFEST),
+            serde_json::to_vec_pretty(&manifest)?,
+        )
+        .await?;
+        Ok(manifest)
+    }
+
+    /// Copies a media file into the archive, None when it is missing on disk
+    async fn export_media(
+        &self,
+        dir: &Path,
+        table: &str,
+        path: &str,
+    ) -> Result<Option<ArchiveMediaEntry>> {
+        let source = PathBuf::from(path);
+        let Some(file_name) = source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+        else {
+            return Ok(None);
+        };
+        let media_dir = dir.join("media").join(table);
+        let copied = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+            if !source.is_file() {
+                return Ok(None);
+            }
+            std::fs::create_dir_all(&media_dir)?;
+            let destination = unique_path(&media_dir, &file_name, |path| !path.exists());
+            let (size, sha256) = hash_file(&source, Some(&destination))?;
+            Ok(Some((destination, size, sha256)))
+        })
+        .await??;
+        let Some((destination, size, sha256)) = copied else {
+            return Ok(None);
+        };
+        let relative = destination
+            .strip_prefix(dir)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok(Some(ArchiveMediaEntry {
+            path: relative,
+            size,
+            sha256,
+        }))
+    }
+
+    /// Imports an archive, its media files are copied into `media_dir`. Nothing is written
+    /// unless every checksum matches the manifest.
+    pub async fn import_archive(&self, dir: &Path, media_dir: &Path) -> Result<ImportReport> {
+        let manifest = {
+            let dir = dir.to_path_buf();
+            tokio::task::spawn_blocking(move || read_archive_manifest(&dir)).await??
+        };
+        for table in &manifest.tables {
+            let (_, sha256) = hash_file_async(archive_path(dir, &table.file)?, None).await?;
+            if sha256 != table.sha256 {
+                return Err(anyhow!("checksum mismatch for {}", table.file));
+            }
+        }
+        for media in &manifest.media {
+            let (size, sha256) = hash_file_async(archive_path(dir, &media.path)?, None).await?;
+            if size != media.size || sha256 != media.sha256 {
+                return Err(anyhow!("checksum mismatch for {}", media.path));
+            }
+        }
+
+        let mut report = ImportReport {
+            source_id: manifest.source_id.clone(),
+            ..Default::default()
+        };
+        let mut importer = RowImporter::new(self, &manifest.source_id).await?;
+        if !manifest.media.is_empty() {
+            tokio::fs::create_dir_all(media_dir).await?;
+        }
+        for media in &manifest.media {
+            let source = archive_path(dir, &media.path)?;
+            let file_name = source
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| anyhow!("invalid media path in archive: {}", media.path))?;
+            let (destination, existing) =
+                media_destination(media_dir, file_name, media.size, &media.sha256).await?;
+            if existing {
+                report.media_existing += 1;
+            } else {
+                hash_file_async(source, Some(destination.clone())).await?;
+                report.media_copied += 1;
+            }
+            importer.media_paths.insert(
+                media.path.clone(),
+                destination.to_string_lossy().to_string(),
+            );
+        }
+
+        for table in ARCHIVE_TABLES {
+            let Some(entry) = manifest
+                .tables
+                .iter()
+                .find(|entry| entry.name == table.name)
+            else {
+                continue;
+            };
+            let mut table_report = ImportTableReport {
+                name: table.name.to_string(),
+                ..Default::default()
+            };
+
+            let file = tokio::fs::File::open(archive_path(dir, &entry.file)?).await?;
+            let mut lines = tokio::io::BufReader::new(file).lines();
+            let mut tx = self.pool.begin().await?;
+            let mut pending = 0;
+            while let Some(line) = lines.next_line().await? {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row: Map<String, Value> = serde_json::from_str(&line)?;
+                importer
+                    .import_row(&mut tx, table, row, &mut table_report)
+                    .await?;
+
+                pending += 1;
+                if pending == ARCHIVE_BATCH_SIZE {
+                    tx.commit().await?;
+                    tx = self.pool.begin().await?;
+                    pending = 0;
+                }
+            }
+            tx.commit().await?;
+            report.tables.push(table_report);
+        }
+        Ok(report)
+    }
+}
+
+/// Imports the rows of another database, giving them local ids and remembering which rows they
+/// became so later imports of the same source find them
+pub(crate) struct RowImporter {
+    source_id: String,
+    /// Whether the rows were exported by this same database, they then keep their ids
+    same_database: bool,
+    /// Media paths of the source and where their files were put
+    pub(crate) media_paths: HashMap<String, String>,
+    ids: HashMap<(&'static str, i64), i64>,
+    columns: HashMap<&'static str, Vec<String>>,
+}
+
+impl RowImporter {
+    pub(crate) async fn new(db: &DatabaseManager, source_id: &str) -> Result<Self> {
+        Ok(Self {
+            source_id: source_id.to_string(),
+            same_database: source_id == db.database_id().await?,
+            media_paths: HashMap::new(),
+            ids: HashMap::new(),
+            columns: HashMap::new(),
+        })
+    }
+
+    /// Local id of a row of the source, None when it was never imported or is gone
+    async fn local_id(
+        &mut self,
+        conn: &mut SqliteConnection,
+        table: &'static str,
+        source_row_id: i64,
+    ) -> Result<Option<i64>> {
+        if let Some(id) = self.ids.get(&(table, source_row_id)) {
+            return Ok(Some(*id));
+        }
+        let id: Option<i64> = if self.same_database {
+            sqlx::query_scalar(&format!("SELECT rowid FROM {} WHERE rowid = ?1", table))
+                .bind(source_row_id)
+                .fetch_optional(&mut *conn)
+                .await?
+        } else {
+            sqlx::query_scalar(&format!(
+                r#"
+                SELECT t.rowid FROM imported_rows i
+                JOIN {} t ON t.rowid = i.row_id
+                WHERE i.source_id = ?1 AND i.table_name = ?2 AND i.source_row_id = ?3
+                "#,
+                table
+            ))
+            .bind(&self.source_id)
+            .bind(table)
+            .bind(source_row_id)
+            .fetch_optional(&mut *conn)
+            .await?
+        };
+        if let Some(id) = id {
+            self.ids.insert((table, source_row_id), id);
+        }
+        Ok(id)
+    }
+
+    /// Imports a row in the archive format. A row of this same database keeps its id while it
+    /// exists and a row imported before maps to the same local row, taking the changes of the
+    /// database it came from. Otherwise the unique columns decide, and a local row matching
+    /// them is left as is.
+    pub(crate) async fn import_row(
+        &mut self,
+        conn: &mut SqliteConnection,
+        table: &ArchiveTable,
+        mut row: Map<String, Value>,
+        report: &mut ImportTableReport,
+    ) -> Result<()> {
+        let origin_id = row.remove(ORIGIN_KEY).and_then(|id| id.as_i64());
+        let source_row_id = match table.has_id {
+            true => row.remove("id"),
+            false => row.remove(ROWID_KEY),
+        }
+        .and_then(|id| id.as_i64());
+
+        for (column, parent) in table.references {
+            let Some(old_id) = row.get(*column).and_then(Value::as_i64) else {
+                continue;
+            };
+            match self.local_id(conn, parent, old_id).await? {
+                Some(new_id) => {
+                    row.insert(column.to_string(), new_id.into());
+                }
+                None => {
+                    report.skipped += 1;
+                    return Ok(());
+                }
+            }
+        }
+        let mut media_moved = false;
+        if let Some(column) = table.media {
+            if let Some(path) = row
+                .get(column)
+                .and_then(Value::as_str)
+                .and_then(|path| self.media_paths.get(path))
+            {
+                row.insert(column.to_string(), path.clone().into());
+                media_moved = true;
+            }
+        }
+        if !self.columns.contains_key(table.name) {
+            let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?1)")
+                .bind(table.name)
+                .fetch_all(&mut *conn)
+                .await?;
+            self.columns.insert(table.name, columns);
+        }
+        let columns = &self.columns[table.name];
+        row.retain(|column, _| columns.contains(column));
+
+        let mut existing: Option<i64> = None;
+        let mut from_source = false;
+        if let Some(origin_id) = origin_id {
+            // a row of this database the source got from here, sent with the id it has here
+            existing = sqlx::query_scalar(&format!("SELECT id FROM {} WHERE id = ?1", table.name))
+                .bind(origin_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+        }
+        if existing.is_none() {
+            if let Some(source_row_id) = source_row_id {
+                existing = self.local_id(conn, table.name, source_row_id).await?;
+                from_source = existing.is_some() && !self.same_database;
+            }
+        }
+        if existing.is_none() && !table.unique.is_empty() {
+            let condition = table
+                .unique
+                .iter()
+                .enumerate()
+                .map(|(i, column)| format!("{} IS ?{}", column, i + 1))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            let sql = format!(
+                "SELECT rowid FROM {} WHERE {} LIMIT 1",
+                table.name, condition
+            );
+            let mut query = sqlx::query(&sql);
+            for column in table.unique {
+                query = bind_json(query, row.get(*column).unwrap_or(&Value::Null))?;
+            }
+            existing = query
+                .fetch_optional(&mut *conn)
+                .await?
+                .map(|row| row.get::<i64, _>(0));
+        }
+
+        let row_id = match existing {
+            Some(row_id) if from_source => {
+                // a media file is only sent again when it changed
+                if !media_moved {
+                    if let Some(column) = table.media {
+                        row.remove(column);
+                    }
+                }
+                if update_row(conn, table.name, row_id, &row).await? {
+                    report.updated += 1;
+                } else {
+                    report.duplicates += 1;
+                }
+                row_id
+            }
+            Some(row_id) => {
+                report.duplicates += 1;
+                row_id
+            }
+            None => {
+                let names: Vec<&String> = row.keys().collect();
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table.name,
+                    names
+                        .iter()
+                        .map(|name| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    (1..=names.len())
+                        .map(|i| format!("?{}", i))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                let mut query = sqlx::query(&sql);
+                for value in row.values() {
+                    query = bind_json(query, value)?;
+                }
+                report.inserted += 1;
+                query.execute(&mut *conn).await?.last_insert_rowid()
+            }
+        };
+        if let Some(source_row_id) = source_row_id {
+            self.ids.insert((table.name, source_row_id), row_id);
+            if !self.same_database {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO imported_rows (source_id, table_name, source_row_id, row_id) VALUES (?1, ?2, ?3, ?4)",
+                )
+                .bind(&self.source_id)
+                .bind(table.name)
+                .bind(source_row_id)
+                .bind(row_id)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the values of a row that differ, returns whether any did
+async fn update_row(
+    conn: &mut SqliteConnection,
+    table: &str,
+    row_id: i64,
+    row: &Map<String, Value>,
+) -> Result<bool> {
+    if row.is_empty() {
+        return Ok(false);
+    }
+    let names: Vec<&String> = row.keys().collect();
+    let sql = format!(
+        "UPDATE {} SET {} WHERE rowid = ?{} AND NOT ({})",
+        table,
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{} = ?{}", name, i + 1))
+            .collect::<Vec<_>>()
+            .join(", "),
+        names.len() + 1,
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{} IS ?{}", name, i + 1))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    );
+    let mut query = sqlx::query(&sql);
+    for value in row.values() {
+        query = bind_json(query, value)?;
+    }
+    let result = query.bind(row_id).execute(&mut *conn).await?;
+    Ok(result.rows_affected() > 0)
+}
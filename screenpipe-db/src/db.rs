@@ -1,33 +1,44 @@
 use chrono::{DateTime, Utc};
 use image::DynamicImage;
 use libsqlite3_sys::sqlite3_auto_extension;
+use rust_stemmers::Algorithm;
 use sqlite_vec::sqlite3_vec_init;
 use sqlx::migrate::MigrateDatabase;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::query::QueryAs;
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqlitePool, SqlitePoolOptions};
 use sqlx::Column;
 use sqlx::Error as SqlxError;
 use sqlx::Row;
 use sqlx::TypeInfo;
 use sqlx::ValueRef;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use zerocopy::AsBytes;
 
 use futures::future::try_join_all;
 
+use crate::query::{fts_quote, parse_query, ParsedQuery};
+
 use crate::{
-    AudioChunksResponse, AudioDevice, AudioEntry, AudioResult, AudioResultRaw, ContentType,
-    DeviceType, FrameData, FrameRow, OCREntry, OCRResult, OCRResultRaw, OcrEngine, OcrTextBlock,
-    Order, SearchMatch, SearchResult, Speaker, TagContentType, TextBounds, TextPosition,
-    TimeSeriesChunk, UiContent, VideoMetadata,
+    AudioChunk, AudioChunksResponse, AudioDevice, AudioEntry, AudioResult, AudioResultRaw,
+    ContentType, DeviceType, FacetCount, FrameData, FrameRow, FtsTokenizer, HistogramBucket,
+    HistogramInterval, OCREntry, OCRResult, OCRResultRaw, OcrEngine, OcrTextBlock, Order,
+    SearchCursor, SearchFacets, SearchMatch, SearchResult, Speaker, TagContentType, TextBounds,
+    TextPosition, TimeSeriesChunk, TranscriptionSegment, UiContent, VideoChunk, VideoMetadata,
 };
 
+/// Default maximum cosine distance for an embedding to match an existing speaker.
+pub const DEFAULT_SPEAKER_MATCH_THRESHOLD: f32 = 0.5;
+
 pub struct DatabaseManager {
     pub pool: SqlitePool,
+    /// Tokenizer of each FTS table, by table name
+    pub(crate) fts_tokenizers: RwLock<HashMap<String, FtsTokenizer>>,
+    pub(crate) stemming: RwLock<Vec<Algorithm>>,
 }
 
 impl DatabaseManager {
@@ -73,10 +84,15 @@ impl DatabaseManager {
             .execute(&pool)
             .await?;
 
-        let db_manager = DatabaseManager { pool };
+        let db_manager = DatabaseManager {
+            pool,
+            fts_tokenizers: RwLock::new(HashMap::new()),
+            stemming: RwLock::new(Vec::new()),
+        };
 
         // Run migrations after establishing the connection
         Self::run_migrations(&db_manager.pool).await?;
+        db_manager.load_fts_tokenizers().await?;
 
         Ok(db_manager)
     }
@@ -211,6 +227,13 @@ impl DatabaseManager {
         .bind(id)
         .execute(&mut *tx)
         .await?;
+
+        // a fresh speaker's centroid is its only embedding
+        sqlx::query("UPDATE speakers SET centroid = vec_f32(?1) WHERE id = ?2")
+            .bind(bytes)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
         tx.commit().await?;
 
         Ok(Speaker {
@@ -247,7 +270,17 @@ impl DatabaseManager {
         &self,
         embedding: &[f32],
     ) -> Result<Option<Speaker>, SqlxError> {
-        let speaker_threshold = 0.5;
+        self.get_speaker_from_embedding_with_threshold(embedding, DEFAULT_SPEAKER_MATCH_THRESHOLD)
+            .await
+    }
+
+    /// Finds the closest speaker whose profile (any stored embedding or the centroid)
+    /// is within `threshold` cosine distance of `embedding`.
+    pub async fn get_speaker_from_embedding_with_threshold(
+        &self,
+        embedding: &[f32],
+        threshold: f32,
+    ) -> Result<Option<Speaker>, SqlxError> {
         let bytes: &[u8] = embedding.as_bytes();
 
         // Using subquery with LIMIT 1 instead of JOIN
@@ -256,20 +289,162 @@ impl DatabaseManager {
              FROM speakers
              WHERE id = (
                  SELECT speaker_id
-                 FROM speaker_embeddings
-                 WHERE vec_distance_cosine(embedding, vec_f32(?1)) < ?2
-                 ORDER BY vec_distance_cosine(embedding, vec_f32(?1))
+                 FROM (
+                     SELECT speaker_id, vec_distance_cosine(embedding, vec_f32(?1)) AS distance
+                     FROM speaker_embeddings
+                     UNION ALL
+                     SELECT id AS speaker_id, vec_distance_cosine(centroid, vec_f32(?1)) AS distance
+                     FROM speakers
+                     WHERE centroid IS NOT NULL
+                 )
+                 WHERE distance < ?2
+                 ORDER BY distance
                  LIMIT 1
              )",
         )
         .bind(bytes)
-        .bind(speaker_threshold)
+        .bind(threshold)
         .fetch_optional(&self.pool)
         .await?;
 
         Ok(speaker)
     }
 
+    /// Enrolls a named speaker from clean voice samples.
+    ///
+    /// When `speaker_id` is given the embeddings are added to that speaker's profile,
+    /// otherwise a new speaker is created. The centroid is recomputed either way.
+    pub async fn enroll_speaker(
+        &self,
+        name: &str,
+        embeddings: &[Vec<f32>],
+        speaker_id: Option<i64>,
+    ) -> Result<Speaker, SqlxError> {
+        if embeddings.is_empty() {
+            return Err(SqlxError::Protocol(
+                "at least one embedding is required to enroll a speaker".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let id = match speaker_id {
+            Some(id) => {
+                let updated = sqlx::query("UPDATE speakers SET name = ?1 WHERE id = ?2")
+                    .bind(name)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                if updated.rows_affected() == 0 {
+                    return Err(SqlxError::RowNotFound);
+                }
+                id
+            }
+            None => sqlx::query("INSERT INTO speakers (name) VALUES (?1)")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid(),
+        };
+
+        for embedding in embeddings {
+            let bytes: &[u8] = embedding.as_bytes();
+            sqlx::query(
+                "INSERT INTO speaker_embeddings (embedding, speaker_id, source) VALUES (vec_f32(?1), ?2, 'enrollment')",
+            )
+            .bind(bytes)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        Self::recompute_speaker_centroid(&mut tx, id).await?;
+
+        tx.commit().await?;
+
+        self.get_speaker_by_id(id).await
+    }
+
+    pub async fn get_speaker_embeddings(
+        &self,
+        speaker_id: i64,
+    ) -> Result<Vec<Vec<f32>>, SqlxError> {
+        let rows: Vec<(Vec<u8>,)> =
+            sqlx::query_as("SELECT embedding FROM speaker_embeddings WHERE speaker_id = ?1")
+                .bind(speaker_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(bytes,)| embedding_from_bytes(&bytes))
+            .collect())
+    }
+
+    pub async fn get_speaker_centroid(
+        &self,
+        speaker_id: i64,
+    ) -> Result<Option<Vec<f32>>, SqlxError> {
+        let centroid: Option<Option<Vec<u8>>> =
+            sqlx::query_scalar("SELECT centroid FROM speakers WHERE id = ?1")
+                .bind(speaker_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(centroid.flatten().map(|bytes| embedding_from_bytes(&bytes)))
+    }
+
+    /// Averages every embedding of the speaker into `speakers.centroid`.
+    async fn recompute_speaker_centroid(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        speaker_id: i64,
+    ) -> Result<(), SqlxError> {
+        let rows: Vec<(Vec<u8>,)> =
+            sqlx::query_as("SELECT embedding FROM speaker_embeddings WHERE speaker_id = ?1")
+                .bind(speaker_id)
+                .fetch_all(&mut **tx)
+                .await?;
+
+        let embeddings: Vec<Vec<f32>> = rows
+            .into_iter()
+            .map(|(bytes,)| embedding_from_bytes(&bytes))
+            .collect();
+
+        let centroid = match embeddings.first() {
+            Some(first) => {
+                let dim = first.len();
+                let mut sum = vec![0.0f32; dim];
+                let mut count = 0.0f32;
+                for embedding in embeddings.iter().filter(|e| e.len() == dim) {
+                    for (acc, value) in sum.iter_mut().zip(embedding) {
+                        *acc += value;
+                    }
+                    count += 1.0;
+                }
+                Some(sum.into_iter().map(|v| v / count).collect::<Vec<f32>>())
+            }
+            None => None,
+        };
+
+        match centroid {
+            Some(centroid) => {
+                sqlx::query("UPDATE speakers SET centroid = vec_f32(?1) WHERE id = ?2")
+                    .bind(centroid.as_bytes())
+                    .bind(speaker_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            None => {
+                sqlx::query("UPDATE speakers SET centroid = NULL WHERE id = ?1")
+                    .bind(speaker_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn update_speaker_name(&self, speaker_id: i64, name: &str) -> Result<i64, SqlxError> {
         let mut tx = self.pool.begin().await?;
         sqlx::query("UPDATE speakers SET name = ?1 WHERE id = ?2")
@@ -285,18 +460,145 @@ impl DatabaseManager {
         &self,
         file_path: &str,
         device_name: &str,
+    ) -> Result<i64, sqlx::Error> {
+        self.insert_video_chunk_with_profile(file_path, device_name, None)
+            .await
+    }
+
+    /// Inserts a video chunk with the json encoding profile it was written with
+    pub async fn insert_video_chunk_with_profile(
+        &self,
+        file_path: &str,
+        device_name: &str,
+        encoding_profile: Option<&str>,
     ) -> Result<i64, sqlx::Error> {
         let mut tx = self.pool.begin().await?;
-        let id = sqlx::query("INSERT INTO video_chunks (file_path, device_name) VALUES (?1, ?2)")
-            .bind(file_path)
-            .bind(device_name)
-            .execute(&mut *tx)
-            .await?
-            .last_insert_rowid();
+        let id = sqlx::query(
+            "INSERT INTO video_chunks (file_path, device_name, encoding_profile) VALUES (?1, ?2, ?3)",
+        )
+        .bind(file_path)
+        .bind(device_name)
+        .bind(encoding_profile)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
         tx.commit().await?;
         Ok(id)
     }
 
+    pub async fn get_video_chunk_encoding_profile(
+        &self,
+        file_path: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let profile: Option<Option<String>> =
+            sqlx::query_scalar("SELECT encoding_profile FROM video_chunks WHERE file_path = ?1")
+                .bind(file_path)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(profile.flatten())
+    }
+
+    /// Chunks not recompressed yet whose last frame is older than `before`, ordered by id
+    /// and starting after `after_id`
+    pub async fn get_video_chunks_to_recompress(
+        &self,
+        before: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<VideoChunk>, sqlx::Error> {
+        sqlx::query_as::<_, VideoChunk>(
+            r#"
+            SELECT
+                video_chunks.id,
+                video_chunks.file_path,
+                video_chunks.device_name,
+                video_chunks.encoding_profile,
+                MAX(frames.timestamp) AS last_frame_at
+            FROM video_chunks
+            JOIN frames ON frames.video_chunk_id = video_chunks.id
+            WHERE video_chunks.recompressed_at IS NULL AND video_chunks.id > ?2
+            GROUP BY video_chunks.id
+            HAVING MAX(frames.timestamp) < ?1
+            ORDER BY video_chunks.id
+            LIMIT ?3
+            "#,
+        )
+        .bind(before)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Points a chunk and its frames to a recompressed file in one transaction. The file keeps
+    /// one of every `frame_step` frames, dropped frames map to the kept frame before them.
+    /// The original file is scheduled for deletion after `delete_old_after`.
+    pub async fn swap_video_chunk_file(
+        &self,
+        video_chunk_id: i64,
+        file_path: &str,
+        encoding_profile: Option<&str>,
+        frame_step: i64,
+        delete_old_after: DateTime<Utc>,
+        reclaimed_bytes: i64,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let old_path: String =
+            sqlx::query_scalar("SELECT file_path FROM video_chunks WHERE id = ?1")
+                .bind(video_chunk_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        sqlx::query(
+            "UPDATE video_chunks SET file_path = ?1, encoding_profile = ?2, recompressed_at = ?3 WHERE id = ?4",
+        )
+        .bind(file_path)
+        .bind(encoding_profile)
+        .bind(Utc::now())
+        .bind(video_chunk_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE frames SET name = ?1, offset_index = offset_index / ?2 WHERE video_chunk_id = ?3",
+        )
+        .bind(file_path)
+        .bind(frame_step.max(1))
+        .bind(video_chunk_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO pending_file_deletions (file_path, delete_after, reclaimed_bytes) VALUES (?1, ?2, ?3)",
+        )
+        .bind(&old_path)
+        .bind(delete_old_after)
+        .bind(reclaimed_bytes)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replaced files due for deletion, with the bytes their removal gives back
+    pub async fn get_due_file_deletions(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT file_path, reclaimed_bytes FROM pending_file_deletions WHERE delete_after <= ?1 ORDER BY delete_after",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Forgets a pending deletion once the file is gone
+    pub async fn remove_pending_file_deletion(&self, file_path: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM pending_file_deletions WHERE file_path = ?1")
+            .bind(file_path)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     pub async fn insert_frame(
         &self,
         device_name: &str,
@@ -368,15 +670,29 @@ impl DatabaseManager {
         text: &str,
         text_json: &str,
         ocr_engine: Arc<OcrEngine>,
+    ) -> Result<(), sqlx::Error> {
+        self.insert_ocr_text_with_layout(frame_id, text, text_json, None, ocr_engine)
+            .await
+    }
+
+    /// Inserts the OCR text of a frame with its structured layout, serialized as json
+    pub async fn insert_ocr_text_with_layout(
+        &self,
+        frame_id: i64,
+        text: &str,
+        text_json: &str,
+        layout: Option<&str>,
+        ocr_engine: Arc<OcrEngine>,
     ) -> Result<(), sqlx::Error> {
         let text_length = text.len() as i64;
         let mut tx = self.pool.begin().await?;
-        sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length) VALUES (?1, ?2, ?3, ?4, ?5)")
+        sqlx::query("INSERT INTO ocr_text (frame_id, text, text_json, ocr_engine, text_length, layout) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
             .bind(frame_id)
             .bind(text)
             .bind(text_json)
             .bind(format!("{:?}", *ocr_engine))
             .bind(text_length)
+            .bind(layout)
             .execute(&mut *tx)
             .await?;
 
@@ -385,6 +701,30 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Inserts the text of a window captured through the accessibility tree
+    pub async fn insert_ui_monitoring(
+        &self,
+        app: &str,
+        window: &str,
+        text_output: &str,
+        initial_traversal_at: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO ui_monitoring (text_output, timestamp, app, window, initial_traversal_at, text_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .bind(text_output)
+        .bind(Utc::now())
+        .bind(app)
+        .bind(window)
+        .bind(initial_traversal_at)
+        .bind(text_output.len() as i64)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        debug!("UI monitoring text inserted into db successfully");
+        Ok(id)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn search(
         &self,
@@ -429,6 +769,7 @@ impl DatabaseManager {
                                 frame_name,
                                 browser_url,
                                 focused,
+                                None,
                             ),
                             self.search_audio(
                                 query,
@@ -438,7 +779,8 @@ impl DatabaseManager {
                                 end_time,
                                 min_length,
                                 max_length,
-                                speaker_ids
+                                speaker_ids,
+                                None
                             ),
                             self.search_ui_monitoring(
                                 query,
@@ -448,6 +790,7 @@ impl DatabaseManager {
                                 end_time,
                                 limit,
                                 offset,
+                                None,
                             )
                         )?;
                         (ocr, Some(audio), ui)
@@ -467,6 +810,7 @@ impl DatabaseManager {
                                 frame_name,
                                 browser_url,
                                 focused,
+                                None,
                             ),
                             self.search_ui_monitoring(
                                 query,
@@ -476,6 +820,7 @@ impl DatabaseManager {
                                 end_time,
                                 limit,
                                 offset,
+                                None,
                             )
                         )?;
                         (ocr, None, ui)
@@ -502,6 +847,7 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        None,
                     )
                     .await?;
                 results.extend(ocr_results.into_iter().map(SearchResult::OCR));
@@ -518,6 +864,7 @@ impl DatabaseManager {
                             min_length,
                             max_length,
                             speaker_ids,
+                            None,
                         )
                         .await?;
                     results.extend(audio_results.into_iter().map(SearchResult::Audio));
@@ -533,6 +880,7 @@ impl DatabaseManager {
                         end_time,
                         limit,
                         offset,
+                        None,
                     )
                     .await?;
                 results.extend(ui_results.into_iter().map(SearchResult::UI));
@@ -548,6 +896,7 @@ impl DatabaseManager {
                         min_length,
                         max_length,
                         speaker_ids,
+                        None,
                     )
                     .await?;
                 let ui_results = self
@@ -559,6 +908,7 @@ impl DatabaseManager {
                         end_time,
                         limit / 2,
                         offset,
+                        None,
                     )
                     .await?;
 
@@ -580,6 +930,7 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        None,
                     )
                     .await?;
                 let ui_results = self
@@ -591,6 +942,7 @@ impl DatabaseManager {
                         end_time,
                         limit / 2,
                         offset,
+                        None,
                     )
                     .await?;
 
@@ -608,6 +960,7 @@ impl DatabaseManager {
                         min_length,
                         max_length,
                         speaker_ids,
+                        None,
                     )
                     .await?;
                 let ocr_results = self
@@ -624,6 +977,7 @@ impl DatabaseManager {
                         frame_name,
                         browser_url,
                         focused,
+                        None,
                     )
                     .await?;
 
@@ -657,6 +1011,115 @@ impl DatabaseManager {
         Ok(results)
     }
 
+    /// Keyset paginated [`DatabaseManager::search`]: results newest first across all searched
+    /// tables, starting after `cursor`. Returns the page and the cursor of the next one, which
+    /// is `None` once the results are exhausted.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_after(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        limit: u32,
+        cursor: Option<&SearchCursor>,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+    ) -> Result<(Vec<SearchResult>, Option<SearchCursor>), sqlx::Error> {
+        let (ocr, audio, ui) = searched_sources(
+            &content_type,
+            app_name,
+            window_name,
+            frame_name,
+            browser_url,
+            focused,
+        );
+        // one more row than asked tells whether another page follows
+        let fetch = limit + 1;
+
+        let ocr_future = async {
+            if !ocr {
+                return Ok(Vec::new());
+            }
+            self.search_ocr(
+                query,
+                fetch,
+                0,
+                start_time,
+                end_time,
+                app_name,
+                window_name,
+                min_length,
+                max_length,
+                frame_name,
+                browser_url,
+                focused,
+                cursor,
+            )
+            .await
+        };
+        let audio_future = async {
+            if !audio {
+                return Ok(Vec::new());
+            }
+            self.search_audio(
+                query,
+                fetch,
+                0,
+                start_time,
+                end_time,
+                min_length,
+                max_length,
+                speaker_ids,
+                cursor,
+            )
+            .await
+        };
+        let ui_future = async {
+            if !ui {
+                return Ok(Vec::new());
+            }
+            self.search_ui_monitoring(
+                query,
+                app_name,
+                window_name,
+                start_time,
+                end_time,
+                fetch,
+                0,
+                cursor,
+            )
+            .await
+        };
+        let (ocr_results, audio_results, ui_results) =
+            tokio::try_join!(ocr_future, audio_future, ui_future)?;
+
+        let mut results: Vec<SearchResult> = ocr_results
+            .into_iter()
+            .map(SearchResult::OCR)
+            .chain(audio_results.into_iter().map(SearchResult::Audio))
+            .chain(ui_results.into_iter().map(SearchResult::UI))
+            .collect();
+        results.sort_by_cached_key(|result| {
+            let cursor = result_cursor(result);
+            std::cmp::Reverse((cursor.timestamp, cursor.source, cursor.id))
+        });
+
+        let next_cursor = if results.len() > limit as usize {
+            results.truncate(limit as usize);
+            results.last().map(result_cursor)
+        } else {
+            None
+        };
+        Ok((results, next_cursor))
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn search_ocr(
         &self,
@@ -672,22 +1135,23 @@ impl DatabaseManager {
         frame_name: Option<&str>,
         browser_url: Option<&str>,
         focused: Option<bool>,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<OCRResult>, sqlx::Error> {
         let mut frame_fts_parts = Vec::new();
 
         if let Some(app) = app_name {
             if !app.is_empty() {
-                frame_fts_parts.push(format!("app_name:{}", app));
+                frame_fts_parts.push(format!("app_name:{}", fts_quote(app)));
             }
         }
         if let Some(window) = window_name {
             if !window.is_empty() {
-                frame_fts_parts.push(format!("window_name:{}", window));
+                frame_fts_parts.push(format!("window_name:{}", fts_quote(window)));
             }
         }
         if let Some(browser) = browser_url {
             if !browser.is_empty() {
-                frame_fts_parts.push(format!("browser_url:{}", browser));
+                frame_fts_parts.push(format!("browser_url:{}", fts_quote(browser)));
             }
         }
         if let Some(is_focused) = focused {
@@ -695,7 +1159,7 @@ impl DatabaseManager {
         }
         if let Some(frame_name) = frame_name {
             if !frame_name.is_empty() {
-                frame_fts_parts.push(format!("name:{}", frame_name));
+                frame_fts_parts.push(format!("name:{}", fts_quote(frame_name)));
             }
         }
 
@@ -731,10 +1195,25 @@ impl DatabaseManager {
             AND (?3 IS NULL OR frames.timestamp <= ?3)
             AND (?4 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?4)
             AND (?5 IS NULL OR COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?5)
+            {cursor_condition}
         GROUP BY frames.id
-        ORDER BY frames.timestamp DESC
+        ORDER BY frames.timestamp DESC, frames.id DESC
         LIMIT ?7 OFFSET ?8
         "#,
+            cursor_condition = cursor
+                .map(|cursor| format!(
+                    "AND {}",
+                    keyset_condition(
+                        cursor,
+                        OCR_SOURCE,
+                        true,
+                        "frames.timestamp",
+                        "frames.id",
+                        "?9",
+                        "?10"
+                    )
+                ))
+                .unwrap_or_default(),
             frame_fts_join = if frame_query.trim().is_empty() {
                 ""
             } else {
@@ -776,6 +1255,8 @@ impl DatabaseManager {
             })
             .bind(limit)
             .bind(offset)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id))
             .fetch_all(&self.pool)
             .await?;
 
@@ -813,10 +1294,12 @@ impl DatabaseManager {
         min_length: Option<usize>,
         max_length: Option<usize>,
         speaker_ids: Option<Vec<i64>>,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<AudioResult>, sqlx::Error> {
         // base query for audio search
         let mut base_sql = String::from(
             "SELECT
+                audio_transcriptions.id,
                 audio_transcriptions.audio_chunk_id,
                 audio_transcriptions.transcription,
                 audio_transcriptions.timestamp,
@@ -861,6 +1344,20 @@ impl DatabaseManager {
         if speaker_ids.is_some() {
             conditions.push("(json_array_length(?) = 0 OR audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?)))");
         }
+        let cursor_condition = cursor.map(|cursor| {
+            keyset_condition(
+                cursor,
+                AUDIO_SOURCE,
+                true,
+                "audio_transcriptions.timestamp",
+                "audio_transcriptions.id",
+                "?",
+                "?",
+            )
+        });
+        if let Some(condition) = &cursor_condition {
+            conditions.push(condition);
+        }
 
         let where_clause = if conditions.is_empty() {
             "WHERE 1=1".to_owned()
@@ -870,7 +1367,7 @@ impl DatabaseManager {
 
         // complete sql with group, order, limit and offset
         let sql = format!(
-            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index ORDER BY audio_transcriptions.timestamp DESC LIMIT ? OFFSET ?",
+            "{} {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index ORDER BY audio_transcriptions.timestamp DESC, audio_transcriptions.id DESC LIMIT ? OFFSET ?",
             base_sql, where_clause
         );
 
@@ -903,6 +1400,9 @@ impl DatabaseManager {
                 .bind(&speaker_ids_json)
                 .bind(&speaker_ids_json);
         }
+        if let Some(cursor) = cursor {
+            query_builder = bind_args(query_builder, keyset_args(cursor, AUDIO_SOURCE));
+        }
         query_builder = query_builder.bind(limit as i64).bind(offset as i64);
 
         let results_raw: Vec<AudioResultRaw> = query_builder.fetch_all(&self.pool).await?;
@@ -920,6 +1420,7 @@ impl DatabaseManager {
                 };
 
                 Ok::<AudioResult, sqlx::Error>(AudioResult {
+                    id: raw.id,
                     audio_chunk_id: raw.audio_chunk_id,
                     transcription: raw.transcription,
                     timestamp: raw.timestamp,
@@ -965,6 +1466,18 @@ impl DatabaseManager {
         .await
     }
 
+    /// Layout json of the OCR text of a frame, `None` for unknown frames and text stored
+    /// without a layout
+    pub async fn get_frame_layout(&self, frame_id: i64) -> Result<Option<String>, sqlx::Error> {
+        let layout = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT layout FROM ocr_text WHERE frame_id = ?1 LIMIT 1",
+        )
+        .bind(frame_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(layout.flatten())
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn count_search_results(
         &self,
@@ -1064,19 +1577,19 @@ impl DatabaseManager {
         }
         if let Some(app) = app_name {
             if !app.is_empty() {
-                frame_fts_parts.push(format!("app_name:{}", app));
-                ui_fts_parts.push(format!("app:\"{}\"", app));
+                frame_fts_parts.push(format!("app_name:{}", fts_quote(app)));
+                ui_fts_parts.push(format!("app:{}", fts_quote(app)));
             }
         }
         if let Some(window) = window_name {
             if !window.is_empty() {
-                frame_fts_parts.push(format!("window_name:{}", window));
-                ui_fts_parts.push(format!("window:\"{}\"", window));
+                frame_fts_parts.push(format!("window_name:{}", fts_quote(window)));
+                ui_fts_parts.push(format!("window:{}", fts_quote(window)));
             }
         }
         if let Some(browser) = browser_url {
             if !browser.is_empty() {
-                frame_fts_parts.push(format!("browser_url:{}", browser));
+                frame_fts_parts.push(format!("browser_url:{}", fts_quote(browser)));
             }
         }
         if let Some(is_focused) = focused {
@@ -1209,6 +1722,289 @@ impl DatabaseManager {
         Ok(count as usize)
     }
 
+    /// Counts the results of a search by app, window, browser domain, speaker, device, content
+    /// type and time bucket, with the same filters as [`DatabaseManager::search`]. At most
+    /// `limit` values are returned per facet, the histogram is not truncated.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_facets(
+        &self,
+        query: &str,
+        content_type: ContentType,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        window_name: Option<&str>,
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+        speaker_ids: Option<Vec<i64>>,
+        frame_name: Option<&str>,
+        browser_url: Option<&str>,
+        focused: Option<bool>,
+        interval: HistogramInterval,
+        limit: u32,
+    ) -> Result<SearchFacets, sqlx::Error> {
+        let (ocr, audio, ui) = searched_sources(
+            &content_type,
+            app_name,
+            window_name,
+            frame_name,
+            browser_url,
+            focused,
+        );
+
+        let mut sources = Vec::new();
+        let mut args = Vec::new();
+
+        if ocr {
+            let mut frame_fts_parts = Vec::new();
+            if let Some(app) = app_name.filter(|app| !app.is_empty()) {
+                frame_fts_parts.push(format!("app_name:{}", fts_quote(app)));
+            }
+            if let Some(window) = window_name.filter(|window| !window.is_empty()) {
+                frame_fts_parts.push(format!("window_name:{}", fts_quote(window)));
+            }
+            if let Some(browser) = browser_url.filter(|browser| !browser.is_empty()) {
+                frame_fts_parts.push(format!("browser_url:{}", fts_quote(browser)));
+            }
+            if let Some(is_focused) = focused {
+                frame_fts_parts.push(format!("focused:{}", if is_focused { "1" } else { "0" }));
+            }
+            if let Some(frame_name) = frame_name.filter(|name| !name.is_empty()) {
+                frame_fts_parts.push(format!("name:{}", fts_quote(frame_name)));
+            }
+
+            let mut sql = String::from(
+                "SELECT 'ocr' AS content_type, frames.timestamp, frames.app_name,
+                        frames.window_name, frames.browser_url, NULL AS speaker,
+                        video_chunks.device_name AS device
+                 FROM frames
+                 JOIN video_chunks ON frames.video_chunk_id = video_chunks.id
+                 JOIN ocr_text ON frames.id = ocr_text.frame_id",
+            );
+            let mut conditions = Vec::new();
+            if !frame_fts_parts.is_empty() {
+                sql.push_str(" JOIN frames_fts ON frames.id = frames_fts.id");
+                conditions.push("frames_fts MATCH ?");
+                args.push(SqlArg::Text(frame_fts_parts.join(" ")));
+            }
+            if !query.is_empty() {
+                sql.push_str(" JOIN ocr_text_fts ON ocr_text.frame_id = ocr_text_fts.frame_id");
+                conditions.push("ocr_text_fts MATCH ?");
+                args.push(SqlArg::Text(query.to_owned()));
+            }
+            if let Some(start) = start_time {
+                conditions.push("frames.timestamp >= ?");
+                args.push(SqlArg::Time(start));
+            }
+            if let Some(end) = end_time {
+                conditions.push("frames.timestamp <= ?");
+                args.push(SqlArg::Time(end));
+            }
+            if let Some(min) = min_length {
+                conditions.push("COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) >= ?");
+                args.push(SqlArg::Int(min as i64));
+            }
+            if let Some(max) = max_length {
+                conditions.push("COALESCE(ocr_text.text_length, LENGTH(ocr_text.text)) <= ?");
+                args.push(SqlArg::Int(max as i64));
+            }
+            conditions.push("1=1");
+            sources.push(format!(
+                "{} WHERE {} GROUP BY frames.id",
+                sql,
+                conditions.join(" AND ")
+            ));
+        }
+
+        if audio {
+            let mut sql = String::from(
+                "SELECT 'audio' AS content_type, audio_transcriptions.timestamp, NULL AS app_name,
+                        NULL AS window_name, NULL AS browser_url, speakers.name AS speaker,
+                        audio_transcriptions.device AS device
+                 FROM audio_transcriptions
+                 LEFT JOIN speakers ON audio_transcriptions.speaker_id = speakers.id",
+            );
+            let mut conditions = vec!["(speakers.id IS NULL OR speakers.hallucination = 0)"];
+            if !query.is_empty() {
+                sql.push_str(" JOIN audio_transcriptions_fts ON audio_transcriptions_fts.audio_chunk_id = audio_transcriptions.audio_chunk_id");
+                conditions.push("audio_transcriptions_fts MATCH ?");
+                args.push(SqlArg::Text(query.to_owned()));
+            }
+            if let Some(start) = start_time {
+                conditions.push("audio_transcriptions.timestamp >= ?");
+                args.push(SqlArg::Time(start));
+            }
+            if let Some(end) = end_time {
+                conditions.push("audio_transcriptions.timestamp <= ?");
+                args.push(SqlArg::Time(end));
+            }
+            if let Some(min) = min_length {
+                conditions.push("COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) >= ?");
+                args.push(SqlArg::Int(min as i64));
+            }
+            if let Some(max) = max_length {
+                conditions.push("COALESCE(audio_transcriptions.text_length, LENGTH(audio_transcriptions.transcription)) <= ?");
+                args.push(SqlArg::Int(max as i64));
+            }
+            if let Some(ids) = speaker_ids.as_ref().filter(|ids| !ids.is_empty()) {
+                conditions
+                    .push("audio_transcriptions.speaker_id IN (SELECT value FROM json_each(?))");
+                args.push(SqlArg::Text(
+                    serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()),
+                ));
+            }
+            sources.push(format!(
+                "{} WHERE {} GROUP BY audio_transcriptions.audio_chunk_id, audio_transcriptions.offset_index",
+                sql,
+                conditions.join(" AND ")
+            ));
+        }
+
+        if ui {
+            let mut ui_fts_parts = Vec::new();
+            if !query.is_empty() {
+                ui_fts_parts.push(query.to_owned());
+            }
+            if let Some(app) = app_name.filter(|app| !app.is_empty()) {
+                ui_fts_parts.push(format!("app:{}", fts_quote(app)));
+            }
+            if let Some(window) = window_name.filter(|window| !window.is_empty()) {
+                ui_fts_parts.push(format!("window:{}", fts_quote(window)));
+            }
+
+            let mut sql = String::from(
+                "SELECT 'ui' AS content_type, ui_monitoring.timestamp, ui_monitoring.app AS app_name,
+                        ui_monitoring.window AS window_name, NULL AS browser_url, NULL AS speaker,
+                        NULL AS device
+                 FROM ui_monitoring",
+            );
+            let mut conditions = Vec::new();
+            if !ui_fts_parts.is_empty() {
+                sql.push_str(
+                    " JOIN ui_monitoring_fts ON ui_monitoring_fts.ui_id = ui_monitoring.id",
+                );
+                conditions.push("ui_monitoring_fts MATCH ?");
+                args.push(SqlArg::Text(ui_fts_parts.join(" ")));
+            }
+            if let Some(start) = start_time {
+                conditions.push("ui_monitoring.timestamp >= ?");
+                args.push(SqlArg::Time(start));
+            }
+            if let Some(end) = end_time {
+                conditions.push("ui_monitoring.timestamp <= ?");
+                args.push(SqlArg::Time(end));
+            }
+            if let Some(min) = min_length {
+                conditions.push(
+                    "COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) >= ?",
+                );
+                args.push(SqlArg::Int(min as i64));
+            }
+            if let Some(max) = max_length {
+                conditions.push(
+                    "COALESCE(ui_monitoring.text_length, LENGTH(ui_monitoring.text_output)) <= ?",
+                );
+                args.push(SqlArg::Int(max as i64));
+            }
+            conditions.push("1=1");
+            sources.push(format!("{} WHERE {}", sql, conditions.join(" AND ")));
+        }
+
+        if sources.is_empty() {
+            return Ok(SearchFacets::default());
+        }
+
+        // the matches are materialized once and every facet is a GROUP BY over them
+        let sql = format!(
+            r#"
+            WITH matches AS MATERIALIZED (
+                {sources}
+            ),
+            urls AS (
+                SELECT CASE WHEN instr(browser_url, '://') > 0
+                            THEN substr(browser_url, instr(browser_url, '://') + 3)
+                            ELSE browser_url END AS rest
+                FROM matches
+                WHERE browser_url IS NOT NULL AND browser_url != ''
+            )
+            SELECT 'app_name' AS facet, app_name AS value, COUNT(*) AS count
+            FROM matches WHERE app_name IS NOT NULL AND app_name != '' GROUP BY app_name
+            UNION ALL
+            SELECT 'window_name', window_name, COUNT(*)
+            FROM matches WHERE window_name IS NOT NULL AND window_name != '' GROUP BY window_name
+            UNION ALL
+            SELECT 'browser_domain', domain, COUNT(*)
+            FROM (
+                SELECT lower(CASE WHEN instr(rest, '/') > 0
+                                  THEN substr(rest, 1, instr(rest, '/') - 1)
+                                  ELSE rest END) AS domain
+                FROM urls
+            )
+            GROUP BY domain
+            UNION ALL
+            SELECT 'speaker', speaker, COUNT(*)
+            FROM matches WHERE speaker IS NOT NULL AND speaker != '' GROUP BY speaker
+            UNION ALL
+            SELECT 'device', device, COUNT(*)
+            FROM matches WHERE device IS NOT NULL AND device != '' GROUP BY device
+            UNION ALL
+            SELECT 'content_type', content_type, COUNT(*) FROM matches GROUP BY content_type
+            UNION ALL
+            SELECT 'time', strftime('{time_format}', timestamp) AS bucket, COUNT(*)
+            FROM matches GROUP BY bucket
+            "#,
+            sources = sources.join("\n                UNION ALL\n                "),
+            time_format = interval.strftime_format(),
+        );
+
+        let rows = bind_args(
+            sqlx::query_as::<_, (String, Option<String>, i64)>(&sql),
+            args,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut facets = SearchFacets::default();
+        for (facet, value, count) in rows {
+            let Some(value) = value else {
+                continue;
+            };
+            let counts = match facet.as_str() {
+                "app_name" => &mut facets.app_name,
+                "window_name" => &mut facets.window_name,
+                "browser_domain" => &mut facets.browser_domain,
+                "speaker" => &mut facets.speaker,
+                "device" => &mut facets.device,
+                "content_type" => &mut facets.content_type,
+                _ => {
+                    if let Ok(start) = DateTime::parse_from_rfc3339(&value) {
+                        facets.time_histogram.push(HistogramBucket {
+                            start: start.with_timezone(&Utc),
+                            count,
+                        });
+                    }
+                    continue;
+                }
+            };
+            counts.push(FacetCount { value, count });
+        }
+
+        for counts in [
+            &mut facets.app_name,
+            &mut facets.window_name,
+            &mut facets.browser_domain,
+            &mut facets.speaker,
+            &mut facets.device,
+            &mut facets.content_type,
+        ] {
+            counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+            counts.truncate(limit as usize);
+        }
+        facets.time_histogram.sort_by_key(|bucket| bucket.start);
+
+        Ok(facets)
+    }
+
     pub async fn get_latest_timestamps(
         &self,
     ) -> Result<
@@ -1462,9 +2258,22 @@ impl DatabaseManager {
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
+    ) -> Result<TimeSeriesChunk, SqlxError> {
+        self.find_video_chunks_after(start, end, None, None).await
+    }
+
+    /// Frames of a time range newest first, at most `limit` frames older than `cursor`. Audio is
+    /// attached to the page whose frames span it.
+    pub async fn find_video_chunks_after(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        cursor: Option<&SearchCursor>,
+        limit: Option<u32>,
     ) -> Result<TimeSeriesChunk, SqlxError> {
         // Get frames with OCR data, grouped by minute to handle multiple monitors
-        let frames_query = r#"
+        let frames_query = format!(
+            r#"
          SELECT
             f.id,
             f.timestamp,
@@ -1477,12 +2286,31 @@ impl DatabaseManager {
         FROM frames f
         JOIN video_chunks vc ON f.video_chunk_id = vc.id
         LEFT JOIN ocr_text ot ON f.id = ot.frame_id
-        WHERE f.timestamp >= ?1 AND f.timestamp <= ?2
+        WHERE f.id IN (
+            SELECT id FROM frames
+            WHERE timestamp >= ?1 AND timestamp <= ?2 {}
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?3
+        )
         ORDER BY f.timestamp DESC, f.offset_index DESC
-    "#;
+    "#,
+            cursor
+                .map(|cursor| format!(
+                    "AND {}",
+                    keyset_condition(cursor, OCR_SOURCE, true, "timestamp", "id", "?4", "?5")
+                ))
+                .unwrap_or_default()
+        );
+
+        // audio up to the previous page, the part older than this page is dropped below
+        let (audio_end, end_condition) = match cursor {
+            Some(cursor) => (cursor.timestamp, "at.timestamp < ?2"),
+            None => (end, "at.timestamp <= ?2"),
+        };
 
         // Get audio data with proper time windows for synchronization
-        let audio_query = r#"
+        let audio_query = format!(
+            r#"
         SELECT
             at.timestamp,
             at.transcription,
@@ -1496,22 +2324,64 @@ impl DatabaseManager {
                  as REAL) as duration_secs
         FROM audio_transcriptions at
         JOIN audio_chunks ac ON at.audio_chunk_id = ac.id
-        WHERE at.timestamp >= ?1 AND at.timestamp <= ?2
+        WHERE at.timestamp >= ?1 AND {}
         ORDER BY at.timestamp DESC
-        "#;
+        "#,
+            end_condition
+        );
 
-        // Execute queries in parallel
+        // Execute queries in parallel, one more frame than asked tells whether another page
+        // follows
         let (frame_rows, audio_rows) = tokio::try_join!(
-            sqlx::query(frames_query)
+            sqlx::query(&frames_query)
                 .bind(start)
                 .bind(end)
+                .bind(limit.map_or(-1, |limit| limit as i64 + 1))
+                .bind(cursor.map(|cursor| cursor.timestamp))
+                .bind(cursor.map(|cursor| cursor.id))
                 .fetch_all(&self.pool),
-            sqlx::query(audio_query)
+            sqlx::query(&audio_query)
                 .bind(start)
-                .bind(end)
+                .bind(audio_end)
                 .fetch_all(&self.pool)
         )?;
 
+        let mut page: Vec<(DateTime<Utc>, i64)> = frame_rows
+            .iter()
+            .map(|row| (row.get("timestamp"), row.get("id")))
+            .collect();
+        page.sort();
+        page.dedup();
+        let next_cursor = match limit {
+            Some(limit) if page.len() > limit as usize => {
+                let (timestamp, id) = page[page.len() - limit as usize];
+                Some(SearchCursor::new(timestamp, id))
+            }
+            _ => None,
+        };
+        let frame_rows: Vec<_> = match &next_cursor {
+            Some(next) => frame_rows
+                .into_iter()
+                .filter(|row| {
+                    (
+                        row.get::<DateTime<Utc>, _>("timestamp"),
+                        row.get::<i64, _>("id"),
+                    ) >= (next.timestamp, next.id)
+                })
+                .collect(),
+            None => frame_rows,
+        };
+
+        // audio between the oldest frame of the page, or the start of the range on the last
+        // page, and the previous page
+        let audio_rows: Vec<_> = match &next_cursor {
+            Some(next) => audio_rows
+                .into_iter()
+                .filter(|row| row.get::<DateTime<Utc>, _>("timestamp") >= next.timestamp)
+                .collect(),
+            None => audio_rows,
+        };
+
         // Process into structured data with device-aware grouping
         let mut frames_map: BTreeMap<(DateTime<Utc>, i64), FrameData> = BTreeMap::new();
 
@@ -1566,6 +2436,7 @@ impl DatabaseManager {
             frames: frames_map.into_values().rev().collect(),
             start_time: start,
             end_time: end,
+            next_cursor,
         })
     }
 
@@ -1579,6 +2450,7 @@ impl DatabaseManager {
         end_time: Option<DateTime<Utc>>,
         limit: u32,
         offset: u32,
+        cursor: Option<&SearchCursor>,
     ) -> Result<Vec<UiContent>, sqlx::Error> {
         // combine search aspects into single fts query
         let mut fts_parts = Vec::new();
@@ -1586,10 +2458,10 @@ impl DatabaseManager {
             fts_parts.push(query.to_owned());
         }
         if let Some(app) = app_name {
-            fts_parts.push(format!("app:{}", app));
+            fts_parts.push(format!("app:{}", fts_quote(app)));
         }
         if let Some(window) = window_name {
-            fts_parts.push(format!("window:{}", window));
+            fts_parts.push(format!("window:{}", fts_quote(window)));
         }
         let combined_query = fts_parts.join(" ");
 
@@ -1627,11 +2499,27 @@ impl DatabaseManager {
             {}
                 AND (?2 IS NULL OR ui_monitoring.timestamp >= ?2)
                 AND (?3 IS NULL OR ui_monitoring.timestamp <= ?3)
+                {}
             GROUP BY ui_monitoring.id
-            ORDER BY ui_monitoring.timestamp DESC
+            ORDER BY ui_monitoring.timestamp DESC, ui_monitoring.id DESC
             LIMIT ?4 OFFSET ?5
             "#,
-            base_sql, where_clause
+            base_sql,
+            where_clause,
+            cursor
+                .map(|cursor| format!(
+                    "AND {}",
+                    keyset_condition(
+                        cursor,
+                        UI_SOURCE,
+                        true,
+                        "ui_monitoring.timestamp",
+                        "ui_monitoring.id",
+                        "?6",
+                        "?7"
+                    )
+                ))
+                .unwrap_or_default()
         );
 
         sqlx::query_as(&sql)
@@ -1644,6 +2532,8 @@ impl DatabaseManager {
             .bind(end_time)
             .bind(limit)
             .bind(offset)
+            .bind(cursor.map(|cursor| cursor.timestamp))
+            .bind(cursor.map(|cursor| cursor.id))
             .fetch_all(&self.pool)
             .await
     }
@@ -1683,6 +2573,121 @@ impl DatabaseManager {
         Ok(tags.into_iter().map(|t| t.0).collect())
     }
 
+    pub async fn count_audio_chunks_in_range(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM audio_chunks WHERE timestamp >= ?1 AND timestamp <= ?2",
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Audio chunks recorded in the time range, ordered by id and starting after `after_id`
+    pub async fn get_audio_chunks_in_range(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<AudioChunk>, sqlx::Error> {
+        sqlx::query_as::<_, AudioChunk>(
+            r#"
+            SELECT id, file_path, timestamp
+            FROM audio_chunks
+            WHERE timestamp >= ?1 AND timestamp <= ?2 AND id > ?3
+            ORDER BY id
+            LIMIT ?4
+            "#,
+        )
+        .bind(start_time)
+        .bind(end_time)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// The device an audio chunk was recorded from, taken from its existing transcriptions
+    pub async fn get_audio_chunk_device(
+        &self,
+        audio_chunk_id: i64,
+    ) -> Result<Option<AudioDevice>, sqlx::Error> {
+        let row: Option<(String, bool)> = sqlx::query_as(
+            "SELECT device, is_input_device FROM audio_transcriptions WHERE audio_chunk_id = ?1 LIMIT 1",
+        )
+        .bind(audio_chunk_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|(name, is_input)| AudioDevice {
+            name,
+            device_type: if is_input {
+                DeviceType::Input
+            } else {
+                DeviceType::Output
+            },
+        }))
+    }
+
+    /// Replaces every transcription of an audio chunk with `segments`, keeping the
+    /// original recording timestamp. Returns the number of inserted transcriptions.
+    pub async fn replace_audio_transcriptions(
+        &self,
+        audio_chunk_id: i64,
+        device: &AudioDevice,
+        transcription_engine: &str,
+        segments: &[TranscriptionSegment],
+    ) -> Result<usize, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let timestamp: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(
+                (SELECT MIN(timestamp) FROM audio_transcriptions WHERE audio_chunk_id = ?1),
+                (SELECT timestamp FROM audio_chunks WHERE id = ?1)
+            )
+            "#,
+        )
+        .bind(audio_chunk_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let timestamp = timestamp.unwrap_or_else(Utc::now);
+
+        sqlx::query("DELETE FROM audio_transcriptions WHERE audio_chunk_id = ?1")
+            .bind(audio_chunk_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut inserted = 0;
+        for segment in segments.iter().filter(|s| !s.transcription.is_empty()) {
+            sqlx::query(
+                "INSERT INTO audio_transcriptions (audio_chunk_id, transcription, offset_index, timestamp, transcription_engine, device, is_input_device, speaker_id, start_time, end_time, text_length) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )
+            .bind(audio_chunk_id)
+            .bind(&segment.transcription)
+            .bind(0)
+            .bind(timestamp)
+            .bind(transcription_engine)
+            .bind(&device.name)
+            .bind(device.device_type == DeviceType::Input)
+            .bind(segment.speaker_id)
+            .bind(segment.start_time)
+            .bind(segment.end_time)
+            .bind(segment.transcription.len() as i64)
+            .execute(&mut *tx)
+            .await?;
+            inserted += 1;
+        }
+
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
     pub async fn get_audio_chunks_for_speaker(
         &self,
         speaker_id: i64,
@@ -1825,6 +2830,9 @@ impl DatabaseManager {
             .execute(&mut *tx)
             .await?;
 
+        // the kept speaker now owns both sets of embeddings
+        Self::recompute_speaker_centroid(&mut tx, speaker_to_keep_id).await?;
+
         tx.commit().await?;
 
         self.get_speaker_by_id(speaker_to_keep_id).await
@@ -1910,7 +2918,11 @@ impl DatabaseManager {
                 )
             ),
             speaker_embedding AS (
-                SELECT embedding FROM speaker_embeddings WHERE speaker_id = ?1
+                SELECT COALESCE(
+                    centroid,
+                    (SELECT embedding FROM speaker_embeddings WHERE speaker_id = ?1 LIMIT 1)
+                ) AS embedding
+                FROM speakers WHERE id = ?1
             )
             SELECT
                 s.id,
@@ -2213,56 +3225,36 @@ impl DatabaseManager {
         }
     }
 
+    /// Frames whose text matches `query`, with the positions of the matching words. The query
+    /// is parsed with [`parse_query`], its `speaker:` and `type:` filters don't apply to frames
+    /// and are left to the caller to reject. `limit` and `offset` count frames, each with all
+    /// its text rows. A `cursor` continues after the last frame of a page in the requested
+    /// order and `offset` is then ignored.
     #[allow(clippy::too_many_arguments)]
     pub async fn search_with_text_positions(
         &self,
         query: &str,
         limit: u32,
         offset: u32,
+        cursor: Option<&SearchCursor>,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
         fuzzy_match: bool,
         order: Order,
         app_names: Option<Vec<String>>,
     ) -> Result<Vec<SearchMatch>, sqlx::Error> {
-        let mut conditions = Vec::new();
-        let mut owned_conditions = Vec::new();
-
-        if start_time.is_some() {
-            conditions.push("f.timestamp >= ?");
-        }
-        if end_time.is_some() {
-            conditions.push("f.timestamp <= ?");
-        }
-
-        // Add app names condition if provided - updated to use f.app_name
-        if let Some(apps) = &app_names {
-            if !apps.is_empty() {
-                let placeholders = vec!["?"; apps.len()].join(",");
-                let app_condition = format!("f.app_name IN ({})", placeholders);
-                owned_conditions.push(app_condition);
-                conditions.push(owned_conditions.last().unwrap().as_str());
-            }
-        }
-
-        // Create an indexed subquery for FTS matching
-        let search_condition = if !query.is_empty() {
-            let fts_match = if fuzzy_match {
-                query
-                    .split_whitespace()
-                    .map(|word| format!("{}*", word))
-                    .collect::<Vec<_>>()
-                    .join(" OR ")
-            } else {
-                query.to_string()
-            };
-            conditions.push(
-                "f.id IN (SELECT frame_id FROM ocr_text_fts WHERE text MATCH ? ORDER BY rank)",
-            );
-            fts_match
-        } else {
-            String::new()
-        };
+        let parsed = parse_query(query).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+        let parsed = self.expand_query(&parsed);
+        let descending = order == Order::Descending;
+        let (where_clause, args) = text_position_filter(
+            &parsed,
+            start_time,
+            end_time,
+            app_names,
+            self.fts_tokenizer_of("ocr_text_fts"),
+            fuzzy_match,
+            cursor.map(|cursor| (cursor, descending)),
+        );
 
         let sql = format!(
             r#"
@@ -2276,57 +3268,37 @@ SELECT
     o.text_json
 FROM frames f
 INNER JOIN ocr_text o ON f.id = o.frame_id
-WHERE {}
-ORDER BY f.timestamp {}
-LIMIT ? OFFSET ?
+WHERE f.id IN (
+    SELECT f.id
+    FROM frames f
+    INNER JOIN ocr_text o ON f.id = o.frame_id
+    WHERE {where_clause}
+    GROUP BY f.id
+    ORDER BY f.timestamp {direction}, f.id {direction}
+    LIMIT ? OFFSET ?
+)
+ORDER BY f.timestamp {direction}, f.id {direction}, o.rowid
 "#,
-            if conditions.is_empty() {
-                "1=1".to_string()
-            } else {
-                conditions.join(" AND ")
-            },
-            match order {
-                Order::Ascending => "ASC",
-                Order::Descending => "DESC",
-            }
+            where_clause = where_clause,
+            direction = if descending { "DESC" } else { "ASC" },
         );
 
-        let mut query_builder = sqlx::query_as::<_, FrameRow>(&sql);
-
-        // Bind timestamp parameters first
-        if let Some(start) = start_time {
-            query_builder = query_builder.bind(start);
-        }
-        if let Some(end) = end_time {
-            query_builder = query_builder.bind(end);
-        }
-
-        // Bind app names if provided
-        if let Some(apps) = app_names {
-            if !apps.is_empty() {
-                for app in apps {
-                    query_builder = query_builder.bind(app);
-                }
-            }
-        }
-
-        // Bind search condition if query is not empty
-        if !query.is_empty() {
-            query_builder = query_builder.bind(&search_condition);
-        }
-
-        // Bind limit and offset
-        query_builder = query_builder.bind(limit as i64).bind(offset as i64);
-
-        let rows = query_builder.fetch_all(&self.pool).await?;
+        // pages hold whole frames so a frame's text rows are never split by the cursor, the
+        // cursor replaces the offset
+        let rows = bind_args(sqlx::query_as::<_, FrameRow>(&sql), args)
+            .bind(limit as i64)
+            .bind(if cursor.is_some() { 0 } else { offset as i64 })
+            .fetch_all(&self.pool)
+            .await?;
+        let terms = parsed.terms().join(" ");
 
         Ok(rows
             .iter()
             .map(|row| {
-                let positions = if !query.is_empty() {
+                let positions = if !terms.is_empty() {
                     let ocr_blocks: Vec<OcrTextBlock> =
                         serde_json::from_str(&row.text_json).unwrap_or_default();
-                    find_matching_positions(&ocr_blocks, query)
+                    find_matching_positions(&ocr_blocks, &terms)
                 } else {
                     Vec::new()
                 };
@@ -2344,6 +3316,235 @@ LIMIT ? OFFSET ?
             })
             .collect())
     }
+
+    /// Number of frames [`DatabaseManager::search_with_text_positions`] matches in total
+    pub async fn count_text_position_matches(
+        &self,
+        query: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        fuzzy_match: bool,
+        app_names: Option<Vec<String>>,
+    ) -> Result<usize, sqlx::Error> {
+        let parsed = parse_query(query).map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+        let (where_clause, args) = text_position_filter(
+            &self.expand_query(&parsed),
+            start_time,
+            end_time,
+            app_names,
+            self.fts_tokenizer_of("ocr_text_fts"),
+            fuzzy_match,
+            None,
+        );
+        let sql = format!(
+            "SELECT COUNT(DISTINCT f.id) FROM frames f INNER JOIN ocr_text o ON f.id = o.frame_id WHERE {}",
+            where_clause
+        );
+        let (count,): (i64,) = bind_args(sqlx::query_as(&sql), args)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as usize)
+    }
+}
+
+/// Source ranks of the cursor keyset, breaking timestamp ties between tables
+const OCR_SOURCE: u8 = 0;
+const AUDIO_SOURCE: u8 = 1;
+const UI_SOURCE: u8 = 2;
+
+/// Tables searched for a content type, as (ocr, audio, ui), the same way as
+/// [`DatabaseManager::search`]: browser and focus filters only apply to frames, and audio has
+/// no app or window
+fn searched_sources(
+    content_type: &ContentType,
+    app_name: Option<&str>,
+    window_name: Option<&str>,
+    frame_name: Option<&str>,
+    browser_url: Option<&str>,
+    focused: Option<bool>,
+) -> (bool, bool, bool) {
+    let no_audio = app_name.is_some() || window_name.is_some();
+    match content_type {
+        _ if focused.is_some() || browser_url.is_some() => (true, false, false),
+        ContentType::All => (true, !no_audio && frame_name.is_none(), true),
+        ContentType::OCR => (true, false, false),
+        ContentType::Audio => (false, !no_audio, false),
+        ContentType::UI => (false, false, true),
+        ContentType::AudioAndUi => (false, true, true),
+        ContentType::OcrAndUi => (true, false, true),
+        ContentType::AudioAndOcr => (true, true, false),
+    }
+}
+
+/// Condition selecting the rows of `source` that come after `cursor`, in descending or
+/// ascending (timestamp, source, id) order. Anonymous placeholders take the values of
+/// [`keyset_args`].
+fn keyset_condition(
+    cursor: &SearchCursor,
+    source: u8,
+    descending: bool,
+    timestamp_column: &str,
+    id_column: &str,
+    timestamp_param: &str,
+    id_param: &str,
+) -> String {
+    let (before, before_or_equal) = if descending { ("<", "<=") } else { (">", ">=") };
+    // a table ranked after the cursor's one comes first at an equal timestamp
+    let source_first = if descending {
+        source < cursor.source
+    } else {
+        source > cursor.source
+    };
+    if source == cursor.source {
+        format!(
+            "({ts} {op} {tp} OR ({ts} = {tp} AND {id} {op} {ip}))",
+            ts = timestamp_column,
+            id = id_column,
+            op = before,
+            tp = timestamp_param,
+            ip = id_param
+        )
+    } else if source_first {
+        format!(
+            "{} {} {}",
+            timestamp_column, before_or_equal, timestamp_param
+        )
+    } else {
+        format!("{} {} {}", timestamp_column, before, timestamp_param)
+    }
+}
+
+/// Value bound to an anonymous placeholder of a query built at runtime
+enum SqlArg {
+    Text(String),
+    Time(DateTime<Utc>),
+    Int(i64),
+}
+
+fn bind_args<'q, O>(
+    mut query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    args: Vec<SqlArg>,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    for arg in args {
+        query = match arg {
+            SqlArg::Text(text) => query.bind(text),
+            SqlArg::Time(time) => query.bind(time),
+            SqlArg::Int(int) => query.bind(int),
+        };
+    }
+    query
+}
+
+/// Values of the anonymous placeholders of a [`keyset_condition`]
+fn keyset_args(cursor: &SearchCursor, source: u8) -> Vec<SqlArg> {
+    if source == cursor.source {
+        vec![
+            SqlArg::Time(cursor.timestamp),
+            SqlArg::Time(cursor.timestamp),
+            SqlArg::Int(cursor.id),
+        ]
+    } else {
+        vec![SqlArg::Time(cursor.timestamp)]
+    }
+}
+
+/// Where clause of the keyword search over `frames f` joined with `ocr_text o`, filters
+/// written in the query fill the ones that were not given
+fn text_position_filter(
+    parsed: &ParsedQuery,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    app_names: Option<Vec<String>>,
+    tokenizer: FtsTokenizer,
+    fuzzy_match: bool,
+    cursor: Option<(&SearchCursor, bool)>,
+) -> (String, Vec<SqlArg>) {
+    let mut conditions = Vec::new();
+    let mut args = Vec::new();
+
+    if let Some(start) = start_time.or(parsed.start_time) {
+        conditions.push("f.timestamp >= ?".to_string());
+        args.push(SqlArg::Time(start));
+    }
+    if let Some(end) = end_time.or(parsed.end_time) {
+        conditions.push("f.timestamp <= ?".to_string());
+        args.push(SqlArg::Time(end));
+    }
+    let app_names = app_names.or_else(|| parsed.app_name.clone().map(|app| vec![app]));
+    if let Some(apps) = app_names.filter(|apps| !apps.is_empty()) {
+        conditions.push(format!(
+            "f.app_name IN ({})",
+            vec!["?"; apps.len()].join(",")
+        ));
+        args.extend(apps.into_iter().map(SqlArg::Text));
+    }
+    // window and url filters match the same way as in `search`
+    let frame_fts_parts: Vec<String> = [
+        ("window_name", parsed.window_name.as_deref()),
+        ("browser_url", parsed.browser_url.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(column, value)| {
+        value
+            .filter(|value| !value.is_empty())
+            .map(|value| format!("{}:{}", column, fts_quote(value)))
+    })
+    .collect();
+    if !frame_fts_parts.is_empty() {
+        conditions.push("f.id IN (SELECT id FROM frames_fts WHERE frames_fts MATCH ?)".to_string());
+        args.push(SqlArg::Text(frame_fts_parts.join(" ")));
+    }
+    if let Some(focused) = parsed.focused {
+        conditions.push("f.focused = ?".to_string());
+        args.push(SqlArg::Int(focused as i64));
+    }
+    // indexed subquery for FTS matching
+    if parsed.text.is_some() {
+        conditions.push(
+            "f.id IN (SELECT frame_id FROM ocr_text_fts WHERE text MATCH ? ORDER BY rank)"
+                .to_string(),
+        );
+        args.push(SqlArg::Text(parsed.fts_query_for(tokenizer, fuzzy_match)));
+    }
+    if let Some((cursor, descending)) = cursor {
+        conditions.push(keyset_condition(
+            cursor,
+            OCR_SOURCE,
+            descending,
+            "f.timestamp",
+            "f.id",
+            "?",
+            "?",
+        ));
+        args.extend(keyset_args(cursor, OCR_SOURCE));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        "1=1".to_string()
+    } else {
+        conditions.join(" AND ")
+    };
+    (where_clause, args)
+}
+
+/// Cursor of a search result, see [`SearchCursor`]
+fn result_cursor(result: &SearchResult) -> SearchCursor {
+    match result {
+        SearchResult::OCR(ocr) => {
+            SearchCursor::with_source(ocr.timestamp, ocr.frame_id, OCR_SOURCE)
+        }
+        SearchResult::Audio(audio) => {
+            SearchCursor::with_source(audio.timestamp, audio.id, AUDIO_SOURCE)
+        }
+        SearchResult::UI(ui) => SearchCursor::with_source(ui.timestamp, ui.id, UI_SOURCE),
+    }
+}
+
+fn embedding_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
 }
 
 pub fn find_matching_positions(blocks: &[OcrTextBlock], query: &str) -> Vec<TextPosition> {
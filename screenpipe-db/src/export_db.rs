@@ -0,0 +1,179 @@
+//! Typed rows of the timeline tables for the analytical exports, read in id order one chunk at
+//! a time so a whole history never sits in memory
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+use crate::{
+    DatabaseManager, TimelineChunk, TimelineColumn, TimelineColumnType, TimelineTable,
+    TimelineValue,
+};
+
+const fn column(name: &'static str, column_type: TimelineColumnType) -> TimelineColumn {
+    TimelineColumn { name, column_type }
+}
+
+const FRAME_COLUMNS: &[TimelineColumn] = &[
+    column("id", TimelineColumnType::Integer),
+    column("timestamp", TimelineColumnType::Timestamp),
+    column("device_name", TimelineColumnType::Text),
+    column("app_name", TimelineColumnType::Text),
+    column("window_name", TimelineColumnType::Text),
+    column("browser_url", TimelineColumnType::Text),
+    column("focused", TimelineColumnType::Boolean),
+    column("video_chunk_id", TimelineColumnType::Integer),
+    column("offset_index", TimelineColumnType::Integer),
+    column("machine_id", TimelineColumnType::Text),
+    column("ocr_text", TimelineColumnType::Text),
+    column("ocr_engine", TimelineColumnType::Text),
+];
+
+// every SELECT lists its columns in the order above, ?1 after id, ?2 start, ?3 end, ?4 limit
+const FRAMES_SELECT: &str = r#"
+    SELECT f.id, f.timestamp, c.device_name, f.app_name, f.window_name, f.browser_url, f.focused,
+        f.video_chunk_id, f.offset_index, f.machine_id,
+        (SELECT group_concat(o.text, char(10)) FROM ocr_text o WHERE o.frame_id = f.id),
+        (SELECT o.ocr_engine FROM ocr_text o WHERE o.frame_id = f.id LIMIT 1)
+    FROM frames f
+    LEFT JOIN video_chunks c ON c.id = f.video_chunk_id
+    WHERE f.id > ?1 AND (?2 IS NULL OR f.timestamp >= ?2) AND (?3 IS NULL OR f.timestamp <= ?3)
+    ORDER BY f.id
+    LIMIT ?4
+"#;
+
+const AUDIO_COLUMNS: &[TimelineColumn] = &[
+    column("id", TimelineColumnType::Integer),
+    column("timestamp", TimelineColumnType::Timestamp),
+    column("device", TimelineColumnType::Text),
+    column("is_input_device", TimelineColumnType::Boolean),
+    column("speaker_id", TimelineColumnType::Integer),
+    column("speaker_name", TimelineColumnType::Text),
+    column("transcription", TimelineColumnType::Text),
+    column("transcription_engine", TimelineColumnType::Text),
+    column("start_time", TimelineColumnType::Real),
+    column("end_time", TimelineColumnType::Real),
+    column("audio_chunk_id", TimelineColumnType::Integer),
+    column("offset_index", TimelineColumnType::Integer),
+    column("machine_id", TimelineColumnType::Text),
+];
+
+const AUDIO_SELECT: &str = r#"
+    SELECT a.id, a.timestamp, a.device, a.is_input_device, a.speaker_id, s.name, a.transcription,
+        a.transcription_engine, a.start_time, a.end_time, a.audio_chunk_id, a.offset_index,
+        a.machine_id
+    FROM audio_transcriptions a
+    LEFT JOIN speakers s ON s.id = a.speaker_id
+    WHERE a.id > ?1 AND (?2 IS NULL OR a.timestamp >= ?2) AND (?3 IS NULL OR a.timestamp <= ?3)
+    ORDER BY a.id
+    LIMIT ?4
+"#;
+
+const UI_COLUMNS: &[TimelineColumn] = &[
+    column("id", TimelineColumnType::Integer),
+    column("timestamp", TimelineColumnType::Timestamp),
+    column("app", TimelineColumnType::Text),
+    column("window", TimelineColumnType::Text),
+    column("text", TimelineColumnType::Text),
+    column("text_length", TimelineColumnType::Integer),
+    column("initial_traversal_at", TimelineColumnType::Timestamp),
+    column("machine_id", TimelineColumnType::Text),
+];
+
+const UI_SELECT: &str = r#"
+    SELECT id, timestamp, app, window, text_output, text_length, initial_traversal_at, machine_id
+    FROM ui_monitoring
+    WHERE id > ?1 AND (?2 IS NULL OR timestamp >= ?2) AND (?3 IS NULL OR timestamp <= ?3)
+    ORDER BY id
+    LIMIT ?4
+"#;
+
+impl TimelineTable {
+    /// Columns of the exported rows, the first one is the id
+    pub fn columns(&self) -> &'static [TimelineColumn] {
+        match self {
+            TimelineTable::Frames => FRAME_COLUMNS,
+            TimelineTable::AudioTranscriptions => AUDIO_COLUMNS,
+            TimelineTable::UiMonitoring => UI_COLUMNS,
+        }
+    }
+
+    fn select(&self) -> &'static str {
+        match self {
+            TimelineTable::Frames => FRAMES_SELECT,
+            TimelineTable::AudioTranscriptions => AUDIO_SELECT,
+            TimelineTable::UiMonitoring => UI_SELECT,
+        }
+    }
+}
+
+/// Value of a column, NULL when it does not decode as its type
+fn decode(row: &SqliteRow, i: usize, column_type: TimelineColumnType) -> TimelineValue {
+    let value = match column_type {
+        TimelineColumnType::Integer => row
+            .try_get_unchecked::<Option<i64>, _>(i)
+            .ok()
+            .flatten()
+            .map(TimelineValue::Integer),
+        TimelineColumnType::Real => row
+            .try_get_unchecked::<Option<f64>, _>(i)
+            .ok()
+            .flatten()
+            .map(TimelineValue::Real),
+        TimelineColumnType::Text => row
+            .try_get_unchecked::<Option<String>, _>(i)
+            .ok()
+            .flatten()
+            .map(TimelineValue::Text),
+        TimelineColumnType::Timestamp => row
+            .try_get_unchecked::<Option<DateTime<Utc>>, _>(i)
+            .ok()
+            .flatten()
+            .map(TimelineValue::Timestamp),
+        TimelineColumnType::Boolean => row
+            .try_get_unchecked::<Option<bool>, _>(i)
+            .ok()
+            .flatten()
+            .map(TimelineValue::Boolean),
+    };
+    value.unwrap_or(TimelineValue::Null)
+}
+
+impl DatabaseManager {
+    /// Up to `limit` rows of a timeline table with an id after `after_id`, in id order.
+    /// `start` and `end` bound the recording time when given.
+    pub async fn timeline_chunk(
+        &self,
+        table: TimelineTable,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<TimelineChunk, sqlx::Error> {
+        let columns = table.columns();
+        let rows = sqlx::query(table.select())
+            .bind(after_id)
+            .bind(start)
+            .bind(end)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        let next_after_id = match rows.last() {
+            Some(last) if rows.len() == limit as usize => Some(last.try_get::<i64, _>(0)?),
+            _ => None,
+        };
+        Ok(TimelineChunk {
+            rows: rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, column)| decode(row, i, column.column_type))
+                        .collect()
+                })
+                .collect(),
+            next_after_id,
+        })
+    }
+}
@@ -0,0 +1,461 @@
+//! Full text search settings: the tokenizer of the FTS tables, rebuilt in the background when
+//! it changes, and the languages whose stems are added to search queries.
+//!
+//! A rebuild creates `<table>_rebuild` with the new tokenizer, copies the rows of the table to
+//! it in batches while copies of the table triggers mirror new writes into it, then swaps it in.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rust_stemmers::{Algorithm, Stemmer};
+use sqlx::SqliteConnection;
+use tracing::{debug, info};
+
+use crate::{DatabaseManager, FtsRebuild, FtsTokenizer, ParsedQuery};
+
+/// FTS tables of the recorded content, in rebuild order
+pub const FTS_TABLES: &[&str] = &[
+    "ocr_text_fts",
+    "audio_transcriptions_fts",
+    "ui_monitoring_fts",
+    "frames_fts",
+];
+
+/// Table whose tokenizer [`DatabaseManager::fts_tokenizer`] reports
+const REFERENCE_FTS_TABLE: &str = "ocr_text_fts";
+
+/// Stems shorter than this would match too many words as prefixes
+const MIN_STEM_CHARS: usize = 3;
+
+impl DatabaseManager {
+    /// Tokenizer of the screen text index. Tables switch one at a time during a rebuild, use
+    /// [`DatabaseManager::fts_tokenizer_of`] for the others.
+    pub fn fts_tokenizer(&self) -> FtsTokenizer {
+        self.fts_tokenizer_of(REFERENCE_FTS_TABLE)
+    }
+
+    /// Tokenizer `table` is indexed with
+    pub fn fts_tokenizer_of(&self, table: &str) -> FtsTokenizer {
+        self.fts_tokenizers
+            .read()
+            .unwrap()
+            .get(table)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Languages whose stems are added to the words of search queries, none by default
+    pub fn set_stemming_languages(&self, algorithms: Vec<Algorithm>) {
+        *self.stemming.write().unwrap() = algorithms;
+    }
+
+    /// `parsed` with the stems of its words in the stemming languages
+    pub fn expand_query(&self, parsed: &ParsedQuery) -> ParsedQuery {
+        let algorithms = self.stemming.read().unwrap().clone();
+        if algorithms.is_empty() {
+            return parsed.clone();
+        }
+        let stemmers: Vec<Stemmer> = algorithms.into_iter().map(Stemmer::create).collect();
+        parsed.with_stems(&|word: &str| {
+            let word = word.to_lowercase();
+            stemmers
+                .iter()
+                .map(|stemmer| stemmer.stem(&word).into_owned())
+                .filter(|stem| stem.chars().count() >= MIN_STEM_CHARS)
+                .collect()
+        })
+    }
+
+    /// FTS match expression of a search query on the screen text, stemmed and written for
+    /// the tokenizer of that table
+    pub fn fts_query(&self, parsed: &ParsedQuery, fuzzy: bool) -> String {
+        self.expand_query(parsed)
+            .fts_query_for(self.fts_tokenizer(), fuzzy)
+    }
+
+    /// Reads the tokenizer of each FTS table and drops the queued rebuilds of tables already
+    /// indexed with the tokenizer they were queued for
+    pub(crate) async fn load_fts_tokenizers(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut tokenizers = HashMap::new();
+        for table in FTS_TABLES {
+            let Some(sql) = table_sql(&mut tx, table).await? else {
+                continue;
+            };
+            let option = tokenize_option(&sql);
+            tokenizers.insert(
+                table.to_string(),
+                FtsTokenizer::from_tokenize_option(&option),
+            );
+
+            let pending: Option<String> = sqlx::query_scalar(
+                "SELECT tokenizer FROM fts_rebuilds WHERE table_name = ?1 AND max_rowid IS NULL",
+            )
+            .bind(table)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let up_to_date = pending
+                .as_deref()
+                .and_then(FtsTokenizer::from_name)
+                .is_some_and(|tokenizer| tokenizer.tokenize_option() == option);
+            if up_to_date {
+                debug!(
+                    "{} is already indexed with {}, skipping its rebuild",
+                    table, option
+                );
+                sqlx::query("DELETE FROM fts_rebuilds WHERE table_name = ?1")
+                    .bind(table)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        *self.fts_tokenizers.write().unwrap() = tokenizers;
+        Ok(())
+    }
+
+    /// Queues a rebuild of the FTS tables not indexed with `tokenizer`, a rebuild started with
+    /// another tokenizer is dropped. Returns the tables that will be rebuilt.
+    pub async fn queue_fts_rebuild(
+        &self,
+        tokenizer: FtsTokenizer,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut queued = Vec::new();
+        for table in FTS_TABLES {
+            let Some(sql) = table_sql(&mut tx, table).await? else {
+                continue;
+            };
+            let pending: Option<(String, Option<i64>)> = sqlx::query_as(
+                "SELECT tokenizer, max_rowid FROM fts_rebuilds WHERE table_name = ?1",
+            )
+            .bind(table)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match pending {
+                // a running rebuild to this tokenizer carries on
+                Some((name, Some(_))) if name == tokenizer.name() => {
+                    queued.push(table.to_string());
+                    continue;
+                }
+                Some((_, Some(_))) => drop_rebuild(&mut tx, table).await?,
+                _ => {}
+            }
+
+            if tokenize_option(&sql) == tokenizer.tokenize_option() {
+                sqlx::query("DELETE FROM fts_rebuilds WHERE table_name = ?1")
+                    .bind(table)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query(
+                    r#"
+                    INSERT INTO fts_rebuilds (table_name, tokenizer) VALUES (?1, ?2)
+                    ON CONFLICT(table_name) DO UPDATE SET
+                        tokenizer = ?2, last_rowid = 0, max_rowid = NULL, queued_at = CURRENT_TIMESTAMP
+                    "#,
+                )
+                .bind(table)
+                .bind(tokenizer.name())
+                .execute(&mut *tx)
+                .await?;
+                queued.push(table.to_string());
+            }
+        }
+        tx.commit().await?;
+        Ok(queued)
+    }
+
+    /// Queued and running rebuilds, in rebuild order
+    pub async fn fts_rebuilds(&self) -> Result<Vec<FtsRebuild>, sqlx::Error> {
+        let rows: Vec<(String, String, i64, Option<i64>)> =
+            sqlx::query_as("SELECT table_name, tokenizer, last_rowid, max_rowid FROM fts_rebuilds")
+                .fetch_all(&self.pool)
+                .await?;
+        let mut rebuilds: Vec<FtsRebuild> = rows
+            .into_iter()
+            .filter_map(|(table_name, tokenizer, last_rowid, max_rowid)| {
+                Some(FtsRebuild {
+                    tokenizer: FtsTokenizer::from_name(&tokenizer)?,
+                    table_name,
+                    last_rowid,
+                    max_rowid,
+                })
+            })
+            .collect();
+        rebuilds.sort_by_key(|rebuild| {
+            FTS_TABLES
+                .iter()
+                .position(|table| *table == rebuild.table_name)
+                .unwrap_or(FTS_TABLES.len())
+        });
+        Ok(rebuilds)
+    }
+
+    /// Runs one step of the first queued rebuild: starting it, copying up to `batch_size` rows
+    /// or swapping the rebuilt table in. Returns false once there is nothing left to rebuild.
+    pub async fn rebuild_fts_batch(&self, batch_size: i64) -> Result<bool, sqlx::Error> {
+        let Some(rebuild) = self.fts_rebuilds().await?.into_iter().next() else {
+            return Ok(false);
+        };
+        let table = rebuild.table_name.as_str();
+        let rebuilt = rebuild_table(table);
+
+        let mut tx = self.pool.begin().await?;
+        let Some(max_rowid) = rebuild.max_rowid else {
+            start_rebuild(&mut tx, table, rebuild.tokenizer).await?;
+            tx.commit().await?;
+            return Ok(true);
+        };
+
+        let (last,): (Option<i64>,) = sqlx::query_as(&format!(
+            "SELECT MAX(rowid) FROM (SELECT rowid FROM {} WHERE rowid > ?1 AND rowid <= ?2 ORDER BY rowid LIMIT ?3)",
+            table
+        ))
+        .bind(rebuild.last_rowid)
+        .bind(max_rowid)
+        .bind(batch_size)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        match last {
+            Some(last) => {
+                let columns = fts_columns(&mut tx, table).await?.join(", ");
+                sqlx::query(&format!(
+                    "INSERT INTO {} ({}) SELECT {} FROM {} WHERE rowid > ?1 AND rowid <= ?2 ORDER BY rowid",
+                    rebuilt, columns, columns, table
+                ))
+                .bind(rebuild.last_rowid)
+                .bind(last)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("UPDATE fts_rebuilds SET last_rowid = ?1 WHERE table_name = ?2")
+                    .bind(last)
+                    .bind(table)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                debug!(
+                    "copied the rows of {} up to {} of {}",
+                    table, last, max_rowid
+                );
+            }
+            None => {
+                swap_rebuild(&mut tx, table).await?;
+                tx.commit().await?;
+                self.fts_tokenizers
+                    .write()
+                    .unwrap()
+                    .insert(table.to_string(), rebuild.tokenizer);
+                info!(
+                    "rebuilt {} with the {} tokenizer",
+                    table,
+                    rebuild.tokenizer.name()
+                );
+            }
+        }
+        Ok(true)
+    }
+
+    /// Runs the queued rebuilds to the end, pausing between batches so writes aren't held up
+    pub async fn rebuild_fts_indexes(
+        &self,
+        batch_size: i64,
+        pause: Duration,
+    ) -> Result<(), sqlx::Error> {
+        while self.rebuild_fts_batch(batch_size).await? {
+            tokio::time::sleep(pause).await;
+        }
+        Ok(())
+    }
+}
+
+fn rebuild_table(table: &str) -> String {
+    format!("{}_rebuild", table)
+}
+
+async fn table_sql(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1")
+        .bind(table)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn fts_columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<String>, sqlx::Error> {
+    let names: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?1)")
+        .bind(table)
+        .fetch_all(conn)
+        .await?;
+    Ok(names
+        .iter()
+        .map(|name| format!("\"{}\"", name.replace('"', "\"\"")))
+        .collect())
+}
+
+/// Arguments of a `CREATE VIRTUAL TABLE ... USING fts5(...)` statement
+fn fts_arguments(sql: &str) -> Vec<String> {
+    let (Some(start), Some(end)) = (sql.find('('), sql.rfind(')')) else {
+        return Vec::new();
+    };
+    sql[start + 1..end]
+        .split(',')
+        .map(|argument| argument.trim().to_string())
+        .filter(|argument| !argument.is_empty())
+        .collect()
+}
+
+/// Value of the `tokenize` option of an FTS5 table, FTS5 defaults to unicode61
+fn tokenize_option(sql: &str) -> String {
+    fts_arguments(sql)
+        .iter()
+        .find_map(|argument| {
+            let (name, value) = argument.split_once('=')?;
+            (name.trim() == "tokenize").then(|| {
+                value
+                    .trim()
+                    .trim_matches(|c| c == '\'' || c == '"')
+                    .to_string()
+            })
+        })
+        .unwrap_or_else(|| "unicode61".to_string())
+}
+
+/// Whether `sql` uses the identifier `name`, and not only a longer one starting with it
+fn uses_identifier(sql: &str, name: &str) -> bool {
+    identifier_positions(sql, name).next().is_some()
+}
+
+fn identifier_positions<'a>(sql: &'a str, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_';
+    sql.match_indices(name).map(|(i, _)| i).filter(move |&i| {
+        !sql[..i].ends_with(is_identifier_char)
+            && !sql[i + name.len()..].starts_with(is_identifier_char)
+    })
+}
+
+fn replace_identifier(sql: &str, name: &str, replacement: &str) -> String {
+    let mut out = String::new();
+    let mut copied = 0;
+    for i in identifier_positions(sql, name) {
+        out.push_str(&sql[copied..i]);
+        out.push_str(replacement);
+        copied = i + name.len();
+    }
+    out.push_str(&sql[copied..]);
+    out
+}
+
+/// Triggers writing to `table`
+async fn triggers_of(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let triggers: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND sql LIKE '%' || ?1 || '%'",
+    )
+    .bind(table)
+    .fetch_all(conn)
+    .await?;
+    Ok(triggers
+        .into_iter()
+        .filter(|(_, sql)| uses_identifier(sql, table))
+        .collect())
+}
+
+async fn start_rebuild(
+    conn: &mut SqliteConnection,
+    table: &str,
+    tokenizer: FtsTokenizer,
+) -> Result<(), sqlx::Error> {
+    let sql = table_sql(conn, table).await?;
+    let rebuilt = rebuild_table(table);
+    let Some(sql) = sql.filter(|sql| tokenize_option(sql) != tokenizer.tokenize_option()) else {
+        // dropped or already indexed with this tokenizer
+        sqlx::query("DELETE FROM fts_rebuilds WHERE table_name = ?1")
+            .bind(table)
+            .execute(conn)
+            .await?;
+        return Ok(());
+    };
+
+    drop_rebuild(conn, table).await?;
+    let mut arguments: Vec<String> = fts_arguments(&sql)
+        .into_iter()
+        .filter(|argument| !argument.starts_with("tokenize"))
+        .collect();
+    arguments.push(format!("tokenize='{}'", tokenizer.tokenize_option()));
+    sqlx::query(&format!(
+        "CREATE VIRTUAL TABLE {} USING fts5({})",
+        rebuilt,
+        arguments.join(", ")
+    ))
+    .execute(&mut *conn)
+    .await?;
+
+    for (name, trigger_sql) in triggers_of(conn, table).await? {
+        let mirror = replace_identifier(&trigger_sql, table, &rebuilt);
+        let mirror = replace_identifier(&mirror, &name, &rebuild_table(&name));
+        sqlx::query(&mirror).execute(&mut *conn).await?;
+    }
+
+    // rows written from now on reach the new table through the mirror triggers
+    sqlx::query(&format!(
+        "UPDATE fts_rebuilds SET last_rowid = 0, max_rowid = (SELECT COALESCE(MAX(rowid), 0) FROM {}) WHERE table_name = ?1",
+        table
+    ))
+    .bind(table)
+    .execute(&mut *conn)
+    .await?;
+    info!(
+        "rebuilding {} with the {} tokenizer",
+        table,
+        tokenizer.name()
+    );
+    Ok(())
+}
+
+async fn drop_rebuild(conn: &mut SqliteConnection, table: &str) -> Result<(), sqlx::Error> {
+    let rebuilt = rebuild_table(table);
+    for (name, _) in triggers_of(conn, &rebuilt).await? {
+        sqlx::query(&format!("DROP TRIGGER IF EXISTS {}", name))
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query(&format!("DROP TABLE IF EXISTS {}", rebuilt))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn swap_rebuild(conn: &mut SqliteConnection, table: &str) -> Result<(), sqlx::Error> {
+    let rebuilt = rebuild_table(table);
+    for (name, _) in triggers_of(conn, &rebuilt).await? {
+        sqlx::query(&format!("DROP TRIGGER {}", name))
+            .execute(&mut *conn)
+            .await?;
+    }
+    // the triggers are recreated once the table they write to is back
+    let triggers = triggers_of(conn, table).await?;
+    for (name, _) in &triggers {
+        sqlx::query(&format!("DROP TRIGGER {}", name))
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query(&format!("DROP TABLE {}", table))
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(&format!("ALTER TABLE {} RENAME TO {}", rebuilt, table))
+        .execute(&mut *conn)
+        .await?;
+    for (_, sql) in &triggers {
+        sqlx::query(sql).execute(&mut *conn).await?;
+    }
+    sqlx::query("DELETE FROM fts_rebuilds WHERE table_name = ?1")
+        .bind(table)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
@@ -1,11 +1,24 @@
+mod analytics_db;
+mod archive_db;
 mod db;
+mod export_db;
+mod fts_db;
 mod migration_worker;
+mod query;
+mod sessions_db;
+mod snippet;
+mod sync_db;
 mod types;
 mod video_db;
 
-pub use db::DatabaseManager;
+pub use analytics_db::MAX_FRAME_GAP_SECS;
+pub use archive_db::{read_archive_manifest, ARCHIVE_FORMAT_VERSION, ARCHIVE_MANIFEST};
+pub use db::{find_matching_positions, DatabaseManager, DEFAULT_SPEAKER_MATCH_THRESHOLD};
+pub use fts_db::FTS_TABLES;
 pub use migration_worker::{
-    create_migration_worker, MigrationCommand, MigrationConfig, MigrationResponse, MigrationStatus,
-    MigrationWorker,
+    create_migration_worker, MigrationCommand, MigrationConfig, MigrationProgress,
+    MigrationResponse, MigrationStatus, MigrationWorker,
 };
+pub use query::{fts_quote, parse_query, ParsedQuery, QueryError, QueryExpr, QUERY_FIELDS};
+pub use snippet::{highlight, snippet, SnippetOptions};
 pub use types::*;
@@ -12,6 +12,8 @@ use tracing::{debug, error, info, warn};
 
 use crate::DatabaseManager;
 
+const OCR_TEXT_TO_FRAMES: &str = "ocr_text_to_frames";
+
 /// Status of a migration job
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum MigrationStatus {
@@ -200,7 +202,9 @@ impl MigrationWorker {
                     error!("Migration failed: {error_msg}");
 
                     // Try to get the current progress
-                    let processed = get_migration_progress(&db.pool).await.unwrap_or(0);
+                    let processed = get_migration_progress(&db.pool, OCR_TEXT_TO_FRAMES)
+                        .await
+                        .unwrap_or(0);
                     let total = get_total_records(&db.pool).await.unwrap_or(0);
 
                     let _ = status_tx
@@ -268,16 +272,17 @@ async fn ensure_migration_table(pool: &SqlitePool) -> Result<()> {
 }
 
 /// Get the current migration progress
-async fn get_migration_progress(pool: &SqlitePool) -> Result<i64> {
+async fn get_migration_progress(pool: &SqlitePool, migration_name: &str) -> Result<i64> {
     ensure_migration_table(pool).await?;
 
     let progress = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT processed_records FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         ORDER BY id DESC LIMIT 1
         "#,
     )
+    .bind(migration_name)
     .fetch_optional(pool)
     .await?
     .unwrap_or(0);
@@ -304,6 +309,7 @@ async fn get_total_records(pool: &SqlitePool) -> Result<i64> {
 /// Update the migration progress
 async fn update_migration_progress(
     pool: &SqlitePool,
+    migration_name: &str,
     last_id: i64,
     total: i64,
     processed: i64,
@@ -318,9 +324,10 @@ async fn update_migration_progress(
     let existing = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT id FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         "#,
     )
+    .bind(migration_name)
     .fetch_optional(pool)
     .await?;
 
@@ -354,7 +361,7 @@ async fn update_migration_progress(
             VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind("ocr_text_to_frames")
+        .bind(migration_name)
         .bind(last_id)
         .bind(total)
         .bind(processed)
@@ -393,15 +400,16 @@ async fn migrate_ocr_data_to_frames(
     let last_processed_id = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT last_processed_id FROM migration_progress 
-        WHERE migration_name = 'ocr_text_to_frames'
+        WHERE migration_name = ?
         ORDER BY id DESC LIMIT 1
         "#,
     )
+    .bind(OCR_TEXT_TO_FRAMES)
     .fetch_optional(pool)
     .await?
     .unwrap_or(0);
 
-    let mut processed_records = get_migration_progress(pool).await?;
+    let mut processed_records = get_migration_progress(pool, OCR_TEXT_TO_FRAMES).await?;
 
     info!(
         "Starting migration: total_records={}, already_processed={}, last_processed_id={}",
@@ -454,6 +462,7 @@ async fn migrate_ocr_data_to_frames(
                 // Update progress
                 if let Err(e) = update_migration_progress(
                     pool,
+                    OCR_TEXT_TO_FRAMES,
                     last_id,
                     total_records,
                     processed_records,
@@ -497,7 +506,15 @@ async fn migrate_ocr_data_to_frames(
 
     // Final update to mark as completed
     if processed_records >= total_records {
-        update_migration_progress(pool, last_id, total_records, processed_records, true).await?;
+        update_migration_progress(
+            pool,
+            OCR_TEXT_TO_FRAMES,
+            last_id,
+            total_records,
+            processed_records,
+            true,
+        )
+        .await?;
     }
 
     Ok((processed_records, duration))
@@ -571,6 +588,122 @@ async fn process_batch(pool: &SqlitePool, last_id: i64, batch_size: i64) -> Resu
     Ok((count, max_id))
 }
 
+/// Persisted progress of a resumable background job
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MigrationProgress {
+    pub last_processed_id: i64,
+    pub total_records: i64,
+    pub processed_records: i64,
+    pub completed: bool,
+}
+
+impl DatabaseManager {
+    /// Load the saved progress of a named background job, if it ever ran
+    pub async fn get_migration_progress(
+        &self,
+        migration_name: &str,
+    ) -> Result<Option<MigrationProgress>> {
+        ensure_migration_table(&self.pool).await?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT last_processed_id, total_records, processed_records, completed_at
+            FROM migration_progress
+            WHERE migration_name = ?
+            ORDER BY id DESC LIMIT 1
+            "#,
+        )
+        .bind(migration_name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| MigrationProgress {
+            last_processed_id: row.get("last_processed_id"),
+            total_records: row.get("total_records"),
+            processed_records: row.get("processed_records"),
+            completed: row
+                .get::<Option<chrono::DateTime<Utc>>, _>("completed_at")
+                .is_some(),
+        }))
+    }
+
+    /// Save the progress of a named background job so it can be resumed later
+    pub async fn update_migration_progress(
+        &self,
+        migration_name: &str,
+        progress: &MigrationProgress,
+    ) -> Result<()> {
+        update_migration_progress(
+            &self.pool,
+            migration_name,
+            progress.last_processed_id,
+            progress.total_records,
+            progress.processed_records,
+            progress.completed,
+        )
+        .await
+    }
+
+    /// Ask a named background job to pause or stop, it may run in another process. Any other
+    /// command clears the request so the job runs on.
+    pub async fn set_job_request(&self, job_name: &str, command: &MigrationCommand) -> Result<()> {
+        let command = match command {
+            MigrationCommand::Pause => "pause",
+            MigrationCommand::Stop => "stop",
+            MigrationCommand::Start | MigrationCommand::Status => {
+                sqlx::query("DELETE FROM job_requests WHERE job_name = ?1")
+                    .bind(job_name)
+                    .execute(&self.pool)
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO job_requests (job_name, command, requested_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT(job_name) DO UPDATE SET
+                command = excluded.command,
+                requested_at = excluded.requested_at
+            "#,
+        )
+        .bind(job_name)
+        .bind(command)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Pause or stop asked for a named background job, if any
+    pub async fn get_job_request(&self, job_name: &str) -> Result<Option<MigrationCommand>> {
+        let command: Option<String> =
+            sqlx::query_scalar("SELECT command FROM job_requests WHERE job_name = ?1")
+                .bind(job_name)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(match command.as_deref() {
+            Some("pause") => Some(MigrationCommand::Pause),
+            Some("stop") => Some(MigrationCommand::Stop),
+            _ => None,
+        })
+    }
+
+    /// Forget the progress of a named background job so the next run starts over
+    pub async fn reset_migration_progress(&self, migration_name: &str) -> Result<()> {
+        ensure_migration_table(&self.pool).await?;
+
+        sqlx::query("DELETE FROM migration_progress WHERE migration_name = ?")
+            .bind(migration_name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
 impl MigrationConfig {
     pub fn new(batch_size: i64, batch_delay_ms: u64, continue_on_error: bool) -> Self {
         Self {
@@ -0,0 +1,5 @@
+-- Speaker profiles: keep several embeddings per speaker and a centroid used for matching
+ALTER TABLE speakers ADD COLUMN centroid BLOB DEFAULT NULL;
+ALTER TABLE speaker_embeddings ADD COLUMN source TEXT NOT NULL DEFAULT 'auto';
+
+CREATE INDEX IF NOT EXISTS idx_speaker_embeddings_speaker_id ON speaker_embeddings(speaker_id);
@@ -0,0 +1,2 @@
+-- Structured OCR layout (blocks, paragraphs, lines and words with their boxes), as json
+ALTER TABLE ocr_text ADD COLUMN layout TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Encoding profile the chunk was written with, as json
+ALTER TABLE video_chunks ADD COLUMN encoding_profile TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- When the chunk was re-encoded into the storage tier of old recordings
+ALTER TABLE video_chunks ADD COLUMN recompressed_at TIMESTAMP DEFAULT NULL;
@@ -0,0 +1,17 @@
+-- Rebuilds of the FTS tables with another tokenizer, done in batches by a background task.
+-- max_rowid is set when the rebuild starts, rows of the table up to it are then copied to
+-- <table_name>_rebuild while triggers mirror new writes into it.
+CREATE TABLE IF NOT EXISTS fts_rebuilds (
+    table_name TEXT PRIMARY KEY,
+    tokenizer TEXT NOT NULL,
+    last_rowid INTEGER NOT NULL DEFAULT 0,
+    max_rowid INTEGER DEFAULT NULL,
+    queued_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Existing indexes are rebuilt with diacritic folding
+INSERT OR IGNORE INTO fts_rebuilds (table_name, tokenizer) VALUES
+    ('ocr_text_fts', 'unicode61'),
+    ('audio_transcriptions_fts', 'unicode61'),
+    ('ui_monitoring_fts', 'unicode61'),
+    ('frames_fts', 'unicode61');
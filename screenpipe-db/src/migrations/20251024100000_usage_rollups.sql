@@ -0,0 +1,70 @@
+-- Screen time and talk time per hour, recomputed from frames and audio_transcriptions for the
+-- hours marked in usage_dirty_hours. Hours are 'YYYY-MM-DDTHH:00:00Z' in UTC.
+CREATE TABLE IF NOT EXISTS screen_time_rollups (
+    hour TEXT NOT NULL,
+    app_name TEXT NOT NULL,
+    window_name TEXT NOT NULL,
+    -- '' outside of browsers
+    browser_domain TEXT NOT NULL,
+    seconds REAL NOT NULL,
+    frames INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_screen_time_rollups_hour ON screen_time_rollups(hour);
+
+CREATE TABLE IF NOT EXISTS talk_time_rollups (
+    hour TEXT NOT NULL,
+    speaker_id INTEGER,
+    seconds REAL NOT NULL,
+    segments INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_talk_time_rollups_hour ON talk_time_rollups(hour);
+
+CREATE TABLE IF NOT EXISTS usage_dirty_hours (
+    hour TEXT PRIMARY KEY
+);
+
+CREATE TRIGGER IF NOT EXISTS frames_usage_ai AFTER INSERT ON frames
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp));
+END;
+
+CREATE TRIGGER IF NOT EXISTS frames_usage_au AFTER UPDATE OF timestamp, app_name, window_name, browser_url ON frames
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp)),
+           (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp));
+END;
+
+-- a frame's time runs until the next frame, so deleting one also changes the hour before it
+CREATE TRIGGER IF NOT EXISTS frames_usage_ad AFTER DELETE ON frames
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp)),
+           (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp, '-1 hour'));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_usage_ai AFTER INSERT ON audio_transcriptions
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_usage_au AFTER UPDATE OF timestamp, speaker_id, start_time, end_time ON audio_transcriptions
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp)),
+           (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp));
+END;
+
+CREATE TRIGGER IF NOT EXISTS audio_transcriptions_usage_ad AFTER DELETE ON audio_transcriptions
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp));
+END;
+
+-- existing recordings are rolled up on the first analytics request
+INSERT OR IGNORE INTO usage_dirty_hours (hour)
+SELECT DISTINCT strftime('%Y-%m-%dT%H:00:00Z', timestamp) FROM frames WHERE timestamp IS NOT NULL
+UNION
+SELECT DISTINCT strftime('%Y-%m-%dT%H:00:00Z', timestamp) FROM audio_transcriptions WHERE timestamp IS NOT NULL;
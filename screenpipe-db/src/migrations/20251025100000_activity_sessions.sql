@@ -0,0 +1,19 @@
+-- Contiguous work sessions built from the frames by the sessionizer. Only the last session is
+-- open: it is recomputed from its start on every pass until the idle gap closes it.
+CREATE TABLE IF NOT EXISTS activity_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time TIMESTAMP NOT NULL,
+    end_time TIMESTAMP NOT NULL,
+    -- app the session spent the most time in
+    app_name TEXT NOT NULL,
+    browser_domain TEXT,
+    title TEXT NOT NULL,
+    -- JSON array of the most frequent words of the session
+    keywords TEXT NOT NULL DEFAULT '[]',
+    frame_count INTEGER NOT NULL,
+    audio_segments INTEGER NOT NULL DEFAULT 0,
+    audio_seconds REAL NOT NULL DEFAULT 0,
+    closed BOOLEAN NOT NULL DEFAULT FALSE
+);
+CREATE INDEX IF NOT EXISTS idx_activity_sessions_start_time ON activity_sessions(start_time);
+CREATE INDEX IF NOT EXISTS idx_activity_sessions_end_time ON activity_sessions(end_time);
@@ -0,0 +1,16 @@
+-- Stable id of this database, written in the archives it exports
+CREATE TABLE IF NOT EXISTS database_identity (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    database_id TEXT NOT NULL
+);
+INSERT OR IGNORE INTO database_identity (id, database_id) VALUES (1, lower(hex(randomblob(16))));
+
+-- Rows imported from archives, keyed by the database that exported them, so importing the same
+-- or an overlapping archive again maps its rows to the ones already imported
+CREATE TABLE IF NOT EXISTS imported_rows (
+    source_id TEXT NOT NULL,
+    table_name TEXT NOT NULL,
+    source_row_id INTEGER NOT NULL,
+    row_id INTEGER NOT NULL,
+    PRIMARY KEY (source_id, table_name, source_row_id)
+);
@@ -0,0 +1,232 @@
+-- Machine that recorded a row, the id of its database. NULL for the rows recorded here.
+ALTER TABLE video_chunks ADD COLUMN machine_id TEXT;
+ALTER TABLE frames ADD COLUMN machine_id TEXT;
+ALTER TABLE audio_chunks ADD COLUMN machine_id TEXT;
+ALTER TABLE audio_transcriptions ADD COLUMN machine_id TEXT;
+ALTER TABLE ui_monitoring ADD COLUMN machine_id TEXT;
+
+-- Change feed served to the machines syncing from this one: every inserted or updated row moves
+-- to the end, so pulling the entries after a watermark returns what changed since
+CREATE TABLE IF NOT EXISTS sync_log (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    row_id INTEGER NOT NULL,
+    UNIQUE (table_name, row_id)
+);
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'tags', rowid FROM tags ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_tags_insert AFTER INSERT ON tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_tags_update AFTER UPDATE ON tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_tags_delete AFTER DELETE ON tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'tags' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'video_chunks', rowid FROM video_chunks ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_video_chunks_insert AFTER INSERT ON video_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'video_chunks' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('video_chunks', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_video_chunks_update AFTER UPDATE ON video_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'video_chunks' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('video_chunks', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_video_chunks_delete AFTER DELETE ON video_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'video_chunks' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'frames', rowid FROM frames ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_frames_insert AFTER INSERT ON frames
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'frames' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('frames', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_frames_update AFTER UPDATE ON frames
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'frames' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('frames', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_frames_delete AFTER DELETE ON frames
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'frames' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'ocr_text', rowid FROM ocr_text ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_insert AFTER INSERT ON ocr_text
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ocr_text', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_update AFTER UPDATE ON ocr_text
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ocr_text', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_delete AFTER DELETE ON ocr_text
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'ocr_text_embeddings', rowid FROM ocr_text_embeddings ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_embeddings_insert AFTER INSERT ON ocr_text_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text_embeddings' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ocr_text_embeddings', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_embeddings_update AFTER UPDATE ON ocr_text_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text_embeddings' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ocr_text_embeddings', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ocr_text_embeddings_delete AFTER DELETE ON ocr_text_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ocr_text_embeddings' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'vision_tags', rowid FROM vision_tags ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_vision_tags_insert AFTER INSERT ON vision_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'vision_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('vision_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_vision_tags_update AFTER UPDATE ON vision_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'vision_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('vision_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_vision_tags_delete AFTER DELETE ON vision_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'vision_tags' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'speakers', rowid FROM speakers ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_speakers_insert AFTER INSERT ON speakers
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speakers' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('speakers', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_speakers_update AFTER UPDATE ON speakers
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speakers' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('speakers', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_speakers_delete AFTER DELETE ON speakers
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speakers' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'speaker_embeddings', rowid FROM speaker_embeddings ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_speaker_embeddings_insert AFTER INSERT ON speaker_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speaker_embeddings' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('speaker_embeddings', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_speaker_embeddings_update AFTER UPDATE ON speaker_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speaker_embeddings' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('speaker_embeddings', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_speaker_embeddings_delete AFTER DELETE ON speaker_embeddings
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'speaker_embeddings' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'audio_chunks', rowid FROM audio_chunks ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_chunks_insert AFTER INSERT ON audio_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_chunks' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_chunks', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_chunks_update AFTER UPDATE ON audio_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_chunks' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_chunks', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_chunks_delete AFTER DELETE ON audio_chunks
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_chunks' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'audio_transcriptions', rowid FROM audio_transcriptions ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_transcriptions_insert AFTER INSERT ON audio_transcriptions
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_transcriptions' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_transcriptions', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_transcriptions_update AFTER UPDATE ON audio_transcriptions
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_transcriptions' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_transcriptions', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_transcriptions_delete AFTER DELETE ON audio_transcriptions
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_transcriptions' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'audio_tags', rowid FROM audio_tags ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_tags_insert AFTER INSERT ON audio_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_tags_update AFTER UPDATE ON audio_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('audio_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_audio_tags_delete AFTER DELETE ON audio_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'audio_tags' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'ui_monitoring', rowid FROM ui_monitoring ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_insert AFTER INSERT ON ui_monitoring
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ui_monitoring', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_update AFTER UPDATE ON ui_monitoring
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ui_monitoring', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_delete AFTER DELETE ON ui_monitoring
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring' AND row_id = OLD.rowid;
+END;
+
+INSERT INTO sync_log (table_name, row_id) SELECT 'ui_monitoring_tags', rowid FROM ui_monitoring_tags ORDER BY rowid;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_tags_insert AFTER INSERT ON ui_monitoring_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ui_monitoring_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_tags_update AFTER UPDATE ON ui_monitoring_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring_tags' AND row_id = NEW.rowid;
+    INSERT INTO sync_log (table_name, row_id) VALUES ('ui_monitoring_tags', NEW.rowid);
+END;
+CREATE TRIGGER IF NOT EXISTS sync_log_ui_monitoring_tags_delete AFTER DELETE ON ui_monitoring_tags
+BEGIN
+    DELETE FROM sync_log WHERE table_name = 'ui_monitoring_tags' AND row_id = OLD.rowid;
+END;
+
+-- Machines this one pulls from and how far it got in their change feed
+CREATE TABLE IF NOT EXISTS sync_peers (
+    url TEXT PRIMARY KEY,
+    peer_id TEXT,
+    watermark INTEGER NOT NULL DEFAULT 0,
+    last_sync_at TIMESTAMP,
+    last_error TEXT
+);
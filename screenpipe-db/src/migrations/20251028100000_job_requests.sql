@@ -0,0 +1,7 @@
+-- Pause or stop asked for a resumable background job, polled by the job so the request can come
+-- from another process than the one running it
+CREATE TABLE IF NOT EXISTS job_requests (
+    job_name TEXT PRIMARY KEY,
+    command TEXT NOT NULL,
+    requested_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Files replaced on disk whose removal is delayed for readers that looked them up before the
+-- swap, kept here so a restart does not leak them
+CREATE TABLE IF NOT EXISTS pending_file_deletions (
+    file_path TEXT PRIMARY KEY,
+    delete_after TIMESTAMP NOT NULL,
+    reclaimed_bytes INTEGER NOT NULL DEFAULT 0
+);
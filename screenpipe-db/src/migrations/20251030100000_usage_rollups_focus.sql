@@ -0,0 +1,20 @@
+-- Screen time counts the focused frames of each device. A new frame gives its time to the frame
+-- before it on the same device, up to 60 seconds earlier and possibly in the previous hour.
+DROP TRIGGER IF EXISTS frames_usage_ai;
+CREATE TRIGGER frames_usage_ai AFTER INSERT ON frames
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp)),
+           (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp, '-60 seconds'));
+END;
+
+DROP TRIGGER IF EXISTS frames_usage_au;
+CREATE TRIGGER frames_usage_au AFTER UPDATE OF timestamp, app_name, window_name, browser_url, focused, video_chunk_id ON frames
+BEGIN
+    INSERT OR IGNORE INTO usage_dirty_hours (hour)
+    VALUES (strftime('%Y-%m-%dT%H:00:00Z', OLD.timestamp)),
+           (strftime('%Y-%m-%dT%H:00:00Z', NEW.timestamp));
+END;
+
+-- hours rolled up before are recomputed by the background refresh
+INSERT OR IGNORE INTO usage_dirty_hours (hour) SELECT DISTINCT hour FROM screen_time_rollups;
@@ -0,0 +1,23 @@
+-- Frames recorded before the sessions already built, by an archive import or a sync, are not
+-- read by the sessionizer anymore. Their earliest timestamp is kept so the next pass rebuilds
+-- the sessions from there.
+CREATE TABLE IF NOT EXISTS sessionize_backlog (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    since TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_sessions_open ON activity_sessions(start_time)
+    WHERE closed = 0;
+
+CREATE TRIGGER IF NOT EXISTS frames_sessionize_ai
+AFTER INSERT ON frames
+WHEN COALESCE(NEW.app_name, '') != '' AND COALESCE(NEW.focused, 1) = 1
+    AND CASE
+        WHEN EXISTS (SELECT 1 FROM activity_sessions WHERE closed = 0)
+            THEN NEW.timestamp < (SELECT MIN(start_time) FROM activity_sessions WHERE closed = 0)
+        ELSE NEW.timestamp <= (SELECT MAX(end_time) FROM activity_sessions)
+    END
+BEGIN
+    INSERT INTO sessionize_backlog (id, since) VALUES (1, NEW.timestamp)
+    ON CONFLICT(id) DO UPDATE SET since = MIN(since, excluded.since);
+END;
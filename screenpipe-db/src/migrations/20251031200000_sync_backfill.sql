@@ -0,0 +1,16 @@
+-- Rows to add to the change feed in batches in the background, up to `up_to`. The feed serves
+-- nothing until they are all in.
+CREATE TABLE IF NOT EXISTS sync_backfill (
+    table_name TEXT PRIMARY KEY,
+    done INTEGER NOT NULL DEFAULT 0,
+    up_to INTEGER NOT NULL
+);
+
+-- Size, modification time and SHA-256 of the media files served to the peers, hashed once
+-- their recording is over and hashed again only if the file changes
+CREATE TABLE IF NOT EXISTS media_hashes (
+    file_path TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    modified_at INTEGER NOT NULL,
+    sha256 TEXT NOT NULL
+);
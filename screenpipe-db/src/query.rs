@@ -0,0 +1,689 @@
+//! Search query language of `/search`, parsed into an AST and rendered to FTS5.
+//!
+//! ```text
+//! app:Slack "quarterly plan" -draft (budget OR forecast*) speaker:Alice after:2026-09-01
+//! ```
+//!
+//! Words are ANDed, `OR` and `AND` combine terms, `-` or `NOT` excludes a term, quotes make a
+//! phrase and a trailing `*` a prefix. `field:value` filters map onto the search parameters.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::{ContentType, FtsTokenizer};
+
+/// Fields accepted as `field:value` filters
+pub const QUERY_FIELDS: &[&str] = &[
+    "app", "window", "url", "speaker", "after", "before", "type", "focused",
+];
+
+/// Parse error with the 1-based character position it was found at
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl StdError for QueryError {}
+
+/// Full text part of a query
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    Term { text: String, prefix: bool },
+    Phrase(String),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+    Not(Box<QueryExpr>),
+}
+
+impl QueryExpr {
+    /// Words matched by the expression, excluded terms left out
+    pub fn terms(&self) -> Vec<String> {
+        match self {
+            QueryExpr::Term { text, .. } | QueryExpr::Phrase(text) => vec![text.clone()],
+            QueryExpr::And(items) | QueryExpr::Or(items) => {
+                items.iter().flat_map(|item| item.terms()).collect()
+            }
+            QueryExpr::Not(_) => Vec::new(),
+        }
+    }
+
+    /// FTS5 match expression, every term and phrase is quoted so punctuation can't break it
+    pub fn to_fts(&self) -> String {
+        self.render(false, false)
+    }
+
+    /// Like [`QueryExpr::to_fts`] with every term matched as a prefix and any term enough
+    pub fn to_fuzzy_fts(&self) -> String {
+        self.render(true, false)
+    }
+
+    /// FTS5 match expression for tables indexed with `tokenizer`. Fuzzy trigram queries also
+    /// match words with one typo, trigram terms under 3 characters match nothing.
+    pub fn to_fts_for(&self, tokenizer: FtsTokenizer, fuzzy: bool) -> String {
+        self.render(fuzzy, fuzzy && tokenizer == FtsTokenizer::Trigram)
+    }
+
+    /// Each word also matches the words starting with its stems, phrases are kept as they are
+    pub fn with_stems(&self, stems: &impl Fn(&str) -> Vec<String>) -> QueryExpr {
+        match self {
+            QueryExpr::Term {
+                text,
+                prefix: false,
+            } => {
+                let mut alternatives = vec![self.clone()];
+                for stem in stems(text) {
+                    let term = QueryExpr::Term {
+                        text: stem,
+                        prefix: true,
+                    };
+                    if !term_is(&term, text) && !alternatives.contains(&term) {
+                        alternatives.push(term);
+                    }
+                }
+                if alternatives.len() == 1 {
+                    self.clone()
+                } else {
+                    QueryExpr::Or(alternatives)
+                }
+            }
+            QueryExpr::Term { .. } | QueryExpr::Phrase(_) => self.clone(),
+            QueryExpr::And(items) => {
+                QueryExpr::And(items.iter().map(|item| item.with_stems(stems)).collect())
+            }
+            QueryExpr::Or(items) => {
+                QueryExpr::Or(items.iter().map(|item| item.with_stems(stems)).collect())
+            }
+            QueryExpr::Not(expr) => QueryExpr::Not(Box::new(expr.with_stems(stems))),
+        }
+    }
+
+    fn render(&self, fuzzy: bool, typos: bool) -> String {
+        match self {
+            QueryExpr::Term { text, prefix } => {
+                let star = if *prefix || fuzzy { "*" } else { "" };
+                let term = format!("{}{}", fts_quote(text), star);
+                if typos {
+                    let mut alternatives = vec![term];
+                    alternatives.extend(typo_alternatives(text));
+                    group(alternatives, " OR ")
+                } else {
+                    term
+                }
+            }
+            QueryExpr::Phrase(text) => fts_quote(text),
+            QueryExpr::And(items) => {
+                // FTS5 NOT is binary, exclusions follow the terms they are taken from
+                let (excluded, included): (Vec<_>, Vec<_>) = items
+                    .iter()
+                    .partition(|item| matches!(item, QueryExpr::Not(_)));
+                let operator = if fuzzy { " OR " } else { " AND " };
+                let mut fts = group(
+                    included
+                        .iter()
+                        .map(|item| item.render(fuzzy, typos))
+                        .collect(),
+                    operator,
+                );
+                for item in excluded {
+                    fts = format!("{} {}", fts, item.render(fuzzy, false));
+                }
+                fts
+            }
+            QueryExpr::Or(items) => group(
+                items.iter().map(|item| item.render(fuzzy, typos)).collect(),
+                " OR ",
+            ),
+            QueryExpr::Not(expr) => format!("NOT {}", group(vec![expr.render(fuzzy, false)], "")),
+        }
+    }
+}
+
+fn term_is(term: &QueryExpr, text: &str) -> bool {
+    matches!(term, QueryExpr::Term { text: other, .. } if other.to_lowercase() == text.to_lowercase())
+}
+
+/// Trigram expressions matching `word` with one character changed, added or missing: the
+/// parts before and after each character, parts under 3 characters can't be matched and are
+/// left out as long as at most one more character is lost
+fn typo_alternatives(word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < 4 {
+        return Vec::new();
+    }
+    let mut alternatives = Vec::new();
+    for i in 0..chars.len() {
+        let parts: Vec<String> = [&chars[..i], &chars[i + 1..]]
+            .into_iter()
+            .filter(|part| part.len() >= 3)
+            .map(|part| part.iter().collect())
+            .collect();
+        let kept: usize = parts.iter().map(|part| part.chars().count()).sum();
+        if parts.is_empty() || kept + 2 < chars.len() {
+            continue;
+        }
+        let alternative = group(parts.iter().map(|part| fts_quote(part)).collect(), " AND ");
+        if !alternatives.contains(&alternative) {
+            alternatives.push(alternative);
+        }
+    }
+    alternatives
+}
+
+/// Quotes a string for FTS5, quotes inside it are doubled
+pub fn fts_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn group(parts: Vec<String>, operator: &str) -> String {
+    if parts.len() == 1 {
+        parts.into_iter().next().unwrap()
+    } else {
+        format!("({})", parts.join(operator))
+    }
+}
+
+/// A parsed query: the full text expression and the field filters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    pub text: Option<QueryExpr>,
+    pub app_name: Option<String>,
+    pub window_name: Option<String>,
+    pub browser_url: Option<String>,
+    pub speakers: Vec<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub content_type: Option<ContentType>,
+    pub focused: Option<bool>,
+}
+
+impl ParsedQuery {
+    /// FTS5 match expression of the text part, empty when the query only has filters
+    pub fn fts_query(&self) -> String {
+        self.text
+            .as_ref()
+            .map(QueryExpr::to_fts)
+            .unwrap_or_default()
+    }
+
+    pub fn fuzzy_fts_query(&self) -> String {
+        self.text
+            .as_ref()
+            .map(QueryExpr::to_fuzzy_fts)
+            .unwrap_or_default()
+    }
+
+    /// Match expression of the text part for tables indexed with `tokenizer`
+    pub fn fts_query_for(&self, tokenizer: FtsTokenizer, fuzzy: bool) -> String {
+        self.text
+            .as_ref()
+            .map(|text| text.to_fts_for(tokenizer, fuzzy))
+            .unwrap_or_default()
+    }
+
+    /// Words of the text part, for highlighting
+    pub fn terms(&self) -> Vec<String> {
+        self.text.as_ref().map(QueryExpr::terms).unwrap_or_default()
+    }
+
+    /// The query with the stems of its words as alternatives, see [`QueryExpr::with_stems`]
+    pub fn with_stems(&self, stems: &impl Fn(&str) -> Vec<String>) -> ParsedQuery {
+        ParsedQuery {
+            text: self.text.as_ref().map(|text| text.with_stems(stems)),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field {
+        name: String,
+        value: String,
+        value_position: usize,
+    },
+    Minus,
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && c != '(' && c != ')' && c != '"'
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // reads a quoted string starting at the opening quote, returns it and the next index
+    let read_quoted = |start: usize| -> Result<(String, usize), QueryError> {
+        let end = chars[start + 1..]
+            .iter()
+            .position(|&c| c == '"')
+            .map(|offset| start + 1 + offset)
+            .ok_or_else(|| QueryError::new("unterminated quote", start + 1))?;
+        Ok((chars[start + 1..end].iter().collect(), end + 1))
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push((Token::Open, position));
+            i += 1;
+        } else if c == ')' {
+            tokens.push((Token::Close, position));
+            i += 1;
+        } else if c == '"' {
+            let (phrase, next) = read_quoted(i)?;
+            tokens.push((Token::Phrase(phrase), position));
+            i = next;
+        } else if c == '-'
+            && chars
+                .get(i + 1)
+                .is_some_and(|&next| is_word_char(next) || next == '"' || next == '(')
+        {
+            tokens.push((Token::Minus, position));
+            i += 1;
+        } else {
+            let start = i;
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let field = word
+                .split_once(':')
+                .filter(|(name, _)| QUERY_FIELDS.contains(&name.to_lowercase().as_str()));
+
+            let token = match (word.as_str(), field) {
+                ("AND", _) => Token::And,
+                ("OR", _) => Token::Or,
+                ("NOT", _) => Token::Not,
+                (_, Some((name, value))) => {
+                    let value_position = start + name.chars().count() + 2;
+                    let value = if value.is_empty() && chars.get(i) == Some(&'"') {
+                        let (quoted, next) = read_quoted(i)?;
+                        i = next;
+                        quoted
+                    } else {
+                        value.to_string()
+                    };
+                    if value.is_empty() {
+                        return Err(QueryError::new(
+                            format!("missing value for {}:", name),
+                            value_position,
+                        ));
+                    }
+                    Token::Field {
+                        name: name.to_lowercase(),
+                        value,
+                        value_position,
+                    }
+                }
+                _ => Token::Word(word),
+            };
+            tokens.push((token, position));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Expression tree before the field filters are taken out of it
+#[derive(Debug)]
+enum Node {
+    Expr(QueryExpr),
+    And(Vec<(Node, usize)>),
+    Or(Vec<(Node, usize)>),
+    Not(Box<(Node, usize)>),
+    Field {
+        name: String,
+        value: String,
+        value_position: usize,
+    },
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map(|(_, position)| *position)
+            .unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<(Node, usize), QueryError> {
+        let first = self.parse_and()?;
+        let position = first.1;
+        let mut items = vec![first];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            items.push(self.parse_and()?);
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else {
+            (Node::Or(items), position)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<(Node, usize), QueryError> {
+        let first = self.parse_unary()?;
+        let position = first.1;
+        let mut items = vec![first];
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => {
+                    self.next();
+                    items.push(self.parse_unary()?);
+                }
+                Some(_) => items.push(self.parse_unary()?),
+            }
+        }
+        Ok(if items.len() == 1 {
+            items.pop().unwrap()
+        } else {
+            (Node::And(items), position)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<(Node, usize), QueryError> {
+        match self.peek() {
+            Some(Token::Minus) | Some(Token::Not) => {
+                let (_, position) = self.next().unwrap();
+                let operand = self.parse_unary()?;
+                Ok((Node::Not(Box::new(operand)), position))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<(Node, usize), QueryError> {
+        let position = self.position();
+        match self.next() {
+            None => Err(QueryError::new("expected a term", position)),
+            Some((Token::Word(word), _)) => {
+                let (text, prefix) = match word.strip_suffix('*') {
+                    Some(text) => (text.to_string(), true),
+                    None => (word, false),
+                };
+                if text.is_empty() {
+                    return Err(QueryError::new("expected a term before '*'", position));
+                }
+                Ok((Node::Expr(QueryExpr::Term { text, prefix }), position))
+            }
+            Some((Token::Phrase(phrase), _)) => {
+                if phrase.trim().is_empty() {
+                    return Err(QueryError::new("empty phrase", position));
+                }
+                Ok((Node::Expr(QueryExpr::Phrase(phrase)), position))
+            }
+            Some((
+                Token::Field {
+                    name,
+                    value,
+                    value_position,
+                },
+                _,
+            )) => Ok((
+                Node::Field {
+                    name,
+                    value,
+                    value_position,
+                },
+                position,
+            )),
+            Some((Token::Open, _)) => {
+                if self.peek() == Some(&Token::Close) {
+                    return Err(QueryError::new("empty parentheses", position));
+                }
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some((Token::Close, _)) => Ok(inner),
+                    _ => Err(QueryError::new("missing closing parenthesis", position)),
+                }
+            }
+            Some((Token::Close, _)) => Err(QueryError::new("unexpected ')'", position)),
+            Some((Token::And, _)) => Err(QueryError::new("expected a term before AND", position)),
+            Some((Token::Or, _)) => Err(QueryError::new("expected a term before OR", position)),
+            Some((Token::Minus, _)) | Some((Token::Not, _)) => {
+                unreachable!("negations are parsed as unary operators")
+            }
+        }
+    }
+}
+
+/// Parses a search query, an empty query gives an empty [`ParsedQuery`]
+pub fn parse_query(input: &str) -> Result<ParsedQuery, QueryError> {
+    let tokens = tokenize(input)?;
+    let mut query = ParsedQuery::default();
+    if tokens.is_empty() {
+        return Ok(query);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        index: 0,
+        end: input.chars().count() + 1,
+    };
+    let root = parser.parse_or()?;
+    if let Some((_, position)) = parser.next() {
+        return Err(QueryError::new("unexpected ')'", position));
+    }
+
+    // field filters are ANDed with the rest of the query, so only top level ones map onto
+    // the search parameters
+    let top_level = match root {
+        (Node::And(items), _) => items,
+        node => vec![node],
+    };
+    let mut text = Vec::new();
+    for (node, position) in top_level {
+        match node {
+            Node::Field {
+                name,
+                value,
+                value_position,
+            } => apply_field(&mut query, &name, value, value_position)?,
+            node => text.push(lower(node, position)?),
+        }
+    }
+
+    let included = text
+        .iter()
+        .filter(|(expr, _)| !matches!(expr, QueryExpr::Not(_)))
+        .count();
+    if included == 0 {
+        if let Some((_, position)) = text.first() {
+            return Err(QueryError::new(
+                "a query can't only exclude terms",
+                *position,
+            ));
+        }
+    }
+    query.text = match text.len() {
+        0 => None,
+        1 => text.pop().map(|(expr, _)| expr),
+        _ => Some(QueryExpr::And(
+            text.into_iter().map(|(expr, _)| expr).collect(),
+        )),
+    };
+    Ok(query)
+}
+
+/// Turns a node of the text part into an expression, rejecting nested field filters and
+/// exclusions that have nothing to be taken from
+fn lower(node: Node, position: usize) -> Result<(QueryExpr, usize), QueryError> {
+    let expr = match node {
+        Node::Expr(expr) => expr,
+        Node::Field { name, .. } => {
+            return Err(QueryError::new(
+                format!("{}: can't be used inside OR, NOT or parentheses", name),
+                position,
+            ))
+        }
+        Node::Not(operand) => {
+            let (operand, operand_position) = *operand;
+            QueryExpr::Not(Box::new(lower(operand, operand_position)?.0))
+        }
+        Node::And(items) => {
+            let items = items
+                .into_iter()
+                .map(|(item, position)| lower(item, position))
+                .collect::<Result<Vec<_>, _>>()?;
+            if items
+                .iter()
+                .all(|(item, _)| matches!(item, QueryExpr::Not(_)))
+            {
+                return Err(QueryError::new(
+                    "a group can't only exclude terms",
+                    position,
+                ));
+            }
+            QueryExpr::And(items.into_iter().map(|(item, _)| item).collect())
+        }
+        Node::Or(items) => {
+            let items = items
+                .into_iter()
+                .map(|(item, position)| lower(item, position))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some((_, position)) = items
+                .iter()
+                .find(|(item, _)| matches!(item, QueryExpr::Not(_)))
+            {
+                return Err(QueryError::new(
+                    "an excluded term can't be an alternative of OR",
+                    *position,
+                ));
+            }
+            QueryExpr::Or(items.into_iter().map(|(item, _)| item).collect())
+        }
+    };
+    Ok((expr, position))
+}
+
+fn set_once<T>(
+    slot: &mut Option<T>,
+    value: T,
+    name: &str,
+    position: usize,
+) -> Result<(), QueryError> {
+    if slot.is_some() {
+        return Err(QueryError::new(
+            format!("{}: can only be given once", name),
+            position,
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn parse_date(value: &str, position: usize) -> Result<DateTime<Utc>, QueryError> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+        .ok_or_else(|| {
+            QueryError::new(
+                format!("invalid date {}, expected YYYY-MM-DD or RFC 3339", value),
+                position,
+            )
+        })
+}
+
+fn apply_field(
+    query: &mut ParsedQuery,
+    name: &str,
+    value: String,
+    position: usize,
+) -> Result<(), QueryError> {
+    match name {
+        "app" => set_once(&mut query.app_name, value, name, position),
+        "window" => set_once(&mut query.window_name, value, name, position),
+        "url" => set_once(&mut query.browser_url, value, name, position),
+        "speaker" => {
+            query.speakers.push(value);
+            Ok(())
+        }
+        "after" => {
+            let date = parse_date(&value, position)?;
+            set_once(&mut query.start_time, date, name, position)
+        }
+        "before" => {
+            let date = parse_date(&value, position)?;
+            set_once(&mut query.end_time, date, name, position)
+        }
+        "type" => {
+            let content_type = serde_json::from_value(serde_json::Value::String(
+                value.to_lowercase(),
+            ))
+            .map_err(|_| {
+                QueryError::new(
+                    format!(
+                        "unknown content type {}, expected all, ocr, audio or ui",
+                        value
+                    ),
+                    position,
+                )
+            })?;
+            set_once(&mut query.content_type, content_type, name, position)
+        }
+        "focused" => {
+            let focused = match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => true,
+                "false" | "no" | "0" => false,
+                _ => {
+                    return Err(QueryError::new(
+                        format!("invalid focused value {}, expected true or false", value),
+                        position,
+                    ))
+                }
+            };
+            set_once(&mut query.focused, focused, name, position)
+        }
+        _ => unreachable!("tokenizer only emits known fields"),
+    }
+}
@@ -0,0 +1,523 @@
+//! Sessionizer grouping the frames into contiguous work sessions. A session ends after an idle
+//! gap or when the focus stays on another app or site longer than a brief switch, and only the
+//! last, open session is recomputed from its start on each pass.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+use tracing::debug;
+
+use crate::{
+    ActivitySession, DatabaseManager, SessionChanges, SessionTranscription, SessionizeOptions,
+};
+
+/// Keywords kept per session
+const SESSION_KEYWORDS: usize = 5;
+/// OCR texts read per session to pick its keywords
+const KEYWORD_TEXTS: i64 = 500;
+
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "been", "before", "being", "could", "does", "down", "each", "even",
+    "from", "have", "here", "http", "https", "into", "just", "like", "more", "most", "much",
+    "only", "other", "over", "same", "should", "some", "such", "than", "that", "their", "them",
+    "then", "there", "these", "they", "this", "those", "through", "very", "want", "were", "what",
+    "when", "where", "which", "while", "will", "with", "would", "your",
+];
+
+/// Frames of the focused window. A capture records one frame per window, each with its own
+/// timestamp, frames recorded before the focus was tracked all count.
+const FOCUSED_FRAME: &str = "COALESCE(f.focused, 1) = 1";
+
+struct SessionFrame {
+    timestamp: DateTime<Utc>,
+    app_name: String,
+    window_name: String,
+    domain: Option<String>,
+    /// Time until the next frame, 0 when an idle gap follows
+    seconds: f64,
+    idle_after: bool,
+}
+
+/// Consecutive frames on the same app and site
+struct Run {
+    frames: std::ops::Range<usize>,
+    /// Preceded by an idle gap
+    after_gap: bool,
+}
+
+impl Run {
+    fn key<'a>(&self, frames: &'a [SessionFrame]) -> (&'a str, Option<&'a str>) {
+        let frame = &frames[self.frames.start];
+        (frame.app_name.as_str(), frame.domain.as_deref())
+    }
+
+    fn duration(&self, frames: &[SessionFrame]) -> chrono::Duration {
+        let last = &frames[self.frames.end - 1];
+        last.timestamp - frames[self.frames.start].timestamp
+            + chrono::Duration::milliseconds((last.seconds * 1000.0) as i64)
+    }
+}
+
+fn url_domain(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let domain = rest.split('/').next().unwrap_or_default().to_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
+
+/// Most frequent words of the texts, counted once per text
+fn top_keywords<'a>(texts: impl Iterator<Item = &'a str>, count: usize) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for text in texts {
+        let words: HashSet<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= 4 && !word.chars().all(|c| c.is_numeric()))
+            .map(str::to_lowercase)
+            .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+            .collect();
+        for word in words {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .take(count)
+        .map(|(word, _)| word)
+        .collect()
+}
+
+/// Item with the most time, then the most frames, then the first seen
+fn dominant<K: PartialEq>(items: impl Iterator<Item = (K, f64)>) -> Option<K> {
+    let mut totals: Vec<(K, f64, usize)> = Vec::new();
+    for (key, seconds) in items {
+        match totals.iter_mut().find(|(k, _, _)| *k == key) {
+            Some(total) => {
+                total.1 += seconds;
+                total.2 += 1;
+            }
+            None => totals.push((key, seconds, 1)),
+        }
+    }
+    totals
+        .into_iter()
+        .reduce(|best, item| {
+            if item.1 > best.1 || (item.1 == best.1 && item.2 > best.2) {
+                item
+            } else {
+                best
+            }
+        })
+        .map(|(key, _, _)| key)
+}
+
+/// Splits the frames into runs then groups the runs into sessions: an idle gap always starts a
+/// new session, a run on another app or site does once it lasts `min_switch`
+fn split_sessions(
+    frames: &[SessionFrame],
+    options: &SessionizeOptions,
+) -> Vec<std::ops::Range<usize>> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let after_gap = i > 0 && frames[i - 1].idle_after;
+        match runs.last_mut() {
+            Some(run)
+                if !after_gap
+                    && run.key(frames) == (frame.app_name.as_str(), frame.domain.as_deref()) =>
+            {
+                run.frames.end = i + 1
+            }
+            _ => runs.push(Run {
+                frames: i..i + 1,
+                after_gap,
+            }),
+        }
+    }
+
+    let mut sessions: Vec<std::ops::Range<usize>> = Vec::new();
+    // key of the last long run of the current session
+    let mut anchor = None;
+    for run in &runs {
+        let long = run.duration(frames) >= options.min_switch;
+        let switched = long && anchor.is_some_and(|anchor| anchor != run.key(frames));
+        match sessions.last_mut() {
+            Some(session) if !run.after_gap && !switched => session.end = run.frames.end,
+            _ => {
+                sessions.push(run.frames.clone());
+                anchor = None;
+            }
+        }
+        if long {
+            anchor = Some(run.key(frames));
+        }
+    }
+    sessions
+}
+
+fn session_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ActivitySession, sqlx::Error> {
+    let keywords: String = row.try_get("keywords")?;
+    Ok(ActivitySession {
+        id: row.try_get("id")?,
+        start_time: row.try_get("start_time")?,
+        end_time: row.try_get("end_time")?,
+        app_name: row.try_get("app_name")?,
+        browser_domain: row.try_get("browser_domain")?,
+        title: row.try_get("title")?,
+        keywords: serde_json::from_str(&keywords).unwrap_or_default(),
+        frame_count: row.try_get("frame_count")?,
+        audio_segments: row.try_get("audio_segments")?,
+        audio_seconds: row.try_get("audio_seconds")?,
+        closed: row.try_get("closed")?,
+    })
+}
+
+impl DatabaseManager {
+    /// Sessionizes the frames recorded since the last pass, recomputing the open session. The
+    /// last session stays open until no frame came for the idle gap.
+    pub async fn sessionize_activity(
+        &self,
+        options: &SessionizeOptions,
+    ) -> Result<SessionChanges, sqlx::Error> {
+        self.drop_sessions_after_late_frames(options).await?;
+        let open = sqlx::query(
+            "SELECT * FROM activity_sessions WHERE closed = 0 ORDER BY start_time DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| session_from_row(&row))
+        .transpose()?;
+        let last_end: Option<DateTime<Utc>> = match &open {
+            Some(_) => None,
+            None => {
+                sqlx::query_scalar("SELECT MAX(end_time) FROM activity_sessions")
+                    .fetch_one(&self.pool)
+                    .await?
+            }
+        };
+        let limit = options.batch_size + open.as_ref().map_or(0, |open| open.frame_count);
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT f.id, f.timestamp, COALESCE(f.app_name, '') AS app_name,
+                COALESCE(f.window_name, '') AS window_name, f.browser_url
+            FROM frames f
+            WHERE (?1 IS NULL OR f.timestamp >= ?1) AND (?2 IS NULL OR f.timestamp > ?2)
+                AND COALESCE(f.app_name, '') != '' AND {FOCUSED_FRAME}
+            ORDER BY f.timestamp, f.id
+            LIMIT ?3
+            "#
+        ))
+        .bind(open.as_ref().map(|open| open.start_time))
+        .bind(last_end)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut frames = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let browser_url: Option<String> = row.try_get("browser_url")?;
+            frames.push(SessionFrame {
+                timestamp: row.try_get("timestamp")?,
+                app_name: row.try_get("app_name")?,
+                window_name: row.try_get("window_name")?,
+                domain: browser_url.as_deref().and_then(url_domain),
+                seconds: 0.0,
+                idle_after: false,
+            });
+        }
+        for i in 1..frames.len() {
+            let gap = frames[i].timestamp - frames[i - 1].timestamp;
+            if gap <= options.idle_gap {
+                frames[i - 1].seconds = gap.num_milliseconds() as f64 / 1000.0;
+            } else {
+                frames[i - 1].idle_after = true;
+            }
+        }
+
+        let now = Utc::now();
+        let mut changes = SessionChanges::default();
+        if frames.is_empty() {
+            if let Some(mut open) = open {
+                if now - open.end_time > options.idle_gap {
+                    sqlx::query("UPDATE activity_sessions SET closed = 1 WHERE id = ?1")
+                        .bind(open.id)
+                        .execute(&self.pool)
+                        .await?;
+                    open.closed = true;
+                    changes.ended.push(open);
+                }
+            }
+            return Ok(changes);
+        }
+
+        let more_frames = frames.len() as i64 == limit;
+        let ranges = split_sessions(&frames, options);
+        let mut tx = self.pool.begin().await?;
+        let session_count = ranges.len();
+        for (i, range) in ranges.into_iter().enumerate() {
+            let session_frames = &frames[range];
+            let first = &session_frames[0];
+            let last = &session_frames[session_frames.len() - 1];
+            let start_time = first.timestamp;
+            let end_time =
+                last.timestamp + chrono::Duration::milliseconds((last.seconds * 1000.0) as i64);
+
+            let app_name = dominant(
+                session_frames
+                    .iter()
+                    .map(|frame| (frame.app_name.as_str(), frame.seconds)),
+            )
+            .unwrap_or_default();
+            let app_frames = || {
+                session_frames
+                    .iter()
+                    .filter(move |frame| frame.app_name == app_name)
+            };
+            let title =
+                dominant(app_frames().map(|frame| (frame.window_name.as_str(), frame.seconds)))
+                    .filter(|title| !title.is_empty())
+                    .unwrap_or(app_name);
+            let browser_domain = dominant(
+                app_frames().filter_map(|frame| Some((frame.domain.as_deref()?, frame.seconds))),
+            );
+
+            let (audio_segments, audio_seconds): (i64, f64) = sqlx::query_as(
+                r#"
+                SELECT COUNT(*), COALESCE(SUM(MAX(end_time - start_time, 0)), 0.0)
+                FROM audio_transcriptions
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                "#,
+            )
+            .bind(start_time)
+            .bind(end_time)
+            .fetch_one(&mut *tx)
+            .await?;
+            let texts: Vec<String> = sqlx::query_scalar(&format!(
+                r#"
+                SELECT text FROM (
+                    SELECT o.text FROM ocr_text o
+                    JOIN frames f ON f.id = o.frame_id
+                    WHERE f.timestamp >= ?1 AND f.timestamp <= ?2
+                        AND {FOCUSED_FRAME}
+                    ORDER BY f.timestamp
+                    LIMIT ?3
+                )
+                UNION ALL
+                SELECT transcription FROM audio_transcriptions
+                WHERE timestamp >= ?1 AND timestamp <= ?2
+                "#
+            ))
+            .bind(start_time)
+            .bind(end_time)
+            .bind(KEYWORD_TEXTS)
+            .fetch_all(&mut *tx)
+            .await?;
+            let keywords = top_keywords(
+                session_frames
+                    .iter()
+                    .map(|frame| frame.window_name.as_str())
+                    .chain(texts.iter().map(String::as_str)),
+                SESSION_KEYWORDS,
+            );
+
+            let is_last = i + 1 == session_count;
+            let closed = !is_last || (!more_frames && now - end_time > options.idle_gap);
+            let mut session = ActivitySession {
+                id: 0,
+                start_time,
+                end_time,
+                app_name: app_name.to_string(),
+                browser_domain: browser_domain.map(str::to_string),
+                title: title.to_string(),
+                keywords,
+                frame_count: session_frames.len() as i64,
+                audio_segments,
+                audio_seconds,
+                closed,
+            };
+            let keywords_json =
+                serde_json::to_string(&session.keywords).unwrap_or_else(|_| "[]".to_string());
+            match (i, &open) {
+                (0, Some(open)) => {
+                    session.id = open.id;
+                    sqlx::query(
+                        r#"
+                        UPDATE activity_sessions
+                        SET start_time = ?1, end_time = ?2, app_name = ?3, browser_domain = ?4,
+                            title = ?5, keywords = ?6, frame_count = ?7, audio_segments = ?8,
+                            audio_seconds = ?9, closed = ?10
+                        WHERE id = ?11
+                        "#,
+                    )
+                    .bind(session.start_time)
+                    .bind(session.end_time)
+                    .bind(&session.app_name)
+                    .bind(&session.browser_domain)
+                    .bind(&session.title)
+                    .bind(&keywords_json)
+                    .bind(session.frame_count)
+                    .bind(session.audio_segments)
+                    .bind(session.audio_seconds)
+                    .bind(session.closed)
+                    .bind(open.id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                _ => {
+                    session.id = sqlx::query(
+                        r#"
+                        INSERT INTO activity_sessions (start_time, end_time, app_name, browser_domain,
+                            title, keywords, frame_count, audio_segments, audio_seconds, closed)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                        "#,
+                    )
+                    .bind(session.start_time)
+                    .bind(session.end_time)
+                    .bind(&session.app_name)
+                    .bind(&session.browser_domain)
+                    .bind(&session.title)
+                    .bind(&keywords_json)
+                    .bind(session.frame_count)
+                    .bind(session.audio_segments)
+                    .bind(session.audio_seconds)
+                    .bind(session.closed)
+                    .execute(&mut *tx)
+                    .await?
+                    .last_insert_rowid();
+                    changes.started.push(session.clone());
+                }
+            }
+            if session.closed {
+                changes.ended.push(session);
+            }
+        }
+        tx.commit().await?;
+        Ok(changes)
+    }
+
+    /// Deletes the sessions that frames recorded before them, by an import or a sync, may
+    /// change: those ending within the idle gap before the earliest such frame and all later
+    /// ones. The pass then rebuilds them from the last session left.
+    async fn drop_sessions_after_late_frames(
+        &self,
+        options: &SessionizeOptions,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let since: Option<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT since FROM sessionize_backlog WHERE id = 1")
+                .fetch_optional(&mut *tx)
+                .await?;
+        let Some(since) = since else {
+            return Ok(());
+        };
+        let dropped = sqlx::query("DELETE FROM activity_sessions WHERE end_time >= ?1")
+            .bind(since - options.idle_gap)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM sessionize_backlog WHERE id = 1")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        debug!(
+            "rebuilding {} sessions after frames recorded since {}",
+            dropped, since
+        );
+        Ok(())
+    }
+
+    /// Sessions overlapping a time range, newest first, with the total count for pagination.
+    /// `query` matches the app, title or keywords.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_activity_sessions(
+        &self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+        app_name: Option<&str>,
+        query: Option<&str>,
+        min_duration_secs: Option<f64>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<ActivitySession>, i64), sqlx::Error> {
+        const FILTER: &str = r#"
+            WHERE (?1 IS NULL OR end_time >= ?1) AND (?2 IS NULL OR start_time <= ?2)
+                AND (?3 IS NULL OR app_name = ?3)
+                AND (?4 IS NULL OR app_name LIKE ?4 OR title LIKE ?4 OR keywords LIKE ?4)
+                AND (?5 IS NULL OR (julianday(end_time) - julianday(start_time)) * 86400 >= ?5)
+        "#;
+        let pattern = query.map(|query| format!("%{}%", query));
+        let rows = sqlx::query(&format!(
+            "SELECT * FROM activity_sessions {FILTER} ORDER BY start_time DESC LIMIT ?6 OFFSET ?7"
+        ))
+        .bind(start_time)
+        .bind(end_time)
+        .bind(app_name)
+        .bind(&pattern)
+        .bind(min_duration_secs)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        let total: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM activity_sessions {FILTER}"))
+                .bind(start_time)
+                .bind(end_time)
+                .bind(app_name)
+                .bind(&pattern)
+                .bind(min_duration_secs)
+                .fetch_one(&self.pool)
+                .await?;
+        let sessions = rows
+            .iter()
+            .map(session_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((sessions, total))
+    }
+
+    pub async fn get_activity_session(
+        &self,
+        id: i64,
+    ) -> Result<Option<ActivitySession>, sqlx::Error> {
+        sqlx::query("SELECT * FROM activity_sessions WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| session_from_row(&row))
+            .transpose()
+    }
+
+    /// Transcription segments recorded during a session, oldest first
+    pub async fn session_transcriptions(
+        &self,
+        session: &ActivitySession,
+    ) -> Result<Vec<SessionTranscription>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT a.id, a.timestamp, a.transcription, a.device, a.speaker_id, s.name,
+                a.start_time, a.end_time
+            FROM audio_transcriptions a
+            LEFT JOIN speakers s ON s.id = a.speaker_id
+            WHERE a.timestamp >= ?1 AND a.timestamp <= ?2
+            ORDER BY a.timestamp, a.offset_index
+            "#,
+        )
+        .bind(session.start_time)
+        .bind(session.end_time)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(SessionTranscription {
+                    id: row.try_get("id")?,
+                    timestamp: row.try_get("timestamp")?,
+                    transcription: row.try_get("transcription")?,
+                    device_name: row.try_get("device")?,
+                    speaker_id: row.try_get("speaker_id")?,
+                    speaker_name: row.try_get("name")?,
+                    start_time: row.try_get("start_time")?,
+                    end_time: row.try_get("end_time")?,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,213 @@
+//! Excerpts of search results with the matched terms marked, like FTS5 `snippet()` and
+//! `highlight()` but working on any text, so OCR, audio and UI results all get one.
+
+/// How [`snippet`] cuts and marks the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnippetOptions {
+    /// Words kept around the matches, 0 keeps the whole text
+    pub tokens: usize,
+    pub start_marker: String,
+    pub end_marker: String,
+    /// Added where the text was cut
+    pub ellipsis: String,
+}
+
+impl Default for SnippetOptions {
+    fn default() -> Self {
+        Self {
+            tokens: 16,
+            start_marker: "**".to_string(),
+            end_marker: "**".to_string(),
+            ellipsis: "…".to_string(),
+        }
+    }
+}
+
+/// A whitespace separated word, `core` is the range without the surrounding punctuation
+struct Word {
+    start: usize,
+    end: usize,
+    core_start: usize,
+    core_end: usize,
+}
+
+/// Matched words `[start, end)` and the index of the term they match
+struct Span {
+    start: usize,
+    end: usize,
+    term: usize,
+}
+
+fn split_words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(word_start)) => {
+                let word = &text[word_start..i];
+                let core_start =
+                    word_start + (word.len() - word.trim_start_matches(is_punct).len());
+                let core_end = core_start.max(word_start + word.trim_end_matches(is_punct).len());
+                words.push(Word {
+                    start: word_start,
+                    end: i,
+                    core_start,
+                    core_end,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn is_punct(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+/// Words are compared case insensitively and as prefixes, so `budget` marks `Budgets`
+fn find_spans(text: &str, words: &[Word], terms: &[String]) -> Vec<Span> {
+    let terms: Vec<Vec<String>> = terms
+        .iter()
+        .map(|term| {
+            term.split_whitespace()
+                .map(|word| word.trim_matches(is_punct).to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect()
+        })
+        .collect();
+    let cores: Vec<String> = words
+        .iter()
+        .map(|word| text[word.core_start..word.core_end].to_lowercase())
+        .collect();
+
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < cores.len() {
+        let longest = terms
+            .iter()
+            .enumerate()
+            .filter(|(_, term)| {
+                !term.is_empty()
+                    && i + term.len() <= cores.len()
+                    && term
+                        .iter()
+                        .zip(&cores[i..])
+                        .all(|(term_word, core)| core.starts_with(term_word.as_str()))
+            })
+            .max_by_key(|(_, term)| term.len());
+        match longest {
+            Some((term, words)) => {
+                spans.push(Span {
+                    start: i,
+                    end: i + words.len(),
+                    term,
+                });
+                i += words.len();
+            }
+            None => i += 1,
+        }
+    }
+    spans
+}
+
+/// Start of the `tokens` words long window holding the most distinct terms, the earliest wins
+fn best_window(spans: &[Span], word_count: usize, tokens: usize) -> usize {
+    let last_start = word_count - tokens;
+    let mut best = (0, 0);
+    for span in spans {
+        let start = span.start.saturating_sub(tokens / 4).min(last_start);
+        let mut terms: Vec<usize> = spans
+            .iter()
+            .filter(|other| other.start >= start && other.end <= start + tokens)
+            .map(|other| other.term)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        if terms.len() > best.1 {
+            best = (start, terms.len());
+        }
+    }
+    best.0
+}
+
+/// Excerpt of `text` around the best match of `terms` with every match wrapped in the markers.
+/// Without any match the excerpt is the start of the text. The whitespace of the excerpt is
+/// collapsed, unless `tokens` is 0 where the whole text is returned as is.
+pub fn snippet(text: &str, terms: &[String], options: &SnippetOptions) -> String {
+    let words = split_words(text);
+    if words.is_empty() {
+        return String::new();
+    }
+    let spans = find_spans(text, &words, terms);
+
+    let whole = options.tokens == 0 || words.len() <= options.tokens;
+    let (first, last) = if whole {
+        (0, words.len())
+    } else {
+        let start = best_window(&spans, words.len(), options.tokens);
+        (start, start + options.tokens)
+    };
+
+    let mut out = String::new();
+    if first > 0 {
+        out.push_str(&options.ellipsis);
+    }
+    if options.tokens == 0 {
+        out.push_str(&text[..words[0].start]);
+    }
+    let mut spans = spans.iter().peekable();
+    let mut open = false;
+    for (i, word) in words.iter().enumerate().take(last).skip(first) {
+        if i > first {
+            if options.tokens == 0 {
+                out.push_str(&text[words[i - 1].end..word.start]);
+            } else {
+                out.push(' ');
+            }
+        }
+        while spans.next_if(|span| span.end <= i).is_some() {}
+        let span_end = spans
+            .peek()
+            .filter(|span| span.start <= i)
+            .map(|span| span.end);
+
+        out.push_str(&text[word.start..word.core_start]);
+        if span_end.is_some() && !open {
+            out.push_str(&options.start_marker);
+            open = true;
+        }
+        out.push_str(&text[word.core_start..word.core_end]);
+        if open && (span_end == Some(i + 1) || i + 1 == last) {
+            out.push_str(&options.end_marker);
+            open = false;
+        }
+        out.push_str(&text[word.core_end..word.end]);
+    }
+    if options.tokens == 0 {
+        out.push_str(&text[words[words.len() - 1].end..]);
+    }
+    if last < words.len() {
+        out.push_str(&options.ellipsis);
+    }
+    out
+}
+
+/// The whole `text` with every match of `terms` wrapped in the markers
+pub fn highlight(text: &str, terms: &[String], start_marker: &str, end_marker: &str) -> String {
+    snippet(
+        text,
+        terms,
+        &SnippetOptions {
+            tokens: 0,
+            start_marker: start_marker.to_string(),
+            end_marker: end_marker.to_string(),
+            ellipsis: String::new(),
+        },
+    )
+}
@@ -0,0 +1,496 @@
+//! Incremental sync between machines. Every database serves the rows changed after a watermark
+//! of its `sync_log` change feed, and applies the batches it pulls from its peers like an
+//! archive import, so the recordings of all the machines end up in one history.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sqlx::Row;
+
+use crate::archive_db::{
+    export_row, hash_file_async, media_destination, RowImporter, ARCHIVE_TABLES, ORIGIN_KEY,
+    ROWID_KEY,
+};
+use crate::{
+    DatabaseManager, ImportReport, ImportTableReport, SyncBatch, SyncMedia, SyncPeer, SyncTableRows,
+};
+
+/// A media file modified more recently may still be recording, the feed stops before its row
+const SYNC_SETTLE_TIME: Duration = Duration::from_secs(30);
+
+fn recently_modified(path: &str) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < SYNC_SETTLE_TIME)
+}
+
+/// Modification time of a file in nanoseconds, 0 when the platform has none
+fn modified_at(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_nanos() as i64)
+}
+
+fn row_id(row: &Map<String, Value>) -> Option<i64> {
+    row.get("id").and_then(Value::as_i64)
+}
+
+impl DatabaseManager {
+    /// Rows changed after `since` in the change feed, at most `limit` changes. The rows that
+    /// were synced from `machine_id`, the database pulling, are left out. Rows referenced by the changed
+    /// ones and changed after `since` too are added, so a batch can always be applied, and those
+    /// from `machine_id` are sent with their id there. The batch is empty while the rows
+    /// recorded before the feed existed are still being added to it.
+    pub async fn sync_changes(
+        &self,
+        since: i64,
+        limit: u32,
+        machine_id: Option<&str>,
+    ) -> Result<SyncBatch> {
+        let database_id = self.database_id().await?;
+        let backfilling: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM sync_backfill)")
+            .fetch_one(&self.pool)
+            .await?;
+        if backfilling {
+            return Ok(SyncBatch {
+                source_id: database_id,
+                since,
+                watermark: since,
+                has_more: false,
+                tables: Vec::new(),
+                media: Vec::new(),
+            });
+        }
+        let entries: Vec<(i64, String, i64)> = sqlx::query_as(
+            "SELECT seq, table_name, row_id FROM sync_log WHERE seq > ?1 ORDER BY seq LIMIT ?2",
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut has_more = limit > 0 && entries.len() == limit as usize;
+        let mut watermark = since;
+        let mut changed: HashMap<&str, Vec<i64>> = HashMap::new();
+        for (seq, table_name, row_id) in &entries {
+            let Some(table) = ARCHIVE_TABLES.iter().find(|t| t.name == table_name) else {
+                watermark = *seq;
+                continue;
+            };
+            if let Some(column) = table.media {
+                // only the files recorded here can still be written to
+                let path: Option<Option<String>> = sqlx::query_scalar(&format!(
+                    "SELECT {} FROM {} WHERE rowid = ?1 AND machine_id IS NULL",
+                    column, table.name
+                ))
+                .bind(row_id)
+                .fetch_optional(&self.pool)
+                .await?;
+                if path.flatten().as_deref().is_some_and(recently_modified) {
+                    has_more = false;
+                    break;
+                }
+            }
+            changed.entry(table.name).or_default().push(*row_id);
+            watermark = *seq;
+        }
+
+        let mut rows: HashMap<&str, Vec<Map<String, Value>>> = HashMap::new();
+        for table in ARCHIVE_TABLES {
+            let Some(ids) = changed.get(table.name) else {
+                continue;
+            };
+            let sql = format!(
+                r#"
+                SELECT rowid AS archive_rowid, * FROM {table}
+                WHERE rowid IN (SELECT value FROM json_each(?1))
+                    AND NOT EXISTS (
+                        SELECT 1 FROM imported_rows i
+                        WHERE i.source_id = ?2 AND i.table_name = '{table}' AND i.row_id = {table}.rowid
+                    )
+                ORDER BY rowid
+                "#,
+                table = table.name,
+            );
+            let batch = sqlx::query(&sql)
+                .bind(serde_json::to_string(ids)?)
+                .bind(machine_id)
+                .fetch_all(&self.pool)
+                .await?;
+            let mut table_rows = Vec::with_capacity(batch.len());
+            for row in &batch {
+                let mut values = export_row(row, &database_id);
+                // rows without an id are recognized by their rowid on the next syncs
+                if !table.has_id {
+                    let rowid: i64 = row.try_get("archive_rowid")?;
+                    values.insert(ROWID_KEY.to_string(), rowid.into());
+                }
+                table_rows.push(values);
+            }
+            rows.insert(table.name, table_rows);
+        }
+
+        // children come after their parents in the table order, so walking it backwards adds
+        // the parents of the added parents too
+        for table in ARCHIVE_TABLES.iter().rev() {
+            for (column, parent) in table.references {
+                let present: HashSet<i64> = rows
+                    .get(parent)
+                    .map(|rows| rows.iter().filter_map(row_id).collect())
+                    .unwrap_or_default();
+                let missing: HashSet<i64> = rows
+                    .get(table.name)
+                    .map(|rows| {
+                        rows.iter()
+                            .filter_map(|row| row.get(*column).and_then(Value::as_i64))
+                            .filter(|id| !present.contains(id))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if missing.is_empty() {
+                    continue;
+                }
+                let sql = format!(
+                    r#"
+                    SELECT p.rowid AS archive_rowid, p.*, i.source_row_id AS sync_origin_id
+                    FROM {parent} p
+                    LEFT JOIN imported_rows i
+                        ON i.source_id = ?2 AND i.table_name = '{parent}' AND i.row_id = p.id
+                    WHERE p.id IN (SELECT value FROM json_each(?1))
+                        AND (i.source_row_id IS NOT NULL OR EXISTS (
+                            SELECT 1 FROM sync_log l
+                            WHERE l.table_name = '{parent}' AND l.row_id = p.rowid AND l.seq > ?3
+                        ))
+                    "#,
+                );
+                let parents = sqlx::query(&sql)
+                    .bind(serde_json::to_string(&missing)?)
+                    .bind(machine_id)
+                    .bind(since)
+                    .fetch_all(&self.pool)
+                    .await?;
+                let parent_rows = rows.entry(parent).or_default();
+                for row in &parents {
+                    let mut values = export_row(row, &database_id);
+                    if let Some(origin_id) = values.remove("sync_origin_id") {
+                        if !origin_id.is_null() {
+                            values.insert(ORIGIN_KEY.to_string(), origin_id);
+                        }
+                    }
+                    parent_rows.push(values);
+                }
+            }
+        }
+
+        let mut batch = SyncBatch {
+            source_id: database_id,
+            since,
+            watermark,
+            has_more,
+            tables: Vec::new(),
+            media: Vec::new(),
+        };
+        for table in ARCHIVE_TABLES {
+            let Some(table_rows) = rows.remove(table.name).filter(|rows| !rows.is_empty()) else {
+                continue;
+            };
+            if let Some(column) = table.media {
+                for row in &table_rows {
+                    // the machine a row came from has its file
+                    if row.contains_key(ORIGIN_KEY) {
+                        continue;
+                    }
+                    let (Some(id), Some(path)) =
+                        (row_id(row), row.get(column).and_then(Value::as_str))
+                    else {
+                        continue;
+                    };
+                    let Some((size, sha256)) = self.media_hash(path).await? else {
+                        continue;
+                    };
+                    batch.media.push(SyncMedia {
+                        table: table.name.to_string(),
+                        row_id: id,
+                        path: path.to_string(),
+                        size,
+                        sha256,
+                    });
+                }
+            }
+            batch.tables.push(SyncTableRows {
+                name: table.name.to_string(),
+                rows: table_rows.into_iter().map(Value::Object).collect(),
+            });
+        }
+        Ok(batch)
+    }
+
+    /// Applies a batch pulled from the peer at `url` and moves its watermark. `downloads` has the
+    /// downloaded media files of the batch by their path on the peer, they are checked against
+    /// the batch and moved into `media_dir`. Rows whose file was not downloaded keep the path.
+    pub async fn apply_sync_batch(
+        &self,
+        url: &str,
+        batch: &SyncBatch,
+        media_dir: &Path,
+        downloads: &HashMap<String, PathBuf>,
+    ) -> Result<ImportReport> {
+        if batch.source_id == self.database_id().await? {
+            return Err(anyhow!("{} serves this same database", url));
+        }
+        for media in &batch.media {
+            let Some(file) = downloads.get(&media.path) else {
+                continue;
+            };
+            let (size, sha256) = hash_file_async(file.clone(), None).await?;
+            if size != media.size || sha256 != media.sha256 {
+                return Err(anyhow!("checksum mismatch for {}", media.path));
+            }
+        }
+
+        let mut report = ImportReport {
+            source_id: batch.source_id.clone(),
+            ..Default::default()
+        };
+        let mut importer = RowImporter::new(self, &batch.source_id).await?;
+        for media in &batch.media {
+            let Some(file) = downloads.get(&media.path) else {
+                continue;
+            };
+            // the peer may run on another platform
+            let file_name = media
+                .path
+                .rsplit(['/', '\\'])
+                .next()
+                .filter(|name| !name.is_empty() && *name != "..")
+                .ok_or_else(|| anyhow!("invalid media path: {}", media.path))?;
+            tokio::fs::create_dir_all(media_dir).await?;
+            let (destination, existing) =
+                media_destination(media_dir, file_name, media.size, &media.sha256).await?;
+            if existing {
+                tokio::fs::remove_file(file).await?;
+                report.media_existing += 1;
+            } else {
+                tokio::fs::rename(file, &destination).await?;
+                self.record_media_hash(&destination.to_string_lossy(), media.size, &media.sha256)
+                    .await?;
+                report.media_copied += 1;
+            }
+            importer.media_paths.insert(
+                media.path.clone(),
+                destination.to_string_lossy().to_string(),
+            );
+        }
+
+        for table in ARCHIVE_TABLES {
+            let Some(entry) = batch.tables.iter().find(|entry| entry.name == table.name) else {
+                continue;
+            };
+            let mut table_report = ImportTableReport {
+                name: table.name.to_string(),
+                ..Default::default()
+            };
+            let mut tx = self.pool.begin().await?;
+            for row in &entry.rows {
+                let Value::Object(row) = row else {
+                    table_report.skipped += 1;
+                    continue;
+                };
+                importer
+                    .import_row(&mut tx, table, row.clone(), &mut table_report)
+                    .await?;
+            }
+            tx.commit().await?;
+            report.tables.push(table_report);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO sync_peers (url, peer_id, watermark, last_sync_at, last_error)
+            VALUES (?1, ?2, ?3, ?4, NULL)
+            ON CONFLICT(url) DO UPDATE SET
+                peer_id = excluded.peer_id,
+                watermark = excluded.watermark,
+                last_sync_at = excluded.last_sync_at,
+                last_error = NULL
+            "#,
+        )
+        .bind(url)
+        .bind(&batch.source_id)
+        .bind(batch.watermark)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(report)
+    }
+
+    /// Adds a batch of the rows recorded before the change feed existed to it, returns whether
+    /// some were left to add
+    pub async fn backfill_sync_log(&self, batch_size: i64) -> Result<bool, sqlx::Error> {
+        let pending: Option<(String, i64, i64)> = sqlx::query_as(
+            "SELECT table_name, done, up_to FROM sync_backfill ORDER BY rowid LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((table_name, done, up_to)) = pending else {
+            return Ok(false);
+        };
+        let table = ARCHIVE_TABLES.iter().find(|t| t.name == table_name);
+        let mut tx = self.pool.begin().await?;
+        let mut last: Option<i64> = None;
+        if let Some(table) = table {
+            last = sqlx::query_scalar(&format!(
+                r#"
+                SELECT MAX(rowid) FROM (
+                    SELECT rowid FROM {table} WHERE rowid > ?1 AND rowid <= ?2
+                    ORDER BY rowid LIMIT ?3
+                )
+                "#,
+                table = table.name
+            ))
+            .bind(done)
+            .bind(up_to)
+            .bind(batch_size)
+            .fetch_one(&mut *tx)
+            .await?;
+        }
+        if let (Some(table), Some(last)) = (table, last) {
+            // the rows changed since are in the feed already, at their place
+            sqlx::query(&format!(
+                r#"
+                INSERT OR IGNORE INTO sync_log (table_name, row_id)
+                SELECT ?1, rowid FROM {table} WHERE rowid > ?2 AND rowid <= ?3 ORDER BY rowid
+                "#,
+                table = table.name
+            ))
+            .bind(table.name)
+            .bind(done)
+            .bind(last)
+            .execute(&mut *tx)
+            .await?;
+        }
+        match last {
+            Some(last) if last < up_to => {
+                sqlx::query("UPDATE sync_backfill SET done = ?1 WHERE table_name = ?2")
+                    .bind(last)
+                    .bind(&table_name)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            _ => {
+                sqlx::query("DELETE FROM sync_backfill WHERE table_name = ?1")
+                    .bind(&table_name)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Size and SHA-256 of a media file, None when it is missing. A file is hashed once and
+    /// again only when its size or modification time changed.
+    async fn media_hash(&self, path: &str) -> Result<Option<(u64, String)>> {
+        let Ok(metadata) = tokio::fs::metadata(path).await else {
+            return Ok(None);
+        };
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        let stored: Option<String> = sqlx::query_scalar(
+            "SELECT sha256 FROM media_hashes WHERE file_path = ?1 AND size = ?2 AND modified_at = ?3",
+        )
+        .bind(path)
+        .bind(metadata.len() as i64)
+        .bind(modified_at(&metadata))
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(sha256) = stored {
+            return Ok(Some((metadata.len(), sha256)));
+        }
+        let (size, sha256) = hash_file_async(PathBuf::from(path), None).await?;
+        self.record_media_hash(path, size, &sha256).await?;
+        Ok(Some((size, sha256)))
+    }
+
+    async fn record_media_hash(&self, path: &str, size: u64, sha256: &str) -> Result<()> {
+        let metadata = tokio::fs::metadata(path).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO media_hashes (file_path, size, modified_at, sha256) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(file_path) DO UPDATE SET
+                size = excluded.size,
+                modified_at = excluded.modified_at,
+                sha256 = excluded.sha256
+            "#,
+        )
+        .bind(path)
+        .bind(size as i64)
+        .bind(modified_at(&metadata))
+        .bind(sha256)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn sync_peers(&self) -> Result<Vec<SyncPeer>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT url, peer_id, watermark, last_sync_at, last_error FROM sync_peers ORDER BY url",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn sync_peer(&self, url: &str) -> Result<Option<SyncPeer>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT url, peer_id, watermark, last_sync_at, last_error FROM sync_peers WHERE url = ?1",
+        )
+        .bind(url)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Keeps the error of the last sync from a peer, its watermark stays where it was
+    pub async fn record_sync_error(&self, url: &str, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_peers (url, last_error) VALUES (?1, ?2)
+            ON CONFLICT(url) DO UPDATE SET last_error = excluded.last_error
+            "#,
+        )
+        .bind(url)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Path of the media file of a row, None for a table without media or a missing row
+    pub async fn sync_media_path(
+        &self,
+        table: &str,
+        row_id: i64,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let Some((name, column)) = ARCHIVE_TABLES
+            .iter()
+            .find(|t| t.name == table)
+            .and_then(|t| Some((t.name, t.media?)))
+        else {
+            return Ok(None);
+        };
+        let path: Option<Option<String>> =
+            sqlx::query_scalar(&format!("SELECT {} FROM {} WHERE id = ?1", column, name))
+                .bind(row_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(path.flatten())
+    }
+}
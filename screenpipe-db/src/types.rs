@@ -86,6 +86,7 @@ pub enum ContentType {
 
 #[derive(FromRow)]
 pub struct AudioResultRaw {
+    pub id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -115,6 +116,7 @@ pub enum DeviceType {
 
 #[derive(OaSchema, Debug, Serialize, Deserialize)]
 pub struct AudioResult {
+    pub id: i64,
     pub audio_chunk_id: i64,
     pub transcription: String,
     pub timestamp: DateTime<Utc>,
@@ -185,6 +187,72 @@ pub struct TimeSeriesChunk {
     pub frames: Vec<FrameData>,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
+    /// Set when the frames were limited and older ones remain
+    pub next_cursor: Option<SearchCursor>,
+}
+
+/// Keyset position after the last result of a page. Results are ordered by timestamp, then by
+/// source table when several are searched, then by id, so a cursor stays valid while new rows
+/// are inserted.
+#[derive(OaSchema, Debug, Clone, PartialEq)]
+pub struct SearchCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: i64,
+    /// Rank of the table the row comes from: 0 for frames, 1 for audio, 2 for ui
+    pub(crate) source: u8,
+}
+
+impl SearchCursor {
+    pub fn new(timestamp: DateTime<Utc>, id: i64) -> Self {
+        Self {
+            timestamp,
+            id,
+            source: 0,
+        }
+    }
+
+    pub(crate) fn with_source(timestamp: DateTime<Utc>, id: i64, source: u8) -> Self {
+        Self {
+            timestamp,
+            id,
+            source,
+        }
+    }
+
+    /// Opaque url safe form of the cursor
+    pub fn encode(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.timestamp.to_rfc3339(),
+            self.source,
+            self.id
+        )
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = cursor
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                let pair = std::str::from_utf8(pair)
+                    .ok()
+                    .filter(|pair| pair.len() == 2)?;
+                u8::from_str_radix(pair, 16).ok()
+            })
+            .collect::<Option<Vec<u8>>>()?;
+        let decoded = String::from_utf8(bytes).ok()?;
+        let mut parts = decoded.split('|');
+        let timestamp = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+        let source = parts.next()?.parse().ok()?;
+        let id = parts.next()?.parse().ok()?;
+        if parts.next().is_some() || source > 2 {
+            return None;
+        }
+        Some(Self::with_source(timestamp.with_timezone(&Utc), id, source))
+    }
 }
 
 #[derive(OaSchema, Debug, Clone, Copy, PartialEq, Eq)]
@@ -209,6 +277,25 @@ pub struct AudioChunk {
     pub timestamp: DateTime<Utc>,
 }
 
+/// A video chunk with the time of its last frame
+#[derive(Debug, Clone, FromRow)]
+pub struct VideoChunk {
+    pub id: i64,
+    pub file_path: String,
+    pub device_name: String,
+    pub encoding_profile: Option<String>,
+    pub last_frame_at: DateTime<Utc>,
+}
+
+/// A transcribed speech segment of an audio chunk, offsets are relative to the chunk file
+#[derive(Debug, Clone)]
+pub struct TranscriptionSegment {
+    pub transcription: String,
+    pub speaker_id: Option<i64>,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
 #[derive(OaSchema, Debug, FromRow)]
 pub struct AudioChunksResponse {
     pub audio_chunk_id: i64,
@@ -234,14 +321,14 @@ pub struct OcrTextBlock {
     pub line_num: String,
 }
 
-#[derive(OaSchema, Debug, Serialize, Clone)]
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone)]
 pub struct TextPosition {
     pub text: String,
     pub confidence: f32,
     pub bounds: TextBounds,
 }
 
-#[derive(OaSchema, Debug, Serialize, Clone)]
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone)]
 pub struct TextBounds {
     pub left: f32,
     pub top: f32,
@@ -273,6 +360,407 @@ pub struct FrameRow {
     pub text_json: String,
 }
 
+/// What screen time is grouped by
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGroup {
+    #[default]
+    App,
+    Window,
+    Domain,
+}
+
+/// Time spent on an app, window or browser domain
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScreenTime {
+    pub value: String,
+    /// App of the window when grouping by window
+    pub app_name: Option<String>,
+    pub seconds: f64,
+    pub frames: i64,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UsageBucket {
+    pub start: DateTime<Utc>,
+    pub seconds: f64,
+}
+
+/// Time a speaker talked, from the start and end of their transcription segments
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TalkTime {
+    /// None for the segments without an identified speaker
+    pub speaker_id: Option<i64>,
+    pub speaker_name: Option<String>,
+    pub seconds: f64,
+    pub segments: i64,
+}
+
+/// Contiguous stretch of activity without an idle gap, split where the focus moves to another
+/// app or site for longer than a brief switch
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ActivitySession {
+    pub id: i64,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// App the session spent the most time in
+    pub app_name: String,
+    pub browser_domain: Option<String>,
+    /// Window of the dominant app the session spent the most time in
+    pub title: String,
+    pub keywords: Vec<String>,
+    pub frame_count: i64,
+    /// Transcription segments recorded during the session
+    pub audio_segments: i64,
+    pub audio_seconds: f64,
+    /// False while the session can still grow
+    pub closed: bool,
+}
+
+/// Transcription segment recorded during an activity session
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionTranscription {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub transcription: String,
+    pub device_name: String,
+    pub speaker_id: Option<i64>,
+    pub speaker_name: Option<String>,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionizeOptions {
+    /// A longer time without frames ends the session
+    pub idle_gap: chrono::Duration,
+    /// Focus on another app or site for at least this long starts a new session, shorter
+    /// switches stay in the current one
+    pub min_switch: chrono::Duration,
+    /// Frames read per pass besides those of the open session
+    pub batch_size: i64,
+}
+
+impl Default for SessionizeOptions {
+    fn default() -> Self {
+        Self {
+            idle_gap: chrono::Duration::minutes(5),
+            min_switch: chrono::Duration::minutes(2),
+            batch_size: 5000,
+        }
+    }
+}
+
+/// Sessions that began or were closed during a sessionizer pass
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SessionChanges {
+    pub started: Vec<ActivitySession>,
+    pub ended: Vec<ActivitySession>,
+}
+
+/// Kind of recordings included in an archive
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveContent {
+    /// Frames with their OCR text and video chunks
+    Ocr,
+    /// Transcriptions with their speakers and audio chunks
+    Audio,
+    Ui,
+}
+
+/// What an export includes, every field left empty includes everything
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ArchiveFilter {
+    #[serde(default)]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub end_time: Option<DateTime<Utc>>,
+    /// Apps of the frames and UI text, audio is not tied to an app
+    #[serde(default)]
+    pub app_names: Vec<String>,
+    #[serde(default)]
+    pub content_types: Vec<ArchiveContent>,
+}
+
+impl ArchiveFilter {
+    pub fn includes(&self, content: ArchiveContent) -> bool {
+        self.content_types.is_empty() || self.content_types.contains(&content)
+    }
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchiveTableEntry {
+    pub name: String,
+    /// NDJSON file of the rows, relative to the archive
+    pub file: String,
+    pub rows: i64,
+    pub sha256: String,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchiveMediaEntry {
+    /// Relative to the archive
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// `manifest.json` of an archive
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    /// Id of the exporting database, imports use it to recognize rows imported before
+    pub source_id: String,
+    pub filter: ArchiveFilter,
+    pub tables: Vec<ArchiveTableEntry>,
+    pub media: Vec<ArchiveMediaEntry>,
+    /// Media files referenced by the rows but missing on disk at export
+    #[serde(default)]
+    pub missing_media: Vec<String>,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ImportTableReport {
+    pub name: String,
+    pub inserted: i64,
+    /// Rows imported before and changed since by the database that recorded them
+    pub updated: i64,
+    /// Rows already in the database, from an earlier import or with the same key
+    pub duplicates: i64,
+    /// Rows referencing a row missing from the archive
+    pub skipped: i64,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub source_id: String,
+    pub tables: Vec<ImportTableReport>,
+    pub media_copied: usize,
+    /// Media files already present with the same content
+    pub media_existing: usize,
+}
+
+impl ImportReport {
+    /// Adds the counts of another import of the same source
+    pub fn merge(&mut self, other: ImportReport) {
+        self.source_id = other.source_id;
+        self.media_copied += other.media_copied;
+        self.media_existing += other.media_existing;
+        for table in other.tables {
+            match self.tables.iter_mut().find(|t| t.name == table.name) {
+                Some(t) => {
+                    t.inserted += table.inserted;
+                    t.updated += table.updated;
+                    t.duplicates += table.duplicates;
+                    t.skipped += table.skipped;
+                }
+                None => self.tables.push(table),
+            }
+        }
+    }
+}
+
+/// Rows of one table in a sync batch
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SyncTableRows {
+    pub name: String,
+    /// Rows as JSON objects, in the archive format
+    pub rows: Vec<serde_json::Value>,
+}
+
+/// Media file of a row of a sync batch, downloaded with `/sync/media`
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SyncMedia {
+    pub table: String,
+    pub row_id: i64,
+    /// Path of the file on the source, as stored in the row
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Rows changed on a machine since a watermark of its change feed
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SyncBatch {
+    /// Database id of the source machine
+    pub source_id: String,
+    pub since: i64,
+    /// Watermark to pull the next batch from
+    pub watermark: i64,
+    /// Whether more changes follow, the next batch is fetched right away
+    pub has_more: bool,
+    /// Tables in import order
+    pub tables: Vec<SyncTableRows>,
+    pub media: Vec<SyncMedia>,
+}
+
+/// A machine this one pulls recordings from
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq, FromRow)]
+pub struct SyncPeer {
+    pub url: String,
+    /// Database id of the peer, known after the first sync
+    pub peer_id: Option<String>,
+    pub watermark: i64,
+    pub last_sync_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Table of the analytical timeline export
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineTable {
+    /// Frames with their OCR text
+    Frames,
+    AudioTranscriptions,
+    UiMonitoring,
+}
+
+impl TimelineTable {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineTable::Frames => "frames",
+            TimelineTable::AudioTranscriptions => "audio_transcriptions",
+            TimelineTable::UiMonitoring => "ui_monitoring",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineColumnType {
+    Integer,
+    Real,
+    Text,
+    /// UTC, microsecond precision
+    Timestamp,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineColumn {
+    pub name: &'static str,
+    pub column_type: TimelineColumnType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Timestamp(DateTime<Utc>),
+    Boolean(bool),
+}
+
+/// Rows of a timeline table in the order of its columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineChunk {
+    pub rows: Vec<Vec<TimelineValue>>,
+    /// Id to read the next chunk after, None once the table is read to its end
+    pub next_after_id: Option<i64>,
+}
+
+/// Tokenizer of the full text indexes, changing it rebuilds them in the background
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FtsTokenizer {
+    /// Words with case and diacritics folded, `cafe` matches `Café`
+    #[default]
+    Unicode61,
+    /// Sequences of 3 characters: matches substrings and text without spaces like CJK, and
+    /// makes fuzzy searches typo tolerant. Terms need at least 3 characters.
+    Trigram,
+}
+
+impl FtsTokenizer {
+    /// Value of the FTS5 `tokenize` option
+    pub fn tokenize_option(&self) -> &'static str {
+        match self {
+            FtsTokenizer::Unicode61 => "unicode61 remove_diacritics 2",
+            FtsTokenizer::Trigram => "trigram",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FtsTokenizer::Unicode61 => "unicode61",
+            FtsTokenizer::Trigram => "trigram",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unicode61" => Some(FtsTokenizer::Unicode61),
+            "trigram" => Some(FtsTokenizer::Trigram),
+            _ => None,
+        }
+    }
+
+    /// Tokenizer of a `tokenize` option, tables created before diacritic folding are unicode61
+    pub(crate) fn from_tokenize_option(option: &str) -> Self {
+        if option.starts_with("trigram") {
+            FtsTokenizer::Trigram
+        } else {
+            FtsTokenizer::Unicode61
+        }
+    }
+}
+
+/// Queued rebuild of an FTS table, once started the rows up to `max_rowid` are copied in
+/// batches
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FtsRebuild {
+    pub table_name: String,
+    pub tokenizer: FtsTokenizer,
+    pub last_rowid: i64,
+    pub max_rowid: Option<i64>,
+}
+
+/// Width of the time histogram buckets of search facets
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HistogramInterval {
+    Hour,
+    #[default]
+    Day,
+}
+
+impl HistogramInterval {
+    /// strftime format truncating a timestamp to the start of its bucket
+    pub(crate) fn strftime_format(&self) -> &'static str {
+        match self {
+            HistogramInterval::Hour => "%Y-%m-%dT%H:00:00Z",
+            HistogramInterval::Day => "%Y-%m-%dT00:00:00Z",
+        }
+    }
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HistogramBucket {
+    pub start: DateTime<Utc>,
+    pub count: i64,
+}
+
+/// Distribution of the results of a search, values sorted by descending count
+#[derive(OaSchema, Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SearchFacets {
+    pub app_name: Vec<FacetCount>,
+    pub window_name: Vec<FacetCount>,
+    pub browser_domain: Vec<FacetCount>,
+    pub speaker: Vec<FacetCount>,
+    pub device: Vec<FacetCount>,
+    pub content_type: Vec<FacetCount>,
+    /// Buckets with at least one result, in chronological order
+    pub time_histogram: Vec<HistogramBucket>,
+}
+
 #[derive(Deserialize, OaSchema, PartialEq, Default)]
 pub enum Order {
     #[serde(rename = "ascending")]
@@ -1,10 +1,16 @@
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Duration;
 
-    use chrono::Utc;
+    use chrono::{Timelike, Utc};
+    use rust_stemmers::Algorithm;
     use screenpipe_db::{
-        AudioDevice, ContentType, DatabaseManager, DeviceType, Frame, OcrEngine, SearchResult,
+        find_matching_positions, highlight, parse_query, snippet, ArchiveFilter, AudioDevice,
+        ContentType, DatabaseManager, DeviceType, FacetCount, Frame, FtsTokenizer,
+        HistogramInterval, MigrationCommand, MigrationProgress, OcrEngine, OcrTextBlock, Order,
+        QueryExpr, SearchCursor, SearchMatch, SearchResult, SessionizeOptions, SnippetOptions,
+        TagContentType, TimelineTable, TimelineValue, TranscriptionSegment, UsageGroup, FTS_TABLES,
     };
 
     async fn setup_test_db() -> DatabaseManager {
@@ -29,6 +35,172 @@ mod tests {
         db
     }
 
+    #[tokio::test]
+    async fn test_insert_and_get_frame_layout() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        let layout = r#"{"width":100,"height":50,"blocks":[],"tables":[]}"#;
+        db.insert_ocr_text_with_layout(
+            frame_id,
+            "Hello, world!",
+            "",
+            Some(layout),
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.get_frame_layout(frame_id).await.unwrap().as_deref(),
+            Some(layout)
+        );
+
+        // text inserted without a layout and unknown frames have none
+        let other_frame_id = db
+            .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(other_frame_id, "Hello", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+        assert_eq!(db.get_frame_layout(other_frame_id).await.unwrap(), None);
+        assert_eq!(db.get_frame_layout(other_frame_id + 1).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_video_chunk_encoding_profile() {
+        let db = setup_test_db().await;
+        let profile = r#"{"codec":"av1","crf":35}"#;
+        db.insert_video_chunk_with_profile("av1_video.mp4", "test_device", Some(profile))
+            .await
+            .unwrap();
+        db.insert_video_chunk("legacy_video.mp4", "test_device")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            db.get_video_chunk_encoding_profile("av1_video.mp4")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some(profile)
+        );
+        assert_eq!(
+            db.get_video_chunk_encoding_profile("legacy_video.mp4")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.get_video_chunk_encoding_profile("missing.mp4")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_swap_recompressed_video_chunk() {
+        let db = setup_test_db().await;
+        let old = Utc::now() - chrono::Duration::days(40);
+        let old_chunk_id = db
+            .insert_video_chunk("old_video.mp4", "test_device")
+            .await
+            .unwrap();
+        for i in 0..5 {
+            db.insert_frame(
+                "test_device",
+                Some(old + chrono::Duration::seconds(i)),
+                None,
+                None,
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        }
+        db.insert_video_chunk("recent_video.mp4", "test_device")
+            .await
+            .unwrap();
+        db.insert_frame("test_device", None, None, None, None, false)
+            .await
+            .unwrap();
+
+        let before = Utc::now() - chrono::Duration::days(30);
+        let chunks = db
+            .get_video_chunks_to_recompress(before, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].id, old_chunk_id);
+        assert_eq!(chunks[0].file_path, "old_video.mp4");
+
+        let delete_after = Utc::now() + chrono::Duration::minutes(5);
+        db.swap_video_chunk_file(
+            old_chunk_id,
+            "old_video_tiered.mp4",
+            Some("{}"),
+            2,
+            delete_after,
+            7_500,
+        )
+        .await
+        .unwrap();
+
+        let frames: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT offset_index, name FROM frames WHERE video_chunk_id = ?1 ORDER BY id",
+        )
+        .bind(old_chunk_id)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        let offsets: Vec<i64> = frames.iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(offsets, vec![0, 0, 1, 1, 2]);
+        assert!(frames
+            .iter()
+            .all(|(_, name)| name == "old_video_tiered.mp4"));
+        assert_eq!(
+            db.get_video_chunk_encoding_profile("old_video_tiered.mp4")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("{}")
+        );
+
+        // recompressed chunks are not picked again
+        assert!(db
+            .get_video_chunks_to_recompress(before, 0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // the original file is only due once its grace period is over
+        assert!(db
+            .get_due_file_deletions(Utc::now())
+            .await
+            .unwrap()
+            .is_empty());
+        let due = db
+            .get_due_file_deletions(delete_after + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(due, vec![("old_video.mp4".to_string(), 7_500)]);
+        db.remove_pending_file_deletion("old_video.mp4")
+            .await
+            .unwrap();
+        assert!(db
+            .get_due_file_deletions(delete_after + chrono::Duration::seconds(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_insert_and_search_ocr() {
         let db = setup_test_db().await;
@@ -458,14 +630,14 @@ mod tests {
 
         // After inserting both audio transcriptions, let's check all audio entries
         let all_audio = db
-            .search_audio("", 100, 0, None, None, None, None, None)
+            .search_audio("", 100, 0, None, None, None, None, None, None)
             .await
             .unwrap();
         println!("All audio entries: {:?}", all_audio);
 
         // Then try specific search
         let audio_results = db
-            .search_audio("2", 100, 0, None, None, None, None, None)
+            .search_audio("2", 100, 0, None, None, None, None, None, None)
             .await
             .unwrap();
         println!("Audio results for '2': {:?}", audio_results);
@@ -953,6 +1125,300 @@ mod tests {
         assert_eq!(speakers[0].name, "speaker 1");
     }
 
+    #[tokio::test]
+    async fn test_merge_speakers_recomputes_centroid() {
+        let db = setup_test_db().await;
+
+        let mut first = vec![0.0; 512];
+        first[0] = 1.0;
+        let mut second = vec![0.0; 512];
+        second[1] = 1.0;
+
+        let speaker_1 = db.insert_speaker(&first).await.unwrap();
+        let speaker_2 = db.insert_speaker(&second).await.unwrap();
+
+        db.merge_speakers(speaker_1.id, speaker_2.id).await.unwrap();
+
+        let embeddings = db.get_speaker_embeddings(speaker_1.id).await.unwrap();
+        assert_eq!(embeddings.len(), 2);
+
+        let centroid = db
+            .get_speaker_centroid(speaker_1.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(centroid.len(), 512);
+        assert!((centroid[0] - 0.5).abs() < f32::EPSILON);
+        assert!((centroid[1] - 0.5).abs() < f32::EPSILON);
+        assert_eq!(centroid[2], 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_enroll_speaker() {
+        let db = setup_test_db().await;
+
+        let mut sample_a = vec![0.0; 512];
+        sample_a[0] = 1.0;
+        sample_a[1] = 0.2;
+        let mut sample_b = vec![0.0; 512];
+        sample_b[0] = 1.0;
+        sample_b[2] = 0.2;
+
+        let speaker = db
+            .enroll_speaker("alice", &[sample_a, sample_b], None)
+            .await
+            .unwrap();
+        assert_eq!(speaker.name, "alice");
+        assert_eq!(
+            db.get_speaker_embeddings(speaker.id).await.unwrap().len(),
+            2
+        );
+
+        // a new sample close to the profile matches the enrolled speaker
+        let mut probe = vec![0.0; 512];
+        probe[0] = 1.0;
+        let matched = db.get_speaker_from_embedding(&probe).await.unwrap();
+        assert_eq!(matched.unwrap().id, speaker.id);
+
+        // enrolling again into the same speaker extends the profile
+        let mut sample_c = vec![0.0; 512];
+        sample_c[0] = 1.0;
+        sample_c[3] = 0.2;
+        let speaker = db
+            .enroll_speaker("alice", &[sample_c], Some(speaker.id))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_speaker_embeddings(speaker.id).await.unwrap().len(),
+            3
+        );
+
+        assert!(db.enroll_speaker("bob", &[], None).await.is_err());
+
+        // an unknown speaker is an error and stores no embeddings
+        let missing = db
+            .enroll_speaker("carol", &[probe.clone()], Some(speaker.id + 100))
+            .await;
+        assert!(matches!(missing, Err(sqlx::Error::RowNotFound)));
+        assert!(db
+            .get_speaker_embeddings(speaker.id + 100)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_speaker_from_embedding_with_threshold() {
+        let db = setup_test_db().await;
+
+        let mut stored = vec![0.0; 512];
+        stored[0] = 1.0;
+        let speaker = db.insert_speaker(&stored).await.unwrap();
+
+        // cosine distance between the two is ~0.29
+        let mut probe = vec![0.0; 512];
+        probe[0] = 1.0;
+        probe[1] = 1.0;
+
+        let strict = db
+            .get_speaker_from_embedding_with_threshold(&probe, 0.1)
+            .await
+            .unwrap();
+        assert!(strict.is_none());
+
+        let loose = db
+            .get_speaker_from_embedding_with_threshold(&probe, 0.4)
+            .await
+            .unwrap();
+        assert_eq!(loose.unwrap().id, speaker.id);
+    }
+
+    #[tokio::test]
+    async fn test_replace_audio_transcriptions() {
+        let db = setup_test_db().await;
+        let device = AudioDevice {
+            name: "test".to_string(),
+            device_type: DeviceType::Input,
+        };
+
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "old transcription",
+            0,
+            "WhisperTiny",
+            &device,
+            None,
+            Some(0.0),
+            Some(5.0),
+        )
+        .await
+        .unwrap();
+
+        let start = Utc::now() - chrono::Duration::minutes(1);
+        let end = Utc::now() + chrono::Duration::minutes(1);
+        assert_eq!(db.count_audio_chunks_in_range(start, end).await.unwrap(), 1);
+        let chunks = db
+            .get_audio_chunks_in_range(start, end, 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(db
+            .get_audio_chunks_in_range(start, end, audio_chunk_id, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let stored_device = db
+            .get_audio_chunk_device(audio_chunk_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored_device, device);
+
+        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        let inserted = db
+            .replace_audio_transcriptions(
+                audio_chunk_id,
+                &device,
+                "WhisperLargeV3",
+                &[
+                    TranscriptionSegment {
+                        transcription: "hello there".to_string(),
+                        speaker_id: Some(speaker.id),
+                        start_time: 0.0,
+                        end_time: 2.0,
+                    },
+                    TranscriptionSegment {
+                        transcription: "general kenobi".to_string(),
+                        speaker_id: None,
+                        start_time: 2.0,
+                        end_time: 4.0,
+                    },
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(inserted, 2);
+        assert_eq!(
+            db.count_audio_transcriptions(audio_chunk_id).await.unwrap(),
+            2
+        );
+
+        let results = db
+            .search(
+                "kenobi",
+                ContentType::Audio,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = db
+            .search(
+                "old",
+                ContentType::Audio,
+                10,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_migration_progress() {
+        let db = setup_test_db().await;
+
+        assert!(db
+            .get_migration_progress("reprocess_audio")
+            .await
+            .unwrap()
+            .is_none());
+
+        let progress = MigrationProgress {
+            last_processed_id: 42,
+            total_records: 100,
+            processed_records: 10,
+            completed: false,
+        };
+        db.update_migration_progress("reprocess_audio", &progress)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_migration_progress("reprocess_audio").await.unwrap(),
+            Some(progress)
+        );
+
+        db.reset_migration_progress("reprocess_audio")
+            .await
+            .unwrap();
+        assert!(db
+            .get_migration_progress("reprocess_audio")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_job_requests() {
+        let db = setup_test_db().await;
+
+        assert!(db
+            .get_job_request("reprocess_audio")
+            .await
+            .unwrap()
+            .is_none());
+
+        db.set_job_request("reprocess_audio", &MigrationCommand::Pause)
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.get_job_request("reprocess_audio").await.unwrap(),
+            Some(MigrationCommand::Pause)
+        ));
+
+        db.set_job_request("reprocess_audio", &MigrationCommand::Stop)
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.get_job_request("reprocess_audio").await.unwrap(),
+            Some(MigrationCommand::Stop)
+        ));
+
+        // starting again clears the request
+        db.set_job_request("reprocess_audio", &MigrationCommand::Start)
+            .await
+            .unwrap();
+        assert!(db
+            .get_job_request("reprocess_audio")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_search_speakers() {
         let db = setup_test_db().await;
@@ -1204,40 +1670,78 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_insert_and_search_ui_monitoring() {
+    async fn test_insert_ui_monitoring() {
         let db = setup_test_db().await;
 
-        // Insert UI monitoring data
-        sqlx::query(
-            r#"
-            INSERT INTO ui_monitoring (
-                text_output,
-                timestamp,
-                app,
-                window,
-                initial_traversal_at
-            ) VALUES (?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind("Hello from UI monitoring")
-        .bind(Utc::now())
-        .bind("test_app")
-        .bind("test_window")
-        .bind(Utc::now())
-        .execute(&db.pool)
-        .await
-        .unwrap();
+        let id = db
+            .insert_ui_monitoring("zoom", "Zoom Meeting", "Mute\nParticipants", Utc::now())
+            .await
+            .unwrap();
+        assert!(id > 0);
 
-        // Test search with app name filter
         let results = db
             .search(
-                "Hello",
+                "Participants",
                 ContentType::UI,
                 100,
                 0,
                 None,
                 None,
-                Some("test_app"),
+                Some("zoom"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        if let SearchResult::UI(ui_result) = &results[0] {
+            assert_eq!(ui_result.id, id);
+            assert_eq!(ui_result.window_name, "Zoom Meeting");
+        } else {
+            panic!("Expected UI result");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_search_ui_monitoring() {
+        let db = setup_test_db().await;
+
+        // Insert UI monitoring data
+        sqlx::query(
+            r#"
+            INSERT INTO ui_monitoring (
+                text_output,
+                timestamp,
+                app,
+                window,
+                initial_traversal_at
+            ) VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind("Hello from UI monitoring")
+        .bind(Utc::now())
+        .bind("test_app")
+        .bind("test_window")
+        .bind(Utc::now())
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        // Test search with app name filter
+        let results = db
+            .search(
+                "Hello",
+                ContentType::UI,
+                100,
+                0,
+                None,
+                None,
+                Some("test_app"),
                 None,
                 None,
                 None,
@@ -1445,4 +1949,1883 @@ mod tests {
             .unwrap();
         assert_eq!(count, 0, "Should count zero results for non-matching query");
     }
+
+    #[test]
+    fn test_parse_query() {
+        let parsed = parse_query(
+            r#"app:Slack "quarterly plan" -draft speaker:Alice after:2026-09-01 type:audio"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.app_name.as_deref(), Some("Slack"));
+        assert_eq!(parsed.speakers, vec!["Alice".to_string()]);
+        assert_eq!(
+            parsed.start_time.unwrap().to_rfc3339(),
+            "2026-09-01T00:00:00+00:00"
+        );
+        assert_eq!(parsed.content_type, Some(ContentType::Audio));
+        assert_eq!(
+            parsed.text,
+            Some(QueryExpr::And(vec![
+                QueryExpr::Phrase("quarterly plan".to_string()),
+                QueryExpr::Not(Box::new(QueryExpr::Term {
+                    text: "draft".to_string(),
+                    prefix: false,
+                })),
+            ]))
+        );
+        assert_eq!(parsed.fts_query(), r#""quarterly plan" NOT "draft""#);
+
+        // punctuation is quoted instead of reaching fts5 as syntax
+        let parsed = parse_query(r#"c++ (budget OR forecast*) window:"Q3 review""#).unwrap();
+        assert_eq!(parsed.window_name.as_deref(), Some("Q3 review"));
+        assert_eq!(
+            parsed.fts_query(),
+            r#"("c++" AND ("budget" OR "forecast"*))"#
+        );
+        assert_eq!(
+            parsed.fuzzy_fts_query(),
+            r#"("c++"* OR ("budget"* OR "forecast"*))"#
+        );
+        assert_eq!(parsed.terms(), vec!["c++", "budget", "forecast"]);
+
+        // filters only, nothing to match
+        let parsed = parse_query("app:zoom.us focused:true").unwrap();
+        assert_eq!(parsed.text, None);
+        assert_eq!(parsed.fts_query(), "");
+        assert_eq!(parsed.focused, Some(true));
+
+        // unknown fields are plain words
+        let parsed = parse_query("https://example.com").unwrap();
+        assert_eq!(parsed.fts_query(), r#""https://example.com""#);
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        let error = |query: &str| parse_query(query).unwrap_err();
+
+        assert_eq!(error(r#"hello "world"#).position, 7);
+        assert_eq!(error("(hello").position, 1);
+        assert_eq!(error("hello)").position, 6);
+        assert_eq!(error("hello OR").position, 9);
+        assert_eq!(error("-draft").position, 1);
+        assert_eq!(error("a OR -b").position, 6);
+        assert_eq!(error("(app:slack OR b)").position, 2);
+        assert_eq!(error("app:a app:b").position, 11);
+        assert_eq!(error("after:yesterday").position, 7);
+        assert_eq!(error("type:video").position, 6);
+        assert_eq!(error("app: hello").position, 5);
+
+        assert_eq!(error("hello)").to_string(), "unexpected ')' at position 6");
+    }
+
+    #[test]
+    fn test_snippet() {
+        let terms = parse_query(r#"budget "next quarter" -draft"#)
+            .unwrap()
+            .terms();
+        let options = SnippetOptions {
+            tokens: 6,
+            ..Default::default()
+        };
+
+        let text = "File Edit View | Alice discussed the Budget, for the next\n quarter with Bob \
+                    and then everyone left the meeting early";
+        assert_eq!(
+            snippet(text, &terms, &options),
+            "…the **Budget**, for the **next quarter**…"
+        );
+        // the window holding the most distinct terms wins over the first match
+        assert_eq!(
+            snippet(
+                "budget talk then lunch then budget for next quarter",
+                &terms,
+                &options
+            ),
+            "…lunch then **budget** for **next quarter**"
+        );
+        // no match keeps the start of the text, short texts are not cut
+        assert_eq!(
+            snippet("one two three four five six seven", &terms, &options),
+            "one two three four five six…"
+        );
+        assert_eq!(
+            snippet("the   budgets", &terms, &options),
+            "the **budgets**"
+        );
+        assert_eq!(snippet("  ", &terms, &options), "");
+
+        let custom = SnippetOptions {
+            tokens: 3,
+            start_marker: "<b>".to_string(),
+            end_marker: "</b>".to_string(),
+            ellipsis: "...".to_string(),
+        };
+        assert_eq!(
+            snippet("a b c next quarter d e f", &terms, &custom),
+            "...<b>next quarter</b> d..."
+        );
+
+        assert_eq!(
+            highlight(" the budget\nfor next quarter ", &terms, "[", "]"),
+            " the [budget]\nfor [next quarter] "
+        );
+    }
+
+    #[test]
+    fn test_find_matching_positions_for_terms() {
+        let block = |text: &str, left: &str| OcrTextBlock {
+            block_num: "1".to_string(),
+            conf: "90.5".to_string(),
+            page_num: "1".to_string(),
+            left: left.to_string(),
+            height: "12".to_string(),
+            level: "5".to_string(),
+            text: text.to_string(),
+            par_num: "1".to_string(),
+            top: "40".to_string(),
+            word_num: "1".to_string(),
+            width: "60".to_string(),
+            line_num: "1".to_string(),
+        };
+        let blocks = vec![
+            block("the", "0"),
+            block("Budget", "30"),
+            block("meeting", "90"),
+        ];
+        let terms = parse_query("budget -meeting").unwrap().terms().join(" ");
+
+        let positions = find_matching_positions(&blocks, &terms);
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].text, "Budget");
+        assert_eq!(positions[0].bounds.left, 30.0);
+        assert_eq!(positions[0].confidence, 90.5);
+    }
+
+    #[test]
+    fn test_fts_query_for_tokenizers() {
+        let parsed = parse_query(r#"budgte "next quarter" -draft"#).unwrap();
+        assert_eq!(
+            parsed.fts_query_for(FtsTokenizer::Unicode61, true),
+            parsed.fuzzy_fts_query()
+        );
+        assert_eq!(
+            parsed.fts_query_for(FtsTokenizer::Trigram, false),
+            parsed.fts_query()
+        );
+        // one typo: the parts around each character, parts under 3 characters are left out
+        assert_eq!(
+            parsed.fts_query_for(FtsTokenizer::Trigram, true),
+            r#"(("budgte"* OR "udgte" OR "dgte" OR "budg" OR "budgt") OR "next quarter") NOT "draft"*"#
+        );
+
+        let stemmed = parse_query(r#"meetings "running late" -drafts"#)
+            .unwrap()
+            .with_stems(&|word: &str| match word {
+                "meetings" => vec!["meet".to_string(), "meetings".to_string()],
+                "drafts" => vec!["draft".to_string()],
+                _ => Vec::new(),
+            });
+        assert_eq!(
+            stemmed.fts_query(),
+            r#"(("meetings" OR "meet"*) AND "running late") NOT ("drafts" OR "draft"*)"#
+        );
+        assert_eq!(stemmed.terms(), vec!["meetings", "meet", "running late"]);
+    }
+
+    async fn count_ocr_matches(db: &DatabaseManager, query: &str) -> usize {
+        let parsed = parse_query(query).unwrap();
+        db.search(
+            &db.fts_query(&parsed, false),
+            ContentType::OCR,
+            100,
+            0,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .len()
+    }
+
+    #[tokio::test]
+    async fn test_fts_rebuild() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let insert = |text: &'static str| {
+            let db = &db;
+            async move {
+                let frame_id = db
+                    .insert_frame("test_device", None, None, Some("app"), Some(""), false)
+                    .await
+                    .unwrap();
+                db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                    .await
+                    .unwrap();
+            }
+        };
+        for text in [
+            "the budget meeting",
+            "我们讨论了预算问题",
+            "Café crème",
+            "weekly meetings",
+        ] {
+            insert(text).await;
+        }
+
+        // the migration queues a rebuild of the existing tables with diacritic folding
+        assert_eq!(db.fts_rebuilds().await.unwrap().len(), FTS_TABLES.len());
+        db.rebuild_fts_indexes(2, Duration::ZERO).await.unwrap();
+        assert!(db.fts_rebuilds().await.unwrap().is_empty());
+        assert_eq!(count_ocr_matches(&db, "cafe").await, 1);
+        assert!(db
+            .queue_fts_rebuild(FtsTokenizer::Unicode61)
+            .await
+            .unwrap()
+            .is_empty());
+        // a rebuild queued for the tokenizer a table already has is dropped
+        sqlx::query(
+            "INSERT INTO fts_rebuilds (table_name, tokenizer) VALUES ('frames_fts', 'unicode61')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        assert!(db
+            .queue_fts_rebuild(FtsTokenizer::Unicode61)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db.fts_rebuilds().await.unwrap().is_empty());
+
+        assert_eq!(count_ocr_matches(&db, "udge").await, 0);
+        assert_eq!(count_ocr_matches(&db, "讨论了").await, 0);
+        let queued = db.queue_fts_rebuild(FtsTokenizer::Trigram).await.unwrap();
+        assert_eq!(queued, FTS_TABLES);
+        // start the rebuild, then write while it copies the rows
+        assert!(db.rebuild_fts_batch(2).await.unwrap());
+        assert!(db.rebuild_fts_batch(2).await.unwrap());
+        insert("budgeting written during the rebuild").await;
+        sqlx::query("DELETE FROM ocr_text WHERE text = 'weekly meetings'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.fts_tokenizer(), FtsTokenizer::Unicode61);
+        db.rebuild_fts_indexes(2, Duration::ZERO).await.unwrap();
+
+        assert_eq!(db.fts_tokenizer(), FtsTokenizer::Trigram);
+        for table in FTS_TABLES {
+            assert_eq!(db.fts_tokenizer_of(table), FtsTokenizer::Trigram);
+        }
+        assert_eq!(count_ocr_matches(&db, "udge").await, 2);
+        assert_eq!(count_ocr_matches(&db, "讨论了").await, 1);
+        assert_eq!(count_ocr_matches(&db, "meetings").await, 0);
+        // the triggers write to the swapped table
+        insert("quarterly forecast").await;
+        assert_eq!(count_ocr_matches(&db, "forecas").await, 1);
+
+        // typos only match in fuzzy searches
+        let parsed = parse_query("forecazt").unwrap();
+        let fuzzy = db.fts_query(&parsed, true);
+        assert_eq!(
+            db.search(
+                &fuzzy,
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .len(),
+            1
+        );
+        assert_eq!(count_ocr_matches(&db, "forecazt").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_stemming() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, Some("app"), Some(""), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(
+            frame_id,
+            "the team meets every monday",
+            "",
+            Arc::new(OcrEngine::Tesseract),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(count_ocr_matches(&db, "meetings").await, 0);
+        db.set_stemming_languages(vec![Algorithm::English]);
+        assert_eq!(count_ocr_matches(&db, "meetings").await, 1);
+        assert_eq!(count_ocr_matches(&db, "meetings -monday").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_parsed_query() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        for (app, text) in [
+            ("zoom.us", "quarterly plan: don't forget (draft)"),
+            ("zoom.us", "quarterly plan final"),
+            ("Slack", "quarterly plan final"),
+        ] {
+            let frame_id = db
+                .insert_frame("test_device", None, None, Some(app), Some(""), false)
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+
+        let parsed = parse_query(r#""plan:" don't -draft app:zoom.us"#).unwrap();
+        assert_eq!(parsed.fts_query(), r#"("plan:" AND "don't") NOT "draft""#);
+        let results = db
+            .search(
+                &parsed.fts_query(),
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                parsed.app_name.as_deref(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        let parsed = parse_query("quarterly -draft app:zoom.us").unwrap();
+        let results = db
+            .search(
+                &parsed.fts_query(),
+                ContentType::OCR,
+                100,
+                0,
+                None,
+                None,
+                parsed.app_name.as_deref(),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        if let SearchResult::OCR(ocr_result) = &results[0] {
+            assert_eq!(ocr_result.ocr_text, "quarterly plan final");
+            assert_eq!(ocr_result.app_name, "zoom.us");
+        } else {
+            panic!("Expected OCR result");
+        }
+
+        // the keyword search parses the query itself
+        let matches = db
+            .search_with_text_positions(
+                "plan app:Slack",
+                10,
+                0,
+                None,
+                None,
+                None,
+                false,
+                Order::Descending,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].app_name, "Slack");
+        assert!(db
+            .search_with_text_positions(
+                "plan)",
+                10,
+                0,
+                None,
+                None,
+                None,
+                false,
+                Order::Descending,
+                None
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_frame_filters() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        for (url, window, focused) in [
+            (Some("https://docs.rs"), "serde docs", true),
+            (Some("https://github.com"), "pull request", false),
+            (None, "terminal", true),
+        ] {
+            let frame_id = db
+                .insert_frame("test_device", None, url, Some("app"), Some(window), focused)
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                "release notes",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        }
+
+        let windows = |matches: Vec<SearchMatch>| {
+            let mut windows: Vec<String> = matches.into_iter().map(|m| m.window_name).collect();
+            windows.sort();
+            windows
+        };
+        for (query, expected) in [
+            ("release window:terminal", vec!["terminal"]),
+            ("release url:github.com", vec!["pull request"]),
+            ("release focused:true", vec!["serde docs", "terminal"]),
+            ("focused:false", vec!["pull request"]),
+        ] {
+            let matches = db
+                .search_with_text_positions(
+                    query,
+                    10,
+                    0,
+                    None,
+                    None,
+                    None,
+                    false,
+                    Order::Descending,
+                    None,
+                )
+                .await
+                .unwrap();
+            assert_eq!(windows(matches), expected, "{}", query);
+            assert_eq!(
+                db.count_text_position_matches(query, None, None, false, None)
+                    .await
+                    .unwrap(),
+                expected.len()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_pages_keep_frames_whole() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        for i in 0..3 {
+            let frame_id = db
+                .insert_frame("test_device", None, None, Some("app"), Some(""), false)
+                .await
+                .unwrap();
+            // the middle frame has two text rows
+            let rows = if i == 1 { 2 } else { 1 };
+            for row in 0..rows {
+                db.insert_ocr_text(
+                    frame_id,
+                    &format!("invoice {} {}", i, row),
+                    "",
+                    Arc::new(OcrEngine::Tesseract),
+                )
+                .await
+                .unwrap();
+            }
+        }
+
+        let search = |cursor: Option<SearchCursor>, offset: u32| {
+            let db = &db;
+            async move {
+                db.search_with_text_positions(
+                    "invoice",
+                    2,
+                    offset,
+                    cursor.as_ref(),
+                    None,
+                    None,
+                    false,
+                    Order::Ascending,
+                    None,
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let first = search(None, 0).await;
+        let texts: Vec<&str> = first.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, vec!["invoice 0 0", "invoice 1 0", "invoice 1 1"]);
+
+        // the offset is ignored with a cursor
+        let last = first.last().unwrap();
+        let second = search(Some(SearchCursor::new(last.timestamp, last.frame_id)), 5).await;
+        let texts: Vec<&str> = second.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, vec!["invoice 2 0"]);
+        assert_eq!(
+            db.count_text_position_matches("invoice", None, None, false, None)
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_facets() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2026-09-01T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (app, url, text, hours) in [
+            (
+                "Arc",
+                Some("https://github.com/org/repo"),
+                "budget review",
+                0,
+            ),
+            ("Arc", Some("https://GitHub.com/issues"), "budget issues", 0),
+            ("Arc", Some("docs.rs/sqlx"), "budget docs", 1),
+            ("Slack", None, "budget chat", 26),
+            ("Slack", None, "lunch", 26),
+        ] {
+            let frame_id = db
+                .insert_frame(
+                    "screen_1",
+                    Some(timestamp + chrono::Duration::hours(hours)),
+                    url,
+                    Some(app),
+                    Some("main"),
+                    false,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+
+        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        db.update_speaker_name(speaker.id, "Alice").await.unwrap();
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "budget meeting",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            Some(speaker.id),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let facets = db
+            .search_facets(
+                "budget",
+                ContentType::All,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                HistogramInterval::Day,
+                10,
+            )
+            .await
+            .unwrap();
+
+        let count = |value: &str, n: i64| FacetCount {
+            value: value.to_string(),
+            count: n,
+        };
+        assert_eq!(facets.app_name, vec![count("Arc", 3), count("Slack", 1)]);
+        assert_eq!(
+            facets.browser_domain,
+            vec![count("github.com", 2), count("docs.rs", 1)]
+        );
+        assert_eq!(facets.speaker, vec![count("Alice", 1)]);
+        assert_eq!(facets.device, vec![count("screen_1", 4), count("mic", 1)]);
+        assert_eq!(
+            facets.content_type,
+            vec![count("ocr", 4), count("audio", 1)]
+        );
+        // the audio transcription was inserted now, after the frames
+        assert_eq!(facets.time_histogram.len(), 3);
+        assert_eq!(
+            facets.time_histogram[0].start.to_rfc3339(),
+            "2026-09-01T00:00:00+00:00"
+        );
+        assert_eq!(facets.time_histogram[0].count, 3);
+        assert_eq!(facets.time_histogram[1].count, 1);
+
+        // same filters as search: an app filter leaves audio out
+        let facets = db
+            .search_facets(
+                "budget",
+                ContentType::All,
+                None,
+                None,
+                Some("Arc"),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                HistogramInterval::Hour,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(facets.app_name, vec![count("Arc", 3)]);
+        assert_eq!(facets.browser_domain, vec![count("github.com", 2)]);
+        assert!(facets.speaker.is_empty());
+        assert_eq!(facets.time_histogram.len(), 2);
+        assert_eq!(
+            facets.time_histogram[1].start.to_rfc3339(),
+            "2026-09-01T11:00:00+00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_cursor_pagination() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let timestamp = Utc::now() - chrono::Duration::minutes(10);
+        // two frames share a timestamp so the id breaks the tie
+        for (i, seconds) in [0, 10, 10, 20, 30].into_iter().enumerate() {
+            let frame_id = db
+                .insert_frame(
+                    "test_device",
+                    Some(timestamp + chrono::Duration::seconds(seconds)),
+                    None,
+                    Some("test"),
+                    Some(""),
+                    false,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                &format!("report {}", i),
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        }
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "report by audio",
+            0,
+            "",
+            &AudioDevice {
+                name: "test".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mut pages = Vec::new();
+        let mut cursor: Option<SearchCursor> = None;
+        loop {
+            let (results, next) = db
+                .search_after(
+                    "report",
+                    ContentType::All,
+                    2,
+                    cursor.as_ref(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            pages.push(results);
+
+            // a frame recorded while paging does not shift the following pages
+            if cursor.is_none() {
+                let frame_id = db
+                    .insert_frame("test_device", None, None, Some("test"), Some(""), false)
+                    .await
+                    .unwrap();
+                db.insert_ocr_text(frame_id, "report new", "", Arc::new(OcrEngine::Tesseract))
+                    .await
+                    .unwrap();
+            }
+
+            match next {
+                Some(next) => cursor = SearchCursor::decode(&next.encode()),
+                None => break,
+            }
+        }
+
+        let texts: Vec<String> = pages
+            .iter()
+            .flatten()
+            .map(|result| match result {
+                SearchResult::OCR(ocr) => ocr.ocr_text.clone(),
+                SearchResult::Audio(audio) => audio.transcription.clone(),
+                SearchResult::UI(ui) => ui.text.clone(),
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "report by audio",
+                "report 4",
+                "report 3",
+                "report 2",
+                "report 1",
+                "report 0"
+            ]
+        );
+        assert_eq!(pages.len(), 3);
+
+        assert!(SearchCursor::decode("not a cursor").is_none());
+
+        // keyword search continues after the last match in either order
+        let first = db
+            .search_with_text_positions(
+                "report",
+                2,
+                0,
+                None,
+                None,
+                None,
+                false,
+                Order::Ascending,
+                None,
+            )
+            .await
+            .unwrap();
+        let last = first.last().unwrap();
+        let second = db
+            .search_with_text_positions(
+                "report",
+                2,
+                0,
+                Some(&SearchCursor::new(last.timestamp, last.frame_id)),
+                None,
+                None,
+                false,
+                Order::Ascending,
+                None,
+            )
+            .await
+            .unwrap();
+        let texts: Vec<&str> = first
+            .iter()
+            .chain(second.iter())
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["report 0", "report 1", "report 2", "report 3"]);
+        assert_eq!(
+            db.count_text_position_matches("report", None, None, false, None)
+                .await
+                .unwrap(),
+            6
+        );
+
+        // timeline pages split the frames and keep every one of them once
+        let start = timestamp - chrono::Duration::minutes(1);
+        let end = Utc::now() + chrono::Duration::minutes(1);
+        let mut frame_ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let chunk = db
+                .find_video_chunks_after(start, end, cursor.as_ref(), Some(4))
+                .await
+                .unwrap();
+            assert!(chunk.frames.len() <= 4);
+            frame_ids.extend(chunk.frames.iter().map(|frame| frame.frame_id));
+            cursor = chunk.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        let all = db.find_video_chunks(start, end).await.unwrap();
+        assert_eq!(all.frames.len(), 6);
+        assert_eq!(
+            frame_ids,
+            all.frames
+                .iter()
+                .map(|frame| frame.frame_id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_rollups() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2026-09-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (app, url, seconds) in [
+            ("Arc", Some("https://github.com/org/repo"), 0),
+            ("Arc", Some("https://Docs.rs/sqlx"), 10),
+            ("Slack", None, 40),
+            // the gaps after the last two frames are capped
+            ("Slack", None, 300),
+            ("Arc", None, 3600),
+        ] {
+            db.insert_frame(
+                "screen_1",
+                Some(timestamp + chrono::Duration::seconds(seconds)),
+                url,
+                Some(app),
+                Some("main"),
+                true,
+            )
+            .await
+            .unwrap();
+        }
+
+        // the hour before the first frame is marked too, it could hold the frame before it
+        assert_eq!(db.refresh_usage_rollups(None).await.unwrap(), 3);
+        let end = timestamp + chrono::Duration::hours(2);
+        let apps = db
+            .screen_time(timestamp, end, UsageGroup::App, None, 10)
+            .await
+            .unwrap();
+        let apps: Vec<(&str, f64, i64)> = apps
+            .iter()
+            .map(|item| (item.value.as_str(), item.seconds, item.frames))
+            .collect();
+        assert_eq!(apps, vec![("Slack", 120.0, 2), ("Arc", 40.0, 2)]);
+        let domains = db
+            .screen_time(timestamp, end, UsageGroup::Domain, Some("Arc"), 10)
+            .await
+            .unwrap();
+        let domains: Vec<(&str, f64)> = domains
+            .iter()
+            .map(|item| (item.value.as_str(), item.seconds))
+            .collect();
+        assert_eq!(domains, vec![("docs.rs", 30.0), ("github.com", 10.0)]);
+
+        // the last frame gets its time once the next one is recorded
+        db.insert_frame(
+            "screen_1",
+            Some(timestamp + chrono::Duration::seconds(3620)),
+            None,
+            Some("Slack"),
+            Some("main"),
+            true,
+        )
+        .await
+        .unwrap();
+        // its own hour and the one of the minute before it
+        assert_eq!(db.refresh_usage_rollups(None).await.unwrap(), 2);
+        let windows = db
+            .screen_time(timestamp, end, UsageGroup::Window, Some("Arc"), 10)
+            .await
+            .unwrap();
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].value, "main");
+        assert_eq!(windows[0].app_name.as_deref(), Some("Arc"));
+        assert_eq!(windows[0].seconds, 60.0);
+        let histogram = db
+            .screen_time_histogram(timestamp, end, HistogramInterval::Hour, None)
+            .await
+            .unwrap();
+        let histogram: Vec<f64> = histogram.iter().map(|bucket| bucket.seconds).collect();
+        assert_eq!(histogram, vec![160.0, 20.0]);
+
+        let speaker = db.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        db.update_speaker_name(speaker.id, "Alice").await.unwrap();
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        let device = AudioDevice {
+            name: "mic".to_string(),
+            device_type: DeviceType::Input,
+        };
+        for (offset, speaker_id, start, end) in [
+            (0, Some(speaker.id), 0.0, 4.5),
+            (1, Some(speaker.id), 5.0, 7.0),
+            (2, None, 7.0, 8.0),
+        ] {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                "hello",
+                offset,
+                "",
+                &device,
+                speaker_id,
+                Some(start),
+                Some(end),
+            )
+            .await
+            .unwrap();
+        }
+        db.refresh_usage_rollups(None).await.unwrap();
+        let now = Utc::now();
+        let talk_time = db
+            .talk_time(
+                now - chrono::Duration::hours(1),
+                now + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        let talk_time: Vec<(Option<&str>, f64, i64)> = talk_time
+            .iter()
+            .map(|item| (item.speaker_name.as_deref(), item.seconds, item.segments))
+            .collect();
+        assert_eq!(talk_time, vec![(Some("Alice"), 6.5, 2), (None, 1.0, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_usage_rollups_count_focused_frames_per_device() {
+        let db = setup_test_db().await;
+        for device in ["screen_1", "screen_2"] {
+            db.insert_video_chunk(&format!("{}.mp4", device), device)
+                .await
+                .unwrap();
+        }
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2026-09-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // every capture records one frame per window, the monitors are captured in turns
+        for (device, seconds, windows) in [
+            ("screen_1", 0, [("Code", true), ("Slack", false)]),
+            ("screen_2", 1, [("Arc", true), ("Mail", false)]),
+            ("screen_1", 10, [("Code", true), ("Slack", false)]),
+            ("screen_1", 20, [("Slack", true), ("Code", false)]),
+            ("screen_1", 30, [("Code", true), ("Slack", false)]),
+            ("screen_2", 31, [("Arc", true), ("Mail", false)]),
+        ] {
+            for (app, focused) in windows {
+                db.insert_frame(
+                    device,
+                    Some(timestamp + chrono::Duration::seconds(seconds)),
+                    None,
+                    Some(app),
+                    Some("main"),
+                    focused,
+                )
+                .await
+                .unwrap();
+            }
+        }
+
+        let end = timestamp + chrono::Duration::hours(1);
+        async fn apps(
+            db: &DatabaseManager,
+            start: chrono::DateTime<Utc>,
+            end: chrono::DateTime<Utc>,
+        ) -> Vec<(String, f64, i64)> {
+            db.screen_time(start, end, UsageGroup::App, None, 10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|item| (item.value, item.seconds, item.frames))
+                .collect()
+        }
+        db.refresh_usage_rollups(None).await.unwrap();
+        assert_eq!(
+            apps(&db, timestamp, end).await,
+            vec![
+                ("Arc".to_string(), 30.0, 1),
+                ("Code".to_string(), 20.0, 2),
+                ("Slack".to_string(), 10.0, 1),
+            ]
+        );
+
+        // once the other monitor moved on, the last frame of the first one is capped
+        db.insert_frame(
+            "screen_2",
+            Some(timestamp + chrono::Duration::seconds(100)),
+            None,
+            Some("Arc"),
+            Some("main"),
+            true,
+        )
+        .await
+        .unwrap();
+        db.refresh_usage_rollups(None).await.unwrap();
+        assert_eq!(
+            apps(&db, timestamp, end).await,
+            vec![
+                ("Arc".to_string(), 90.0, 2),
+                ("Code".to_string(), 80.0, 3),
+                ("Slack".to_string(), 10.0, 1),
+            ]
+        );
+        // refreshing only the recent hours leaves the older ones marked
+        db.insert_frame(
+            "screen_2",
+            Some(timestamp + chrono::Duration::seconds(120)),
+            None,
+            Some("Arc"),
+            Some("main"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.refresh_usage_rollups(Some(end + chrono::Duration::hours(1)))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(db.refresh_usage_rollups(None).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sessionize_activity() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let base = Utc::now().with_nanosecond(0).unwrap() - chrono::Duration::hours(3);
+        let mut frames = Vec::new();
+        // a 30s look at github stays in the coding session, 200s on docs.rs starts a new one
+        for (app, window, url, from, to) in [
+            ("Code", "main.rs", None, 0, 300),
+            ("Arc", "repo", Some("https://github.com/org/repo"), 300, 330),
+            ("Code", "lib.rs", None, 330, 600),
+            ("Arc", "sqlx", Some("https://docs.rs/sqlx"), 600, 800),
+            ("Slack", "general", None, 1690, 1760),
+        ] {
+            for seconds in (from..to).step_by(10) {
+                frames.push((app, window, url, seconds));
+            }
+        }
+        for (app, window, url, seconds) in frames {
+            db.insert_frame(
+                "screen_1",
+                Some(base + chrono::Duration::seconds(seconds)),
+                url,
+                Some(app),
+                Some(window),
+                true,
+            )
+            .await
+            .unwrap();
+        }
+
+        // small batches keep recomputing the open session until all frames are read
+        let options = SessionizeOptions {
+            batch_size: 25,
+            ..Default::default()
+        };
+        let mut started = Vec::new();
+        let mut ended = Vec::new();
+        for _ in 0..10 {
+            let changes = db.sessionize_activity(&options).await.unwrap();
+            started.extend(changes.started.into_iter().map(|session| session.id));
+            ended.extend(changes.ended.into_iter().map(|session| session.id));
+        }
+        assert_eq!(started.len(), 3);
+        assert_eq!(ended, started);
+
+        let (sessions, total) = db
+            .list_activity_sessions(None, None, None, None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 3);
+        let summary: Vec<(&str, Option<&str>, &str, i64, i64)> = sessions
+            .iter()
+            .map(|session| {
+                (
+                    session.app_name.as_str(),
+                    session.browser_domain.as_deref(),
+                    session.title.as_str(),
+                    (session.end_time - session.start_time).num_seconds(),
+                    session.frame_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Slack", None, "general", 60, 7),
+                ("Arc", Some("docs.rs"), "sqlx", 190, 20),
+                ("Code", None, "main.rs", 600, 60),
+            ]
+        );
+        assert!(sessions.iter().all(|session| session.closed));
+        assert_eq!(sessions[2].start_time, base);
+
+        // recent activity stays open and gets the audio recorded during it
+        let now = Utc::now();
+        for seconds in [-20, -10, 5] {
+            let frame_id = db
+                .insert_frame(
+                    "screen_1",
+                    Some(now + chrono::Duration::seconds(seconds)),
+                    None,
+                    Some("Zoom"),
+                    Some("Weekly sync"),
+                    true,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(
+                frame_id,
+                "quarterly budget review",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        }
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "the budget looks fine",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            Some(1.0),
+            Some(4.0),
+        )
+        .await
+        .unwrap();
+        let changes = db.sessionize_activity(&options).await.unwrap();
+        assert_eq!(changes.started.len(), 1);
+        assert!(changes.ended.is_empty());
+        let session = &changes.started[0];
+        assert!(!session.closed);
+        assert_eq!(session.title, "Weekly sync");
+        assert_eq!(session.keywords[0], "budget");
+        assert_eq!(session.audio_segments, 1);
+        assert_eq!(session.audio_seconds, 3.0);
+        let transcriptions = db.session_transcriptions(session).await.unwrap();
+        assert_eq!(transcriptions.len(), 1);
+        assert_eq!(transcriptions[0].transcription, "the budget looks fine");
+
+        let (sessions, total) = db
+            .list_activity_sessions(None, None, None, Some("budget"), None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(sessions[0].id, session.id);
+        let (sessions, _) = db
+            .list_activity_sessions(None, None, None, None, Some(180.0), 10, 0)
+            .await
+            .unwrap();
+        let apps: Vec<&str> = sessions.iter().map(|s| s.app_name.as_str()).collect();
+        assert_eq!(apps, vec!["Arc", "Code"]);
+        let (sessions, total) = db
+            .list_activity_sessions(
+                Some(base + chrono::Duration::seconds(700)),
+                Some(base + chrono::Duration::seconds(1700)),
+                None,
+                None,
+                None,
+                1,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(sessions[0].app_name, "Slack");
+        assert_eq!(
+            db.get_activity_session(session.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .title,
+            "Weekly sync"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sessionize_focused_window_and_late_frames() {
+        let db = setup_test_db().await;
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let base = Utc::now().with_nanosecond(0).unwrap() - chrono::Duration::hours(3);
+        async fn capture(
+            db: &DatabaseManager,
+            at: chrono::DateTime<Utc>,
+            windows: &[(&str, &str, bool)],
+        ) {
+            // one frame per window, each stamped when it is recorded
+            for (i, (app, window, focused)) in windows.iter().enumerate() {
+                db.insert_frame(
+                    "screen_1",
+                    Some(at + chrono::Duration::milliseconds(i as i64 * 20)),
+                    None,
+                    Some(app),
+                    Some(window),
+                    *focused,
+                )
+                .await
+                .unwrap();
+            }
+        }
+        for seconds in (0..300).step_by(10) {
+            capture(
+                &db,
+                base + chrono::Duration::seconds(seconds),
+                &[
+                    ("Slack", "general", false),
+                    ("Code", "main.rs", true),
+                    ("Spotify", "Discover", false),
+                ],
+            )
+            .await;
+        }
+        let options = SessionizeOptions::default();
+        let changes = db.sessionize_activity(&options).await.unwrap();
+        assert_eq!(changes.started.len(), 1);
+        let session = &changes.started[0];
+        assert_eq!(session.app_name, "Code");
+        assert_eq!(session.frame_count, 30);
+        assert!(session.closed);
+
+        // an imported hour before the sessions already built rebuilds them from there
+        for seconds in (0..200).step_by(10) {
+            capture(
+                &db,
+                base - chrono::Duration::hours(1) + chrono::Duration::seconds(seconds),
+                &[("Figma", "mockups", true), ("Slack", "general", false)],
+            )
+            .await;
+        }
+        let changes = db.sessionize_activity(&options).await.unwrap();
+        let started: Vec<(&str, i64)> = changes
+            .started
+            .iter()
+            .map(|session| (session.app_name.as_str(), session.frame_count))
+            .collect();
+        assert_eq!(started, vec![("Figma", 20), ("Code", 30)]);
+        assert!(db
+            .sessionize_activity(&options)
+            .await
+            .unwrap()
+            .started
+            .is_empty());
+        let (sessions, total) = db
+            .list_activity_sessions(None, None, None, None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(sessions[1].start_time, base - chrono::Duration::hours(1));
+    }
+
+    #[tokio::test]
+    async fn test_archive_export_import() {
+        let source = setup_test_db().await;
+        let target = setup_test_db().await;
+        let dir = std::env::temp_dir().join(format!(
+            "screenpipe_archive_test_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let recordings = dir.join("recordings");
+        std::fs::create_dir_all(&recordings).unwrap();
+        let video_path = recordings.join("monitor_1.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        let audio_path = recordings.join("mic.mp4");
+        std::fs::write(&audio_path, b"audio").unwrap();
+
+        source
+            .insert_video_chunk(video_path.to_str().unwrap(), "screen_1")
+            .await
+            .unwrap();
+        for (app, text) in [("Arc", "budget review"), ("Slack", "lunch plans")] {
+            let frame_id = source
+                .insert_frame("screen_1", None, None, Some(app), Some("main"), false)
+                .await
+                .unwrap();
+            source
+                .insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+            source
+                .add_tags(frame_id, TagContentType::Vision, vec![app.to_lowercase()])
+                .await
+                .unwrap();
+        }
+        let speaker = source.insert_speaker(&vec![0.1; 512]).await.unwrap();
+        source
+            .update_speaker_name(speaker.id, "Alice")
+            .await
+            .unwrap();
+        let audio_chunk_id = source
+            .insert_audio_chunk(audio_path.to_str().unwrap())
+            .await
+            .unwrap();
+        source
+            .insert_audio_transcription(
+                audio_chunk_id,
+                "budget meeting",
+                0,
+                "",
+                &AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: DeviceType::Input,
+                },
+                Some(speaker.id),
+                Some(0.0),
+                Some(2.0),
+            )
+            .await
+            .unwrap();
+        // the target already has rows, the imported ones get new ids
+        target.insert_speaker(&vec![0.5; 512]).await.unwrap();
+
+        let archive = dir.join("archive");
+        let filter = ArchiveFilter {
+            app_names: vec!["Arc".to_string()],
+            ..Default::default()
+        };
+        let manifest = source.export_archive(&archive, &filter).await.unwrap();
+        assert_eq!(manifest.source_id, source.database_id().await.unwrap());
+        let rows: Vec<(&str, i64)> = manifest
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.rows))
+            .collect();
+        assert!(rows.contains(&("frames", 1)));
+        assert!(rows.contains(&("tags", 1)));
+        assert!(rows.contains(&("speaker_embeddings", 1)));
+        assert!(rows.contains(&("audio_transcriptions", 1)));
+        assert_eq!(manifest.media.len(), 2);
+        assert!(source.export_archive(&archive, &filter).await.is_err());
+
+        let media_dir = dir.join("data");
+        let report = target.import_archive(&archive, &media_dir).await.unwrap();
+        assert_eq!(report.media_copied, 2);
+        let inserted: Vec<(&str, i64)> = report
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.inserted))
+            .collect();
+        assert!(inserted.contains(&("frames", 1)));
+        assert!(inserted.contains(&("ocr_text", 1)));
+        assert!(inserted.contains(&("vision_tags", 1)));
+        assert!(report.tables.iter().all(|table| table.skipped == 0));
+
+        let texts: Vec<String> = sqlx::query_scalar("SELECT text FROM ocr_text")
+            .fetch_all(&target.pool)
+            .await
+            .unwrap();
+        assert_eq!(texts, vec!["budget review"]);
+        let (speaker_name, file_path): (String, String) = sqlx::query_as(
+            r#"
+            SELECT s.name, c.file_path FROM audio_transcriptions a
+            JOIN speakers s ON s.id = a.speaker_id
+            JOIN audio_chunks c ON c.id = a.audio_chunk_id
+            "#,
+        )
+        .fetch_one(&target.pool)
+        .await
+        .unwrap();
+        assert_eq!(speaker_name, "Alice");
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"audio");
+        assert!(file_path.starts_with(media_dir.to_str().unwrap()));
+        let embeddings: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM speaker_embeddings")
+            .fetch_one(&target.pool)
+            .await
+            .unwrap();
+        assert_eq!(embeddings, 2);
+
+        // importing again finds every row and file already there
+        let report = target.import_archive(&archive, &media_dir).await.unwrap();
+        assert_eq!(report.media_existing, 2);
+        assert!(report.tables.iter().all(|table| table.inserted == 0));
+        let frames: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames")
+            .fetch_one(&target.pool)
+            .await
+            .unwrap();
+        assert_eq!(frames, 1);
+
+        // a modified table file is refused before anything is written
+        let frames_file = archive.join("tables/frames.ndjson");
+        let mut content = std::fs::read_to_string(&frames_file).unwrap();
+        content.push_str("{}\n");
+        std::fs::write(&frames_file, content).unwrap();
+        let error = target
+            .import_archive(&archive, &media_dir)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeline_chunks() {
+        let db = setup_test_db().await;
+        db.insert_video_chunk("video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        for (i, text) in ["first frame", "second frame", "third frame"]
+            .iter()
+            .enumerate()
+        {
+            let frame_id = db
+                .insert_frame(
+                    "screen_1",
+                    Some(start + chrono::Duration::minutes(i as i64)),
+                    None,
+                    Some("Code"),
+                    Some("main.rs"),
+                    i == 0,
+                )
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+
+        let table = TimelineTable::Frames;
+        let columns: Vec<&str> = table.columns().iter().map(|c| c.name).collect();
+        let column = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+        let chunk = db.timeline_chunk(table, None, None, 0, 2).await.unwrap();
+        assert_eq!(chunk.rows.len(), 2);
+        let first = &chunk.rows[0];
+        assert_eq!(first.len(), columns.len());
+        assert!(matches!(
+            first[column("timestamp")],
+            TimelineValue::Timestamp(_)
+        ));
+        assert_eq!(first[column("focused")], TimelineValue::Boolean(true));
+        assert_eq!(
+            first[column("device_name")],
+            TimelineValue::Text("screen_1".to_string())
+        );
+        assert_eq!(
+            first[column("ocr_text")],
+            TimelineValue::Text("first frame".to_string())
+        );
+        assert_eq!(first[column("machine_id")], TimelineValue::Null);
+
+        // the last chunk is shorter and ends the table
+        let after_id = chunk.next_after_id.unwrap();
+        let chunk = db
+            .timeline_chunk(table, None, None, after_id, 2)
+            .await
+            .unwrap();
+        assert_eq!(chunk.rows.len(), 1);
+        assert_eq!(chunk.next_after_id, None);
+        assert_eq!(
+            chunk.rows[0][column("ocr_text")],
+            TimelineValue::Text("third frame".to_string())
+        );
+
+        let end = start + chrono::Duration::seconds(90);
+        let chunk = db
+            .timeline_chunk(table, Some(start), Some(end), 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(chunk.rows.len(), 2);
+
+        let audio_chunk_id = db.insert_audio_chunk("audio.mp4").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "hello there",
+            0,
+            "",
+            &AudioDevice {
+                name: "mic".to_string(),
+                device_type: DeviceType::Input,
+            },
+            None,
+            Some(1.5),
+            Some(3.0),
+        )
+        .await
+        .unwrap();
+        let table = TimelineTable::AudioTranscriptions;
+        let chunk = db.timeline_chunk(table, None, None, 0, 10).await.unwrap();
+        let row: Vec<(&str, &TimelineValue)> = table
+            .columns()
+            .iter()
+            .map(|c| c.name)
+            .zip(&chunk.rows[0])
+            .collect();
+        assert!(row.contains(&(
+            "transcription",
+            &TimelineValue::Text("hello there".to_string())
+        )));
+        assert!(row.contains(&("start_time", &TimelineValue::Real(1.5))));
+        assert!(row.contains(&("is_input_device", &TimelineValue::Boolean(true))));
+        assert!(row.contains(&("speaker_name", &TimelineValue::Null)));
+    }
+
+    #[tokio::test]
+    async fn test_sync_between_machines() {
+        let laptop = setup_test_db().await;
+        let desktop = setup_test_db().await;
+        let laptop_id = laptop.database_id().await.unwrap();
+        let desktop_id = desktop.database_id().await.unwrap();
+        let dir = std::env::temp_dir().join(format!(
+            "screenpipe_sync_test_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let recordings = dir.join("recordings");
+        std::fs::create_dir_all(&recordings).unwrap();
+        let video_path = recordings.join("monitor_1.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        // a finished chunk, not written to for a while
+        std::fs::File::options()
+            .write(true)
+            .open(&video_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(600))
+            .unwrap();
+
+        laptop
+            .insert_video_chunk(video_path.to_str().unwrap(), "screen_1")
+            .await
+            .unwrap();
+        let frame_id = laptop
+            .insert_frame("screen_1", None, None, Some("Arc"), Some("main"), false)
+            .await
+            .unwrap();
+        laptop
+            .insert_ocr_text(
+                frame_id,
+                "budget review",
+                "",
+                Arc::new(OcrEngine::Tesseract),
+            )
+            .await
+            .unwrap();
+        let speaker = laptop.insert_speaker(&vec![0.1; 512]).await.unwrap();
+
+        // the desktop downloads the media of the batch and applies it
+        let media_dir = dir.join("data");
+        let pull = |batch: screenpipe_db::SyncBatch| {
+            let downloads: std::collections::HashMap<String, std::path::PathBuf> = batch
+                .media
+                .iter()
+                .map(|media| {
+                    let download = dir.join(format!("{}.part", media.sha256));
+                    std::fs::copy(&media.path, &download).unwrap();
+                    (media.path.clone(), download)
+                })
+                .collect();
+            (batch, downloads)
+        };
+        let (batch, downloads) = pull(
+            laptop
+                .sync_changes(0, 1000, Some(&desktop_id))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(batch.source_id, laptop_id);
+        assert!(!batch.has_more);
+        assert_eq!(batch.media.len(), 1);
+        let report = desktop
+            .apply_sync_batch("http://laptop", &batch, &media_dir, &downloads)
+            .await
+            .unwrap();
+        assert_eq!(report.media_copied, 1);
+        let inserted: Vec<(&str, i64)> = report
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.inserted))
+            .collect();
+        assert!(inserted.contains(&("frames", 1)));
+        assert!(inserted.contains(&("ocr_text", 1)));
+        assert!(inserted.contains(&("speakers", 1)));
+
+        let (machine_id, file_path): (String, String) = sqlx::query_as(
+            "SELECT f.machine_id, c.file_path FROM frames f JOIN video_chunks c ON c.id = f.video_chunk_id",
+        )
+        .fetch_one(&desktop.pool)
+        .await
+        .unwrap();
+        assert_eq!(machine_id, laptop_id);
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"video");
+        let peer = desktop.sync_peer("http://laptop").await.unwrap().unwrap();
+        assert_eq!(peer.peer_id.as_deref(), Some(laptop_id.as_str()));
+        assert_eq!(peer.watermark, batch.watermark);
+
+        // nothing new after the watermark, then a rename made on the laptop is applied
+        let batch = laptop
+            .sync_changes(peer.watermark, 1000, Some(&desktop_id))
+            .await
+            .unwrap();
+        assert!(batch.tables.is_empty());
+        laptop
+            .update_speaker_name(speaker.id, "Alice")
+            .await
+            .unwrap();
+        let (batch, downloads) = pull(
+            laptop
+                .sync_changes(peer.watermark, 1000, Some(&desktop_id))
+                .await
+                .unwrap(),
+        );
+        let report = desktop
+            .apply_sync_batch("http://laptop", &batch, &media_dir, &downloads)
+            .await
+            .unwrap();
+        assert_eq!(report.tables[0].name, "speakers");
+        assert_eq!(report.tables[0].updated, 1);
+        let names: Vec<Option<String>> = sqlx::query_scalar("SELECT name FROM speakers")
+            .fetch_all(&desktop.pool)
+            .await
+            .unwrap();
+        assert_eq!(names, vec![Some("Alice".to_string())]);
+
+        // the laptop's rows are not sent back to it, a tag added on the desktop is, with the
+        // frame it tags referenced by its id on the laptop
+        let desktop_frame: i64 = sqlx::query_scalar("SELECT id FROM frames")
+            .fetch_one(&desktop.pool)
+            .await
+            .unwrap();
+        desktop
+            .add_tags(
+                desktop_frame,
+                TagContentType::Vision,
+                vec!["finance".to_string()],
+            )
+            .await
+            .unwrap();
+        let (batch, downloads) = pull(
+            desktop
+                .sync_changes(0, 1000, Some(&laptop_id))
+                .await
+                .unwrap(),
+        );
+        assert!(batch.media.is_empty());
+        let report = laptop
+            .apply_sync_batch("http://desktop", &batch, &media_dir, &downloads)
+            .await
+            .unwrap();
+        let counts: Vec<(&str, i64, i64)> = report
+            .tables
+            .iter()
+            .map(|table| (table.name.as_str(), table.inserted, table.duplicates))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("tags", 1, 0),
+                ("video_chunks", 0, 1),
+                ("frames", 0, 1),
+                ("vision_tags", 1, 0)
+            ]
+        );
+        let frames: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames")
+            .fetch_one(&laptop.pool)
+            .await
+            .unwrap();
+        assert_eq!(frames, 1);
+
+        // a chunk still being recorded holds the feed back until it is finished
+        let open_path = recordings.join("monitor_2.mp4");
+        std::fs::write(&open_path, b"recording").unwrap();
+        laptop
+            .insert_video_chunk(open_path.to_str().unwrap(), "screen_1")
+            .await
+            .unwrap();
+        let peer = desktop.sync_peer("http://laptop").await.unwrap().unwrap();
+        let batch = laptop
+            .sync_changes(peer.watermark, 1000, Some(&desktop_id))
+            .await
+            .unwrap();
+        assert!(batch.tables.is_empty());
+        assert!(!batch.has_more);
+        std::fs::File::options()
+            .write(true)
+            .open(&open_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(600))
+            .unwrap();
+        let batch = laptop
+            .sync_changes(batch.watermark, 1000, Some(&desktop_id))
+            .await
+            .unwrap();
+        assert_eq!(batch.tables[0].name, "video_chunks");
+        assert_eq!(batch.media[0].path, open_path.to_str().unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_log_backfill_and_media_hashes() {
+        let db = setup_test_db().await;
+        let dir = std::env::temp_dir().join(format!(
+            "screenpipe_sync_backfill_test_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video_path = dir.join("monitor_1.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&video_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(600))
+            .unwrap();
+        db.insert_video_chunk(video_path.to_str().unwrap(), "screen_1")
+            .await
+            .unwrap();
+        for _ in 0..5 {
+            db.insert_frame("screen_1", None, None, Some("Arc"), Some("main"), true)
+                .await
+                .unwrap();
+        }
+
+        // rows recorded before the change feed existed, as after the upgrade adding it
+        sqlx::query("DELETE FROM sync_log")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO sync_backfill (table_name, up_to)
+            SELECT 'video_chunks', MAX(rowid) FROM video_chunks
+            UNION ALL SELECT 'frames', MAX(rowid) FROM frames
+            "#,
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let batch = db.sync_changes(0, 1000, None).await.unwrap();
+        assert!(batch.tables.is_empty());
+        assert!(!batch.has_more);
+        assert_eq!(batch.watermark, 0);
+
+        // a row changed during the backfill keeps its place at the end of the feed
+        sqlx::query("UPDATE frames SET window_name = 'renamed' WHERE id = 1")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let mut passes = 0;
+        while db.backfill_sync_log(2).await.unwrap() {
+            passes += 1;
+        }
+        assert_eq!(passes, 4);
+        let feed: Vec<(String, i64)> =
+            sqlx::query_as("SELECT table_name, row_id FROM sync_log ORDER BY seq")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        let feed: Vec<(&str, i64)> = feed
+            .iter()
+            .map(|(table, row_id)| (table.as_str(), *row_id))
+            .collect();
+        assert_eq!(
+            feed,
+            vec![
+                ("frames", 1),
+                ("video_chunks", 1),
+                ("frames", 2),
+                ("frames", 3),
+                ("frames", 4),
+                ("frames", 5)
+            ]
+        );
+
+        // the media file is hashed once, then while it is unchanged its stored hash is sent
+        let batch = db.sync_changes(0, 1000, None).await.unwrap();
+        assert_eq!(batch.media.len(), 1);
+        let sha256 = batch.media[0].sha256.clone();
+        let stored: String = sqlx::query_scalar("SELECT sha256 FROM media_hashes")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, sha256);
+        sqlx::query("UPDATE media_hashes SET sha256 = 'stored'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let batch = db.sync_changes(0, 1000, None).await.unwrap();
+        assert_eq!(batch.media[0].sha256, "stored");
+        std::fs::write(&video_path, b"recompressed video").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&video_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(300))
+            .unwrap();
+        let batch = db.sync_changes(0, 1000, None).await.unwrap();
+        assert_eq!(batch.media[0].size, 18);
+        assert_ne!(batch.media[0].sha256, "stored");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
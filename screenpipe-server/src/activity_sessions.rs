@@ -0,0 +1,42 @@
+//! Background sessionizer: groups the recorded frames into work sessions and announces their
+//! boundaries as `activity_session_started` and `activity_session_ended` events
+
+use screenpipe_db::{DatabaseManager, SessionizeOptions};
+use screenpipe_events::send_event;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+pub const DEFAULT_SESSION_IDLE_GAP_SECS: u64 = 300;
+pub const DEFAULT_SESSION_MIN_SWITCH_SECS: u64 = 120;
+
+/// How often the sessionizer reads the new frames
+const SESSIONIZE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Runs a sessionizer pass every minute until the process exits. A pass reads a batch of frames,
+/// so a backlog is worked through one batch per minute.
+pub async fn run_sessionizer(db: Arc<DatabaseManager>, options: SessionizeOptions) {
+    loop {
+        match db.sessionize_activity(&options).await {
+            Ok(changes) => {
+                debug!(
+                    "sessionizer started {} sessions and ended {}",
+                    changes.started.len(),
+                    changes.ended.len()
+                );
+                for session in changes.started {
+                    if let Err(e) = send_event("activity_session_started", session) {
+                        warn!("failed to send session started event: {}", e);
+                    }
+                }
+                for session in changes.ended {
+                    if let Err(e) = send_event("activity_session_ended", session) {
+                        warn!("failed to send session ended event: {}", e);
+                    }
+                }
+            }
+            Err(e) => error!("sessionizer failed: {}", e),
+        }
+        tokio::time::sleep(SESSIONIZE_INTERVAL).await;
+    }
+}
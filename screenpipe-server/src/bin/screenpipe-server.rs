@@ -9,23 +9,36 @@ use screenpipe_audio::{
     core::device::{
         default_input_device, default_output_device, list_audio_devices, parse_audio_device,
     },
+    reprocess::ReprocessConfig,
+    vad::{eval::evaluate_vad, VadConfigFile},
 };
 use screenpipe_core::find_ffmpeg_path;
 use screenpipe_db::{
-    create_migration_worker, DatabaseManager, MigrationCommand, MigrationConfig, MigrationStatus,
+    create_migration_worker, ArchiveFilter, DatabaseManager, FtsTokenizer, MigrationCommand,
+    MigrationConfig, MigrationStatus, TimelineTable,
 };
 use screenpipe_server::{
+    activity_sessions::run_sessionizer,
     cli::{
         AudioCommand, Cli, CliAudioTranscriptionEngine, CliOcrEngine, Command, MigrationSubCommand,
         OutputFormat, PipeCommand, VisionCommand,
     },
-    handle_index_command,
+    filtering::stemming_algorithm,
+    handle_index_command, handle_reprocess_command,
     pipe_manager::PipeInfo,
-    start_continuous_recording, watch_pid, PipeManager, ResourceMonitor, SCServer,
+    start_continuous_recording,
+    sync::{run_sync, run_sync_log_backfill},
+    timeline_export::export_timeline_to_file,
+    usage_rollups::run_usage_rollups,
+    video_tiering::run_video_tiering,
+    watch_pid, PipeManager, ResourceMonitor, SCServer,
 };
-use screenpipe_vision::monitor::list_monitors;
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 use screenpipe_vision::run_ui;
+use screenpipe_vision::{
+    capture_rate::CaptureRateConfig, frame_source::FrameSourceConfig, monitor::list_monitors,
+    ocr_pool::OcrPool,
+};
 use serde_json::{json, Value};
 use std::{
     env, fs, io::Write, net::SocketAddr, ops::Deref, path::PathBuf, sync::Arc, time::Duration,
@@ -48,6 +61,11 @@ const DISPLAY: &str = r"
 
 ";
 
+/// Rows copied per batch when rebuilding the full text indexes
+const FTS_REBUILD_BATCH_SIZE: i64 = 1000;
+/// Pause between two batches of the rebuild, leaving room for the recording writes
+const FTS_REBUILD_PAUSE: Duration = Duration::from_millis(100);
+
 fn get_base_dir(custom_path: &Option<String>) -> anyhow::Result<PathBuf> {
     let default_path = home_dir()
         .ok_or_else(|| anyhow::anyhow!("failed to get home directory"))?
@@ -474,6 +492,226 @@ async fn main() -> anyhow::Result<()> {
 
                 return Ok(());
             }
+            Command::Reprocess {
+                start_time,
+                end_time,
+                audio_transcription_engine,
+                language,
+                deepgram_api_key,
+                vad_engine,
+                vad_config,
+                speaker_match_threshold,
+                data_dir,
+                subcommand,
+                output,
+                batch_size,
+                batch_delay_ms,
+                continue_on_error,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = Arc::new(
+                    DatabaseManager::new(&format!(
+                        "{}/db.sqlite",
+                        local_data_dir.to_string_lossy()
+                    ))
+                    .await
+                    .map_err(|e| {
+                        error!("failed to initialize database: {:?}", e);
+                        e
+                    })?,
+                );
+
+                let mut config = ReprocessConfig::new(
+                    *start_time,
+                    end_time.unwrap_or_else(chrono::Utc::now),
+                    audio_transcription_engine.clone().into(),
+                );
+                config.languages = language.clone();
+                config.deepgram_api_key = deepgram_api_key
+                    .clone()
+                    .or_else(|| env::var("DEEPGRAM_API_KEY").ok());
+                config.vad_engine = vad_engine.clone().into();
+                if let Some(path) = vad_config {
+                    let vad_config = VadConfigFile::load(path).map_err(|e| {
+                        anyhow::anyhow!("failed to load vad config {}: {}", path.display(), e)
+                    })?;
+                    config.vad_config = vad_config.default;
+                    config.device_vad_configs = vad_config.devices;
+                }
+                config.speaker_match_threshold = *speaker_match_threshold;
+                config.batch_size = *batch_size;
+                config.batch_delay_ms = *batch_delay_ms;
+                config.continue_on_error = *continue_on_error;
+
+                handle_reprocess_command(db, config, subcommand.as_ref(), output).await?;
+                return Ok(());
+            }
+            Command::Export {
+                path,
+                start_time,
+                end_time,
+                app_names,
+                content_types,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new(&format!(
+                    "{}/db.sqlite",
+                    local_data_dir.to_string_lossy()
+                ))
+                .await?;
+                let filter = ArchiveFilter {
+                    start_time: *start_time,
+                    end_time: *end_time,
+                    app_names: app_names.clone(),
+                    content_types: content_types.iter().cloned().map(Into::into).collect(),
+                };
+
+                let manifest = db.export_archive(path, &filter).await?;
+                match output {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&manifest)?),
+                    OutputFormat::Text => {
+                        for table in &manifest.tables {
+                            println!("{}: {} rows", table.name, table.rows);
+                        }
+                        println!(
+                            "exported {} media files to {}",
+                            manifest.media.len(),
+                            path.display()
+                        );
+                        if !manifest.missing_media.is_empty() {
+                            println!(
+                                "{} media files were missing and not exported",
+                                manifest.missing_media.len()
+                            );
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            Command::Import {
+                path,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new(&format!(
+                    "{}/db.sqlite",
+                    local_data_dir.to_string_lossy()
+                ))
+                .await?;
+
+                let report = db
+                    .import_archive(path, &local_data_dir.join("data"))
+                    .await?;
+                match output {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                    OutputFormat::Text => {
+                        for table in &report.tables {
+                            println!(
+                                "{}: {} inserted, {} already present, {} skipped",
+                                table.name, table.inserted, table.duplicates, table.skipped
+                            );
+                        }
+                        println!(
+                            "copied {} media files, {} were already present",
+                            report.media_copied, report.media_existing
+                        );
+                    }
+                }
+                return Ok(());
+            }
+            Command::ExportTimeline {
+                path,
+                table,
+                format,
+                start_time,
+                end_time,
+                data_dir,
+                output,
+            } => {
+                let local_data_dir = get_base_dir(data_dir)?;
+                let db = DatabaseManager::new(&format!(
+                    "{}/db.sqlite",
+                    local_data_dir.to_string_lossy()
+                ))
+                .await?;
+
+                let table: TimelineTable = table.clone().into();
+                let rows =
+                    export_timeline_to_file(&db, table, *format, *start_time, *end_time, path)
+                        .await?;
+                match output {
+                    OutputFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "table": table,
+                            "rows": rows,
+                            "path": path,
+                        }))?
+                    ),
+                    OutputFormat::Text => println!(
+                        "exported {} rows of {} to {}",
+                        rows,
+                        table.as_str(),
+                        path.display()
+                    ),
+                }
+                return Ok(());
+            }
+            Command::VadEval {
+                paths,
+                vad_engine,
+                vad_config,
+                speech_threshold,
+                silence_threshold,
+                frame_history,
+                speech_frame_threshold,
+                output,
+            } => {
+                let mut config = match vad_config {
+                    Some(path) => VadConfigFile::load(path)?.default,
+                    None => Default::default(),
+                };
+                if let Some(speech_threshold) = speech_threshold {
+                    config.speech_threshold = *speech_threshold;
+                }
+                if let Some(silence_threshold) = silence_threshold {
+                    config.silence_threshold = *silence_threshold;
+                }
+                if let Some(frame_history) = frame_history {
+                    config.frame_history = *frame_history;
+                }
+                if let Some(speech_frame_threshold) = speech_frame_threshold {
+                    config.speech_frame_threshold = *speech_frame_threshold;
+                }
+
+                let report = evaluate_vad(vad_engine.clone().into(), config, paths).await?;
+                match output {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                    OutputFormat::Text => {
+                        for file in &report.files {
+                            println!(
+                                "{}: precision {:.3}, recall {:.3}, f1 {:.3}",
+                                file.path.display(),
+                                file.score.precision(),
+                                file.score.recall(),
+                                file.score.f1()
+                            );
+                        }
+                        println!(
+                            "total ({} frames): precision {:.3}, recall {:.3}, f1 {:.3}, accuracy {:.3}",
+                            report.total.frames(),
+                            report.total.precision(),
+                            report.total.recall(),
+                            report.total.f1(),
+                            report.total.accuracy()
+                        );
+                    }
+                }
+                return Ok(());
+            }
             Command::Add {
                 path,
                 output,
@@ -540,6 +778,18 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    if !cli.sync_peers.is_empty() && cli.sync_secret.is_none() {
+        return Err(anyhow::anyhow!(
+            "--sync-peer needs --sync-secret, the secret shared by the machines syncing together"
+        ));
+    }
+    if !cli.listen_address.is_loopback() {
+        warn!(
+            "listening on {}, the api is reachable from the network",
+            cli.listen_address
+        );
+    }
+
     if !is_local_ipv4_port_free(cli.port) {
         error!(
             "you're likely already running screenpipe instance in a different environment, e.g. terminal/ide, close it and restart or use different port"
@@ -618,10 +868,25 @@ async fn main() -> anyhow::Result<()> {
     } else {
         cli.monitor_id.clone()
     };
+    let frame_sources = if cli.frame_source.is_empty() {
+        monitor_ids
+            .iter()
+            .map(|&id| FrameSourceConfig::Monitor(id))
+            .collect::<Vec<_>>()
+    } else {
+        cli.frame_source
+            .iter()
+            .map(|spec| FrameSourceConfig::parse(spec))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
 
     let languages = cli.unique_languages().unwrap();
     let languages_clone = languages.clone();
 
+    db.set_stemming_languages(languages.iter().filter_map(stemming_algorithm).collect());
+    let fts_tokenizer: FtsTokenizer = cli.fts_tokenizer.clone().into();
+    let fts_rebuilds = db.queue_fts_rebuild(fts_tokenizer).await?;
+
     let ocr_engine_clone = cli.ocr_engine.clone();
     let vad_engine = cli.vad_engine.clone();
     let vad_engine_clone = vad_engine.clone();
@@ -632,12 +897,22 @@ async fn main() -> anyhow::Result<()> {
     let pipes_runtime = Runtime::new().unwrap();
 
     let vision_handle = vision_runtime.handle().clone();
+    let ocr_pool = {
+        // workers run next to the captures, on the vision runtime
+        let _guard = vision_runtime.enter();
+        OcrPool::new(
+            cli.ocr_workers.unwrap_or(frame_sources.len()),
+            cli.ocr_queue_size,
+        )
+    };
     let pipes_handle = pipes_runtime.handle().clone();
 
     let db_clone = Arc::clone(&db);
     let output_path_clone = Arc::new(local_data_dir.join("data").to_string_lossy().into_owned());
     let shutdown_tx_clone = shutdown_tx.clone();
-    let monitor_ids_clone = monitor_ids.clone();
+    let frame_sources_clone = frame_sources.clone();
+    let ocr_pool_clone = ocr_pool.clone();
+    let recording_ocr_engine = Arc::new(cli.recording_ocr_engine());
     let ignored_windows_clone = cli.ignored_windows.clone();
     let included_windows_clone = cli.included_windows.clone();
     let realtime_audio_devices_clone = realtime_audio_devices.clone();
@@ -648,18 +923,48 @@ async fn main() -> anyhow::Result<()> {
         eprintln!("invalid fps value: {}. using default of 1.0", cli.fps);
         1.0
     };
+    let capture_rate = CaptureRateConfig {
+        min_fps: cli.min_fps.unwrap_or(fps.min(cli.max_fps.unwrap_or(fps))),
+        max_fps: cli.max_fps.unwrap_or(fps.max(cli.min_fps.unwrap_or(fps))),
+        diff_threshold: cli.frame_diff_threshold,
+    };
+    capture_rate
+        .validate()
+        .map_err(|e| anyhow::anyhow!("invalid capture rate: {}", e))?;
+    if !(0.0..=1.0).contains(&cli.reocr_min_confidence) {
+        return Err(anyhow::anyhow!(
+            "reocr min confidence must be between 0 and 1"
+        ));
+    }
+    let encoding_profile = cli
+        .video_encoding_profile()
+        .map_err(|e| anyhow::anyhow!("invalid video profile: {}", e))?;
+    let encoding_profile_clone = encoding_profile.clone();
+    let tiering_config = cli
+        .video_tiering_config()
+        .map_err(|e| anyhow::anyhow!("invalid video tiering: {}", e))?;
 
     let audio_chunk_duration = Duration::from_secs(cli.audio_chunk_duration);
 
+    let vad_config = match &cli.vad_config {
+        Some(path) => VadConfigFile::load(path)
+            .map_err(|e| anyhow::anyhow!("failed to load vad config {}: {}", path.display(), e))?,
+        None => VadConfigFile::default(),
+    };
+
     let mut audio_manager_builder = AudioManagerBuilder::new()
         .audio_chunk_duration(audio_chunk_duration)
         .vad_engine(vad_engine.into())
         .vad_sensitivity(cli.vad_sensitivity.into())
+        .vad_config(vad_config.default)
+        .device_vad_configs(vad_config.devices)
         .languages(languages.clone())
         .transcription_engine(cli.audio_transcription_engine.into())
         .realtime(cli.enable_realtime_audio_transcription)
+        .realtime_engine(cli.realtime_audio_engine.clone().into())
         .enabled_devices(audio_devices)
         .deepgram_api_key(cli.deepgram_api_key.clone())
+        .speaker_match_threshold(cli.speaker_match_threshold)
         .output_path(PathBuf::from(output_path_clone.clone().to_string()));
 
     let audio_manager = match audio_manager_builder.build(db.clone()).await {
@@ -678,10 +983,11 @@ async fn main() -> anyhow::Result<()> {
                 let recording_future = start_continuous_recording(
                     db_clone.clone(),
                     output_path_clone.clone(),
-                    fps,
+                    capture_rate.clone(),
                     Duration::from_secs(cli.video_chunk_duration),
-                    Arc::new(cli.ocr_engine.clone().into()),
-                    monitor_ids_clone.clone(),
+                    recording_ocr_engine.clone(),
+                    ocr_pool_clone.clone(),
+                    frame_sources_clone.clone(),
                     cli.use_pii_removal,
                     cli.disable_vision,
                     &vision_handle,
@@ -690,6 +996,7 @@ async fn main() -> anyhow::Result<()> {
                     languages_clone.clone(),
                     cli.capture_unfocused_windows,
                     cli.enable_realtime_audio_transcription,
+                    encoding_profile_clone.clone(),
                 );
 
                 let result = tokio::select! {
@@ -726,14 +1033,17 @@ async fn main() -> anyhow::Result<()> {
 
     let server = SCServer::new(
         db_server,
-        SocketAddr::from(([127, 0, 0, 1], cli.port)),
+        SocketAddr::new(cli.listen_address, cli.port),
         local_data_dir_clone_2,
         pipe_manager.clone(),
         cli.disable_vision,
         cli.disable_audio,
         cli.enable_ui_monitoring,
         audio_manager.clone(),
-    );
+    )
+    .with_ocr_pool(ocr_pool)
+    .with_encoding_profile(encoding_profile.clone())
+    .with_sync(cli.sync_peers.clone(), cli.sync_secret.clone());
 
     // print screenpipe in gradient
     println!("\n\n{}", DISPLAY.truecolor(147, 112, 219).bold());
@@ -751,7 +1061,18 @@ async fn main() -> anyhow::Result<()> {
     println!("┌────────────────────────┬────────────────────────────────────┐");
     println!("│ setting                │ value                              │");
     println!("├────────────────────────┼────────────────────────────────────┤");
-    println!("│ fps                    │ {:<34} │", cli.fps);
+    if cli.min_fps.is_some() || cli.max_fps.is_some() {
+        println!(
+            "│ fps                    │ {:<34} │",
+            format!(
+                "{}-{} adaptive",
+                cli.min_fps.unwrap_or(cli.fps),
+                cli.max_fps.unwrap_or(cli.fps)
+            )
+        );
+    } else {
+        println!("│ fps                    │ {:<34} │", cli.fps);
+    }
     println!(
         "│ audio chunk duration   │ {:<34} │",
         format!("{} seconds", cli.audio_chunk_duration)
@@ -760,7 +1081,21 @@ async fn main() -> anyhow::Result<()> {
         "│ video chunk duration   │ {:<34} │",
         format!("{} seconds", cli.video_chunk_duration)
     );
+    println!(
+        "│ video profile          │ {:<34} │",
+        format_cell(
+            &format!(
+                "{} ({:?}, crf {}, {})",
+                cli.video_profile,
+                encoding_profile.codec,
+                encoding_profile.crf,
+                encoding_profile.preset
+            ),
+            VALUE_WIDTH
+        )
+    );
     println!("│ port                   │ {:<34} │", cli.port);
+    println!("│ listen address         │ {:<34} │", cli.listen_address);
     println!(
         "│ realtime audio enabled │ {:<34} │",
         cli.enable_realtime_audio_transcription
@@ -775,6 +1110,12 @@ async fn main() -> anyhow::Result<()> {
         "│ ocr engine             │ {:<34} │",
         format!("{:?}", ocr_engine_clone)
     );
+    if let Some(secondary) = &cli.secondary_ocr_engine {
+        println!(
+            "│ secondary ocr engine   │ {:<34} │",
+            format!("{:?} below {}", secondary, cli.reocr_min_confidence)
+        );
+    }
     println!(
         "│ vad engine             │ {:<34} │",
         format!("{:?}", vad_engine_clone)
@@ -877,12 +1218,15 @@ async fn main() -> anyhow::Result<()> {
 
     if cli.disable_vision {
         println!("│ {:<22} │ {:<34} │", "", "vision disabled");
-    } else if monitor_ids.is_empty() {
+    } else if frame_sources.is_empty() {
         println!("│ {:<22} │ {:<34} │", "", "no monitors available");
     } else {
-        let total_monitors = monitor_ids.len();
-        for (_, monitor) in monitor_ids.iter().enumerate().take(MAX_ITEMS_TO_DISPLAY) {
-            let monitor_str = format!("id: {}", monitor);
+        let total_monitors = frame_sources.len();
+        for (_, source) in frame_sources.iter().enumerate().take(MAX_ITEMS_TO_DISPLAY) {
+            let monitor_str = match source.monitor_id() {
+                Some(id) => format!("id: {}", id),
+                None => source.to_string(),
+            };
             let formatted_monitor = format_cell(&monitor_str, VALUE_WIDTH);
             println!("│ {:<22} │ {:<34} │", "", formatted_monitor);
         }
@@ -1093,6 +1437,109 @@ async fn main() -> anyhow::Result<()> {
     let ctrl_c_future = signal::ctrl_c();
     pin_mut!(ctrl_c_future);
 
+    // Rebuild the full text indexes when the tokenizer changed
+    if !fts_rebuilds.is_empty() {
+        info!(
+            "rebuilding full text indexes with the {} tokenizer: {}",
+            fts_tokenizer.name(),
+            fts_rebuilds.join(", ")
+        );
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                result = db_clone.rebuild_fts_indexes(FTS_REBUILD_BATCH_SIZE, FTS_REBUILD_PAUSE) => match result {
+                    Ok(()) => info!("full text indexes rebuilt"),
+                    Err(e) => error!("failed to rebuild full text indexes: {}", e),
+                },
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, pausing full text index rebuild");
+                }
+            }
+        });
+    }
+
+    // Recompress old video chunks into the storage tier
+    if let Some(tiering_config) = tiering_config {
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                _ = run_video_tiering(db_clone, tiering_config) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping video tiering");
+                }
+            }
+        });
+    }
+
+    // Pull the recordings of the other machines
+    if !cli.sync_peers.is_empty() {
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        let sync_peers = cli.sync_peers.clone();
+        let sync_secret = cli.sync_secret.clone().unwrap_or_default();
+        let media_dir = local_data_dir.join("data");
+        let sync_interval = Duration::from_secs(cli.sync_interval);
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                _ = run_sync(db_clone, sync_peers, sync_secret, media_dir, sync_interval) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping sync");
+                }
+            }
+        });
+    }
+
+    // Add the rows recorded before the change feed existed to it
+    {
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                _ = run_sync_log_backfill(db_clone) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping change feed backfill");
+                }
+            }
+        });
+    }
+
+    // Group the recorded frames into activity sessions
+    {
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        let sessionize_options = cli.sessionize_options();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                _ = run_sessionizer(db_clone, sessionize_options) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping sessionizer");
+                }
+            }
+        });
+    }
+
+    // Keep the usage rollups up to date, starting with the hours changed while stopped
+    {
+        let db_clone = db.clone();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+            tokio::select! {
+                _ = run_usage_rollups(db_clone) => {}
+                _ = shutdown_rx.recv() => {
+                    info!("received shutdown signal, stopping usage rollups");
+                }
+            }
+        });
+    }
+
     // Start the UI monitoring task
     #[cfg(target_os = "macos")]
     if cli.enable_ui_monitoring {
@@ -1121,6 +1568,34 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
+    #[cfg(target_os = "linux")]
+    if cli.enable_ui_monitoring {
+        let shutdown_tx_clone = shutdown_tx.clone();
+        let db_clone = db.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_tx_clone.subscribe();
+
+            loop {
+                tokio::select! {
+                    result = run_ui(db_clone.clone()) => {
+                        match result {
+                            Ok(_) => break,
+                            Err(e) => {
+                                error!("ui monitoring error: {}", e);
+                                tokio::time::sleep(Duration::from_secs(5)).await;
+                                continue;
+                            }
+                        }
+                    }
+                    _ = shutdown_rx.recv() => {
+                        info!("received shutdown signal, stopping ui monitoring");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
     tokio::select! {
         _ = handle => info!("recording completed"),
         result = &mut server_future => {
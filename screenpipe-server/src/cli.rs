@@ -1,14 +1,24 @@
 use std::{path::PathBuf, sync::Arc};
 
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand, ValueHint};
 use clap_complete::{generate, Shell};
 use clap::CommandFactory;
-use screenpipe_audio::{vad::{VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine};
-use screenpipe_vision::{custom_ocr::CustomOcrConfig, utils::OcrEngine as CoreOcrEngine};
+use screenpipe_audio::{vad::{VadSensitivity, VadEngineEnum}, core::engine::AudioTranscriptionEngine as CoreAudioTranscriptionEngine, transcription::realtime::RealtimeTranscriptionEngine};
+use screenpipe_vision::{
+    custom_ocr::CustomOcrConfig, ocr_ensemble::OcrEnsemble, utils::OcrEngine as CoreOcrEngine,
+};
 use clap::ValueEnum;
 use screenpipe_core::Language;
+use screenpipe_db::ArchiveContent;
+use screenpipe_db::FtsTokenizer;
+use screenpipe_db::SessionizeOptions;
+use screenpipe_db::TimelineTable;
 use screenpipe_db::OcrEngine as DBOcrEngine;
 use screenpipe_db::CustomOcrConfig as DBCustomOcrConfig;
+use crate::timeline_export::TimelineFormat;
+use crate::video_encoding::{EncodingProfile, VideoCodec};
+use crate::video_tiering::TieringConfig;
 #[derive(Clone, Debug, ValueEnum, PartialEq)]
 pub enum CliAudioTranscriptionEngine {
     #[clap(name = "deepgram")]
@@ -105,6 +115,9 @@ pub enum CliVadEngine {
     WebRtc,
     #[clap(name = "silero")]
     Silero,
+    /// Noise floor tracking and spectral subtraction, no model needed
+    #[clap(name = "energy")]
+    Energy,
 }
 
 impl From<CliVadEngine> for VadEngineEnum {
@@ -112,6 +125,96 @@ impl From<CliVadEngine> for VadEngineEnum {
         match cli_engine {
             CliVadEngine::WebRtc => VadEngineEnum::WebRtc,
             CliVadEngine::Silero => VadEngineEnum::Silero,
+            CliVadEngine::Energy => VadEngineEnum::Energy,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliRealtimeTranscriptionEngine {
+    Deepgram,
+    /// Local whisper model, works offline
+    Whisper,
+}
+
+impl From<CliRealtimeTranscriptionEngine> for RealtimeTranscriptionEngine {
+    fn from(cli_engine: CliRealtimeTranscriptionEngine) -> Self {
+        match cli_engine {
+            CliRealtimeTranscriptionEngine::Deepgram => RealtimeTranscriptionEngine::Deepgram,
+            CliRealtimeTranscriptionEngine::Whisper => RealtimeTranscriptionEngine::Whisper,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliVideoCodec {
+    H264,
+    H265,
+    Av1,
+    Vp9,
+}
+
+impl From<CliVideoCodec> for VideoCodec {
+    fn from(cli_codec: CliVideoCodec) -> Self {
+        match cli_codec {
+            CliVideoCodec::H264 => VideoCodec::H264,
+            CliVideoCodec::H265 => VideoCodec::H265,
+            CliVideoCodec::Av1 => VideoCodec::Av1,
+            CliVideoCodec::Vp9 => VideoCodec::Vp9,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliFtsTokenizer {
+    /// Words, with case and diacritics folded
+    Unicode61,
+    /// Substrings and typo tolerant fuzzy search, for CJK and other text without spaces
+    Trigram,
+}
+
+impl From<CliFtsTokenizer> for FtsTokenizer {
+    fn from(cli_tokenizer: CliFtsTokenizer) -> Self {
+        match cli_tokenizer {
+            CliFtsTokenizer::Unicode61 => FtsTokenizer::Unicode61,
+            CliFtsTokenizer::Trigram => FtsTokenizer::Trigram,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliArchiveContent {
+    /// Frames with their OCR text and video files
+    Ocr,
+    /// Transcriptions with their speakers and audio files
+    Audio,
+    Ui,
+}
+
+impl From<CliArchiveContent> for ArchiveContent {
+    fn from(cli_content: CliArchiveContent) -> Self {
+        match cli_content {
+            CliArchiveContent::Ocr => ArchiveContent::Ocr,
+            CliArchiveContent::Audio => ArchiveContent::Audio,
+            CliArchiveContent::Ui => ArchiveContent::Ui,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
+pub enum CliTimelineTable {
+    /// Frames with their app, window and OCR text
+    Frames,
+    AudioTranscriptions,
+    UiMonitoring,
+}
+
+impl From<CliTimelineTable> for TimelineTable {
+    fn from(cli_table: CliTimelineTable) -> Self {
+        match cli_table {
+            CliTimelineTable::Frames => TimelineTable::Frames,
+            CliTimelineTable::AudioTranscriptions => TimelineTable::AudioTranscriptions,
+            CliTimelineTable::UiMonitoring => TimelineTable::UiMonitoring,
         }
     }
 }
@@ -150,6 +253,18 @@ pub struct Cli {
     #[cfg_attr(not(target_os = "macos"), arg(short, long, default_value_t = 1.0))]
     #[cfg_attr(target_os = "macos", arg(short, long, default_value_t = 0.5))] 
     pub fps: f64, // ! not crazy about this (inconsistent behaviour across platforms) see https://github.com/mediar-ai/screenpipe/issues/173
+
+    /// Lowest capture rate, reached after the screen has been idle for a while. Defaults to --fps
+    #[arg(long)]
+    pub min_fps: Option<f64>,
+
+    /// Highest capture rate, reached during bursts of screen activity. Defaults to --fps
+    #[arg(long)]
+    pub max_fps: Option<f64>,
+
+    /// Share of the screen that must change for a frame to be processed
+    #[arg(long, default_value_t = screenpipe_vision::capture_rate::DEFAULT_DIFF_THRESHOLD)]
+    pub frame_diff_threshold: f64,
     
     /// Audio chunk duration in seconds
     #[arg(short = 'd', long, default_value_t = 30)]
@@ -159,11 +274,18 @@ pub struct Cli {
     #[arg(short = 'p', long, default_value_t = 3030)]
     pub port: u16,
 
+    /// Address the server listens on. Other machines can only reach the API, sync included,
+    /// when it is not a loopback address, e.g. 0.0.0.0
+    #[arg(long, default_value_t = std::net::IpAddr::from([127, 0, 0, 1]))]
+    pub listen_address: std::net::IpAddr,
+
     /// Disable audio recording
     #[arg(long, default_value_t = false)]
     pub disable_audio: bool,
 
-    /// Audio devices to use (can be specified multiple times)
+    /// Audio devices to use (can be specified multiple times).
+    /// Virtual devices record from other sources, e.g. "file:/path/to/audio.wav (input)",
+    /// "pipe:-?rate=16000&format=s16le (input)" for stdin or "rtp://0.0.0.0:5004?codec=pcmu (input)"
     #[arg(short = 'i', long)]
     pub audio_device: Vec<String>,
 
@@ -191,6 +313,10 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub enable_realtime_audio_transcription: bool,
 
+    /// Engine used for realtime audio transcription
+    #[arg(long, value_enum, default_value_t = CliRealtimeTranscriptionEngine::Deepgram)]
+    pub realtime_audio_engine: CliRealtimeTranscriptionEngine,
+
     /// Enable realtime vision
     #[arg(long, default_value_t = true)]
     pub enable_realtime_vision: bool,
@@ -214,10 +340,35 @@ pub struct Cli {
     )]
     pub ocr_engine: CliOcrEngine,
 
+    /// OCR engine that re-OCRs windows the main engine is not confident about, e.g.
+    /// `-o tesseract --secondary-ocr-engine custom`. The most confident result is kept
+    #[arg(long, value_enum)]
+    pub secondary_ocr_engine: Option<CliOcrEngine>,
+
+    /// Confidence, from 0 to 1, below which a window is OCR'd again by the secondary engine
+    #[arg(long, default_value_t = screenpipe_vision::ocr_ensemble::DEFAULT_MIN_CONFIDENCE)]
+    pub reocr_min_confidence: f64,
+
+    /// Number of OCR workers shared by all monitors. Defaults to one per monitor
+    #[arg(long)]
+    pub ocr_workers: Option<usize>,
+
+    /// Frames waiting for OCR before the oldest is dropped. A monitor never has more than one
+    /// frame waiting, a newer frame replaces it
+    #[arg(long, default_value_t = screenpipe_vision::ocr_pool::DEFAULT_OCR_QUEUE_SIZE)]
+    pub ocr_queue_size: usize,
+
     /// Monitor IDs to use, these will be used to select the monitors to record
     #[arg(short = 'm', long)]
     pub monitor_id: Vec<u32>,
 
+    /// Frame sources to record instead of the monitors, can be repeated:
+    /// `monitor:<id>`, `dir:<path>[?loop=true]` for a directory of images,
+    /// `video:<path>` for a video file, or `stdin` for a stream of PNG images, e.g.
+    /// ffmpeg -f v4l2 -i /dev/video0 -f image2pipe -c:v png - | screenpipe --frame-source stdin
+    #[arg(long)]
+    pub frame_source: Vec<String>,
+
     #[arg(short = 'l', long, value_enum)]
     pub language: Vec<Language>,
 
@@ -230,9 +381,14 @@ pub struct Cli {
     pub disable_vision: bool,
 
     /// VAD engine to use for speech detection
-    #[arg(long, value_enum, default_value_t = CliVadEngine::Silero)] // Silero or WebRtc
+    #[arg(long, value_enum, default_value_t = CliVadEngine::Silero)]
     pub vad_engine: CliVadEngine,
 
+    /// JSON file with VAD thresholds, optionally per device, example:
+    /// {"speech_threshold": 0.6, "devices": {"MacBook Pro Microphone (input)": {"speech_threshold": 0.4}}}
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub vad_config: Option<PathBuf>,
+
     /// List of windows to ignore (by title) for screen recording - we use contains to match, example:
     /// --ignored-windows "Spotify" --ignored-windows "Bit" will ignore both "Bitwarden" and "Bittorrent"
     /// --ignored-windows "x" will ignore "Home / X" and "SpaceX"
@@ -249,6 +405,83 @@ pub struct Cli {
     #[arg(long, default_value_t = 60)]
     pub video_chunk_duration: u64,
 
+    /// Video encoding profile: default, compatible (h264), small, av1, vp9, archive, or a JSON file, example:
+    /// {"codec": "av1", "crf": 32, "preset": "fast", "max_width": 2560, "keyframe_interval": 300}
+    /// The --video-* options below override single settings of the profile
+    #[arg(long, default_value = "default")]
+    pub video_profile: String,
+
+    /// Video codec of the recorded chunks
+    #[arg(long, value_enum)]
+    pub video_codec: Option<CliVideoCodec>,
+
+    /// Constant rate factor, lower is better quality and bigger files (h264/h265: 0-51, av1/vp9: 0-63)
+    #[arg(long)]
+    pub video_crf: Option<u8>,
+
+    /// Encoder speed preset, from ultrafast to veryslow
+    #[arg(long)]
+    pub video_preset: Option<String>,
+
+    /// Pixel format of the chunks, e.g. yuv420p or yuv444p for sharper text
+    #[arg(long)]
+    pub video_pixel_format: Option<String>,
+
+    /// Frames wider than this are scaled down, keeping their aspect ratio
+    #[arg(long)]
+    pub video_max_width: Option<u32>,
+
+    /// Frames taller than this are scaled down, keeping their aspect ratio
+    #[arg(long)]
+    pub video_max_height: Option<u32>,
+
+    /// Factor (0-1] applied to every frame before encoding
+    #[arg(long)]
+    pub video_scale: Option<f64>,
+
+    /// Frames between two keyframes, lower values make frame extraction faster
+    #[arg(long)]
+    pub video_keyframe_interval: Option<u32>,
+
+    /// Recompress video chunks older than this many days into a smaller storage tier.
+    /// Disabled by default
+    #[arg(long)]
+    pub tiering_after_days: Option<u64>,
+
+    /// Encoding profile of recompressed chunks, a built-in profile name or a JSON file
+    #[arg(long, default_value = crate::video_tiering::DEFAULT_TIERING_PROFILE)]
+    pub tiering_profile: String,
+
+    /// Keep one of every N frames of recompressed chunks, dropped frames show the kept frame before them
+    #[arg(long, default_value_t = crate::video_tiering::DEFAULT_FRAME_STEP)]
+    pub tiering_frame_step: u32,
+
+    /// Seconds without any frame that end an activity session
+    #[arg(long, default_value_t = crate::activity_sessions::DEFAULT_SESSION_IDLE_GAP_SECS)]
+    pub session_idle_gap: u64,
+
+    /// Seconds the focus must stay on another app or site to start a new activity session
+    #[arg(long, default_value_t = crate::activity_sessions::DEFAULT_SESSION_MIN_SWITCH_SECS)]
+    pub session_min_switch: u64,
+
+    /// URL of another screenpipe instance to pull recordings from, e.g. http://desktop.local:3030.
+    /// Can be repeated
+    #[arg(long = "sync-peer")]
+    pub sync_peers: Vec<String>,
+
+    /// Seconds between two pulls from the sync peers
+    #[arg(long, default_value_t = crate::sync::DEFAULT_SYNC_INTERVAL_SECS)]
+    pub sync_interval: u64,
+
+    /// Secret shared by the machines syncing together. The change feed is only served to the
+    /// requests sending it, and sent when pulling from the sync peers
+    #[arg(long, env = "SCREENPIPE_SYNC_SECRET", hide_env_values = true)]
+    pub sync_secret: Option<String>,
+
+    /// Tokenizer of the full text search indexes, changing it rebuilds them in the background
+    #[arg(long, value_enum, default_value_t = CliFtsTokenizer::Unicode61)]
+    pub fts_tokenizer: CliFtsTokenizer,
+
     /// Deepgram API Key for audio transcription
     #[arg(long = "deepgram-api-key")]
     pub deepgram_api_key: Option<String>,
@@ -261,6 +494,11 @@ pub struct Cli {
     #[arg(long, value_enum, default_value_t = CliVadSensitivity::High)]
     pub vad_sensitivity: CliVadSensitivity,
 
+    /// Maximum cosine distance (0-2) for a voice to match a known speaker.
+    /// Higher values merge more voices into existing speakers, lower values create more unnamed speakers
+    #[arg(long, default_value_t = 0.5)]
+    pub speaker_match_threshold: f32,
+
     /// Disable telemetry
     #[arg(long, default_value_t = false)]
     pub disable_telemetry: bool,
@@ -269,7 +507,7 @@ pub struct Cli {
     #[arg(long, default_value_t = false)]
     pub enable_llm: bool,
 
-    /// Enable UI monitoring (macOS only)
+    /// Enable UI monitoring (macOS, and Linux through AT-SPI)
     #[arg(long, default_value_t = false)]
     pub enable_ui_monitoring: bool,
     
@@ -298,6 +536,73 @@ impl Cli {
         }
         Ok(unique_langs.into_iter().collect())
     }
+
+    /// OCR engine of the recording, an ensemble when a secondary engine is set
+    pub fn recording_ocr_engine(&self) -> CoreOcrEngine {
+        match &self.secondary_ocr_engine {
+            Some(secondary) => CoreOcrEngine::Ensemble(OcrEnsemble::new(
+                self.ocr_engine.clone().into(),
+                secondary.clone().into(),
+                self.reocr_min_confidence,
+            )),
+            None => self.ocr_engine.clone().into(),
+        }
+    }
+
+    /// Encoding profile of the recording, --video-profile with the --video-* overrides applied
+    pub fn video_encoding_profile(&self) -> anyhow::Result<EncodingProfile> {
+        let mut profile = EncodingProfile::load(&self.video_profile)?;
+        if let Some(codec) = &self.video_codec {
+            profile.codec = codec.clone().into();
+        }
+        if let Some(crf) = self.video_crf {
+            profile.crf = crf;
+        }
+        if let Some(preset) = &self.video_preset {
+            profile.preset = preset.clone();
+        }
+        if let Some(pixel_format) = &self.video_pixel_format {
+            profile.pixel_format = pixel_format.clone();
+        }
+        if self.video_max_width.is_some() {
+            profile.max_width = self.video_max_width;
+        }
+        if self.video_max_height.is_some() {
+            profile.max_height = self.video_max_height;
+        }
+        if let Some(scale) = self.video_scale {
+            profile.scale = scale;
+        }
+        if self.video_keyframe_interval.is_some() {
+            profile.keyframe_interval = self.video_keyframe_interval;
+        }
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Storage tiering of old video chunks, None unless --tiering-after-days is set
+    pub fn video_tiering_config(&self) -> anyhow::Result<Option<TieringConfig>> {
+        let Some(days) = self.tiering_after_days else {
+            return Ok(None);
+        };
+        let config = TieringConfig {
+            older_than: chrono::Duration::days(days as i64),
+            profile: EncodingProfile::load(&self.tiering_profile)?,
+            frame_step: self.tiering_frame_step,
+        };
+        config.validate()?;
+        Ok(Some(config))
+    }
+
+    /// How the recorded frames are grouped into activity sessions
+    pub fn sessionize_options(&self) -> SessionizeOptions {
+        SessionizeOptions {
+            idle_gap: chrono::Duration::seconds(self.session_idle_gap as i64),
+            min_switch: chrono::Duration::seconds(self.session_min_switch as i64),
+            ..Default::default()
+        }
+    }
+
     pub fn handle_completions(&self, shell: Shell) -> anyhow::Result<()> {
         let mut cmd = Self::command();
         generate(shell, &mut cmd, "screenpipe", &mut std::io::stdout());
@@ -375,6 +680,142 @@ pub enum Command {
         #[arg(long, default_value_t = true)]
         continue_on_error: bool,
     },
+    /// Re-run diarization and transcription on stored audio in a time range, in the background
+    Reprocess {
+        /// Start of the time range to reprocess (RFC 3339, e.g. 2026-09-01T00:00:00Z)
+        #[arg(long)]
+        start_time: DateTime<Utc>,
+        /// End of the time range to reprocess (RFC 3339). Default to now
+        #[arg(long)]
+        end_time: Option<DateTime<Utc>>,
+        /// Audio transcription engine to use for the new transcriptions
+        #[arg(short = 'a', long, value_enum, default_value_t = CliAudioTranscriptionEngine::WhisperLargeV3Turbo)]
+        audio_transcription_engine: CliAudioTranscriptionEngine,
+        /// Languages to transcribe
+        #[arg(short = 'l', long, value_enum)]
+        language: Vec<Language>,
+        /// Deepgram API Key for audio transcription
+        #[arg(long = "deepgram-api-key")]
+        deepgram_api_key: Option<String>,
+        /// VAD engine to use for speech detection
+        #[arg(long, value_enum, default_value_t = CliVadEngine::Silero)]
+        vad_engine: CliVadEngine,
+        /// JSON file with VAD thresholds, optionally per device, same format as for recording
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        vad_config: Option<PathBuf>,
+        /// Maximum cosine distance (0-2) for a voice to match a known speaker
+        #[arg(long, default_value_t = 0.5)]
+        speaker_match_threshold: f32,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// The subcommand for the reprocessing job
+        #[command(subcommand)]
+        subcommand: Option<MigrationSubCommand>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+        /// Number of audio chunks fetched from the database at once
+        #[arg(long, default_value_t = 50)]
+        batch_size: i64,
+        /// Delay between audio chunks in milliseconds
+        #[arg(long, default_value_t = 100)]
+        batch_delay_ms: u64,
+        /// Continue processing if errors occur
+        #[arg(long, default_value_t = true)]
+        continue_on_error: bool,
+    },
+    /// Score a VAD engine and its thresholds against labeled recordings.
+    /// Each wav file needs a label file next to it with the same name and a .txt extension,
+    /// one `start<TAB>end` line in seconds per speech region (Audacity label export)
+    VadEval {
+        /// Labeled wav files or directories containing them
+        #[arg(required = true, value_hint = ValueHint::AnyPath)]
+        paths: Vec<PathBuf>,
+        /// VAD engine to evaluate
+        #[arg(long, value_enum, default_value_t = CliVadEngine::Silero)]
+        vad_engine: CliVadEngine,
+        /// JSON file with the VAD thresholds to evaluate, per device settings are ignored
+        #[arg(long, value_hint = ValueHint::FilePath)]
+        vad_config: Option<PathBuf>,
+        /// Score above which a frame counts as speech
+        #[arg(long)]
+        speech_threshold: Option<f32>,
+        /// Score below which a frame counts as silence
+        #[arg(long)]
+        silence_threshold: Option<f32>,
+        /// Number of recent frames the decision is based on
+        #[arg(long)]
+        frame_history: Option<usize>,
+        /// Speech frames needed in the history to report speech
+        #[arg(long)]
+        speech_frame_threshold: Option<usize>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Export recordings to a portable archive: a directory with one NDJSON file per table and
+    /// the video and audio files they reference
+    Export {
+        /// Directory to write the archive to, must be missing or empty
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+        /// Start of the time range to export (RFC 3339). Default to the first recording
+        #[arg(long)]
+        start_time: Option<DateTime<Utc>>,
+        /// End of the time range to export (RFC 3339). Default to the last recording
+        #[arg(long)]
+        end_time: Option<DateTime<Utc>>,
+        /// Only export the frames and UI text of this app, can be repeated
+        #[arg(long = "app-name")]
+        app_names: Vec<String>,
+        /// Content to export, can be repeated. Default to everything
+        #[arg(long = "content-type", value_enum)]
+        content_types: Vec<CliArchiveContent>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Import an archive made by `export`, rows already in the database are skipped
+    Import {
+        /// Archive directory
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Export a timeline table to a Parquet or CSV file, for analysis in DuckDB, pandas or a
+    /// spreadsheet
+    ExportTimeline {
+        /// File to write
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+        /// Table to export
+        #[arg(long, value_enum)]
+        table: CliTimelineTable,
+        /// File format
+        #[arg(long, value_enum, default_value_t = TimelineFormat::Parquet)]
+        format: TimelineFormat,
+        /// Start of the time range to export (RFC 3339). Default to the first recording
+        #[arg(long)]
+        start_time: Option<DateTime<Utc>>,
+        /// End of the time range to export (RFC 3339). Default to the last recording
+        #[arg(long)]
+        end_time: Option<DateTime<Utc>>,
+        /// Data directory. Default to $HOME/.screenpipe
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        data_dir: Option<String>,
+        /// Output format
+        #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for
@@ -1,3 +1,4 @@
+use crate::video_encoding::EncodingProfile;
 use crate::VideoCapture;
 use anyhow::Result;
 use futures::future::join_all;
@@ -5,7 +6,10 @@ use screenpipe_core::pii_removal::remove_pii;
 use screenpipe_core::Language;
 use screenpipe_db::{DatabaseManager, Speaker};
 use screenpipe_events::{poll_meetings_events, send_event};
+use screenpipe_vision::capture_rate::CaptureRateConfig;
 use screenpipe_vision::core::WindowOcr;
+use screenpipe_vision::frame_source::FrameSourceConfig;
+use screenpipe_vision::ocr_pool::OcrPool;
 use screenpipe_vision::OcrEngine;
 use std::sync::Arc;
 use std::time::Duration;
@@ -16,10 +20,11 @@ use tracing::{debug, error, info, warn};
 pub async fn start_continuous_recording(
     db: Arc<DatabaseManager>,
     output_path: Arc<String>,
-    fps: f64,
+    capture_rate: CaptureRateConfig,
     video_chunk_duration: Duration,
     ocr_engine: Arc<OcrEngine>,
-    monitor_ids: Vec<u32>,
+    ocr_pool: Arc<OcrPool>,
+    frame_sources: Vec<FrameSourceConfig>,
     use_pii_removal: bool,
     vision_disabled: bool,
     vision_handle: &Handle,
@@ -28,31 +33,37 @@ pub async fn start_continuous_recording(
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
     realtime_vision: bool,
+    encoding_profile: EncodingProfile,
 ) -> Result<()> {
-    info!("Starting video recording for monitors {:?}", monitor_ids);
+    info!("Starting video recording for {:?}", frame_sources);
     let video_tasks = if !vision_disabled {
-        monitor_ids
+        frame_sources
             .iter()
-            .map(|&monitor_id| {
+            .map(|source| {
+                let source = source.clone();
                 let db_manager_video = Arc::clone(&db);
                 let output_path_video = Arc::clone(&output_path);
                 let ocr_engine = Arc::clone(&ocr_engine);
+                let ocr_pool = Arc::clone(&ocr_pool);
                 let ignored_windows_video = ignored_windows.to_vec();
                 let include_windows_video = include_windows.to_vec();
 
                 let languages = languages.clone();
+                let capture_rate = capture_rate.clone();
+                let encoding_profile = encoding_profile.clone();
 
-                info!("Starting video recording for monitor {}", monitor_id);
+                info!("Starting video recording for {}", source);
                 vision_handle.spawn(async move {
                     // Wrap in a loop with recovery logic
                     loop {
-                        info!("Starting/restarting vision capture for monitor {}", monitor_id);
+                        info!("Starting/restarting vision capture for {}", source);
                         match record_video(
                             db_manager_video.clone(),
                             output_path_video.clone(),
-                            fps,
+                            capture_rate.clone(),
                             ocr_engine.clone(),
-                            monitor_id,
+                            ocr_pool.clone(),
+                            source.clone(),
                             use_pii_removal,
                             &ignored_windows_video,
                             &include_windows_video,
@@ -60,16 +71,24 @@ pub async fn start_continuous_recording(
                             languages.clone(),
                             capture_unfocused_windows,
                             realtime_vision,
+                            encoding_profile.clone(),
                         )
                         .await
                         {
+                            Ok(_) if source.is_finite() => {
+                                info!("record_video for {} recorded all frames", source);
+                                return Ok(());
+                            }
                             Ok(_) => {
-                                warn!("record_video for monitor {} completed unexpectedly but without error", monitor_id);
+                                warn!(
+                                    "record_video for {} completed unexpectedly but without error",
+                                    source
+                                );
                                 // Short delay before restarting to prevent CPU spinning
                                 tokio::time::sleep(Duration::from_secs(5)).await;
                             }
                             Err(e) => {
-                                error!("record_video for monitor {} failed with error: {}", monitor_id, e);
+                                error!("record_video for {} failed with error: {}", source, e);
                                 // Short delay before restarting to prevent CPU spinning
                                 tokio::time::sleep(Duration::from_secs(5)).await;
                             }
@@ -102,7 +121,7 @@ pub async fn start_continuous_recording(
     for (i, result) in video_results.await.into_iter().enumerate() {
         if let Err(e) = result {
             if !e.is_cancelled() {
-                error!("Video recording error for monitor {}: {:?}", i, e);
+                error!("Video recording error for source {}: {:?}", i, e);
             }
         }
     }
@@ -114,9 +133,10 @@ pub async fn start_continuous_recording(
 async fn record_video(
     db: Arc<DatabaseManager>,
     output_path: Arc<String>,
-    fps: f64,
+    capture_rate: CaptureRateConfig,
     ocr_engine: Arc<OcrEngine>,
-    monitor_id: u32,
+    ocr_pool: Arc<OcrPool>,
+    source: FrameSourceConfig,
     use_pii_removal: bool,
     ignored_windows: &[String],
     include_windows: &[String],
@@ -124,9 +144,10 @@ async fn record_video(
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
     realtime_vision: bool,
+    encoding_profile: EncodingProfile,
 ) -> Result<()> {
-    info!("record_video: Starting for monitor {}", monitor_id);
-    let device_name = Arc::new(format!("monitor_{}", monitor_id));
+    info!("record_video: Starting for {}", source);
+    let device_name = Arc::new(source.device_name());
 
     // Add heartbeat counter
     let mut heartbeat_counter: u64 = 0;
@@ -137,19 +158,27 @@ async fn record_video(
     // Add health check interval
     let health_check_interval = 500; // Check task health every 500 iterations
 
+    // the profile is stored with every chunk so old chunks stay decodable after it changes
+    let profile_json = Arc::new(serde_json::to_string(&encoding_profile)?);
+
     // Define a simpler callback that just returns the file path
     let new_chunk_callback = {
         let db_clone = Arc::clone(&db);
         let device_name_clone = Arc::clone(&device_name);
+        let profile_json_clone = Arc::clone(&profile_json);
         move |file_path: &str| {
             let file_path = file_path.to_string();
             let db = Arc::clone(&db_clone);
             let device_name = Arc::clone(&device_name_clone);
+            let profile_json = Arc::clone(&profile_json_clone);
 
             // Just spawn the task directly
             tokio::spawn(async move {
                 debug!("Inserting new video chunk: {}", file_path);
-                if let Err(e) = db.insert_video_chunk(&file_path, &device_name).await {
+                if let Err(e) = db
+                    .insert_video_chunk_with_profile(&file_path, &device_name, Some(&profile_json))
+                    .await
+                {
                     error!("Failed to insert new video chunk: {}", e);
                 } else {
                     debug!("Successfully inserted video chunk: {}", file_path);
@@ -158,24 +187,25 @@ async fn record_video(
         }
     };
 
-    info!("Creating VideoCapture for monitor {}", monitor_id);
+    info!("Creating VideoCapture for {}", source);
+    // chunks are encoded at the max rate, then retimed to the rate frames came in at
+    let fps = capture_rate.max_fps;
     let video_capture = VideoCapture::new(
         &output_path,
-        fps,
+        capture_rate,
         video_chunk_duration,
         new_chunk_callback,
         Arc::clone(&ocr_engine),
-        monitor_id,
+        ocr_pool,
+        source.clone(),
         ignored_windows,
         include_windows,
         languages,
         capture_unfocused_windows,
+        encoding_profile,
     );
 
-    info!(
-        "Starting main video processing loop for monitor {}",
-        monitor_id
-    );
+    info!("Starting main video processing loop for {}", source);
     let mut last_frame_time = std::time::Instant::now();
     let mut frames_processed = 0;
 
@@ -194,14 +224,14 @@ async fn record_video(
                 0.0
             };
             info!(
-                    "record_video: Heartbeat for monitor {} - iteration {}, uptime: {}s, frames processed: {}, frames/sec: {:.2}",
-                    monitor_id, heartbeat_counter, uptime, frames_processed, frames_per_sec
+                    "record_video: Heartbeat for {} - iteration {}, uptime: {}s, frames processed: {}, frames/sec: {:.2}",
+                    source, heartbeat_counter, uptime, frames_processed, frames_per_sec
                 );
         }
 
         // Periodically check database health
         if heartbeat_counter % db_health_check_interval == 0 {
-            debug!("Checking database health for monitor {}", monitor_id);
+            debug!("Checking database health for {}", source);
             // Just log that we're checking the DB health
             debug!("Database health check periodic reminder");
             // We'll rely on the actual DB operations during normal processing to detect issues
@@ -209,14 +239,11 @@ async fn record_video(
 
         // In the try-catch block inside the loop, add health checks
         if heartbeat_counter % health_check_interval == 0 {
-            debug!(
-                "Checking VideoCapture task health for monitor {}",
-                monitor_id
-            );
+            debug!("Checking VideoCapture task health for {}", source);
             if !video_capture.check_health() {
                 error!(
-                    "One or more VideoCapture tasks have terminated for monitor {}",
-                    monitor_id
+                    "One or more VideoCapture tasks have terminated for {}",
+                    source
                 );
                 // Instead of immediately failing, log the error and continue
                 // This helps us diagnose which task is failing
@@ -271,6 +298,11 @@ async fn record_video(
                         } else {
                             &window_result.text
                         };
+                        let mut layout = window_result.layout.clone();
+                        if use_pii_removal {
+                            layout.redact(remove_pii);
+                        }
+                        let layout = serde_json::to_string(&layout).ok();
 
                         if realtime_vision {
                             let send_event_start = std::time::Instant::now();
@@ -303,11 +335,12 @@ async fn record_video(
 
                         let insert_ocr_start = std::time::Instant::now();
                         if let Err(e) = db
-                            .insert_ocr_text(
+                            .insert_ocr_text_with_layout(
                                 frame_id,
                                 text,
                                 &text_json,
-                                Arc::new((*ocr_engine).clone().into()),
+                                layout.as_deref(),
+                                Arc::new(window_result.ocr_engine.clone().into()),
                             )
                             .await
                         {
@@ -341,13 +374,16 @@ async fn record_video(
                     }
                 }
             }
+        } else if video_capture.is_finished() {
+            info!(
+                "record_video: Processed all {} frames of {}",
+                frames_processed, source
+            );
+            return Ok(());
         } else {
             // Log when frame queue is empty
             if heartbeat_counter % 10 == 0 {
-                debug!(
-                    "record_video: No frames in queue for monitor {}",
-                    monitor_id
-                );
+                debug!("record_video: No frames in queue for {}", source);
             }
         }
 
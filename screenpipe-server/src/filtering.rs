@@ -1,10 +1,35 @@
 use ndarray::{Array2, Axis};
 use rust_stemmers::{Algorithm, Stemmer};
+use screenpipe_core::Language;
 use sqlx::{query_as, sqlite::SqlitePool};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use tracing::debug;
 
+/// Snowball stemmer of a language, None for the languages it doesn't cover
+pub fn stemming_algorithm(language: &Language) -> Option<Algorithm> {
+    Some(match language {
+        Language::Arabic => Algorithm::Arabic,
+        Language::Danish => Algorithm::Danish,
+        Language::Dutch => Algorithm::Dutch,
+        Language::English => Algorithm::English,
+        Language::Finnish => Algorithm::Finnish,
+        Language::French => Algorithm::French,
+        Language::German => Algorithm::German,
+        Language::Greek => Algorithm::Greek,
+        Language::Hungarian => Algorithm::Hungarian,
+        Language::Italian => Algorithm::Italian,
+        Language::Norwegian => Algorithm::Norwegian,
+        Language::Portuguese => Algorithm::Portuguese,
+        Language::Romanian => Algorithm::Romanian,
+        Language::Russian => Algorithm::Russian,
+        Language::Spanish => Algorithm::Spanish,
+        Language::Swedish => Algorithm::Swedish,
+        Language::Turkish => Algorithm::Turkish,
+        _ => return None,
+    })
+}
+
 fn keep_least_similar(chunks: &[String], percentage: f64) -> Vec<usize> {
     if chunks.is_empty() {
         return vec![];
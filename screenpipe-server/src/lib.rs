@@ -1,3 +1,4 @@
+pub mod activity_sessions;
 mod add;
 mod auto_destruct;
 pub mod chunking;
@@ -5,11 +6,17 @@ pub mod cli;
 pub mod core;
 pub mod filtering;
 pub mod pipe_manager;
+mod reprocess;
 mod resource_monitor;
 mod server;
+pub mod sync;
 pub mod text_embeds;
+pub mod timeline_export;
+pub mod usage_rollups;
 mod video;
 pub mod video_cache;
+pub mod video_encoding;
+pub mod video_tiering;
 pub mod video_utils;
 pub use add::handle_index_command;
 pub use auto_destruct::watch_pid;
@@ -17,6 +24,7 @@ pub use axum::Json as JsonResponse;
 pub use cli::Cli;
 pub use core::start_continuous_recording;
 pub use pipe_manager::PipeManager;
+pub use reprocess::handle_reprocess_command;
 pub use resource_monitor::{ResourceMonitor, RestartSignal};
 pub use screenpipe_core::Language;
 pub use server::health_check;
@@ -27,4 +35,5 @@ pub use server::PaginatedResponse;
 pub use server::SCServer;
 pub use server::{api_list_monitors, MonitorInfo};
 pub use video::VideoCapture;
+pub use video_encoding::EncodingProfile;
 pub mod embedding;
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use screenpipe_audio::reprocess::{create_reprocess_worker, ReprocessConfig};
+use screenpipe_db::{DatabaseManager, MigrationCommand, MigrationStatus};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+use crate::cli::{MigrationSubCommand, OutputFormat};
+
+/// Drive an audio reprocessing job from the CLI and report its progress until it ends
+pub async fn handle_reprocess_command(
+    db: Arc<DatabaseManager>,
+    config: ReprocessConfig,
+    subcommand: Option<&MigrationSubCommand>,
+    output: &OutputFormat,
+) -> Result<()> {
+    let job_name = config.job_name();
+    let (cmd_tx, mut status_rx, worker_handle) = create_reprocess_worker(db.clone(), config);
+
+    let cmd = match subcommand {
+        Some(MigrationSubCommand::Start) | None => MigrationCommand::Start,
+        Some(MigrationSubCommand::Pause) => MigrationCommand::Pause,
+        Some(MigrationSubCommand::Stop) => MigrationCommand::Stop,
+        Some(MigrationSubCommand::Status) => MigrationCommand::Status,
+    };
+
+    match cmd {
+        MigrationCommand::Start => {
+            cmd_tx.send(MigrationCommand::Start).await?;
+            info!("started audio reprocessing job: {}", job_name);
+
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                cmd_tx.send(MigrationCommand::Status).await?;
+
+                // drain progress updates and keep the latest one
+                let mut latest = None;
+                while let Ok(response) = status_rx.try_recv() {
+                    latest = Some(response.status);
+                }
+                let status = match latest {
+                    Some(status) => status,
+                    None => match status_rx.recv().await {
+                        Some(response) => response.status,
+                        None => break,
+                    },
+                };
+
+                print_status(&status, output)?;
+                if matches!(
+                    status,
+                    MigrationStatus::Completed { .. } | MigrationStatus::Failed { .. }
+                ) {
+                    break;
+                }
+            }
+        }
+        // the job runs in another process, it picks the request up before its next chunk
+        command => {
+            if matches!(command, MigrationCommand::Pause | MigrationCommand::Stop) {
+                db.set_job_request(&job_name, &command).await?;
+                info!(
+                    "requested {:?} of audio reprocessing job: {}",
+                    command, job_name
+                );
+            }
+            let status = match db.get_migration_progress(&job_name).await? {
+                Some(progress) if progress.completed => MigrationStatus::Completed {
+                    total_records: progress.total_records,
+                    duration_secs: 0,
+                },
+                Some(progress) => MigrationStatus::Paused {
+                    total_records: progress.total_records,
+                    processed_records: progress.processed_records,
+                },
+                None => MigrationStatus::NotStarted,
+            };
+            print_status(&status, output)?;
+        }
+    }
+
+    let _ = cmd_tx.send(MigrationCommand::Stop).await;
+    if let Err(e) = worker_handle.await {
+        error!("error waiting for reprocess worker to finish: {}", e);
+    }
+
+    Ok(())
+}
+
+fn print_status(status: &MigrationStatus, output: &OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(status)?),
+        OutputFormat::Text => match status {
+            MigrationStatus::Running {
+                total_records,
+                processed_records,
+            }
+            | MigrationStatus::Paused {
+                total_records,
+                processed_records,
+            } => {
+                info!(
+                    "reprocessed audio chunks: {}/{} ({:.2}%)",
+                    processed_records,
+                    total_records,
+                    if *total_records > 0 {
+                        (*processed_records as f64 / *total_records as f64) * 100.0
+                    } else {
+                        0.0
+                    }
+                );
+            }
+            MigrationStatus::Completed {
+                total_records,
+                duration_secs,
+            } => {
+                info!(
+                    "audio reprocessing completed: {} chunks in {} seconds",
+                    total_records, duration_secs
+                );
+            }
+            MigrationStatus::Failed {
+                total_records,
+                processed_records,
+                error,
+            } => {
+                error!(
+                    "audio reprocessing failed: {}/{} chunks processed. error: {}",
+                    processed_records, total_records, error
+                );
+            }
+            MigrationStatus::NotStarted => info!("audio reprocessing not started"),
+        },
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use chrono::Local;
 use reqwest::Client;
+use screenpipe_vision::capture_rate::{set_system_pressure, SystemPressure};
 use serde_json::json;
 use serde_json::Value;
 use std::env;
@@ -11,11 +12,16 @@ use std::io::SeekFrom;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use sysinfo::{PidExt, ProcessExt, System, SystemExt};
+use sysinfo::{CpuExt, PidExt, ProcessExt, System, SystemExt};
 use tracing::debug;
 use tracing::trace;
 use tracing::{error, info, warn};
 
+/// System wide cpu usage above which screen capture backs off
+const HIGH_CPU_PERCENT: f32 = 80.0;
+/// System wide memory usage above which screen capture backs off
+const HIGH_MEMORY_PERCENT: f64 = 90.0;
+
 pub struct ResourceMonitor {
     start_time: Instant,
     resource_log_file: Option<String>, // analyse output here: https://colab.research.google.com/drive/1zELlGdzGdjChWKikSqZTHekm5XRxY-1r?usp=sharing
@@ -235,6 +241,22 @@ impl ResourceMonitor {
         }
     }
 
+    /// Tells the screen capture to slow down while the machine is busy or on battery
+    fn update_capture_pressure(&self, sys: &System) {
+        let memory_percent = if sys.total_memory() > 0 {
+            sys.used_memory() as f64 / sys.total_memory() as f64 * 100.0
+        } else {
+            0.0
+        };
+        let pressure = SystemPressure {
+            high_cpu: sys.global_cpu_info().cpu_usage() > HIGH_CPU_PERCENT,
+            high_memory: memory_percent > HIGH_MEMORY_PERCENT,
+            on_battery: is_on_battery(),
+        };
+        debug!("capture pressure: {:?}", pressure);
+        set_system_pressure(pressure);
+    }
+
     pub fn start_monitoring(
         self: &Arc<Self>,
         interval: Duration,
@@ -251,6 +273,7 @@ impl ResourceMonitor {
                 tokio::select! {
                     _ = tokio::time::sleep(interval) => {
                         sys.refresh_all();
+                        monitor.update_capture_pressure(&sys);
                         let now = Instant::now();
                         let should_send_to_posthog = now.duration_since(last_posthog_update) >= posthog_interval;
 
@@ -307,3 +330,41 @@ impl ResourceMonitor {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+fn is_on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.flatten().any(|supply| {
+        let read = |name: &str| {
+            std::fs::read_to_string(supply.path().join(name))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        read("type") == "Battery" && read("status") == "Discharging"
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn is_on_battery() -> bool {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains("'Battery Power'"))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn is_on_battery() -> bool {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // ACLineStatus is 0 when offline, 1 when online and 255 when unknown
+    unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.ACLineStatus == 0
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn is_on_battery() -> bool {
+    false
+}
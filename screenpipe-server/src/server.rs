@@ -4,8 +4,11 @@ use axum::{
         ws::{Message, WebSocket, WebSocketUpgrade},
         Json, Path, Query, State,
     },
-    http::StatusCode,
-    response::{IntoResponse, Json as JsonResponse, Response},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Json as JsonResponse, Response,
+    },
     routing::get,
     serve, Router,
 };
@@ -15,8 +18,11 @@ use screenpipe_core::Desktop;
 
 use chrono::TimeZone;
 use screenpipe_db::{
-    ContentType, DatabaseManager, FrameData, Order, SearchMatch, SearchResult, Speaker,
-    TagContentType,
+    find_matching_positions, parse_query, snippet, ActivitySession, ArchiveFilter, ArchiveManifest,
+    ContentType, DatabaseManager, FrameData, HistogramInterval, ImportReport, MigrationCommand,
+    MigrationStatus, OcrTextBlock, Order, QueryError, ScreenTime, SearchCursor, SearchFacets,
+    SearchResult, SessionTranscription, SnippetOptions, Speaker, SyncBatch, SyncPeer,
+    TagContentType, TalkTime, TextPosition, TimelineTable, UsageBucket, UsageGroup,
 };
 
 use tokio_util::io::ReaderStream;
@@ -24,16 +30,22 @@ use tokio_util::io::ReaderStream;
 use tokio::fs::File;
 
 use futures::{
-    future::{try_join, try_join_all},
+    future::{try_join, try_join3, try_join_all},
     SinkExt, StreamExt,
 };
 use image::ImageFormat::{self};
-use screenpipe_events::{send_event, subscribe_to_all_events, Event as ScreenpipeEvent};
+use screenpipe_events::{
+    send_event, subscribe_to_all_events, subscribe_to_event, Event as ScreenpipeEvent,
+};
 
 use crate::{
+    cli::CliAudioTranscriptionEngine,
     embedding::embedding_endpoint::create_embeddings,
+    timeline_export::{timeline_stream, TimelineFormat},
+    usage_rollups::REQUEST_REFRESH_HOURS,
     video::{finish_ffmpeg_process, start_ffmpeg_process, write_frame_to_ffmpeg, MAX_FPS},
     video_cache::{AudioEntry, DeviceFrame, FrameCache, FrameMetadata, TimeSeriesFrame},
+    video_encoding::EncodingProfile,
     video_utils::{
         extract_frame, extract_frame_from_video, extract_high_quality_frame, merge_videos,
         validate_media, MergeVideosRequest, MergeVideosResponse, ValidateMediaParams,
@@ -43,13 +55,21 @@ use crate::{
 use chrono::{DateTime, Utc};
 use screenpipe_audio::{
     audio_manager::AudioManager,
-    core::device::{
-        default_input_device, default_output_device, list_audio_devices, AudioDevice, DeviceType,
+    core::{
+        device::{
+            default_input_device, default_output_device, list_audio_devices, AudioDevice,
+            DeviceType,
+        },
+        engine::AudioTranscriptionEngine,
     },
+    reprocess::{create_reprocess_worker, ReprocessConfig},
+    transcription::realtime::{RealtimeTranscriptionEvent, REALTIME_TRANSCRIPTION_EVENT},
 };
 use tracing::{debug, error, info};
 
+use screenpipe_vision::capture_rate::{capture_rates, system_pressure};
 use screenpipe_vision::monitor::{get_monitor_by_id, list_monitors};
+use screenpipe_vision::ocr_pool::{OcrPool, OcrPoolMetrics};
 use screenpipe_vision::OcrEngine;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{json, Value};
@@ -92,6 +112,19 @@ pub struct AppState {
     pub ui_monitoring_enabled: bool,
     pub frame_cache: Option<Arc<FrameCache>>,
     pub frame_image_cache: Option<Arc<Mutex<FrameImageCache>>>,
+    pub reprocess_job: Mutex<Option<ReprocessJob>>,
+    pub ocr_pool: Option<Arc<OcrPool>>,
+    pub encoding_profile: EncodingProfile,
+    pub sync_peers: Vec<String>,
+    pub sync_secret: Option<String>,
+}
+
+/// Handle on the audio reprocessing job started through the API
+pub struct ReprocessJob {
+    pub job_name: String,
+    pub config: ReprocessConfig,
+    pub cmd_tx: mpsc::Sender<MigrationCommand>,
+    pub status: Arc<Mutex<MigrationStatus>>,
 }
 
 // Update the SearchQuery struct
@@ -127,6 +160,42 @@ pub(crate) struct SearchQuery {
     focused: Option<bool>,
     #[serde(default)]
     browser_url: Option<String>,
+    /// Also return the distribution of the results
+    #[serde(default)]
+    facets: bool,
+    #[serde(default)]
+    facet_interval: HistogramInterval,
+    #[serde(default = "default_facet_limit")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    facet_limit: u32,
+    /// Keyset pagination: `next_cursor` of the previous page, empty for the first one. Results
+    /// are then ordered newest first across content types and `offset` is not used.
+    #[serde(default)]
+    cursor: Option<String>,
+    /// Add an excerpt around the matched terms to every result, and the boxes of the matched
+    /// words to OCR results
+    #[serde(default)]
+    snippets: bool,
+    /// Words of the excerpt, 0 marks the terms in the whole text
+    #[serde(default = "default_snippet_tokens")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    snippet_tokens: u32,
+    #[serde(default = "default_highlight_marker")]
+    highlight_start: String,
+    #[serde(default = "default_highlight_marker")]
+    highlight_end: String,
+}
+
+fn default_facet_limit() -> u32 {
+    10
+}
+
+fn default_snippet_tokens() -> u32 {
+    16
+}
+
+fn default_highlight_marker() -> String {
+    "**".to_string()
 }
 
 #[derive(OaSchema, Deserialize)]
@@ -159,6 +228,26 @@ pub struct PaginationInfo {
     pub limit: u32,
     pub offset: u32,
     pub total: i64,
+    /// Cursor of the next page when paginating with `cursor`, absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+fn invalid_cursor_response() -> (StatusCode, JsonResponse<Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        JsonResponse(json!({"error": "invalid cursor"})),
+    )
+}
+
+/// Decodes the `cursor` parameter, an empty one starts from the first page
+fn decode_cursor(cursor: &str) -> Result<Option<SearchCursor>, (StatusCode, JsonResponse<Value>)> {
+    if cursor.is_empty() {
+        return Ok(None);
+    }
+    SearchCursor::decode(cursor)
+        .map(Some)
+        .ok_or_else(invalid_cursor_response)
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -178,6 +267,15 @@ pub struct DeleteSpeakerRequest {
     pub id: i64,
 }
 
+#[derive(OaSchema, Serialize, Deserialize, Debug)]
+pub struct EnrollSpeakerRequest {
+    pub name: String,
+    /// Clean voice sample of the speaker (wav, mp3, mp4...), relative to the data directory
+    pub audio_path: String,
+    /// Add the sample to an existing speaker instead of creating a new one
+    pub speaker_id: Option<i64>,
+}
+
 #[derive(OaSchema, Deserialize)]
 struct MarkAsHallucinationRequest {
     speaker_id: i64,
@@ -205,6 +303,12 @@ pub struct OCRContent {
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
     pub focused: Option<bool>,
+    /// Excerpt with the matched terms marked, when `snippets` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Boxes of the OCR words matching the query, when `snippets` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_positions: Option<Vec<TextPosition>>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -220,6 +324,8 @@ pub struct AudioContent {
     pub speaker: Option<Speaker>,
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(OaSchema, Serialize, Deserialize, Debug)]
@@ -234,6 +340,8 @@ pub struct UiContent {
     pub offset_index: i64,
     pub frame_name: Option<String>,
     pub browser_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(OaSchema, Serialize)]
@@ -289,12 +397,116 @@ pub struct HealthCheckResponse {
     pub message: String,
     pub verbose_instructions: Option<String>,
     pub device_status_details: Option<String>,
+    /// Effective capture rate of each frame source
+    #[serde(default)]
+    pub capture_rates: Vec<CaptureRateStatus>,
+    /// Reasons screen capture is currently slowed down: high_cpu, high_memory, on_battery
+    #[serde(default)]
+    pub capture_backoff: Vec<String>,
+    #[serde(default)]
+    pub ocr_pool: Option<OcrPoolStatus>,
+}
+
+/// Queue and latency of the OCR workers
+#[derive(Serialize, OaSchema, Deserialize)]
+pub struct OcrPoolStatus {
+    pub workers: usize,
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub completed: u64,
+    pub failed: u64,
+    pub coalesced: u64,
+    pub dropped: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+    pub engines: Vec<OcrEngineLatencyStatus>,
+}
+
+#[derive(Serialize, OaSchema, Deserialize)]
+pub struct OcrEngineLatencyStatus {
+    pub engine: String,
+    pub frames: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+impl From<OcrPoolMetrics> for OcrPoolStatus {
+    fn from(metrics: OcrPoolMetrics) -> Self {
+        Self {
+            workers: metrics.workers,
+            queue_depth: metrics.queue_depth,
+            queue_capacity: metrics.queue_capacity,
+            completed: metrics.completed,
+            failed: metrics.failed,
+            coalesced: metrics.coalesced,
+            dropped: metrics.dropped,
+            avg_wait_ms: metrics.avg_wait_ms,
+            max_wait_ms: metrics.max_wait_ms,
+            engines: metrics
+                .engines
+                .into_iter()
+                .map(|e| OcrEngineLatencyStatus {
+                    engine: e.engine,
+                    frames: e.frames,
+                    avg_ms: e.avg_ms,
+                    max_ms: e.max_ms,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, OaSchema, Deserialize)]
+pub struct CaptureRateStatus {
+    pub device_name: String,
+    pub fps: f64,
 }
 
 #[derive(OaSchema, Serialize, Deserialize)]
 pub struct SearchResponse {
     pub data: Vec<ContentItem>,
     pub pagination: PaginationInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<SearchFacets>,
+}
+
+fn query_error_response(e: QueryError) -> (StatusCode, JsonResponse<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        JsonResponse(json!({
+            "error": format!("invalid search query: {}", e),
+            "position": e.position,
+        })),
+    )
+}
+
+/// Ids of the speakers named by `speaker:` filters, names are matched case insensitively
+async fn resolve_speaker_names(
+    db: &DatabaseManager,
+    names: &[String],
+) -> Result<Vec<i64>, (StatusCode, JsonResponse<serde_json::Value>)> {
+    let mut ids = Vec::new();
+    for name in names {
+        let speakers = db.search_speakers(name).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to look up speaker: {}", e)})),
+            )
+        })?;
+        let matching: Vec<i64> = speakers
+            .into_iter()
+            .filter(|speaker| speaker.name.eq_ignore_ascii_case(name))
+            .map(|speaker| speaker.id)
+            .collect();
+        if matching.is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({"error": format!("unknown speaker: {}", name)})),
+            ));
+        }
+        ids.extend(matching);
+    }
+    Ok(ids)
 }
 
 // Update the search function
@@ -321,41 +533,127 @@ pub(crate) async fn search(
         query.focused,
     );
 
-    let query_str = query.q.as_deref().unwrap_or("");
+    let parsed = parse_query(query.q.as_deref().unwrap_or("")).map_err(query_error_response)?;
+    let parsed = state.db.expand_query(&parsed);
+    let query_str = parsed.fts_query_for(state.db.fts_tokenizer(), false);
+
+    // filters written in the query fill the parameters that were not given
+    let content_type = match (&query.content_type, &parsed.content_type) {
+        (ContentType::All, Some(content_type)) => content_type.clone(),
+        _ => query.content_type.clone(),
+    };
+    let start_time = query.start_time.or(parsed.start_time);
+    let end_time = query.end_time.or(parsed.end_time);
+    let app_name = query.app_name.as_deref().or(parsed.app_name.as_deref());
+    let window_name = query
+        .window_name
+        .as_deref()
+        .or(parsed.window_name.as_deref());
+    let browser_url = query
+        .browser_url
+        .as_deref()
+        .or(parsed.browser_url.as_deref());
+    let focused = query.focused.or(parsed.focused);
+    let mut speaker_ids = query.speaker_ids.clone();
+    if !parsed.speakers.is_empty() {
+        let resolved = resolve_speaker_names(&state.db, &parsed.speakers).await?;
+        speaker_ids.get_or_insert_with(Vec::new).extend(resolved);
+    }
+
+    let facets = async {
+        if !query.facets {
+            return Ok(None);
+        }
+        state
+            .db
+            .search_facets(
+                &query_str,
+                content_type.clone(),
+                start_time,
+                end_time,
+                app_name,
+                window_name,
+                query.min_length,
+                query.max_length,
+                speaker_ids.clone(),
+                query.frame_name.as_deref(),
+                browser_url,
+                focused,
+                query.facet_interval,
+                query.facet_limit,
+            )
+            .await
+            .map(Some)
+    };
 
-    let content_type = query.content_type.clone();
+    let cursor = match &query.cursor {
+        Some(cursor) => Some(decode_cursor(cursor)?),
+        None => None,
+    };
+    let page = async {
+        match &cursor {
+            Some(cursor) => {
+                let (results, next) = state
+                    .db
+                    .search_after(
+                        &query_str,
+                        content_type.clone(),
+                        query.pagination.limit,
+                        cursor.as_ref(),
+                        start_time,
+                        end_time,
+                        app_name,
+                        window_name,
+                        query.min_length,
+                        query.max_length,
+                        speaker_ids.clone(),
+                        query.frame_name.as_deref(),
+                        browser_url,
+                        focused,
+                    )
+                    .await?;
+                Ok::<_, sqlx::Error>((results, next.map(|next| next.encode())))
+            }
+            None => state
+                .db
+                .search(
+                    &query_str,
+                    content_type.clone(),
+                    query.pagination.limit,
+                    query.pagination.offset,
+                    start_time,
+                    end_time,
+                    app_name,
+                    window_name,
+                    query.min_length,
+                    query.max_length,
+                    speaker_ids.clone(),
+                    query.frame_name.as_deref(),
+                    browser_url,
+                    focused,
+                )
+                .await
+                .map(|results| (results, None)),
+        }
+    };
 
-    let (results, total) = try_join(
-        state.db.search(
-            query_str,
-            content_type.clone(),
-            query.pagination.limit,
-            query.pagination.offset,
-            query.start_time,
-            query.end_time,
-            query.app_name.as_deref(),
-            query.window_name.as_deref(),
-            query.min_length,
-            query.max_length,
-            query.speaker_ids.clone(),
-            query.frame_name.as_deref(),
-            query.browser_url.as_deref(),
-            query.focused,
-        ),
+    let ((results, next_cursor), total, facets) = try_join3(
+        page,
         state.db.count_search_results(
-            query_str,
-            content_type,
-            query.start_time,
-            query.end_time,
-            query.app_name.as_deref(),
-            query.window_name.as_deref(),
+            &query_str,
+            content_type.clone(),
+            start_time,
+            end_time,
+            app_name,
+            window_name,
             query.min_length,
             query.max_length,
-            query.speaker_ids.clone(),
+            speaker_ids.clone(),
             query.frame_name.as_deref(),
-            query.browser_url.as_deref(),
-            query.focused,
+            browser_url,
+            focused,
         ),
+        facets,
     )
     .await
     .map_err(|e| {
@@ -366,6 +664,19 @@ pub(crate) async fn search(
         )
     })?;
 
+    let terms = parsed.terms();
+    let snippet_options = SnippetOptions {
+        tokens: query.snippet_tokens as usize,
+        start_marker: query.highlight_start.clone(),
+        end_marker: query.highlight_end.clone(),
+        ..Default::default()
+    };
+    let excerpt = |text: &str| {
+        query
+            .snippets
+            .then(|| snippet(text, &terms, &snippet_options))
+    };
+
     let mut content_items: Vec<ContentItem> = results
         .iter()
         .map(|result| match result {
@@ -382,6 +693,12 @@ pub(crate) async fn search(
                 frame_name: Some(ocr.frame_name.clone()),
                 browser_url: ocr.browser_url.clone(),
                 focused: ocr.focused,
+                snippet: excerpt(&ocr.ocr_text),
+                text_positions: (query.snippets && !terms.is_empty()).then(|| {
+                    let blocks: Vec<OcrTextBlock> =
+                        serde_json::from_str(&ocr.text_json).unwrap_or_default();
+                    find_matching_positions(&blocks, &terms.join(" "))
+                }),
             }),
             SearchResult::Audio(audio) => ContentItem::Audio(AudioContent {
                 chunk_id: audio.audio_chunk_id,
@@ -395,6 +712,7 @@ pub(crate) async fn search(
                 speaker: audio.speaker.clone(),
                 start_time: audio.start_time,
                 end_time: audio.end_time,
+                snippet: excerpt(&audio.transcription),
             }),
             SearchResult::UI(ui) => ContentItem::UI(UiContent {
                 id: ui.id,
@@ -407,6 +725,7 @@ pub(crate) async fn search(
                 offset_index: ui.offset_index,
                 frame_name: ui.frame_name.clone(),
                 browser_url: ui.browser_url.clone(),
+                snippet: excerpt(&ui.text),
             }),
         })
         .collect();
@@ -443,7 +762,9 @@ pub(crate) async fn search(
             limit: query.pagination.limit,
             offset: query.pagination.offset,
             total: total as i64,
+            next_cursor,
         },
+        facets,
     }))
 }
 
@@ -745,6 +1066,23 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<He
         )
     };
 
+    let mut capture_rates: Vec<CaptureRateStatus> = capture_rates()
+        .into_iter()
+        .map(|(device_name, fps)| CaptureRateStatus { device_name, fps })
+        .collect();
+    capture_rates.sort_by(|a, b| a.device_name.cmp(&b.device_name));
+
+    let pressure = system_pressure();
+    let capture_backoff = [
+        (pressure.high_cpu, "high_cpu"),
+        (pressure.high_memory, "high_memory"),
+        (pressure.on_battery, "on_battery"),
+    ]
+    .iter()
+    .filter(|(active, _)| *active)
+    .map(|(_, reason)| reason.to_string())
+    .collect();
+
     JsonResponse(HealthCheckResponse {
         status: overall_status.to_string(),
         status_code,
@@ -764,6 +1102,12 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> JsonResponse<He
         message,
         verbose_instructions,
         device_status_details,
+        capture_rates,
+        capture_backoff,
+        ocr_pool: state
+            .ocr_pool
+            .as_ref()
+            .map(|pool| OcrPoolStatus::from(pool.metrics())),
     })
 }
 
@@ -1039,6 +1383,10 @@ pub struct SCServer {
     vision_disabled: bool,
     audio_disabled: bool,
     ui_monitoring_enabled: bool,
+    ocr_pool: Option<Arc<OcrPool>>,
+    encoding_profile: EncodingProfile,
+    sync_peers: Vec<String>,
+    sync_secret: Option<String>,
 }
 
 impl SCServer {
@@ -1062,9 +1410,36 @@ impl SCServer {
             audio_disabled,
             ui_monitoring_enabled,
             audio_manager,
+            ocr_pool: None,
+            encoding_profile: EncodingProfile::default(),
+            sync_peers: Vec::new(),
+            sync_secret: None,
         }
     }
 
+    /// Reports the OCR queue and latencies in /health
+    pub fn with_ocr_pool(mut self, ocr_pool: Arc<OcrPool>) -> Self {
+        self.ocr_pool = Some(ocr_pool);
+        self
+    }
+
+    /// Profile of the video chunks written from frames added through /add
+    pub fn with_encoding_profile(mut self, encoding_profile: EncodingProfile) -> Self {
+        self.encoding_profile = encoding_profile;
+        self
+    }
+
+    /// Serves the change feed to the machines sending `secret`, and lets /sync/pull pull from
+    /// `peers` only
+    pub fn with_sync(mut self, peers: Vec<String>, secret: Option<String>) -> Self {
+        self.sync_peers = peers
+            .iter()
+            .map(|peer| peer.trim_end_matches('/').to_string())
+            .collect();
+        self.sync_secret = secret;
+        self
+    }
+
     pub async fn start(self, enable_frame_cache: bool) -> Result<(), std::io::Error> {
         // Create the OpenAPI server
         let app = self.create_router(enable_frame_cache).await;
@@ -1113,6 +1488,11 @@ impl SCServer {
             } else {
                 None
             },
+            reprocess_job: Mutex::new(None),
+            ocr_pool: self.ocr_pool.clone(),
+            encoding_profile: self.encoding_profile.clone(),
+            sync_peers: self.sync_peers.clone(),
+            sync_secret: self.sync_secret.clone(),
         });
 
         let cors = CorsLayer::new()
@@ -1140,6 +1520,7 @@ impl SCServer {
             .post("/pipes/delete", delete_pipe_handler)
             .post("/pipes/purge", purge_pipe_handler)
             .get("/frames/:frame_id", get_frame_data)
+            .get("/frames/:frame_id/layout", get_frame_layout_handler)
             .get("/health", health_check)
             .post("/raw_sql", execute_raw_sql)
             .post("/add", add_to_database)
@@ -1149,6 +1530,7 @@ impl SCServer {
             .post("/speakers/delete", delete_speaker_handler)
             .post("/speakers/hallucination", mark_as_hallucination_handler)
             .post("/speakers/merge", merge_speakers_handler)
+            .post("/speakers/enroll", enroll_speaker_handler)
             .get("/speakers/similar", get_similar_speakers_handler)
             .post("/experimental/frames/merge", merge_frames_handler)
             .get("/experimental/validate/media", validate_media_handler)
@@ -1163,6 +1545,19 @@ impl SCServer {
             .post("/v1/embeddings", create_embeddings)
             .post("/audio/device/start", start_audio_device)
             .post("/audio/device/stop", stop_audio_device)
+            .post("/audio/reprocess", start_reprocess_handler)
+            .post("/audio/reprocess/pause", pause_reprocess_handler)
+            .post("/audio/reprocess/stop", stop_reprocess_handler)
+            .get("/audio/reprocess/status", reprocess_status_handler)
+            .get("/analytics/usage", usage_handler)
+            .get("/analytics/usage/speakers", speaker_usage_handler)
+            .get("/sessions", list_sessions_handler)
+            .get("/sessions/:session_id", get_session_handler)
+            .post("/archive/export", export_archive_handler)
+            .post("/archive/import", import_archive_handler)
+            .get("/sync/peers", sync_peers_handler)
+            .post("/sync/pull", sync_pull_handler)
+            .get("/export/timeline", export_timeline_handler)
             .route_yaml_spec("/openapi.yaml")
             .route_json_spec("/openapi.json")
             .freeze();
@@ -1174,7 +1569,12 @@ impl SCServer {
             .route("/stream/frames", get(stream_frames_handler))
             .route("/ws/events", get(ws_events_handler))
             .route("/ws/health", get(ws_health_handler))
+            .route("/ws/transcriptions", get(ws_transcriptions_handler))
+            .route("/sse/transcriptions", get(sse_transcriptions_handler))
             .route("/frames/export", get(handle_video_export_ws))
+            // NOTE: the change feed is for the sync peers only, behind the shared secret
+            .route("/sync/changes", get(sync_changes_handler))
+            .route("/sync/media", get(sync_media_handler))
             .with_state(app_state)
             .layer(cors)
             .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default()))
@@ -1334,8 +1734,9 @@ async fn write_frames_to_video(
     frames: &Vec<FrameContent>,
     video_file_path: &str,
     fps: f64,
+    encoding_profile: &EncodingProfile,
 ) -> Result<(), anyhow::Error> {
-    let mut ffmpeg_child = start_ffmpeg_process(video_file_path, fps).await?;
+    let mut ffmpeg_child = start_ffmpeg_process(video_file_path, fps, encoding_profile).await?;
     let mut ffmpeg_stdin = ffmpeg_child
         .stdin
         .take()
@@ -1401,9 +1802,15 @@ pub(crate) async fn add_to_database(
                         .expect("Failed to create valid path")
                         .to_string();
 
+                    let profile_json =
+                        serde_json::to_string(&state.encoding_profile).unwrap_or_default();
                     if let Err(e) = state
                         .db
-                        .insert_video_chunk(&video_file_path, &device_name)
+                        .insert_video_chunk_with_profile(
+                            &video_file_path,
+                            &device_name,
+                            Some(&profile_json),
+                        )
                         .await
                     {
                         error!(
@@ -1418,7 +1825,14 @@ pub(crate) async fn add_to_database(
                         ));
                     }
 
-                    if let Err(e) = write_frames_to_video(frames, &video_file_path, MAX_FPS).await {
+                    if let Err(e) = write_frames_to_video(
+                        frames,
+                        &video_file_path,
+                        MAX_FPS,
+                        &state.encoding_profile,
+                    )
+                    .await
+                    {
                         error!(
                             "Failed to write frames to video file {}: {}",
                             video_file_path, e
@@ -1838,6 +2252,47 @@ async fn merge_speakers_handler(
     Ok(JsonResponse(json!({"success": true})))
 }
 
+#[oasgen]
+async fn enroll_speaker_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<EnrollSpeakerRequest>,
+) -> Result<JsonResponse<Speaker>, (StatusCode, JsonResponse<Value>)> {
+    let audio_path = screenpipe_path(&state, "data", &payload.audio_path)?;
+    if !audio_path.is_file() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": format!("audio file not found: {}", payload.audio_path)})),
+        ));
+    }
+
+    let speaker = state
+        .audio_manager
+        .enroll_speaker(&audio_path, &payload.name, payload.speaker_id)
+        .await
+        .map_err(|e| match e.downcast_ref::<sqlx::Error>() {
+            Some(sqlx::Error::RowNotFound) => (
+                StatusCode::NOT_FOUND,
+                JsonResponse(json!({
+                    "error": format!("speaker {} not found", payload.speaker_id.unwrap_or_default())
+                })),
+            ),
+            Some(_) => {
+                error!("failed to enroll speaker {}: {}", payload.name, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonResponse(json!({"error": e.to_string()})),
+                )
+            }
+            // the sample could not be decoded or holds no usable speech
+            None => (
+                StatusCode::BAD_REQUEST,
+                JsonResponse(json!({"error": format!("invalid enrollment sample: {}", e)})),
+            ),
+        })?;
+
+    Ok(JsonResponse(speaker))
+}
+
 #[oasgen]
 async fn get_similar_speakers_handler(
     State(state): State<Arc<AppState>>,
@@ -1963,101 +2418,591 @@ pub struct AudioDeviceControlResponse {
 // }
 
 #[derive(OaSchema, Deserialize)]
-struct EventsQuery {
-    images: Option<bool>,
+pub struct UsageQuery {
+    /// Defaults to 7 days before `end_time`
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    /// Defaults to now
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    group_by: UsageGroup,
+    #[serde(default)]
+    app_name: Option<String>,
+    #[serde(default)]
+    interval: HistogramInterval,
+    #[serde(default = "default_limit")]
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    limit: u32,
 }
 
-#[derive(Debug, OaSchema, Deserialize)]
-struct SemanticSearchQuery {
-    text: String,
-    limit: Option<u32>,
-    threshold: Option<f32>,
+impl UsageQuery {
+    fn range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let end = self.end_time.unwrap_or_else(Utc::now);
+        let start = self
+            .start_time
+            .unwrap_or_else(|| end - chrono::Duration::days(7));
+        (start, end)
+    }
 }
 
-#[oasgen]
-async fn semantic_search_handler(
-    Query(query): Query<SemanticSearchQuery>,
-    State(state): State<Arc<AppState>>,
-) -> Result<JsonResponse<Vec<screenpipe_db::OCRResult>>, (StatusCode, JsonResponse<Value>)> {
-    let limit = query.limit.unwrap_or(10);
-    let threshold = query.threshold.unwrap_or(0.3);
+#[derive(OaSchema, Serialize)]
+pub struct UsageResponse {
+    total_seconds: f64,
+    items: Vec<ScreenTime>,
+    histogram: Vec<UsageBucket>,
+}
 
-    debug!(
-        "semantic search for '{}' with limit {} and threshold {}",
-        query.text, limit, threshold
-    );
+#[derive(OaSchema, Serialize)]
+pub struct SpeakerUsageResponse {
+    total_seconds: f64,
+    speakers: Vec<TalkTime>,
+}
 
-    // Generate embedding for search text
-    let embedding = match generate_embedding(&query.text, 0).await {
-        Ok(emb) => emb,
-        Err(e) => {
-            error!("failed to generate embedding: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                JsonResponse(json!({"error": format!("failed to generate embedding: {}", e)})),
-            ));
-        }
-    };
+/// Brings the rollups of the last hours up to date, the background refresh does the rest
+async fn refresh_recent_usage(
+    db: &DatabaseManager,
+) -> Result<(), (StatusCode, JsonResponse<Value>)> {
+    let since = Utc::now() - chrono::Duration::hours(REQUEST_REFRESH_HOURS);
+    db.refresh_usage_rollups(Some(since))
+        .await
+        .map(|_| ())
+        .map_err(usage_error)
+}
 
-    // Search database for similar embeddings
-    match state
+fn usage_error(e: sqlx::Error) -> (StatusCode, JsonResponse<Value>) {
+    error!("failed to compute usage: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        JsonResponse(json!({"error": format!("failed to compute usage: {}", e)})),
+    )
+}
+
+/// Screen time per app, window or browser domain, with its histogram per hour or day
+#[oasgen]
+async fn usage_handler(
+    Query(query): Query<UsageQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<UsageResponse>, (StatusCode, JsonResponse<Value>)> {
+    let (start, end) = query.range();
+    refresh_recent_usage(&state.db).await?;
+    let app_name = query.app_name.as_deref();
+    let items = state
         .db
-        .search_similar_embeddings(embedding, limit, threshold)
+        .screen_time(start, end, query.group_by, app_name, query.limit)
         .await
-    {
-        Ok(results) => {
-            debug!("found {} similar results", results.len());
-            Ok(JsonResponse(results))
-        }
-        Err(e) => {
-            error!("failed to search embeddings: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                JsonResponse(json!({"error": format!("failed to search embeddings: {}", e)})),
-            ))
-        }
-    }
+        .map_err(usage_error)?;
+    let histogram = state
+        .db
+        .screen_time_histogram(start, end, query.interval, app_name)
+        .await
+        .map_err(usage_error)?;
+    Ok(JsonResponse(UsageResponse {
+        total_seconds: histogram.iter().map(|bucket| bucket.seconds).sum(),
+        items,
+        histogram,
+    }))
 }
 
-#[derive(Serialize, OaSchema, Deserialize)]
-pub struct VisionDeviceControlRequest {
-    device_id: u32,
+/// Talk time per speaker
+#[oasgen]
+async fn speaker_usage_handler(
+    Query(query): Query<UsageQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<SpeakerUsageResponse>, (StatusCode, JsonResponse<Value>)> {
+    let (start, end) = query.range();
+    refresh_recent_usage(&state.db).await?;
+    let speakers = state.db.talk_time(start, end).await.map_err(usage_error)?;
+    Ok(JsonResponse(SpeakerUsageResponse {
+        total_seconds: speakers.iter().map(|speaker| speaker.seconds).sum(),
+        speakers,
+    }))
 }
 
-// impl VisionDeviceControlRequest {
-//     pub fn new(device_id: u32) -> Self {
-//         Self { device_id }
-//     }
-// }
+#[derive(OaSchema, Deserialize)]
+pub struct SessionsQuery {
+    #[serde(default)]
+    start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    app_name: Option<String>,
+    /// Matches the app, title or keywords of the sessions
+    #[serde(default)]
+    q: Option<String>,
+    /// Shortest session returned, in seconds
+    #[serde(default)]
+    min_duration: Option<f64>,
+    #[serde(default = "default_limit")]
+    limit: u32,
+    #[serde(default)]
+    offset: u32,
+}
 
-#[derive(Serialize)]
-pub struct VisionDeviceControlResponse {
-    success: bool,
-    message: String,
+#[derive(OaSchema, Serialize)]
+pub struct SessionResponse {
+    session: ActivitySession,
+    /// Audio recorded during the session
+    transcriptions: Vec<SessionTranscription>,
 }
 
-// async fn start_vision_device(
-//     State(state): State<Arc<AppState>>,
-//     Json(payload): Json<VisionDeviceControlRequest>,
-// ) -> Result<JsonResponse<VisionDeviceControlResponse>, (StatusCode, JsonResponse<Value>)> {
-//     debug!("starting vision device: {}", payload.device_id);
-//     // Validate device exists
-//     let monitors = list_monitors().await;
-//     if !monitors.iter().any(|m| m.id() == payload.device_id) {
-//         return Err((
-//             StatusCode::BAD_REQUEST,
-//             JsonResponse(json!({
-//                 "error": format!("monitor not found: {}", payload.device_id),
-//                 "success": false
-//             })),
-//         ));
-//     }
+fn sessions_error(e: sqlx::Error) -> (StatusCode, JsonResponse<Value>) {
+    error!("failed to fetch activity sessions: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        JsonResponse(json!({"error": format!("failed to fetch activity sessions: {}", e)})),
+    )
+}
 
-//     debug!("starting vision device: {}", payload.device_id);
-//     let _ = state
-//         .device_manager
-//         .update_device(DeviceControl {
-//             device: screenpipe_core::DeviceType::Vision(payload.device_id),
+/// Work sessions overlapping a time range, newest first
+#[oasgen]
+async fn list_sessions_handler(
+    Query(query): Query<SessionsQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let (sessions, total) = state
+        .db
+        .list_activity_sessions(
+            query.start_time,
+            query.end_time,
+            query.app_name.as_deref(),
+            query.q.as_deref().filter(|q| !q.is_empty()),
+            query.min_duration,
+            query.limit,
+            query.offset,
+        )
+        .await
+        .map_err(sessions_error)?;
+    Ok(JsonResponse(json!(PaginatedResponse {
+        data: sessions,
+        pagination: PaginationInfo {
+            limit: query.limit,
+            offset: query.offset,
+            total,
+            next_cursor: None,
+        },
+    })))
+}
+
+#[oasgen]
+async fn get_session_handler(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<i64>,
+) -> Result<JsonResponse<SessionResponse>, (StatusCode, JsonResponse<Value>)> {
+    let session = state
+        .db
+        .get_activity_session(session_id)
+        .await
+        .map_err(sessions_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                JsonResponse(json!({"error": format!("session {} not found", session_id)})),
+            )
+        })?;
+    let transcriptions = state
+        .db
+        .session_transcriptions(&session)
+        .await
+        .map_err(sessions_error)?;
+    Ok(JsonResponse(SessionResponse {
+        session,
+        transcriptions,
+    }))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct ExportArchiveRequest {
+    /// Directory to write the archive to, relative to the archives directory of the data
+    /// directory, must be missing or empty
+    path: String,
+    #[serde(default)]
+    filter: ArchiveFilter,
+}
+
+#[derive(OaSchema, Deserialize)]
+struct ImportArchiveRequest {
+    /// Archive directory, relative to the archives directory of the data directory
+    path: String,
+}
+
+/// Database failures are the server's, anything else is a bad archive or path
+fn archive_error(action: &str, e: anyhow::Error) -> (StatusCode, JsonResponse<Value>) {
+    error!("failed to {} archive: {:?}", action, e);
+    let status = if e.downcast_ref::<sqlx::Error>().is_some() {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    (
+        status,
+        JsonResponse(json!({"error": format!("failed to {} archive: {}", action, e)})),
+    )
+}
+
+/// Directory of an archive in the archives directory, refusing paths leaving it
+fn archive_dir(state: &AppState, path: &str) -> Result<PathBuf, (StatusCode, JsonResponse<Value>)> {
+    screenpipe_path(state, "archives", path)
+}
+
+/// `path` inside the `dir` directory of the screenpipe directory, refusing paths leaving it
+fn screenpipe_path(
+    state: &AppState,
+    dir: &str,
+    path: &str,
+) -> Result<PathBuf, (StatusCode, JsonResponse<Value>)> {
+    let relative = std::path::Path::new(path);
+    if path.is_empty()
+        || relative
+            .components()
+            .any(|component| !matches!(component, std::path::Component::Normal(_)))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": format!("path must be relative to the {} directory", dir)
+            })),
+        ));
+    }
+    Ok(state.screenpipe_dir.join(dir).join(relative))
+}
+
+/// Writes the recordings matching the filter and their media files to an archive directory
+#[oasgen]
+async fn export_archive_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<ExportArchiveRequest>,
+) -> Result<JsonResponse<ArchiveManifest>, (StatusCode, JsonResponse<Value>)> {
+    let dir = archive_dir(&state, &payload.path)?;
+    let manifest = state
+        .db
+        .export_archive(&dir, &payload.filter)
+        .await
+        .map_err(|e| archive_error("export", e))?;
+    Ok(JsonResponse(manifest))
+}
+
+/// Imports an archive, copying its media files to the data directory
+#[oasgen]
+async fn import_archive_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<ImportArchiveRequest>,
+) -> Result<JsonResponse<ImportReport>, (StatusCode, JsonResponse<Value>)> {
+    let dir = archive_dir(&state, &payload.path)?;
+    let report = state
+        .db
+        .import_archive(&dir, &state.screenpipe_dir.join("data"))
+        .await
+        .map_err(|e| archive_error("import", e))?;
+    Ok(JsonResponse(report))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct SyncChangesQuery {
+    /// Watermark of the last batch pulled, 0 for everything
+    #[serde(default)]
+    since: i64,
+    #[serde(default = "default_sync_limit")]
+    limit: u32,
+    /// Database id of the machine pulling, the rows synced from it are not sent back
+    machine_id: Option<String>,
+}
+
+fn default_sync_limit() -> u32 {
+    500
+}
+
+#[derive(OaSchema, Deserialize)]
+struct SyncMediaQuery {
+    table: String,
+    id: i64,
+}
+
+#[derive(OaSchema, Deserialize)]
+struct SyncPullRequest {
+    /// URL of the screenpipe instance to pull from, one of the `--sync-peer`s
+    url: String,
+}
+
+fn sync_error(e: anyhow::Error) -> (StatusCode, JsonResponse<Value>) {
+    error!("sync failed: {:?}", e);
+    let status = if e.downcast_ref::<reqwest::Error>().is_some() {
+        StatusCode::BAD_GATEWAY
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (
+        status,
+        JsonResponse(json!({"error": format!("sync failed: {}", e)})),
+    )
+}
+
+/// Refuses the requests without the sync secret, and all of them when no secret is set
+fn check_sync_secret(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, JsonResponse<Value>)> {
+    let Some(secret) = state.sync_secret.as_deref() else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            JsonResponse(json!({"error": "sync is disabled, start screenpipe with --sync-secret"})),
+        ));
+    };
+    let sent = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // compares every byte, so the time taken tells nothing about the secret
+    let matches = sent.len() == secret.len()
+        && sent
+            .bytes()
+            .zip(secret.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if !matches {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            JsonResponse(json!({"error": "invalid sync secret"})),
+        ));
+    }
+    Ok(())
+}
+
+/// Change feed of this machine: the rows changed after a watermark, pulled by the other
+/// machines syncing from it
+async fn sync_changes_handler(
+    Query(query): Query<SyncChangesQuery>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<JsonResponse<SyncBatch>, (StatusCode, JsonResponse<Value>)> {
+    check_sync_secret(&state, &headers)?;
+    let batch = state
+        .db
+        .sync_changes(
+            query.since,
+            query.limit.clamp(1, 5000),
+            query.machine_id.as_deref(),
+        )
+        .await
+        .map_err(sync_error)?;
+    Ok(JsonResponse(batch))
+}
+
+/// Media file of a row of the change feed
+async fn sync_media_handler(
+    Query(query): Query<SyncMediaQuery>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    check_sync_secret(&state, &headers)?;
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": format!("no media file for {} {}", query.table, query.id)
+            })),
+        )
+    };
+    let path = state
+        .db
+        .sync_media_path(&query.table, query.id)
+        .await
+        .map_err(|e| sync_error(e.into()))?
+        .ok_or_else(not_found)?;
+    let file = File::open(&path).await.map_err(|_| not_found())?;
+    Response::builder()
+        .header("content-type", "application/octet-stream")
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .map_err(|e| sync_error(e.into()))
+}
+
+/// Machines this one pulls from, with their watermark and last error
+#[oasgen]
+async fn sync_peers_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Vec<SyncPeer>>, (StatusCode, JsonResponse<Value>)> {
+    let peers = state
+        .db
+        .sync_peers()
+        .await
+        .map_err(|e| sync_error(e.into()))?;
+    Ok(JsonResponse(peers))
+}
+
+/// Pulls the changes of one of the configured sync peers now
+#[oasgen]
+async fn sync_pull_handler(
+    State(state): State<Arc<AppState>>,
+    JsonResponse(payload): JsonResponse<SyncPullRequest>,
+) -> Result<JsonResponse<ImportReport>, (StatusCode, JsonResponse<Value>)> {
+    let url = payload.url.trim_end_matches('/');
+    let secret = match state.sync_secret.as_deref() {
+        Some(secret) if state.sync_peers.iter().any(|peer| peer == url) => secret,
+        _ => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                JsonResponse(json!({
+                    "error": format!(
+                        "{} is not a sync peer, start screenpipe with --sync-peer and --sync-secret",
+                        url
+                    )
+                })),
+            ))
+        }
+    };
+    let report = crate::sync::sync_from_peer(
+        &state.db,
+        &crate::sync::sync_client(),
+        url,
+        secret,
+        &state.screenpipe_dir.join("data"),
+    )
+    .await
+    .map_err(sync_error)?;
+    Ok(JsonResponse(report))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct TimelineExportQuery {
+    table: TimelineTable,
+    #[serde(default)]
+    format: TimelineFormat,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+}
+
+/// Streams a timeline table as Parquet or CSV, for analysis in DuckDB, pandas or a spreadsheet
+#[oasgen]
+async fn export_timeline_handler(
+    Query(query): Query<TimelineExportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
+    let export_error = |e: anyhow::Error| {
+        error!("failed to export {}: {:?}", query.table.as_str(), e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("failed to export timeline: {}", e)})),
+        )
+    };
+    let stream = timeline_stream(
+        state.db.clone(),
+        query.table,
+        query.format,
+        query.start_time,
+        query.end_time,
+    )
+    .map_err(export_error)?;
+    Response::builder()
+        .header("content-type", query.format.content_type())
+        .header(
+            "content-disposition",
+            format!(
+                "attachment; filename=\"{}.{}\"",
+                query.table.as_str(),
+                query.format.extension()
+            ),
+        )
+        .body(Body::from_stream(stream))
+        .map_err(|e| export_error(e.into()))
+}
+
+#[derive(OaSchema, Deserialize)]
+struct EventsQuery {
+    images: Option<bool>,
+}
+
+#[derive(Debug, OaSchema, Deserialize)]
+struct SemanticSearchQuery {
+    text: String,
+    limit: Option<u32>,
+    threshold: Option<f32>,
+}
+
+#[oasgen]
+async fn semantic_search_handler(
+    Query(query): Query<SemanticSearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Vec<screenpipe_db::OCRResult>>, (StatusCode, JsonResponse<Value>)> {
+    let limit = query.limit.unwrap_or(10);
+    let threshold = query.threshold.unwrap_or(0.3);
+
+    debug!(
+        "semantic search for '{}' with limit {} and threshold {}",
+        query.text, limit, threshold
+    );
+
+    // Generate embedding for search text
+    let embedding = match generate_embedding(&query.text, 0).await {
+        Ok(emb) => emb,
+        Err(e) => {
+            error!("failed to generate embedding: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to generate embedding: {}", e)})),
+            ));
+        }
+    };
+
+    // Search database for similar embeddings
+    match state
+        .db
+        .search_similar_embeddings(embedding, limit, threshold)
+        .await
+    {
+        Ok(results) => {
+            debug!("found {} similar results", results.len());
+            Ok(JsonResponse(results))
+        }
+        Err(e) => {
+            error!("failed to search embeddings: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonResponse(json!({"error": format!("failed to search embeddings: {}", e)})),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize, OaSchema, Deserialize)]
+pub struct VisionDeviceControlRequest {
+    device_id: u32,
+}
+
+// impl VisionDeviceControlRequest {
+//     pub fn new(device_id: u32) -> Self {
+//         Self { device_id }
+//     }
+// }
+
+#[derive(Serialize)]
+pub struct VisionDeviceControlResponse {
+    success: bool,
+    message: String,
+}
+
+// async fn start_vision_device(
+//     State(state): State<Arc<AppState>>,
+//     Json(payload): Json<VisionDeviceControlRequest>,
+// ) -> Result<JsonResponse<VisionDeviceControlResponse>, (StatusCode, JsonResponse<Value>)> {
+//     debug!("starting vision device: {}", payload.device_id);
+//     // Validate device exists
+//     let monitors = list_monitors().await;
+//     if !monitors.iter().any(|m| m.id() == payload.device_id) {
+//         return Err((
+//             StatusCode::BAD_REQUEST,
+//             JsonResponse(json!({
+//                 "error": format!("monitor not found: {}", payload.device_id),
+//                 "success": false
+//             })),
+//         ));
+//     }
+
+//     debug!("starting vision device: {}", payload.device_id);
+//     let _ = state
+//         .device_manager
+//         .update_device(DeviceControl {
+//             device: screenpipe_core::DeviceType::Vision(payload.device_id),
 //             is_running: true,
 //             is_paused: false,
 //         })
@@ -2161,6 +3106,84 @@ async fn handle_socket(socket: WebSocket, query: Query<EventsQuery>) {
     debug!("WebSocket connection closed");
 }
 
+#[derive(Deserialize)]
+struct TranscriptionStreamQuery {
+    /// Only stream transcripts of this device, e.g. "MacBook Pro Microphone (input)"
+    device: Option<String>,
+    /// Skip interim results
+    #[serde(default)]
+    final_only: bool,
+}
+
+fn realtime_transcriptions(
+    query: TranscriptionStreamQuery,
+) -> impl futures::Stream<Item = RealtimeTranscriptionEvent> {
+    subscribe_to_event::<RealtimeTranscriptionEvent>(REALTIME_TRANSCRIPTION_EVENT)
+        .map(|event| event.data)
+        .filter(move |transcript| {
+            futures::future::ready(
+                query
+                    .device
+                    .as_ref()
+                    .map_or(true, |device| *device == transcript.device)
+                    && (!query.final_only || transcript.is_final),
+            )
+        })
+}
+
+// interim and final realtime transcripts as server-sent events
+async fn sse_transcriptions_handler(
+    Query(query): Query<TranscriptionStreamQuery>,
+) -> Sse<impl futures::Stream<Item = Result<SseEvent, axum::Error>>> {
+    let stream = realtime_transcriptions(query).map(|transcript| {
+        let kind = if transcript.is_final {
+            "final"
+        } else {
+            "interim"
+        };
+        SseEvent::default().event(kind).json_data(&transcript)
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn ws_transcriptions_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<TranscriptionStreamQuery>,
+) -> Response {
+    ws.on_upgrade(|socket| handle_transcriptions_socket(socket, query))
+}
+
+async fn handle_transcriptions_socket(socket: WebSocket, query: TranscriptionStreamQuery) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut transcripts = Box::pin(realtime_transcriptions(query));
+
+    loop {
+        tokio::select! {
+            transcript = transcripts.next() => {
+                let Some(transcript) = transcript else {
+                    break;
+                };
+                let message = serde_json::to_string(&transcript).unwrap_or_default();
+                if let Err(e) = sender.send(Message::Text(message)).await {
+                    debug!("failed to send transcript over websocket: {}", e);
+                    break;
+                }
+            }
+            message = receiver.next() => {
+                if !matches!(message, Some(Ok(_))) {
+                    break;
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                let _ = sender.send(Message::Ping(vec![])).await;
+            }
+        }
+    }
+
+    debug!("transcriptions websocket closed");
+}
+
 async fn ws_health_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
     ws.on_upgrade(move |socket| handle_health_socket(socket, state))
 }
@@ -2310,6 +3333,176 @@ async fn stop_audio(
     }
 }
 
+#[derive(OaSchema, Deserialize)]
+pub struct ReprocessAudioRequest {
+    pub start_time: DateTime<Utc>,
+    /// Default to now
+    pub end_time: Option<DateTime<Utc>>,
+    /// Transcription engine, same names as the `--audio-transcription-engine` CLI flag.
+    /// Default to the engine used for live recording
+    pub engine: Option<String>,
+}
+
+#[oasgen]
+async fn start_reprocess_handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ReprocessAudioRequest>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let mut job = state.reprocess_job.lock().await;
+
+    let options = state.audio_manager.options().await;
+    let engine: Option<AudioTranscriptionEngine> = match payload.engine {
+        Some(name) => Some(
+            <CliAudioTranscriptionEngine as clap::ValueEnum>::from_str(&name, true)
+                .map_err(|e| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        JsonResponse(json!({"error": format!("invalid engine {}: {}", name, e)})),
+                    )
+                })?
+                .into(),
+        ),
+        None => None,
+    };
+
+    if let Some(existing) = job.as_ref() {
+        match &*existing.status.lock().await {
+            MigrationStatus::Paused { .. } => {
+                // omitted end time and engine keep those of the paused job
+                let paused = &existing.config;
+                let paused_engine: &AudioTranscriptionEngine = &paused.transcription_engine;
+                let same_job = payload.start_time == paused.start_time
+                    && payload.end_time.unwrap_or(paused.end_time) == paused.end_time
+                    && engine.as_ref().unwrap_or(paused_engine) == paused_engine;
+                if !same_job {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        JsonResponse(json!({
+                            "error": format!(
+                                "reprocessing job {} is paused with a different time range or engine, stop it before starting another",
+                                existing.job_name
+                            )
+                        })),
+                    ));
+                }
+
+                let _ = existing.cmd_tx.send(MigrationCommand::Start).await;
+                return Ok(JsonResponse(
+                    json!({"success": true, "job": existing.job_name, "resumed": true}),
+                ));
+            }
+            MigrationStatus::Completed { .. } | MigrationStatus::Failed { .. } => {
+                let _ = existing.cmd_tx.send(MigrationCommand::Stop).await;
+            }
+            // a job that has not reported its first status yet is starting
+            MigrationStatus::Running { .. } | MigrationStatus::NotStarted => {
+                return Err((
+                    StatusCode::CONFLICT,
+                    JsonResponse(json!({
+                        "error": format!("reprocessing job {} is already running", existing.job_name)
+                    })),
+                ));
+            }
+        }
+    }
+
+    let engine = engine.unwrap_or_else(|| (*options.transcription_engine).clone());
+
+    let end_time = payload.end_time.unwrap_or_else(Utc::now);
+    if end_time <= payload.start_time {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({"error": "end_time must be after start_time"})),
+        ));
+    }
+
+    let mut config = ReprocessConfig::new(payload.start_time, end_time, engine);
+    config.languages = options.languages;
+    config.deepgram_api_key = options.deepgram_api_key;
+    config.vad_engine = options.vad_engine;
+    config.vad_config = options.vad_config;
+    config.device_vad_configs = options.device_vad_configs;
+    config.speaker_match_threshold = options.speaker_match_threshold;
+
+    let job_name = config.job_name();
+    let (cmd_tx, mut status_rx, _) = create_reprocess_worker(state.db.clone(), config.clone());
+    let status = Arc::new(Mutex::new(MigrationStatus::NotStarted));
+
+    // keep the latest status around so the status endpoint never blocks on the worker
+    let status_clone = status.clone();
+    tokio::spawn(async move {
+        while let Some(response) = status_rx.recv().await {
+            *status_clone.lock().await = response.status;
+        }
+    });
+
+    if let Err(e) = cmd_tx.send(MigrationCommand::Start).await {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": format!("failed to start reprocessing: {}", e)})),
+        ));
+    }
+
+    info!("started audio reprocessing job {}", job_name);
+    *job = Some(ReprocessJob {
+        job_name: job_name.clone(),
+        config,
+        cmd_tx,
+        status,
+    });
+
+    Ok(JsonResponse(json!({"success": true, "job": job_name})))
+}
+
+#[oasgen]
+async fn pause_reprocess_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    send_reprocess_command(&state, MigrationCommand::Pause).await
+}
+
+#[oasgen]
+async fn stop_reprocess_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let response = send_reprocess_command(&state, MigrationCommand::Stop).await?;
+    state.reprocess_job.lock().await.take();
+    Ok(response)
+}
+
+#[oasgen]
+async fn reprocess_status_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let job = state.reprocess_job.lock().await;
+    match job.as_ref() {
+        Some(job) => {
+            let status = job.status.lock().await.clone();
+            Ok(JsonResponse(json!({"job": job.job_name, "status": status})))
+        }
+        None => Ok(JsonResponse(
+            json!({"job": null, "status": MigrationStatus::NotStarted}),
+        )),
+    }
+}
+
+async fn send_reprocess_command(
+    state: &AppState,
+    command: MigrationCommand,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let job = state.reprocess_job.lock().await;
+    match job.as_ref() {
+        Some(job) => {
+            let _ = job.cmd_tx.send(command).await;
+            Ok(JsonResponse(json!({"success": true, "job": job.job_name})))
+        }
+        None => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({"error": "no reprocessing job is running"})),
+        )),
+    }
+}
+
 pub async fn handle_video_export_ws(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
@@ -2461,7 +3654,15 @@ async fn handle_video_export(
         .await;
 
     // Create video
-    match write_frames_to_video(&frames, output_path.to_str().unwrap(), payload.fps).await {
+    // exports are shared outside screenpipe, keep them in the default profile
+    match write_frames_to_video(
+        &frames,
+        output_path.to_str().unwrap(),
+        payload.fps,
+        &EncodingProfile::default(),
+    )
+    .await
+    {
         Ok(_) => match tokio::fs::read(&output_path).await {
             Ok(video_data) => {
                 let _ = socket
@@ -2648,28 +3849,89 @@ async fn get_pipe_build_status(
 async fn keyword_search_handler(
     Query(query): Query<KeywordSearchRequest>,
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<SearchMatch>>, (StatusCode, JsonResponse<Value>)> {
-    let matches = state
-        .db
-        .search_with_text_positions(
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    let parsed = parse_query(&query.query).map_err(query_error_response)?;
+    // keyword search only looks at screen text, it has no speakers or other content to filter
+    if !parsed.speakers.is_empty()
+        || !matches!(
+            parsed.content_type,
+            None | Some(ContentType::All) | Some(ContentType::OCR)
+        )
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            JsonResponse(json!({
+                "error": "keyword search only supports ocr content, speaker: and type: filters other than type:ocr are not allowed"
+            })),
+        ));
+    }
+    let cursor = match &query.cursor {
+        Some(cursor) => Some(decode_cursor(cursor)?),
+        None => None,
+    };
+
+    let internal_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({"error": e.to_string()})),
+        )
+    };
+
+    // with a cursor the offset is ignored, the same way as in /search
+    let paginated = cursor.is_some();
+    let cursor = cursor.flatten();
+    let offset = if paginated { 0 } else { query.offset };
+    let search = state.db.search_with_text_positions(
+        &query.query,
+        query.limit,
+        offset,
+        cursor.as_ref(),
+        query.start_time,
+        query.end_time,
+        query.fuzzy_match,
+        query.order,
+        query.app_names.clone(),
+    );
+
+    // without a cursor the response stays the bare array existing clients expect
+    if !paginated {
+        let matches = search.await.map_err(internal_error)?;
+        return Ok(JsonResponse(json!(matches)));
+    }
+
+    let (matches, total) = try_join(
+        search,
+        state.db.count_text_position_matches(
             &query.query,
-            query.limit,
-            query.offset,
             query.start_time,
             query.end_time,
             query.fuzzy_match,
-            query.order,
             query.app_names,
-        )
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                JsonResponse(json!({"error": e.to_string()})),
-            )
-        })?;
+        ),
+    )
+    .await
+    .map_err(internal_error)?;
+
+    // pages hold whole frames, a frame can have several matches
+    let mut frame_ids: Vec<i64> = matches.iter().map(|m| m.frame_id).collect();
+    frame_ids.dedup();
+    let next_cursor = if frame_ids.len() == query.limit as usize {
+        matches
+            .last()
+            .map(|last| SearchCursor::new(last.timestamp, last.frame_id).encode())
+    } else {
+        None
+    };
 
-    Ok(JsonResponse(matches))
+    Ok(JsonResponse(json!(PaginatedResponse {
+        data: matches,
+        pagination: PaginationInfo {
+            limit: query.limit,
+            offset,
+            total: total as i64,
+            next_cursor,
+        },
+    })))
 }
 
 fn from_comma_separated_string<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
@@ -2698,6 +3960,10 @@ pub struct KeywordSearchRequest {
     #[serde(default)]
     #[serde(deserialize_with = "from_comma_separated_string")]
     app_names: Option<Vec<String>>,
+    /// Keyset pagination: `next_cursor` of the previous page, empty for the first one. The
+    /// matches are then returned with their pagination instead of as a bare list.
+    #[serde(default)]
+    cursor: Option<String>,
 }
 
 #[oasgen]
@@ -2791,6 +4057,42 @@ pub async fn get_frame_data(
     }
 }
 
+/// Structured OCR layout of a frame: blocks, paragraphs, lines and words with their boxes, in
+/// reading order, and the tables found in it
+#[oasgen]
+pub async fn get_frame_layout_handler(
+    State(state): State<Arc<AppState>>,
+    Path(frame_id): Path<i64>,
+) -> Result<JsonResponse<Value>, (StatusCode, JsonResponse<Value>)> {
+    match state.db.get_frame_layout(frame_id).await {
+        Ok(Some(layout)) => serde_json::from_str(&layout)
+            .map(JsonResponse)
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonResponse(json!({
+                        "error": format!("Invalid stored layout: {}", e),
+                        "frame_id": frame_id
+                    })),
+                )
+            }),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            JsonResponse(json!({
+                "error": "No layout for this frame",
+                "frame_id": frame_id
+            })),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({
+                "error": format!("Database error: {}", e),
+                "frame_id": frame_id
+            })),
+        )),
+    }
+}
+
 async fn serve_file(path: &str) -> Result<Response, (StatusCode, JsonResponse<Value>)> {
     match File::open(path).await {
         Ok(file) => {
@@ -0,0 +1,198 @@
+//! Pulls the recordings of other screenpipe instances through their `/sync` API, so the
+//! machines of one person end up with a single history
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use screenpipe_db::{DatabaseManager, ImportReport, SyncBatch, SyncMedia};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+pub const DEFAULT_SYNC_INTERVAL_SECS: u64 = 300;
+
+/// Changes pulled per request
+const SYNC_BATCH_SIZE: u32 = 500;
+/// Rows added to the change feed per backfill transaction
+const SYNC_BACKFILL_BATCH_SIZE: i64 = 5000;
+
+/// One sync at a time, the scheduled ones and those asked through the API share the downloads
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+pub fn sync_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+async fn fetch_batch(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    since: i64,
+    machine_id: &str,
+) -> Result<SyncBatch> {
+    let response = client
+        .get(format!("{}/sync/changes", url))
+        .bearer_auth(secret)
+        .query(&[
+            ("since", since.to_string()),
+            ("limit", SYNC_BATCH_SIZE.to_string()),
+            ("machine_id", machine_id.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(response.json().await?)
+}
+
+async fn download_media(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    media: &SyncMedia,
+    destination: &Path,
+) -> Result<()> {
+    let mut response = client
+        .get(format!("{}/sync/media", url))
+        .bearer_auth(secret)
+        .query(&[
+            ("table", media.table.clone()),
+            ("id", media.row_id.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut file = tokio::fs::File::create(destination).await?;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+/// Pulls everything that changed on the peer at `url` since the last sync, batch after batch,
+/// sending the shared `secret`. Media files go to `media_dir`, a failed download keeps the path
+/// of the peer in the row.
+pub async fn sync_from_peer(
+    db: &DatabaseManager,
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    media_dir: &Path,
+) -> Result<ImportReport> {
+    let url = url.trim_end_matches('/');
+    let _guard = SYNC_LOCK.lock().await;
+    let result = pull_changes(db, client, url, secret, media_dir).await;
+    if let Err(e) = &result {
+        if let Err(e) = db.record_sync_error(url, &e.to_string()).await {
+            warn!("failed to record sync error of {}: {}", url, e);
+        }
+    }
+    result
+}
+
+async fn pull_changes(
+    db: &DatabaseManager,
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    media_dir: &Path,
+) -> Result<ImportReport> {
+    let machine_id = db.database_id().await?;
+    let peer = db.sync_peer(url).await?;
+    let since = peer.as_ref().map_or(0, |peer| peer.watermark);
+    let mut batch = fetch_batch(client, url, secret, since, &machine_id).await?;
+    // another database answers at this address now, its feed is pulled from the start
+    if since > 0
+        && peer
+            .and_then(|peer| peer.peer_id)
+            .is_some_and(|peer_id| peer_id != batch.source_id)
+    {
+        info!("{} serves another database, syncing it from the start", url);
+        batch = fetch_batch(client, url, secret, 0, &machine_id).await?;
+    }
+
+    let mut report = ImportReport::default();
+    loop {
+        if !batch.media.is_empty() {
+            tokio::fs::create_dir_all(media_dir).await?;
+        }
+        let mut downloads: HashMap<String, PathBuf> = HashMap::new();
+        for media in &batch.media {
+            let destination =
+                media_dir.join(format!(".sync-{}-{}.part", media.table, media.row_id));
+            match download_media(client, url, secret, media, &destination).await {
+                Ok(()) => {
+                    downloads.insert(media.path.clone(), destination);
+                }
+                Err(e) => {
+                    warn!("failed to download {} from {}: {}", media.path, url, e);
+                    let _ = tokio::fs::remove_file(&destination).await;
+                }
+            }
+        }
+        let applied = db
+            .apply_sync_batch(url, &batch, media_dir, &downloads)
+            .await;
+        // the applied downloads were moved into place
+        for file in downloads.values() {
+            let _ = tokio::fs::remove_file(file).await;
+        }
+        report.merge(applied?);
+        if !batch.has_more {
+            break;
+        }
+        batch = fetch_batch(client, url, secret, batch.watermark, &machine_id).await?;
+    }
+    Ok(report)
+}
+
+/// Pulls from every peer at `interval` until the process exits
+pub async fn run_sync(
+    db: Arc<DatabaseManager>,
+    peers: Vec<String>,
+    secret: String,
+    media_dir: PathBuf,
+    interval: Duration,
+) {
+    let client = sync_client();
+    loop {
+        for url in &peers {
+            match sync_from_peer(&db, &client, url, &secret, &media_dir).await {
+                Ok(report) => {
+                    let inserted: i64 = report.tables.iter().map(|table| table.inserted).sum();
+                    let updated: i64 = report.tables.iter().map(|table| table.updated).sum();
+                    info!(
+                        "synced from {}: {} rows inserted, {} updated, {} media files copied",
+                        url, inserted, updated, report.media_copied
+                    );
+                }
+                Err(e) => error!("sync from {} failed: {}", url, e),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Adds the rows recorded before the change feed existed to it, a batch at a time so recording
+/// goes on meanwhile. The feed is served once they are all in.
+pub async fn run_sync_log_backfill(db: Arc<DatabaseManager>) {
+    loop {
+        match db.backfill_sync_log(SYNC_BACKFILL_BATCH_SIZE).await {
+            Ok(true) => tokio::time::sleep(Duration::from_millis(100)).await,
+            Ok(false) => {
+                debug!("change feed is complete");
+                return;
+            }
+            Err(e) => {
+                error!("failed to backfill the change feed: {}", e);
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Analytical export of the timeline tables to Parquet or CSV, for DuckDB, pandas and the like.
+//! Rows are read and encoded one chunk at a time, so the output can be streamed however long
+//! the history is.
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::body::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use futures::Stream;
+use oasgen::OaSchema;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use screenpipe_db::{
+    DatabaseManager, TimelineColumn, TimelineColumnType, TimelineTable, TimelineValue,
+};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+
+/// Rows read per query, and per Parquet row group
+pub const TIMELINE_CHUNK_SIZE: u32 = 10_000;
+
+#[derive(OaSchema, Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineFormat {
+    /// Typed columns, timestamps in UTC microseconds
+    #[default]
+    Parquet,
+    /// Timestamps in RFC 3339, empty fields for NULL. Texts starting with `=`, `+`, `-`, `@`,
+    /// a tab or a carriage return get a leading `'`, so a spreadsheet doesn't run them as formulas
+    Csv,
+}
+
+impl TimelineFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TimelineFormat::Parquet => "application/vnd.apache.parquet",
+            TimelineFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TimelineFormat::Parquet => "parquet",
+            TimelineFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Buffer the Parquet writer writes into, drained after every row group
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn arrow_type(column_type: TimelineColumnType) -> DataType {
+    match column_type {
+        TimelineColumnType::Integer => DataType::Int64,
+        TimelineColumnType::Real => DataType::Float64,
+        TimelineColumnType::Text => DataType::Utf8,
+        TimelineColumnType::Timestamp => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        }
+        TimelineColumnType::Boolean => DataType::Boolean,
+    }
+}
+
+fn arrow_column(
+    rows: &[Vec<TimelineValue>],
+    i: usize,
+    column_type: TimelineColumnType,
+) -> ArrayRef {
+    match column_type {
+        TimelineColumnType::Integer => {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for row in rows {
+                match &row[i] {
+                    TimelineValue::Integer(value) => builder.append_value(*value),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        TimelineColumnType::Real => {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for row in rows {
+                match &row[i] {
+                    TimelineValue::Real(value) => builder.append_value(*value),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        TimelineColumnType::Text => {
+            let mut builder = StringBuilder::new();
+            for row in rows {
+                match &row[i] {
+                    TimelineValue::Text(value) => builder.append_value(value),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        TimelineColumnType::Timestamp => {
+            let mut builder =
+                TimestampMicrosecondBuilder::with_capacity(rows.len()).with_timezone("UTC");
+            for row in rows {
+                match &row[i] {
+                    TimelineValue::Timestamp(value) => {
+                        builder.append_value(value.timestamp_micros())
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        TimelineColumnType::Boolean => {
+            let mut builder = BooleanBuilder::with_capacity(rows.len());
+            for row in rows {
+                match &row[i] {
+                    TimelineValue::Boolean(value) => builder.append_value(*value),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+fn csv_field(value: &TimelineValue, line: &mut String) {
+    match value {
+        TimelineValue::Null => {}
+        TimelineValue::Integer(value) => line.push_str(&value.to_string()),
+        TimelineValue::Real(value) => line.push_str(&value.to_string()),
+        TimelineValue::Boolean(value) => line.push_str(&value.to_string()),
+        TimelineValue::Timestamp(value) => {
+            line.push_str(&value.to_rfc3339_opts(SecondsFormat::Micros, true))
+        }
+        TimelineValue::Text(value) => {
+            let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+                Cow::Owned(format!("'{}", value))
+            } else {
+                Cow::Borrowed(value.as_str())
+            };
+            if value.contains([',', '"', '\n', '\r']) {
+                line.push('"');
+                line.push_str(&value.replace('"', "\"\""));
+                line.push('"');
+            } else {
+                line.push_str(&value);
+            }
+        }
+    }
+}
+
+fn csv_header(columns: &[TimelineColumn]) -> String {
+    let names: Vec<&str> = columns.iter().map(|column| column.name).collect();
+    format!("{}\n", names.join(","))
+}
+
+enum Encoding {
+    Csv {
+        header_written: bool,
+    },
+    Parquet {
+        schema: SchemaRef,
+        writer: Box<ArrowWriter<SharedBuffer>>,
+        buffer: SharedBuffer,
+    },
+}
+
+/// Encodes the rows of a table chunk after chunk
+pub struct TimelineEncoder {
+    columns: &'static [TimelineColumn],
+    encoding: Encoding,
+}
+
+impl TimelineEncoder {
+    pub fn new(table: TimelineTable, format: TimelineFormat) -> Result<Self> {
+        let columns = table.columns();
+        let encoding = match format {
+            TimelineFormat::Csv => Encoding::Csv {
+                header_written: false,
+            },
+            TimelineFormat::Parquet => {
+                let schema: SchemaRef = Arc::new(Schema::new(
+                    columns
+                        .iter()
+                        .map(|column| Field::new(column.name, arrow_type(column.column_type), true))
+                        .collect::<Vec<_>>(),
+                ));
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let buffer = SharedBuffer::default();
+                let writer =
+                    ArrowWriter::try_new(buffer.clone(), schema.clone(), Some(properties))?;
+                Encoding::Parquet {
+                    schema,
+                    writer: Box::new(writer),
+                    buffer,
+                }
+            }
+        };
+        Ok(Self { columns, encoding })
+    }
+
+    /// Encodes a chunk of rows, returns the bytes to write out. A Parquet chunk is a row group.
+    pub fn encode(&mut self, rows: &[Vec<TimelineValue>]) -> Result<Vec<u8>> {
+        match &mut self.encoding {
+            Encoding::Csv { header_written } => {
+                let mut out = String::new();
+                if !*header_written {
+                    out.push_str(&csv_header(self.columns));
+                    *header_written = true;
+                }
+                for row in rows {
+                    for (i, value) in row.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        csv_field(value, &mut out);
+                    }
+                    out.push('\n');
+                }
+                Ok(out.into_bytes())
+            }
+            Encoding::Parquet {
+                schema,
+                writer,
+                buffer,
+            } => {
+                if !rows.is_empty() {
+                    let arrays = self
+                        .columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, column)| arrow_column(rows, i, column.column_type))
+                        .collect();
+                    writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+                    writer.flush()?;
+                }
+                Ok(buffer.take())
+            }
+        }
+    }
+
+    /// Ends the output, with the Parquet footer
+    pub fn finish(self) -> Result<Vec<u8>> {
+        match self.encoding {
+            Encoding::Csv { header_written } => Ok(match header_written {
+                true => Vec::new(),
+                false => csv_header(self.columns).into_bytes(),
+            }),
+            Encoding::Parquet { writer, buffer, .. } => {
+                (*writer).close()?;
+                Ok(buffer.take())
+            }
+        }
+    }
+}
+
+/// Encoder of a streamed export and the id to read the next chunk after, no id once the rows
+/// are all encoded and no encoder once the output is finished
+type StreamState = (Option<TimelineEncoder>, Option<i64>);
+
+async fn next_chunk(
+    db: Arc<DatabaseManager>,
+    table: TimelineTable,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    (encoder, after_id): StreamState,
+) -> Result<Option<(Bytes, StreamState)>> {
+    let Some(mut encoder) = encoder else {
+        return Ok(None);
+    };
+    let Some(after_id) = after_id else {
+        return Ok(Some((Bytes::from(encoder.finish()?), (None, None))));
+    };
+    let chunk = db
+        .timeline_chunk(table, start, end, after_id, TIMELINE_CHUNK_SIZE)
+        .await?;
+    let bytes = encoder.encode(&chunk.rows)?;
+    Ok(Some((
+        Bytes::from(bytes),
+        (Some(encoder), chunk.next_after_id),
+    )))
+}
+
+/// Export of a table as a stream of encoded chunks, for a response body
+pub fn timeline_stream(
+    db: Arc<DatabaseManager>,
+    table: TimelineTable,
+    format: TimelineFormat,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<impl Stream<Item = Result<Bytes>> + Send + 'static> {
+    let encoder = TimelineEncoder::new(table, format)?;
+    Ok(futures::stream::try_unfold(
+        (Some(encoder), Some(0)),
+        move |state| next_chunk(db.clone(), table, start, end, state),
+    ))
+}
+
+/// Writes the export of a table to a file, returns how many rows it has
+pub async fn export_timeline_to_file(
+    db: &DatabaseManager,
+    table: TimelineTable,
+    format: TimelineFormat,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    path: &Path,
+) -> Result<u64> {
+    let mut encoder = TimelineEncoder::new(table, format)?;
+    let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(path).await?);
+    let mut rows = 0;
+    let mut after_id = Some(0);
+    while let Some(after) = after_id {
+        let chunk = db
+            .timeline_chunk(table, start, end, after, TIMELINE_CHUNK_SIZE)
+            .await?;
+        rows += chunk.rows.len() as u64;
+        file.write_all(&encoder.encode(&chunk.rows)?).await?;
+        after_id = chunk.next_after_id;
+    }
+    file.write_all(&encoder.finish()?).await?;
+    file.flush().await?;
+    Ok(rows)
+}
@@ -0,0 +1,27 @@
+//! Background refresh of the screen time and talk time rollups, so the first request after an
+//! upgrade or a large import doesn't recompute every hour of the history
+
+use screenpipe_db::DatabaseManager;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// How often the rollups of changed hours are recomputed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Hours refreshed on request, older ones are left to the background refresh
+pub const REQUEST_REFRESH_HOURS: i64 = 2;
+
+/// Refreshes the rollups of every changed hour, then every five minutes until the process exits
+pub async fn run_usage_rollups(db: Arc<DatabaseManager>) {
+    let mut first = true;
+    loop {
+        match db.refresh_usage_rollups(None).await {
+            Ok(hours) if first && hours > 0 => info!("rolled up usage of {} hours", hours),
+            Ok(hours) => debug!("refreshed usage rollups of {} hours", hours),
+            Err(e) => error!("failed to refresh usage rollups: {}", e),
+        }
+        first = false;
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
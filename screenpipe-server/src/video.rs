@@ -1,17 +1,22 @@
+use crate::video_encoding::EncodingProfile;
 use chrono::Utc;
 use crossbeam::queue::ArrayQueue;
 use image::ImageFormat::{self};
 use screenpipe_core::{find_ffmpeg_path, Language};
+use screenpipe_vision::capture_rate::{AdaptiveCaptureRate, CaptureRateConfig};
+use screenpipe_vision::frame_source::FrameSourceConfig;
 use screenpipe_vision::monitor::get_monitor_by_id;
+use screenpipe_vision::ocr_pool::OcrPool;
 use screenpipe_vision::{
-    capture_screenshot_by_window::WindowFilters, continuous_capture, CaptureResult, OcrEngine,
+    capture_screenshot_by_window::WindowFilters, continuous_capture_from_source, CaptureResult,
+    OcrEngine,
 };
 use std::borrow::Cow;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::io::AsyncBufReadExt;
 use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
@@ -33,40 +38,51 @@ pub struct VideoCapture {
     video_thread_handle: tokio::task::JoinHandle<()>,
     monitor_check_handle: tokio::task::JoinHandle<()>, // New handle for monitor check
     monitor_available: Arc<AtomicBool>,                // Flag to track monitor availability
-    monitor_id: u32,                                   // Store monitor ID for availability checks
+    source: FrameSourceConfig,                         // Store the source for availability checks
+    // Set once a finite source ran out and all its frames were queued
+    source_finished: Arc<AtomicBool>,
 }
 
 impl VideoCapture {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         output_path: &str,
-        fps: f64,
+        capture_rate: CaptureRateConfig,
         video_chunk_duration: Duration,
         new_chunk_callback: impl Fn(&str) + Send + Sync + 'static,
         ocr_engine: Arc<OcrEngine>,
-        monitor_id: u32,
+        ocr_pool: Arc<OcrPool>,
+        source: FrameSourceConfig,
         ignore_list: &[String],
         include_list: &[String],
         languages: Vec<Language>,
         capture_unfocused_windows: bool,
+        encoding_profile: EncodingProfile,
     ) -> Self {
-        let fps = if fps.is_finite() && fps > 0.0 {
-            fps
-        } else {
-            warn!("Invalid FPS value: {}. Using default of 1.0", fps);
-            1.0
+        let capture_rate = match capture_rate.validate() {
+            Ok(()) => capture_rate,
+            Err(e) => {
+                warn!(
+                    "Invalid capture rate {:?}: {}. Using 1 fps",
+                    capture_rate, e
+                );
+                CaptureRateConfig::fixed(1.0)
+            }
         };
-        let interval = Duration::from_secs_f64(1.0 / fps);
+        let fps = capture_rate.max_fps;
         let video_frame_queue = Arc::new(ArrayQueue::new(MAX_QUEUE_SIZE));
         let ocr_frame_queue = Arc::new(ArrayQueue::new(MAX_QUEUE_SIZE));
         let new_chunk_callback = Arc::new(new_chunk_callback);
         let new_chunk_callback_clone = Arc::clone(&new_chunk_callback);
         let monitor_available = Arc::new(AtomicBool::new(true));
         let monitor_available_clone = monitor_available.clone();
+        let source_finished = Arc::new(AtomicBool::new(false));
+        let queue_source_finished = source_finished.clone();
+        let video_source_finished = source_finished.clone();
 
         info!(
-            "Starting VideoCapture for monitor {}, max queue size: {}, fps: {}",
-            monitor_id, MAX_QUEUE_SIZE, fps
+            "Starting VideoCapture for {}, max queue size: {}, fps: {}-{}",
+            source, MAX_QUEUE_SIZE, capture_rate.min_fps, fps
         );
 
         let capture_video_frame_queue = video_frame_queue.clone();
@@ -80,47 +96,60 @@ impl VideoCapture {
         let capture_window_filters = window_filters.clone();
         let capture_languages = languages.clone();
         let capture_result_sender = result_sender.clone();
-        let capture_interval = interval;
+        let capture_rate_config = capture_rate.clone();
         let capture_unfocused = capture_unfocused_windows;
+        let capture_source = source.clone();
 
         // Store task handles for health monitoring
         let capture_thread = tokio::spawn(async move {
-            info!(
-                "Starting continuous_capture task for monitor {}",
-                monitor_id
-            );
+            info!("Starting continuous_capture task for {}", capture_source);
 
             loop {
                 // Check if monitor is available before starting capture
                 if !capture_monitor_available.load(Ordering::SeqCst) {
                     warn!(
-                        "Monitor {} is not available, waiting before starting capture",
-                        monitor_id
+                        "{} is not available, waiting before starting capture",
+                        capture_source
                     );
                     tokio::time::sleep(Duration::from_secs(5)).await;
                     continue;
                 }
 
-                info!("Starting continuous_capture for monitor {}", monitor_id);
-
-                match continuous_capture(
-                    capture_result_sender.clone(),
-                    capture_interval,
-                    (*capture_ocr_engine).clone(),
-                    monitor_id,
-                    capture_window_filters.clone(),
-                    capture_languages.clone(),
-                    capture_unfocused,
-                )
-                .await
+                info!("Starting continuous_capture for {}", capture_source);
+
+                let result = match capture_source
+                    .open(capture_window_filters.clone(), capture_unfocused, fps)
+                    .await
                 {
+                    Ok(frame_source) => continuous_capture_from_source(
+                        frame_source,
+                        capture_result_sender.clone(),
+                        AdaptiveCaptureRate::new(capture_rate_config.clone())
+                            .with_device_name(&capture_source.device_name()),
+                        ocr_pool.source(
+                            &capture_source.device_name(),
+                            (*capture_ocr_engine).clone(),
+                            capture_languages.clone(),
+                        ),
+                    )
+                    .await
+                    .map_err(anyhow::Error::msg),
+                    Err(e) => Err(e),
+                };
+
+                match result {
+                    Ok(_) if capture_source.is_finite() => {
+                        info!("continuous_capture for {} read all frames", capture_source);
+                        // dropping the sender lets the queue task know no frames are coming
+                        break;
+                    }
                     Ok(_) => warn!(
-                        "continuous_capture task for monitor {} completed unexpectedly",
-                        monitor_id
+                        "continuous_capture task for {} completed unexpectedly",
+                        capture_source
                     ),
                     Err(e) => error!(
-                        "continuous_capture task for monitor {} failed with error: {}",
-                        monitor_id, e
+                        "continuous_capture task for {} failed with error: {}",
+                        capture_source, e
                     ),
                 }
 
@@ -131,8 +160,9 @@ impl VideoCapture {
         });
 
         // In the _queue_thread
+        let queue_source = source.clone();
         let queue_thread = tokio::spawn(async move {
-            info!("Starting queue processing task for monitor {}", monitor_id);
+            info!("Starting queue processing task for {}", queue_source);
             let mut processed_count = 0;
             let start_time = std::time::Instant::now();
             let mut last_log_time = start_time;
@@ -189,8 +219,8 @@ impl VideoCapture {
                         0.0
                     };
                     info!(
-                        "Queue stats for monitor {}: processed {} frames in {:.1}s ({:.2} fps), queue sizes: video={}/{}, ocr={}/{}",
-                        monitor_id, processed_count, elapsed_secs, rate,
+                        "Queue stats for {}: processed {} frames in {:.1}s ({:.2} fps), queue sizes: video={}/{}, ocr={}/{}",
+                        queue_source, processed_count, elapsed_secs, rate,
                         capture_video_frame_queue.len(), capture_video_frame_queue.capacity(),
                         capture_ocr_frame_queue.len(), capture_ocr_frame_queue.capacity()
                     );
@@ -222,47 +252,56 @@ impl VideoCapture {
                 );
             }
 
-            warn!(
-                "Queue processing task terminated for monitor {} - channel closed",
-                monitor_id
-            );
+            if queue_source.is_finite() {
+                info!("Queued all frames of {}", queue_source);
+                queue_source_finished.store(true, Ordering::SeqCst);
+            } else {
+                warn!(
+                    "Queue processing task terminated for {} - channel closed",
+                    queue_source
+                );
+            }
         });
 
         let video_frame_queue_clone = video_frame_queue.clone();
 
         let output_path = output_path.to_string();
+        let video_source = source.clone();
         let video_thread = tokio::spawn(async move {
-            info!(
-                "Starting save_frames_as_video task for monitor {}",
-                monitor_id
-            );
+            info!("Starting save_frames_as_video task for {}", video_source);
             match save_frames_as_video(
                 &video_frame_queue_clone,
                 &output_path,
                 fps,
                 new_chunk_callback_clone,
-                monitor_id,
+                &video_source,
                 video_chunk_duration,
+                &video_source_finished,
+                &encoding_profile,
             )
             .await
             {
+                Ok(_) if video_source.is_finite() => {
+                    info!("save_frames_as_video wrote all frames of {}", video_source)
+                }
                 Ok(_) => warn!(
-                    "save_frames_as_video task completed unexpectedly for monitor {}",
-                    monitor_id
+                    "save_frames_as_video task completed unexpectedly for {}",
+                    video_source
                 ),
                 Err(e) => error!(
-                    "save_frames_as_video task failed for monitor {}: {}",
-                    monitor_id, e
+                    "save_frames_as_video task failed for {}: {}",
+                    video_source, e
                 ),
             }
-            warn!(
-                "save_frames_as_video task terminated for monitor {}",
-                monitor_id
-            );
+            warn!("save_frames_as_video task terminated for {}", video_source);
         });
 
-        // Add monitor availability check task
+        // Add monitor availability check task, other sources are always available
+        let monitor_id = source.monitor_id();
         let monitor_check_handle = tokio::spawn(async move {
+            let Some(monitor_id) = monitor_id else {
+                return std::future::pending().await;
+            };
             info!(
                 "Starting monitor availability check for monitor {}",
                 monitor_id
@@ -300,15 +339,18 @@ impl VideoCapture {
             video_thread_handle: video_thread,
             monitor_check_handle,
             monitor_available,
-            monitor_id,
+            source,
+            source_finished,
         }
     }
 
     // Modify check_health to include monitor check task
     pub fn check_health(&self) -> bool {
-        let capture_ok = !self.capture_thread_handle.is_finished();
-        let queue_ok = !self.queue_thread_handle.is_finished();
-        let video_ok = !self.video_thread_handle.is_finished();
+        // the tasks of a finite source stop once all its frames are processed
+        let source_finished = self.source_finished.load(Ordering::SeqCst);
+        let capture_ok = !self.capture_thread_handle.is_finished() || source_finished;
+        let queue_ok = !self.queue_thread_handle.is_finished() || source_finished;
+        let video_ok = !self.video_thread_handle.is_finished() || source_finished;
         let monitor_check_ok = !self.monitor_check_handle.is_finished();
         let monitor_available = self.monitor_available.load(Ordering::SeqCst);
 
@@ -325,7 +367,7 @@ impl VideoCapture {
             error!("monitor check task has terminated unexpectedly");
         }
         if !monitor_available {
-            warn!("monitor {} is currently unavailable", self.monitor_id);
+            warn!("{} is currently unavailable", self.source);
         }
 
         capture_ok && queue_ok && video_ok && monitor_check_ok
@@ -335,9 +377,20 @@ impl VideoCapture {
     pub fn is_monitor_available(&self) -> bool {
         self.monitor_available.load(Ordering::SeqCst)
     }
+
+    /// Whether a finite source has been fully captured, encoded and handed over for OCR storage
+    pub fn is_finished(&self) -> bool {
+        self.source_finished.load(Ordering::SeqCst)
+            && self.video_thread_handle.is_finished()
+            && self.ocr_frame_queue.is_empty()
+    }
 }
 
-pub async fn start_ffmpeg_process(output_file: &str, fps: f64) -> Result<Child, anyhow::Error> {
+pub async fn start_ffmpeg_process(
+    output_file: &str,
+    fps: f64,
+    encoding_profile: &EncodingProfile,
+) -> Result<Child, anyhow::Error> {
     // Overriding fps with max fps if over the max and warning user
     let fps = if fps > MAX_FPS {
         warn!("Overriding FPS from {} to {}", fps, MAX_FPS);
@@ -350,30 +403,18 @@ pub async fn start_ffmpeg_process(output_file: &str, fps: f64) -> Result<Child,
     let fps_str = fps.to_string();
     let mut command = Command::new(find_ffmpeg_path().unwrap());
     let mut args = vec![
-        "-f",
-        "image2pipe",
-        "-vcodec",
-        "png",
-        "-r",
-        &fps_str,
-        "-i",
-        "-",
-        "-vf",
-        "pad=width=ceil(iw/2)*2:height=ceil(ih/2)*2",
+        "-f".to_string(),
+        "image2pipe".to_string(),
+        "-vcodec".to_string(),
+        "png".to_string(),
+        "-r".to_string(),
+        fps_str,
+        "-i".to_string(),
+        "-".to_string(),
     ];
 
-    args.extend_from_slice(&[
-        "-vcodec",
-        "libx265",
-        "-tag:v",
-        "hvc1",
-        "-preset",
-        "ultrafast",
-        "-crf",
-        "23",
-    ]);
-
-    args.extend_from_slice(&["-pix_fmt", "yuv420p", output_file]);
+    args.extend(encoding_profile.ffmpeg_args());
+    args.push(output_file.to_string());
 
     command
         .args(&args)
@@ -405,22 +446,30 @@ async fn log_ffmpeg_output(stream: impl AsyncBufReadExt + Unpin, stream_name: &s
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn save_frames_as_video(
     frame_queue: &Arc<ArrayQueue<Arc<CaptureResult>>>,
     output_path: &str,
     fps: f64,
     new_chunk_callback: Arc<dyn Fn(&str) + Send + Sync>,
-    monitor_id: u32,
+    source: &FrameSourceConfig,
     video_chunk_duration: Duration,
+    source_finished: &AtomicBool,
+    encoding_profile: &EncodingProfile,
 ) -> Result<(), anyhow::Error> {
-    info!(
-        "Starting save_frames_as_video function for monitor {}",
-        monitor_id
-    );
+    info!("Starting save_frames_as_video function for {}", source);
+    let device_name = source.device_name();
+    let all_frames_written = || source_finished.load(Ordering::SeqCst) && frame_queue.is_empty();
     let frames_per_video = (fps * video_chunk_duration.as_secs_f64()).ceil() as usize;
     let mut frame_count = 0;
     let mut current_ffmpeg: Option<Child> = None;
     let mut current_stdin: Option<ChildStdin> = None;
+    // the capture rate adapts, so live chunks end on time and are retimed to the rate their
+    // frames were captured at. A finite source is read as fast as it goes, at the set rate.
+    let real_time = !source.is_finite();
+    let mut current_file = String::new();
+    let mut first_frame_at = Instant::now();
+    let mut last_frame_at = first_frame_at;
 
     // Track health metrics
     let start_time = std::time::Instant::now();
@@ -430,39 +479,62 @@ async fn save_frames_as_video(
     let stats_interval = Duration::from_secs(60);
 
     loop {
-        if frame_count >= frames_per_video || current_ffmpeg.is_none() {
+        let chunk_elapsed = real_time && first_frame_at.elapsed() >= video_chunk_duration;
+        if frame_count >= frames_per_video
+            || chunk_elapsed
+            || current_ffmpeg.is_none()
+            || all_frames_written()
+        {
             if let Some(child) = current_ffmpeg.take() {
                 info!(
-                    "Finishing FFmpeg process for monitor {} after {} frames",
-                    monitor_id, frame_count
+                    "Finishing FFmpeg process for {} after {} frames",
+                    source, frame_count
                 );
                 finish_ffmpeg_process(child, current_stdin.take()).await;
                 chunks_total += 1;
+                if real_time {
+                    let encoded_fps = fps.min(MAX_FPS);
+                    if let Some(captured_fps) =
+                        captured_fps(frame_count, first_frame_at, last_frame_at, encoded_fps)
+                    {
+                        let file = current_file.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = retime_chunk(&file, encoded_fps, captured_fps).await {
+                                warn!("failed to retime {}: {}", file, e);
+                            }
+                        });
+                    }
+                }
             }
 
             frame_count = 0;
-            debug!("Waiting for first frame for monitor {}", monitor_id);
-            let first_frame = wait_for_first_frame(frame_queue).await;
+            debug!("Waiting for first frame for {}", source);
+            let Some(first_frame) = wait_for_first_frame(frame_queue, source_finished).await else {
+                info!(
+                    "All {} frames of {} written in {} chunks",
+                    frames_total, source, chunks_total
+                );
+                return Ok(());
+            };
             let buffer = encode_frame(&first_frame);
-            debug!("Got first frame for new chunk for monitor {}", monitor_id);
+            debug!("Got first frame for new chunk for {}", source);
+            first_frame_at = first_frame.timestamp;
+            last_frame_at = first_frame.timestamp;
 
-            let output_file = create_output_file(output_path, monitor_id);
-            info!(
-                "Starting new video chunk: {} for monitor {}",
-                output_file, monitor_id
-            );
+            let output_file = create_output_file(output_path, &device_name);
+            info!("Starting new video chunk: {} for {}", output_file, source);
             new_chunk_callback(&output_file);
 
-            match start_ffmpeg_process(&output_file, fps).await {
+            match start_ffmpeg_process(&output_file, fps, encoding_profile).await {
                 Ok(mut child) => {
                     let mut stdin = child.stdin.take().expect("Failed to open stdin");
                     spawn_ffmpeg_loggers(child.stderr.take(), child.stdout.take());
 
-                    debug!("Writing first frame to FFmpeg for monitor {}", monitor_id);
+                    debug!("Writing first frame to FFmpeg for {}", source);
                     if let Err(e) = write_frame_to_ffmpeg(&mut stdin, &buffer).await {
                         error!(
-                            "Failed to write first frame to ffmpeg for monitor {}: {}",
-                            monitor_id, e
+                            "Failed to write first frame to ffmpeg for {}: {}",
+                            source, e
                         );
                         continue;
                     }
@@ -471,16 +543,14 @@ async fn save_frames_as_video(
 
                     current_ffmpeg = Some(child);
                     current_stdin = Some(stdin);
+                    current_file = output_file.clone();
                     info!(
-                        "New FFmpeg process started for file: {} (monitor {})",
-                        output_file, monitor_id
+                        "New FFmpeg process started for file: {} ({})",
+                        output_file, source
                     );
                 }
                 Err(e) => {
-                    error!(
-                        "Failed to start FFmpeg process for monitor {}: {}",
-                        monitor_id, e
-                    );
+                    error!("Failed to start FFmpeg process for {}: {}", source, e);
                     continue;
                 }
             }
@@ -495,22 +565,26 @@ async fn save_frames_as_video(
                 0.0
             };
             info!(
-                "Video stats for monitor {}: processed {} frames in {} chunks over {}s ({:.2} avg fps)",
-                monitor_id, frames_total, chunks_total, runtime, fps_avg
+                "Video stats for {}: processed {} frames in {} chunks over {}s ({:.2} avg fps)",
+                source, frames_total, chunks_total, runtime, fps_avg
             );
             last_stats_time = now;
         }
 
         debug!(
-            "Processing frames for monitor {}, current count: {}/{}",
-            monitor_id, frame_count, frames_per_video
+            "Processing frames for {}, current count: {}/{}",
+            source, frame_count, frames_per_video
         );
+        let chunk_deadline = real_time.then(|| first_frame_at + video_chunk_duration);
         process_frames(
             frame_queue,
             &mut current_stdin,
             &mut frame_count,
             frames_per_video,
+            chunk_deadline,
+            &mut last_frame_at,
             fps,
+            source_finished,
         )
         .await;
 
@@ -527,11 +601,15 @@ async fn save_frames_as_video(
 
 async fn wait_for_first_frame(
     frame_queue: &Arc<ArrayQueue<Arc<CaptureResult>>>,
-) -> Arc<CaptureResult> {
+    source_finished: &AtomicBool,
+) -> Option<Arc<CaptureResult>> {
     loop {
         if let Some(result) = frame_queue.pop() {
             debug!("Got first frame for new chunk");
-            return result;
+            return Some(result);
+        }
+        if source_finished.load(Ordering::SeqCst) && frame_queue.is_empty() {
+            return None;
         }
         tokio::time::sleep(Duration::from_millis(10)).await;
     }
@@ -546,11 +624,11 @@ fn encode_frame(frame: &CaptureResult) -> Vec<u8> {
     buffer
 }
 
-fn create_output_file(output_path: &str, monitor_id: u32) -> String {
+fn create_output_file(output_path: &str, device_name: &str) -> String {
     let time = Utc::now();
     let formatted_time = time.format("%Y-%m-%d_%H-%M-%S").to_string();
     PathBuf::from(output_path)
-        .join(format!("monitor_{}_{}.mp4", monitor_id, formatted_time))
+        .join(format!("{}_{}.mp4", device_name, formatted_time))
         .to_str()
         .expect("Failed to create valid path")
         .to_string()
@@ -565,16 +643,23 @@ fn spawn_ffmpeg_loggers(stderr: Option<ChildStderr>, stdout: Option<ChildStdout>
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_frames(
     frame_queue: &Arc<ArrayQueue<Arc<CaptureResult>>>,
     current_stdin: &mut Option<ChildStdin>,
     frame_count: &mut usize,
     frames_per_video: usize,
+    chunk_deadline: Option<Instant>,
+    last_frame_at: &mut Instant,
     fps: f64,
+    source_finished: &AtomicBool,
 ) {
     let write_timeout = Duration::from_secs_f64(1.0 / fps);
-    while *frame_count < frames_per_video {
+    while *frame_count < frames_per_video
+        && !chunk_deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    {
         if let Some(frame) = frame_queue.pop() {
+            *last_frame_at = frame.timestamp;
             let buffer = encode_frame(&frame);
             if let Some(stdin) = current_stdin.as_mut() {
                 if let Err(e) = write_frame_with_retry(stdin, &buffer).await {
@@ -586,6 +671,9 @@ async fn process_frames(
 
                 flush_ffmpeg_input(stdin, *frame_count, fps).await;
             }
+        } else if source_finished.load(Ordering::SeqCst) {
+            // the chunk ends early with the last frame of a finite source
+            break;
         } else {
             tokio::time::sleep(write_timeout).await;
         }
@@ -632,6 +720,50 @@ async fn flush_ffmpeg_input(stdin: &mut ChildStdin, frame_count: usize, fps: f64
     }
 }
 
+/// Rate the frames of a chunk were captured at, None when it is close enough to the rate the
+/// chunk was encoded at or there are too few frames to tell
+fn captured_fps(
+    frame_count: usize,
+    first_frame_at: Instant,
+    last_frame_at: Instant,
+    encoded_fps: f64,
+) -> Option<f64> {
+    let span = last_frame_at.duration_since(first_frame_at).as_secs_f64();
+    if frame_count < 2 || span <= 0.0 {
+        return None;
+    }
+    let captured_fps = ((frame_count - 1) as f64 / span).min(encoded_fps);
+    ((encoded_fps - captured_fps) / encoded_fps > 0.05).then_some(captured_fps)
+}
+
+/// Rewrites the timestamps of a finished chunk so it plays at the rate its frames were
+/// captured at. Frames stay evenly spaced, `offset_index / fps` still finds them.
+async fn retime_chunk(file: &str, encoded_fps: f64, captured_fps: f64) -> anyhow::Result<()> {
+    let ffmpeg_path = find_ffmpeg_path().ok_or_else(|| anyhow::anyhow!("ffmpeg not found"))?;
+    let retimed = PathBuf::from(file).with_extension("retimed.mp4");
+    let scale = format!("{:.6}", encoded_fps / captured_fps);
+    let output = Command::new(ffmpeg_path)
+        .args(["-y", "-v", "error", "-itsscale", &scale, "-i", file])
+        .args(["-c", "copy"])
+        .arg(&retimed)
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let _ = tokio::fs::remove_file(&retimed).await;
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    tokio::fs::rename(&retimed, file).await?;
+    debug!(
+        "retimed {} from {} to {:.3} fps",
+        file, encoded_fps, captured_fps
+    );
+    Ok(())
+}
+
 pub async fn finish_ffmpeg_process(child: Child, stdin: Option<ChildStdin>) {
     drop(stdin); // Ensure stdin is closed
     match child.wait_with_output().await {
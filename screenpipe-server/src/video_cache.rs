@@ -6,7 +6,7 @@ use screenpipe_core::find_ffmpeg_path;
 use screenpipe_db::{DatabaseManager, FrameData, OCREntry};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -669,20 +669,21 @@ async fn extract_frame(
         (source_fps / 0.05).round() as i64 // 1 frame every 20 seconds for older content
     } else {
         (source_fps / 0.1).round() as i64 // 1 frame every 10 seconds for recent content
-    };
+    }
+    .max(1); // recompressed chunks can have a very low frame rate
 
     debug!(
         "extracting frames with interval {} (source: {}fps, target: {}fps)",
         frame_interval, source_fps, 0.1
     );
 
-    // Calculate which frames to extract
-    let frame_positions: Vec<String> = tasks
+    // Calculate which frames to extract, frames of recompressed chunks can share an offset
+    let frame_positions: BTreeSet<i64> = tasks
         .iter()
         .filter_map(|(frame, _)| {
             // Only select frames that align with our target FPS
             if frame.offset_index % frame_interval == 0 {
-                Some(frame.offset_index.to_string())
+                Some(frame.offset_index)
             } else {
                 None
             }
@@ -695,7 +696,14 @@ async fn extract_frame(
     }
 
     // Join frame numbers with commas and wrap in select filter
-    let select_filter = format!("select='eq(n,{})'", frame_positions.join(")+eq(n,"));
+    let select_filter = format!(
+        "select='eq(n,{})'",
+        frame_positions
+            .iter()
+            .map(|position| position.to_string())
+            .collect::<Vec<_>>()
+            .join(")+eq(n,")
+    );
 
     let mut cmd = Command::new(&ffmpeg);
     cmd.args([
@@ -731,26 +739,32 @@ async fn extract_frame(
     }
 
     let mut processed = 0;
-    let mut entries = tokio::fs::read_dir(temp_dir.path()).await?;
-    let mut all_frames = Vec::new();
-
-    while let Some(entry) = entries.next_entry().await? {
-        let frame_data = tokio::fs::read(entry.path()).await?;
-        all_frames.push(frame_data);
+    // selected frames are written in stream order, frame1.jpg holds the lowest offset
+    let mut frames_by_offset = HashMap::new();
+    for (index, offset_index) in frame_positions.iter().enumerate() {
+        let frame_path = temp_dir.path().join(format!("frame{}.jpg", index + 1));
+        match tokio::fs::read(&frame_path).await {
+            Ok(frame_data) => {
+                frames_by_offset.insert(*offset_index, frame_data);
+            }
+            Err(_) => {
+                debug!("warning: ran out of frames at offset {}", offset_index);
+                break;
+            }
+        }
     }
 
-    debug!("extracted {} frames from video", all_frames.len());
-
-    for (task_index, (chunk, device_data)) in tasks.iter().enumerate() {
-        if task_index >= all_frames.len() {
-            debug!("warning: ran out of frames at index {}", task_index);
-            break;
-        }
+    debug!("extracted {} frames from video", frames_by_offset.len());
 
-        let (frame, _) = &tasks[task_index];
-        let frame_data = &all_frames[task_index];
+    for (chunk, device_data) in &tasks {
+        let Some(frame_data) = frames_by_offset.get(&chunk.offset_index) else {
+            continue;
+        };
         let cache_key = format!("{}||{}", chunk.timestamp, device_data.device_name);
-        debug!("processing frame {} with key {}", task_index, cache_key);
+        debug!(
+            "processing frame at offset {} with key {}",
+            chunk.offset_index, cache_key
+        );
 
         // Store in cache first
         let (response_tx, response_rx) = oneshot::channel();
@@ -777,7 +791,7 @@ async fn extract_frame(
                 error: None,
                 timestamp: chunk.timestamp,
                 frame_data: vec![DeviceFrame {
-                    frame_id: frame.frame_id,
+                    frame_id: chunk.frame_id,
                     device_id: device_data.device_name.clone(),
                     image_data: frame_data.clone(),
                     metadata: FrameMetadata {
@@ -0,0 +1,282 @@
+//! Encoding profiles of the recorded video chunks
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// x264 style speed presets, from fastest to smallest output
+pub const PRESETS: &[&str] = &[
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+];
+
+/// Names accepted by [`EncodingProfile::builtin`]
+pub const BUILTIN_PROFILES: &[&str] = &["default", "compatible", "small", "av1", "vp9", "archive"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Av1,
+    Vp9,
+}
+
+impl VideoCodec {
+    /// ffmpeg encoder used for the codec
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::H265 => "libx265",
+            VideoCodec::Av1 => "libsvtav1",
+            VideoCodec::Vp9 => "libvpx-vp9",
+        }
+    }
+
+    fn max_crf(&self) -> u8 {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => 51,
+            VideoCodec::Av1 | VideoCodec::Vp9 => 63,
+        }
+    }
+
+    /// Pixel formats the encoder takes without conversion
+    fn pixel_formats(&self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &["yuv420p", "yuv422p", "yuv444p"],
+            VideoCodec::Av1 => &["yuv420p", "yuv420p10le"],
+            VideoCodec::H265 | VideoCodec::Vp9 => &[
+                "yuv420p",
+                "yuv422p",
+                "yuv444p",
+                "yuv420p10le",
+                "yuv422p10le",
+                "yuv444p10le",
+            ],
+        }
+    }
+}
+
+/// How frames are encoded into video chunks, recorded with every chunk
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingProfile {
+    pub codec: VideoCodec,
+    /// Constant rate factor, lower values give better quality and bigger files
+    pub crf: u8,
+    /// One of [`PRESETS`], mapped to the closest speed setting of AV1 and VP9
+    pub preset: String,
+    pub pixel_format: String,
+    /// Frames wider or taller than the maximum are scaled down, keeping their aspect ratio
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Factor applied to every frame before encoding, 1.0 keeps the captured size
+    pub scale: f64,
+    /// Frames between two keyframes (GOP size), lower values make frame extraction faster
+    pub keyframe_interval: Option<u32>,
+}
+
+impl Default for EncodingProfile {
+    fn default() -> Self {
+        Self {
+            codec: VideoCodec::H265,
+            crf: 23,
+            preset: "ultrafast".to_string(),
+            pixel_format: "yuv420p".to_string(),
+            max_width: None,
+            max_height: None,
+            scale: 1.0,
+            keyframe_interval: None,
+        }
+    }
+}
+
+impl EncodingProfile {
+    pub fn builtin(name: &str) -> Option<Self> {
+        let default = Self::default();
+        let profile = match name {
+            "default" => default,
+            // plays in every browser and player
+            "compatible" => Self {
+                codec: VideoCodec::H264,
+                preset: "veryfast".to_string(),
+                ..default
+            },
+            "small" => Self {
+                crf: 30,
+                preset: "faster".to_string(),
+                max_width: Some(1920),
+                max_height: Some(1080),
+                keyframe_interval: Some(300),
+                ..default
+            },
+            "av1" => Self {
+                codec: VideoCodec::Av1,
+                crf: 35,
+                preset: "faster".to_string(),
+                keyframe_interval: Some(300),
+                ..default
+            },
+            "vp9" => Self {
+                codec: VideoCodec::Vp9,
+                crf: 36,
+                preset: "veryfast".to_string(),
+                ..default
+            },
+            // storage tier of old recordings, slow to encode but a fraction of the size
+            "archive" => Self {
+                crf: 32,
+                preset: "medium".to_string(),
+                scale: 0.75,
+                max_width: Some(1920),
+                max_height: Some(1080),
+                ..default
+            },
+            _ => return None,
+        };
+        Some(profile)
+    }
+
+    /// Loads a built-in profile by name, or a json profile from a file, e.g.
+    /// `{"codec": "av1", "crf": 32, "max_width": 2560}`
+    pub fn load(spec: &str) -> anyhow::Result<Self> {
+        let profile = match Self::builtin(spec) {
+            Some(profile) => profile,
+            None if Path::new(spec).is_file() => {
+                serde_json::from_str(&std::fs::read_to_string(spec)?)?
+            }
+            None => {
+                return Err(anyhow::anyhow!(
+                    "unknown video profile {}, expected one of {} or a json file",
+                    spec,
+                    BUILTIN_PROFILES.join(", ")
+                ))
+            }
+        };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.crf > self.codec.max_crf() {
+            return Err(anyhow::anyhow!(
+                "video crf must be at most {} for {:?}",
+                self.codec.max_crf(),
+                self.codec
+            ));
+        }
+        if !PRESETS.contains(&self.preset.as_str()) {
+            return Err(anyhow::anyhow!(
+                "unknown video preset {}, expected one of {}",
+                self.preset,
+                PRESETS.join(", ")
+            ));
+        }
+        if !self
+            .codec
+            .pixel_formats()
+            .contains(&self.pixel_format.as_str())
+        {
+            return Err(anyhow::anyhow!(
+                "pixel format {} is not supported by {:?}, expected one of {}",
+                self.pixel_format,
+                self.codec,
+                self.codec.pixel_formats().join(", ")
+            ));
+        }
+        if !(self.scale > 0.0 && self.scale <= 1.0) {
+            return Err(anyhow::anyhow!("video scale must be in (0, 1]"));
+        }
+        if matches!(self.max_width, Some(0)) || matches!(self.max_height, Some(0)) {
+            return Err(anyhow::anyhow!("video max resolution must not be 0"));
+        }
+        if matches!(self.keyframe_interval, Some(0)) {
+            return Err(anyhow::anyhow!("video keyframe interval must not be 0"));
+        }
+        Ok(())
+    }
+
+    /// Filter graph applied before encoding, chroma subsampling needs even dimensions
+    pub fn video_filter(&self) -> String {
+        let mut filters = Vec::new();
+        if self.scale != 1.0 {
+            filters.push(format!("scale=iw*{}:ih*{}", self.scale, self.scale));
+        }
+        if self.max_width.is_some() || self.max_height.is_some() {
+            let width = self.max_width.map_or("iw".to_string(), |w| w.to_string());
+            let height = self.max_height.map_or("ih".to_string(), |h| h.to_string());
+            filters.push(format!(
+                "scale=w='min(iw,{})':h='min(ih,{})':force_original_aspect_ratio=decrease",
+                width, height
+            ));
+        }
+        filters.push("pad=width=ceil(iw/2)*2:height=ceil(ih/2)*2".to_string());
+        filters.join(",")
+    }
+
+    /// ffmpeg output options for the profile, placed between the input and the output file
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let mut args = vec!["-vf".to_string(), self.video_filter()];
+        args.extend(self.encoder_args());
+        args
+    }
+
+    /// ffmpeg output options without the filter graph, for callers adding their own filters
+    /// in front of [`EncodingProfile::video_filter`]
+    pub fn encoder_args(&self) -> Vec<String> {
+        let speed = PRESETS.iter().position(|p| *p == self.preset).unwrap_or(0);
+        let mut args = vec!["-vcodec".to_string(), self.codec.encoder().to_string()];
+
+        let codec_args: Vec<String> = match self.codec {
+            // hvc1 lets QuickTime and Safari play the chunks
+            VideoCodec::H265 => vec!["-tag:v", "hvc1", "-preset", self.preset.as_str()]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            VideoCodec::H264 => vec!["-preset".to_string(), self.preset.clone()],
+            // SVT-AV1 presets go from 0 (slowest) to 13 (fastest)
+            VideoCodec::Av1 => {
+                let preset = [12, 11, 10, 9, 8, 6, 4, 2, 1][speed];
+                vec!["-preset".to_string(), preset.to_string()]
+            }
+            // crf mode of libvpx needs a zero target bitrate
+            VideoCodec::Vp9 => {
+                let (deadline, cpu_used) = [
+                    ("realtime", 8),
+                    ("realtime", 7),
+                    ("realtime", 6),
+                    ("realtime", 5),
+                    ("good", 4),
+                    ("good", 2),
+                    ("good", 1),
+                    ("good", 0),
+                    ("best", 0),
+                ][speed];
+                vec![
+                    "-deadline".to_string(),
+                    deadline.to_string(),
+                    "-cpu-used".to_string(),
+                    cpu_used.to_string(),
+                    "-row-mt".to_string(),
+                    "1".to_string(),
+                    "-b:v".to_string(),
+                    "0".to_string(),
+                ]
+            }
+        };
+        args.extend(codec_args);
+
+        args.extend(["-crf".to_string(), self.crf.to_string()]);
+        if let Some(interval) = self.keyframe_interval {
+            args.extend(["-g".to_string(), interval.to_string()]);
+        }
+        args.extend(["-pix_fmt".to_string(), self.pixel_format.clone()]);
+        args
+    }
+}
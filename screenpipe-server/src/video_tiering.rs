@@ -0,0 +1,249 @@
+//! Storage tiering of old recordings: video chunks past an age are re-encoded with a smaller
+//! profile and fewer frames, then swapped in place of the original file
+
+use crate::video_encoding::EncodingProfile;
+use crate::video_utils::get_video_fps;
+use anyhow::Result;
+use chrono::Utc;
+use screenpipe_core::find_ffmpeg_path;
+use screenpipe_db::{DatabaseManager, VideoChunk};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{debug, error, info, warn};
+
+pub const DEFAULT_TIERING_PROFILE: &str = "archive";
+pub const DEFAULT_FRAME_STEP: u32 = 2;
+
+/// How often the tiering job looks for chunks to recompress
+const TIERING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Original files are kept a while after the swap for readers that looked them up before it,
+/// then removed by the next pass, or the first pass after a restart
+const OLD_FILE_GRACE: Duration = Duration::from_secs(5 * 60);
+const BATCH_SIZE: i64 = 50;
+
+#[derive(Clone, Debug)]
+pub struct TieringConfig {
+    /// Chunks whose last frame is older than this are recompressed
+    pub older_than: chrono::Duration,
+    pub profile: EncodingProfile,
+    /// One of every `frame_step` frames is kept, 1 keeps all of them
+    pub frame_step: u32,
+}
+
+impl TieringConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.older_than <= chrono::Duration::zero() {
+            return Err(anyhow::anyhow!("tiering age must be positive"));
+        }
+        if self.frame_step == 0 {
+            return Err(anyhow::anyhow!("tiering frame step must be at least 1"));
+        }
+        self.profile.validate()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct TieringReport {
+    pub chunks_recompressed: usize,
+    pub chunks_failed: usize,
+    /// Original files removed once their grace period was over
+    pub files_removed: usize,
+    /// Space given back by the removed files, net of their recompressed replacement
+    pub bytes_reclaimed: u64,
+}
+
+/// Space a swap gives back once the original is removed, a chunk growing when re-encoded
+/// is not reported as negative space
+pub fn reclaimable_bytes(bytes_before: u64, bytes_after: u64) -> u64 {
+    bytes_before.saturating_sub(bytes_after)
+}
+
+/// Runs a tiering pass every hour until the process exits
+pub async fn run_video_tiering(db: Arc<DatabaseManager>, config: TieringConfig) {
+    info!(
+        "starting video tiering of chunks older than {} days",
+        config.older_than.num_days()
+    );
+    loop {
+        match recompress_old_chunks(&db, &config).await {
+            Ok(report)
+                if report.chunks_recompressed > 0
+                    || report.chunks_failed > 0
+                    || report.files_removed > 0 =>
+            {
+                info!(
+                    "video tiering recompressed {} chunks ({} failed), removed {} originals, reclaimed {:.1} MB",
+                    report.chunks_recompressed,
+                    report.chunks_failed,
+                    report.files_removed,
+                    report.bytes_reclaimed as f64 / 1_000_000.0
+                )
+            }
+            Ok(_) => debug!("video tiering found no chunk to recompress"),
+            Err(e) => error!("video tiering failed: {}", e),
+        }
+        tokio::time::sleep(TIERING_INTERVAL).await;
+    }
+}
+
+/// Removes the originals whose grace period is over, then recompresses every chunk older
+/// than the configured age. A chunk that fails is left as is and picked again by the next pass.
+pub async fn recompress_old_chunks(
+    db: &DatabaseManager,
+    config: &TieringConfig,
+) -> Result<TieringReport> {
+    let mut report = TieringReport::default();
+    remove_replaced_files(db, &mut report).await?;
+
+    let ffmpeg_path =
+        find_ffmpeg_path().ok_or_else(|| anyhow::anyhow!("failed to find ffmpeg path"))?;
+    let profile_json = serde_json::to_string(&config.profile)?;
+    let before = Utc::now() - config.older_than;
+    let mut after_id = 0;
+
+    loop {
+        let chunks = db
+            .get_video_chunks_to_recompress(before, after_id, BATCH_SIZE)
+            .await?;
+        let Some(last) = chunks.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for chunk in chunks {
+            match recompress_chunk(&ffmpeg_path, db, &chunk, config, &profile_json).await {
+                Ok((bytes_before, bytes_after)) => {
+                    debug!(
+                        "recompressed {} from {} to {} bytes",
+                        chunk.file_path, bytes_before, bytes_after
+                    );
+                    report.chunks_recompressed += 1;
+                }
+                Err(e) => {
+                    warn!("failed to recompress {}: {}", chunk.file_path, e);
+                    report.chunks_failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Deletes the replaced files that are due, a file already gone counts as removed
+async fn remove_replaced_files(db: &DatabaseManager, report: &mut TieringReport) -> Result<()> {
+    for (file_path, reclaimed_bytes) in db.get_due_file_deletions(Utc::now()).await? {
+        match tokio::fs::remove_file(&file_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("failed to remove recompressed chunk {}: {}", file_path, e);
+                continue;
+            }
+        }
+        db.remove_pending_file_deletion(&file_path).await?;
+        report.files_removed += 1;
+        report.bytes_reclaimed += reclaimed_bytes.max(0) as u64;
+    }
+    Ok(())
+}
+
+/// Path of the recompressed file, next to the original
+fn tiered_path(file_path: &str) -> PathBuf {
+    let path = Path::new(file_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}_tiered.mp4", stem))
+}
+
+async fn recompress_chunk(
+    ffmpeg_path: &PathBuf,
+    db: &DatabaseManager,
+    chunk: &VideoChunk,
+    config: &TieringConfig,
+    profile_json: &str,
+) -> Result<(u64, u64)> {
+    let old_metadata = tokio::fs::metadata(&chunk.file_path).await?;
+    let source_fps = get_video_fps(ffmpeg_path, &chunk.file_path).await?;
+    let fps = source_fps / config.frame_step as f64;
+    let new_path = tiered_path(&chunk.file_path);
+
+    // kept frames are retimed to the lower rate so frame n of the new file is frame
+    // n * frame_step of the original, at the same position in time
+    let mut filters = Vec::new();
+    if config.frame_step > 1 {
+        filters.push(format!("select='not(mod(n\\,{}))'", config.frame_step));
+        filters.push(format!("setpts=N/({}*TB)", fps));
+    }
+    filters.push(config.profile.video_filter());
+
+    let mut args = vec![
+        "-y".to_string(),
+        "-v".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        chunk.file_path.clone(),
+        "-vf".to_string(),
+        filters.join(","),
+        "-r".to_string(),
+        fps.to_string(),
+    ];
+    args.extend(config.profile.encoder_args());
+    args.extend(["-an".to_string(), "-f".to_string(), "mp4".to_string()]);
+    args.push(new_path.to_string_lossy().into_owned());
+
+    debug!("recompressing {} with args {:?}", chunk.file_path, args);
+    let output = Command::new(ffmpeg_path)
+        .args(&args)
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let _ = tokio::fs::remove_file(&new_path).await;
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    // the frame cache skips files modified in the last minute as still being recorded
+    if let Ok(modified) = old_metadata.modified() {
+        if let Err(e) = OpenOptions::new()
+            .write(true)
+            .open(&new_path)
+            .and_then(|file| file.set_modified(modified))
+        {
+            debug!(
+                "failed to keep modification time of {}: {}",
+                chunk.file_path, e
+            );
+        }
+    }
+    let new_size = tokio::fs::metadata(&new_path).await?.len();
+
+    // readers pick either the old file with the old offsets or the new file with the new ones
+    let delete_old_after = Utc::now() + chrono::Duration::from_std(OLD_FILE_GRACE)?;
+    let reclaimed = reclaimable_bytes(old_metadata.len(), new_size);
+    if let Err(e) = db
+        .swap_video_chunk_file(
+            chunk.id,
+            &new_path.to_string_lossy(),
+            Some(profile_json),
+            config.frame_step as i64,
+            delete_old_after,
+            reclaimed as i64,
+        )
+        .await
+    {
+        let _ = tokio::fs::remove_file(&new_path).await;
+        return Err(e.into());
+    }
+
+    Ok((old_metadata.len(), new_size))
+}
@@ -55,7 +55,9 @@ pub async fn extract_frame(file_path: &str, offset_index: i64) -> Result<String>
             "-i",
             file_path,
             "-vf",
-            "scale=iw*0.75:ih*0.75", // Scale down to 75% of original size
+            // Scale down to 75% of original size, 10 bit and 4:4:4 chunks need the
+            // conversion to a pixel format mjpeg can encode
+            "scale=iw*0.75:ih*0.75,format=yuvj420p",
             "-vframes",
             "1",
             "-f",
@@ -246,7 +248,7 @@ pub async fn extract_frames_from_video(
     };
 
     let target_fps = if source_fps > 10.0 { 1.0 } else { source_fps };
-    let fps_filter = format!("fps={}", target_fps);
+    let fps_filter = format!("fps={},format=yuvj420p", target_fps);
 
     // Extract frames using ffmpeg
     let status = Command::new(&ffmpeg_path)
@@ -307,7 +309,28 @@ pub async fn extract_frames_from_video(
     Ok(frames)
 }
 
-async fn get_video_fps(ffmpeg_path: &PathBuf, video_path: &str) -> Result<f64> {
+/// Seconds into the chunk of the frame at `offset_index`. Frames are evenly spaced at the
+/// chunk frame rate, recompressed chunks have a lower rate and offsets divided to match
+pub(crate) async fn frame_offset_seconds(
+    ffmpeg_path: &PathBuf,
+    file_path: &str,
+    offset_index: i64,
+) -> f64 {
+    let fps = match get_video_fps(ffmpeg_path, file_path).await {
+        Ok(fps) if fps > 0.0 => fps,
+        Ok(fps) => {
+            error!("invalid video fps {}, using default 1fps", fps);
+            1.0
+        }
+        Err(e) => {
+            error!("failed to get video fps, using default 1fps: {}", e);
+            1.0
+        }
+    };
+    offset_index as f64 / fps
+}
+
+pub(crate) async fn get_video_fps(ffmpeg_path: &PathBuf, video_path: &str) -> Result<f64> {
     let ffprobe_path = ffmpeg_path.with_file_name("ffprobe");
 
     let output = Command::new(&ffprobe_path)
@@ -557,15 +580,7 @@ impl VideoMetadataOverride {
 pub async fn extract_frame_from_video(file_path: &str, offset_index: i64) -> Result<String> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
 
-    let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
-        Ok(fps) => fps,
-        Err(e) => {
-            error!("failed to get video fps, using default 1fps: {}", e);
-            1.0
-        }
-    };
-
-    let offset_seconds = offset_index as f64 * source_fps;
+    let offset_seconds = frame_offset_seconds(&ffmpeg_path, file_path, offset_index).await;
     let offset_str = format!("{:.3}", offset_seconds);
 
     // Create a temporary directory for frames if it doesn't exist
@@ -577,10 +592,9 @@ pub async fn extract_frame_from_video(file_path: &str, offset_index: i64) -> Res
     let output_path = frames_dir.join(&frame_filename);
 
     debug!(
-        "extracting frame from {} at offset {} and fps {} to {}",
+        "extracting frame from {} at offset {} to {}",
         file_path,
         offset_str,
-        source_fps,
         output_path.display()
     );
 
@@ -661,15 +675,7 @@ pub async fn extract_high_quality_frame(
 ) -> Result<String> {
     let ffmpeg_path = find_ffmpeg_path().expect("failed to find ffmpeg path");
 
-    let source_fps = match get_video_fps(&ffmpeg_path, file_path).await {
-        Ok(fps) => fps,
-        Err(e) => {
-            error!("failed to get video fps, using default 1fps: {}", e);
-            1.0
-        }
-    };
-
-    let frame_time = offset_index as f64 * source_fps;
+    let frame_time = frame_offset_seconds(&ffmpeg_path, file_path, offset_index).await;
 
     let frame_filename = format!(
         "frame_{}_{}.png",
@@ -1,11 +1,14 @@
 #[cfg(test)]
 mod tests {
+    use arrow_array::{Array, BooleanArray, RecordBatch, StringArray, TimestampMicrosecondArray};
+    use arrow_schema::{DataType, TimeUnit};
     use axum::body::to_bytes;
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use axum::Router;
     use chrono::DateTime;
     use chrono::{Duration, Utc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
     use screenpipe_audio::audio_manager::AudioManagerBuilder;
     use screenpipe_db::{ContentType, DatabaseManager, SearchResult};
     use screenpipe_server::PipeManager;
@@ -28,7 +31,16 @@ mod tests {
         let _ = env_logger::builder().is_test(true).try_init();
     }
 
+    const SYNC_SECRET: &str = "shared-sync-secret";
+
     async fn setup_test_app() -> (Router, Arc<DatabaseManager>) {
+        setup_test_app_in(PathBuf::from(""), Vec::new()).await
+    }
+
+    async fn setup_test_app_in(
+        screenpipe_dir: PathBuf,
+        sync_peers: Vec<String>,
+    ) -> (Router, Arc<DatabaseManager>) {
         let db = Arc::new(DatabaseManager::new("sqlite::memory:").await.unwrap());
 
         let audio_manager = Arc::new(
@@ -42,19 +54,790 @@ mod tests {
         let app = SCServer::new(
             db.clone(),
             SocketAddr::from(([127, 0, 0, 1], 23948)),
-            PathBuf::from(""),
+            screenpipe_dir,
             Arc::new(PipeManager::new(PathBuf::from(""))),
             false,
             false,
             false,
             audio_manager,
-        );
+        )
+        .with_sync(sync_peers, Some(SYNC_SECRET.to_string()));
 
         let router = app.create_router(true).await;
         init();
         (router, db)
     }
 
+    #[tokio::test]
+    async fn test_search_query_language() {
+        let (app, db) = setup_test_app().await;
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.wav").await.unwrap();
+        db.insert_audio_transcription(
+            audio_chunk_id,
+            "the quarterly plan (draft) is ready",
+            0,
+            "",
+            &screenpipe_db::AudioDevice {
+                name: "test".to_string(),
+                device_type: screenpipe_db::DeviceType::Input,
+            },
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // punctuation is escaped and type: picks the content type
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=%22plan%20(draft)%22%20type:audio")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let search_response: PaginatedResponse<ContentItem> =
+            serde_json::from_slice(&body).unwrap();
+        assert_eq!(search_response.data.len(), 1);
+
+        // malformed queries are rejected with the position of the error
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=plan%20after:yesterday")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error["position"], 12);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_facets() {
+        let (app, db) = setup_test_app().await;
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.wav").await.unwrap();
+        for (offset, text) in ["budget review", "budget plan"].into_iter().enumerate() {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                text,
+                offset as i64,
+                "",
+                &screenpipe_db::AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: screenpipe_db::DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=budget&content_type=audio&facets=true&facet_interval=hour")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let search_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let facets = &search_response["facets"];
+        assert_eq!(facets["device"][0]["value"], "mic");
+        assert_eq!(facets["device"][0]["count"], 2);
+        assert_eq!(facets["content_type"][0]["value"], "audio");
+
+        // facets are only computed when asked for
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=budget&content_type=audio")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let search_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(search_response.get("facets").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_with_cursor() {
+        let (app, db) = setup_test_app().await;
+
+        let audio_chunk_id = db.insert_audio_chunk("test_audio.wav").await.unwrap();
+        for offset in 0..3 {
+            db.insert_audio_transcription(
+                audio_chunk_id,
+                &format!("standup note {}", offset),
+                offset,
+                "",
+                &screenpipe_db::AudioDevice {
+                    name: "mic".to_string(),
+                    device_type: screenpipe_db::DeviceType::Input,
+                },
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let mut seen = 0;
+        let mut cursor = String::new();
+        loop {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!(
+                            "/search?q=standup&content_type=audio&limit=2&cursor={}",
+                            cursor
+                        ))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let page: PaginatedResponse<ContentItem> = serde_json::from_slice(&body).unwrap();
+            seen += page.data.len();
+            assert_eq!(page.pagination.total, 3);
+            match page.pagination.next_cursor {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        assert_eq!(seen, 3);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=standup&cursor=zz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_snippets() {
+        let (app, db) = setup_test_app().await;
+
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "test_device")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("test_device", None, None, None, None, true)
+            .await
+            .unwrap();
+        let text_json = r#"[
+            {"block_num":"1","conf":"91.0","page_num":"1","left":"10","height":"12","level":"5","text":"the","par_num":"1","top":"40","word_num":"1","width":"30","line_num":"1"},
+            {"block_num":"1","conf":"88.0","page_num":"1","left":"45","height":"12","level":"5","text":"budget","par_num":"1","top":"40","word_num":"2","width":"60","line_num":"1"}
+        ]"#;
+        db.insert_ocr_text(
+            frame_id,
+            "File Edit View Window Help we discussed the budget with the whole team today",
+            text_json,
+            Arc::new(OcrEngine::Tesseract.into()),
+        )
+        .await
+        .unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=budget&content_type=ocr&snippets=true&snippet_tokens=4&highlight_start=%3Cb%3E&highlight_end=%3C%2Fb%3E")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: PaginatedResponse<ContentItem> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page.data.len(), 1);
+        match &page.data[0] {
+            ContentItem::OCR(ocr) => {
+                assert_eq!(ocr.snippet.as_deref(), Some("…the <b>budget</b> with the…"));
+                let positions = ocr.text_positions.as_ref().unwrap();
+                assert_eq!(positions.len(), 1);
+                assert_eq!(positions[0].text, "budget");
+                assert_eq!(positions[0].bounds.left, 45.0);
+            }
+            _ => panic!("expected an ocr result"),
+        }
+
+        // snippets are opt-in
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=budget&content_type=ocr")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: PaginatedResponse<ContentItem> = serde_json::from_slice(&body).unwrap();
+        match &page.data[0] {
+            ContentItem::OCR(ocr) => {
+                assert!(ocr.snippet.is_none());
+                assert!(ocr.text_positions.is_none());
+            }
+            _ => panic!("expected an ocr result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_analytics() {
+        let (app, db) = setup_test_app().await;
+
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let timestamp = DateTime::parse_from_rfc3339("2026-09-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (app_name, url, seconds) in [
+            ("Arc", Some("https://github.com/org/repo"), 0),
+            ("Slack", None, 30),
+            ("Arc", None, 40),
+        ] {
+            db.insert_frame(
+                "screen_1",
+                Some(timestamp + Duration::seconds(seconds)),
+                url,
+                Some(app_name),
+                Some("main"),
+                false,
+            )
+            .await
+            .unwrap();
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/analytics/usage?start_time=2026-09-01T00:00:00Z&end_time=2026-09-02T00:00:00Z&group_by=domain")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // the last frame has no next one yet
+        assert_eq!(usage["total_seconds"], 40.0);
+        assert_eq!(usage["items"][0]["value"], "github.com");
+        assert_eq!(usage["items"][0]["seconds"], 30.0);
+        assert_eq!(usage["histogram"][0]["start"], "2026-09-01T00:00:00Z");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/analytics/usage/speakers")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(usage["total_seconds"], 0.0);
+        assert!(usage["speakers"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_activity_sessions() {
+        let (app, db) = setup_test_app().await;
+
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let start = Utc::now() - Duration::hours(2);
+        for (app_name, seconds) in [("Code", 0), ("Code", 30), ("Slack", 1000), ("Slack", 1030)] {
+            db.insert_frame(
+                "screen_1",
+                Some(start + Duration::seconds(seconds)),
+                None,
+                Some(app_name),
+                Some("main"),
+                false,
+            )
+            .await
+            .unwrap();
+        }
+        db.sessionize_activity(&Default::default()).await.unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions?app_name=Code")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let sessions: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(sessions["pagination"]["total"], 1);
+        assert_eq!(sessions["data"][0]["app_name"], "Code");
+        assert_eq!(sessions["data"][0]["closed"], true);
+        let session_id = sessions["data"][0]["id"].as_i64().unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/sessions/{}", session_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let session: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(session["session"]["frame_count"], 2);
+        assert!(session["transcriptions"].as_array().unwrap().is_empty());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sessions/999")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_archive_endpoints() {
+        let screenpipe_dir = std::env::temp_dir().join(format!(
+            "screenpipe_archive_endpoint_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let (app, db) = setup_test_app_in(screenpipe_dir.clone(), Vec::new()).await;
+
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        let frame_id = db
+            .insert_frame("screen_1", None, None, Some("Code"), Some("main"), false)
+            .await
+            .unwrap();
+        db.insert_ocr_text(frame_id, "archive me", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+        let export = |path: &str, filter: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/archive/export")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"path": path, "filter": filter}).to_string(),
+                ))
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(export(
+                "backup",
+                serde_json::json!({"content_types": ["ocr"]}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let tables = manifest["tables"].as_array().unwrap();
+        let frames = tables.iter().find(|t| t["name"] == "frames").unwrap();
+        assert_eq!(frames["rows"], 1);
+        assert!(tables.iter().all(|t| t["name"] != "audio_transcriptions"));
+        assert_eq!(manifest["missing_media"][0], "test_video.mp4");
+        assert!(screenpipe_dir
+            .join("archives/backup")
+            .join(screenpipe_db::ARCHIVE_MANIFEST)
+            .exists());
+
+        // archives stay in the archives directory
+        let outside = std::env::temp_dir().join("screenpipe_archive_outside");
+        for path in ["../backup", outside.to_str().unwrap(), ""] {
+            let response = app
+                .clone()
+                .oneshot(export(path, serde_json::json!({})))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(!outside.exists());
+
+        // an archive is never overwritten
+        let response = app
+            .clone()
+            .oneshot(export("backup", serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/archive/import")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({"path": "backup"}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        // the archive comes from this database, every row is already there
+        let tables = report["tables"].as_array().unwrap();
+        assert!(tables.iter().all(|t| t["inserted"] == 0));
+        let frames = tables.iter().find(|t| t["name"] == "frames").unwrap();
+        assert_eq!(frames["duplicates"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_enroll_speaker_rejects_bad_samples() {
+        let screenpipe_dir = std::env::temp_dir().join(format!(
+            "screenpipe_enroll_endpoint_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        std::fs::create_dir_all(screenpipe_dir.join("data")).unwrap();
+        std::fs::write(screenpipe_dir.join("data/notes.wav"), "not audio").unwrap();
+        let (app, _db) = setup_test_app_in(screenpipe_dir.clone(), Vec::new()).await;
+        let enroll = |audio_path: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/speakers/enroll")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"name": "Alice", "audio_path": audio_path}).to_string(),
+                ))
+                .unwrap()
+        };
+
+        // samples are read from the data directory only
+        let outside = screenpipe_dir.join("notes.wav");
+        for path in ["../notes.wav", outside.to_str().unwrap(), "", "missing.wav"] {
+            let response = app.clone().oneshot(enroll(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        // a file that does not decode is the caller's mistake
+        let response = app.clone().oneshot(enroll("notes.wav")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(error["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid enrollment sample"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_between_instances() {
+        let (laptop_app, laptop_db) = setup_test_app().await;
+        // the laptop serves its change feed over http
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let laptop_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, laptop_app).await.unwrap() });
+        let (desktop_app, desktop_db) =
+            setup_test_app_in(PathBuf::from(""), vec![laptop_url.clone()]).await;
+        let dir = std::env::temp_dir().join(format!(
+            "screenpipe_sync_endpoint_{}",
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video_path = dir.join("monitor_1.mp4");
+        std::fs::write(&video_path, b"video").unwrap();
+        // a finished chunk, not written to for a while
+        std::fs::File::options()
+            .write(true)
+            .open(&video_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(600))
+            .unwrap();
+        laptop_db
+            .insert_video_chunk(video_path.to_str().unwrap(), "screen_1")
+            .await
+            .unwrap();
+        let frame_id = laptop_db
+            .insert_frame("screen_1", None, None, Some("Code"), Some("main"), false)
+            .await
+            .unwrap();
+        laptop_db
+            .insert_ocr_text(frame_id, "synced text", "", Arc::new(OcrEngine::Tesseract))
+            .await
+            .unwrap();
+
+        // the change feed is only served with the shared secret
+        let client = screenpipe_server::sync::sync_client();
+        for secret in [None, Some("guess")] {
+            let mut request = client.get(format!("{}/sync/changes", laptop_url));
+            if let Some(secret) = secret {
+                request = request.bearer_auth(secret);
+            }
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        }
+        let response = client
+            .get(format!("{}/sync/media?table=video_chunks&id=1", laptop_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let media_dir = dir.join("data");
+        let report = screenpipe_server::sync::sync_from_peer(
+            &desktop_db,
+            &client,
+            &laptop_url,
+            SYNC_SECRET,
+            &media_dir,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.media_copied, 1);
+        let frames = report.tables.iter().find(|t| t.name == "frames").unwrap();
+        assert_eq!(frames.inserted, 1);
+
+        let (machine_id, file_path): (String, String) = sqlx::query_as(
+            "SELECT f.machine_id, c.file_path FROM frames f JOIN video_chunks c ON c.id = f.video_chunk_id",
+        )
+        .fetch_one(&desktop_db.pool)
+        .await
+        .unwrap();
+        assert_eq!(machine_id, laptop_db.database_id().await.unwrap());
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"video");
+
+        let response = desktop_app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sync/peers")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let peers: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(peers[0]["url"], laptop_url);
+        assert!(peers[0]["watermark"].as_i64().unwrap() > 0);
+        assert!(peers[0]["last_error"].is_null());
+
+        // pulling again through the api finds nothing new
+        let response = desktop_app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sync/pull")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({"url": laptop_url}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(report["tables"].as_array().unwrap().is_empty());
+
+        // only the configured peers can be pulled from
+        let response = desktop_app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sync/pull")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({"url": "http://169.254.169.254"}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeline_export() {
+        let (app, db) = setup_test_app().await;
+
+        let _ = db
+            .insert_video_chunk("test_video.mp4", "screen_1")
+            .await
+            .unwrap();
+        for text in ["hello, world", "plain", "=HYPERLINK(\"http://x\")"] {
+            let frame_id = db
+                .insert_frame("screen_1", None, None, Some("Code"), Some("main"), true)
+                .await
+                .unwrap();
+            db.insert_ocr_text(frame_id, text, "", Arc::new(OcrEngine::Tesseract))
+                .await
+                .unwrap();
+        }
+
+        let export = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+        let response = export("/export/timeline?table=frames&format=csv")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-disposition"],
+            "attachment; filename=\"frames.csv\""
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "id,timestamp,device_name,app_name,window_name,browser_url,focused,video_chunk_id,offset_index,machine_id,ocr_text,ocr_engine"
+        );
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("1,"));
+        assert!(lines[1].contains(",screen_1,Code,main,,true,"));
+        assert!(lines[1].contains(",\"hello, world\","));
+        assert!(lines[2].contains(",plain,"));
+        // a spreadsheet opening the file doesn't run the text as a formula
+        assert!(lines[3].contains(",\"'=HYPERLINK(\"\"http://x\"\")\","));
+
+        // an empty range still has the header
+        let response = export("/export/timeline?table=ui_monitoring&format=csv")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "id,timestamp,app,window,text,text_length,initial_traversal_at,machine_id\n"
+        );
+
+        let response = export("/export/timeline?table=frames").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/vnd.apache.parquet"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(body)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+        let schema = batch.schema();
+        let data_type = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+        assert_eq!(
+            data_type("timestamp"),
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+        );
+        assert_eq!(data_type("browser_url"), DataType::Utf8);
+        assert_eq!(data_type("focused"), DataType::Boolean);
+        assert_eq!(data_type("id"), DataType::Int64);
+
+        let timestamps: Vec<DateTime<Utc>> =
+            sqlx::query_scalar("SELECT timestamp FROM frames ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        let column = batch.column_by_name("timestamp").unwrap();
+        let exported = column
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(
+            exported.values().to_vec(),
+            timestamps
+                .iter()
+                .map(|timestamp| timestamp.timestamp_micros())
+                .collect::<Vec<_>>()
+        );
+        let browser_urls = batch.column_by_name("browser_url").unwrap();
+        assert_eq!(browser_urls.null_count(), 3);
+        let column = batch.column_by_name("focused").unwrap();
+        let focused = column.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(focused.null_count(), 0);
+        assert!(focused.values().iter().all(|focused| focused));
+        let column = batch.column_by_name("ocr_text").unwrap();
+        let texts = column.as_any().downcast_ref::<StringArray>().unwrap();
+        // only the CSV export prefixes formulas
+        assert_eq!(
+            texts.iter().collect::<Vec<_>>(),
+            vec![
+                Some("hello, world"),
+                Some("plain"),
+                Some("=HYPERLINK(\"http://x\")")
+            ]
+        );
+
+        let response = export("/export/timeline?table=frames&format=xml")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_search_audio_with_length_constraints() {
         let (app, db) = setup_test_app().await;
@@ -0,0 +1,129 @@
+use screenpipe_server::video_encoding::{EncodingProfile, VideoCodec, BUILTIN_PROFILES};
+
+#[test]
+fn test_default_profile_keeps_legacy_args() {
+    let args = EncodingProfile::default().ffmpeg_args();
+    assert_eq!(
+        args,
+        vec![
+            "-vf",
+            "pad=width=ceil(iw/2)*2:height=ceil(ih/2)*2",
+            "-vcodec",
+            "libx265",
+            "-tag:v",
+            "hvc1",
+            "-preset",
+            "ultrafast",
+            "-crf",
+            "23",
+            "-pix_fmt",
+            "yuv420p",
+        ]
+    );
+}
+
+#[test]
+fn test_builtin_profiles_are_valid() {
+    for name in BUILTIN_PROFILES {
+        let profile = EncodingProfile::load(name).unwrap();
+        assert_eq!(Some(profile), EncodingProfile::builtin(name));
+    }
+    assert!(EncodingProfile::load("not-a-profile").is_err());
+}
+
+#[test]
+fn test_invalid_profiles_are_rejected() {
+    let h264 = EncodingProfile {
+        codec: VideoCodec::H264,
+        ..Default::default()
+    };
+    assert!(EncodingProfile {
+        crf: 52,
+        ..h264.clone()
+    }
+    .validate()
+    .is_err());
+    assert!(EncodingProfile {
+        preset: "instant".to_string(),
+        ..h264.clone()
+    }
+    .validate()
+    .is_err());
+    // 10 bit is not supported by libx264 builds shipped with ffmpeg
+    assert!(EncodingProfile {
+        pixel_format: "yuv420p10le".to_string(),
+        ..h264.clone()
+    }
+    .validate()
+    .is_err());
+    assert!(EncodingProfile {
+        scale: 0.0,
+        ..h264.clone()
+    }
+    .validate()
+    .is_err());
+    assert!(EncodingProfile {
+        keyframe_interval: Some(0),
+        ..h264.clone()
+    }
+    .validate()
+    .is_err());
+
+    // av1 allows a higher crf
+    let av1 = EncodingProfile {
+        codec: VideoCodec::Av1,
+        crf: 60,
+        ..Default::default()
+    };
+    assert!(av1.validate().is_ok());
+}
+
+#[test]
+fn test_video_filter_downscales_and_pads() {
+    let profile = EncodingProfile {
+        scale: 0.5,
+        max_width: Some(1920),
+        ..Default::default()
+    };
+    assert_eq!(
+        profile.video_filter(),
+        "scale=iw*0.5:ih*0.5,\
+         scale=w='min(iw,1920)':h='min(ih,ih)':force_original_aspect_ratio=decrease,\
+         pad=width=ceil(iw/2)*2:height=ceil(ih/2)*2"
+    );
+}
+
+#[test]
+fn test_codec_specific_args() {
+    let vp9 = EncodingProfile::builtin("vp9").unwrap().ffmpeg_args();
+    assert!(vp9.windows(2).any(|w| w == ["-vcodec", "libvpx-vp9"]));
+    assert!(vp9.windows(2).any(|w| w == ["-b:v", "0"]));
+
+    let av1_profile = EncodingProfile::builtin("av1").unwrap();
+    let av1 = av1_profile.ffmpeg_args();
+    assert_eq!(av1[2..], av1_profile.encoder_args()[..]);
+    assert!(av1.windows(2).any(|w| w == ["-vcodec", "libsvtav1"]));
+    assert!(av1.windows(2).any(|w| w == ["-g", "300"]));
+}
+
+#[test]
+fn test_load_profile_from_json_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("profile.json");
+    std::fs::write(&path, r#"{"codec": "h264", "crf": 28, "max_height": 1080}"#).unwrap();
+
+    // missing settings fall back to the defaults
+    let profile = EncodingProfile::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(
+        profile,
+        EncodingProfile {
+            codec: VideoCodec::H264,
+            crf: 28,
+            max_height: Some(1080),
+            ..Default::default()
+        }
+    );
+
+    std::fs::write(&path, r#"{"codec": "h264", "crf": 70}"#).unwrap();
+    assert!(EncodingProfile::load(path.to_str().unwrap()).is_err());
+}
@@ -0,0 +1,124 @@
+use screenpipe_core::find_ffmpeg_path;
+use screenpipe_db::DatabaseManager;
+use screenpipe_server::video_encoding::EncodingProfile;
+use screenpipe_server::video_tiering::{
+    reclaimable_bytes, recompress_old_chunks, TieringConfig, DEFAULT_TIERING_PROFILE,
+};
+use screenpipe_server::video_utils::extract_frame_from_video;
+
+fn tiering_config() -> TieringConfig {
+    TieringConfig {
+        older_than: chrono::Duration::days(30),
+        profile: EncodingProfile::builtin(DEFAULT_TIERING_PROFILE).unwrap(),
+        frame_step: 2,
+    }
+}
+
+#[test]
+fn test_tiering_config_validation() {
+    assert!(tiering_config().validate().is_ok());
+    assert!(TieringConfig {
+        frame_step: 0,
+        ..tiering_config()
+    }
+    .validate()
+    .is_err());
+    assert!(TieringConfig {
+        older_than: chrono::Duration::zero(),
+        ..tiering_config()
+    }
+    .validate()
+    .is_err());
+    assert!(TieringConfig {
+        profile: EncodingProfile {
+            crf: 60,
+            ..Default::default()
+        },
+        ..tiering_config()
+    }
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn test_reclaimable_bytes() {
+    assert_eq!(reclaimable_bytes(10_000, 2_500), 7_500);
+    // a chunk growing when re-encoded is not reported as negative space
+    assert_eq!(reclaimable_bytes(1_000, 1_200), 0);
+}
+
+/// Mean luma of an extracted frame
+fn frame_luma(path: &str) -> f64 {
+    let image = image::open(path).unwrap().to_luma8();
+    image.pixels().map(|pixel| pixel.0[0] as f64).sum::<f64>() / image.pixels().len() as f64
+}
+
+#[tokio::test]
+#[ignore] // needs ffmpeg
+async fn test_frame_seek_survives_recompression() {
+    let dir = tempfile::tempdir().unwrap();
+    let video_path = dir.path().join("monitor_1.mp4");
+    // 10 frames at 2 fps, each one brighter than the one before
+    let status = tokio::process::Command::new(find_ffmpeg_path().unwrap())
+        .args([
+            "-y",
+            "-v",
+            "error",
+            "-f",
+            "lavfi",
+            "-i",
+            "color=c=black:s=64x64:r=2:d=5,geq=lum='N*25':cb=128:cr=128",
+            "-c:v",
+            "libx264",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(&video_path)
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success());
+
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    let video_path = video_path.to_str().unwrap();
+    db.insert_video_chunk(video_path, "screen_1").await.unwrap();
+    let recorded_at = chrono::Utc::now() - chrono::Duration::days(40);
+    let mut frame_ids = Vec::new();
+    for i in 0..10 {
+        let frame_id = db
+            .insert_frame(
+                "screen_1",
+                Some(recorded_at + chrono::Duration::milliseconds(500 * i)),
+                None,
+                Some("Code"),
+                Some("main.rs"),
+                true,
+            )
+            .await
+            .unwrap();
+        frame_ids.push(frame_id);
+    }
+    let frame = |frame_id: i64| {
+        sqlx::query_as::<_, (String, i64)>("SELECT name, offset_index FROM frames WHERE id = ?1")
+            .bind(frame_id)
+            .fetch_one(&db.pool)
+    };
+
+    let (path, offset_index) = frame(frame_ids[6]).await.unwrap();
+    assert_eq!(offset_index, 6);
+    let before = frame_luma(&extract_frame_from_video(&path, offset_index).await.unwrap());
+    assert!((before - 150.0).abs() < 12.0, "luma {}", before);
+
+    let report = recompress_old_chunks(&db, &tiering_config()).await.unwrap();
+    assert_eq!(report.chunks_recompressed, 1);
+    let (path, offset_index) = frame(frame_ids[6]).await.unwrap();
+    assert_ne!(path, video_path);
+    assert_eq!(offset_index, 3);
+    let after = frame_luma(&extract_frame_from_video(&path, offset_index).await.unwrap());
+    assert!(
+        (after - before).abs() < 12.0,
+        "luma {} then {}",
+        before,
+        after
+    );
+}
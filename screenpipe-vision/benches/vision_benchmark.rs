@@ -8,6 +8,7 @@ use std::sync::Arc;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use screenpipe_vision::capture_screenshot_by_window::WindowFilters;
 use screenpipe_vision::monitor::get_default_monitor;
+use screenpipe_vision::ocr_pool::{OcrPool, DEFAULT_OCR_QUEUE_SIZE};
 use screenpipe_vision::{continuous_capture, OcrEngine};
 use tokio::sync::mpsc;
 use tokio::time::Duration;
@@ -16,11 +17,13 @@ async fn benchmark_continuous_capture(duration_secs: u64) -> f64 {
     let (result_tx, mut result_rx) = mpsc::channel(100);
 
     let window_filters = Arc::new(WindowFilters::new(&[], &[]));
+    let ocr_pool = OcrPool::new(1, DEFAULT_OCR_QUEUE_SIZE);
     let capture_handle = tokio::spawn(async move {
         continuous_capture(
             result_tx,
             Duration::from_millis(100),
             OcrEngine::Tesseract,
+            ocr_pool,
             get_default_monitor().await.id(),
             window_filters,
             vec![],
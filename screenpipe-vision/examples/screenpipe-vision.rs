@@ -1,5 +1,6 @@
 use clap::Parser;
 use screenpipe_core::Language;
+use screenpipe_vision::ocr_pool::{OcrPool, DEFAULT_OCR_QUEUE_SIZE};
 use screenpipe_vision::{
     capture_screenshot_by_window::WindowFilters, continuous_capture, OcrEngine,
 };
@@ -40,11 +41,13 @@ async fn main() {
         .unwrap();
 
     let window_filters = Arc::new(WindowFilters::new(&[], &[]));
+    let ocr_pool = OcrPool::new(1, DEFAULT_OCR_QUEUE_SIZE);
 
     let _ = continuous_capture(
         result_tx,
         Duration::from_secs_f32(1.0 / cli.fps),
         OcrEngine::AppleNative,
+        ocr_pool,
         monitor_id.unwrap(),
         window_filters,
         languages.clone(),
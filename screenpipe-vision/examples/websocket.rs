@@ -4,6 +4,7 @@ use clap::Parser;
 use futures_util::{SinkExt, StreamExt};
 use image::ImageEncoder;
 use screenpipe_vision::capture_screenshot_by_window::WindowFilters;
+use screenpipe_vision::ocr_pool::{OcrPool, DEFAULT_OCR_QUEUE_SIZE};
 use screenpipe_vision::{
     continuous_capture, monitor::get_default_monitor, CaptureResult, OcrEngine,
 };
@@ -85,6 +86,7 @@ async fn main() -> Result<()> {
         &cli.ignored_windows,
         &cli.included_windows,
     ));
+    let ocr_pool = OcrPool::new(1, DEFAULT_OCR_QUEUE_SIZE);
 
     tokio::spawn(async move {
         continuous_capture(
@@ -98,6 +100,7 @@ async fn main() -> Result<()> {
             } else {
                 OcrEngine::Tesseract
             },
+            ocr_pool,
             id,
             window_filters,
             vec![],
@@ -0,0 +1,423 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::debug;
+
+use super::BrowserUrlDetector;
+
+/// How long the url of a browser process is reused before its session file is checked again
+const URL_CACHE_TTL: Duration = Duration::from_secs(2);
+
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+const SNSS_MAGIC: &[u8] = b"SNSS";
+
+// chromium session commands, see components/sessions/core/session_service_commands.cc
+const SET_TAB_WINDOW: u8 = 0;
+const SET_TAB_INDEX_IN_WINDOW: u8 = 2;
+const UPDATE_TAB_NAVIGATION: u8 = 6;
+const SET_SELECTED_NAVIGATION_INDEX: u8 = 7;
+const SET_SELECTED_TAB_IN_INDEX: u8 = 8;
+const SET_ACTIVE_WINDOW: u8 = 20;
+
+struct CachedUrl {
+    checked_at: Instant,
+    session_file: PathBuf,
+    modified: SystemTime,
+    url: Option<String>,
+}
+
+/// Last url found per browser process id. Session files are only parsed again when they change.
+static URL_CACHE: Lazy<Mutex<HashMap<i32, CachedUrl>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Browser {
+    Firefox,
+    /// Chromium based browser with its config directory name
+    Chromium(&'static str),
+}
+
+impl Browser {
+    fn from_app_name(app_name: &str) -> Option<Self> {
+        let app_name = app_name.to_lowercase();
+        let browser = if app_name.contains("firefox") {
+            Browser::Firefox
+        } else if app_name.contains("brave") {
+            Browser::Chromium("BraveSoftware/Brave-Browser")
+        } else if app_name.contains("edge") {
+            Browser::Chromium("microsoft-edge")
+        } else if app_name.contains("vivaldi") {
+            Browser::Chromium("vivaldi")
+        } else if app_name.contains("opera") {
+            Browser::Chromium("opera")
+        } else if app_name.contains("chromium") {
+            Browser::Chromium("chromium")
+        } else if app_name.contains("chrome") {
+            Browser::Chromium("google-chrome")
+        } else {
+            return None;
+        };
+        Some(browser)
+    }
+}
+
+/// Reads the url of the active tab from the session files firefox and chromium based browsers
+/// keep up to date while they run. Browsers write them a few seconds after a navigation, so
+/// the url can lag behind the screen.
+#[derive(Default)]
+pub struct LinuxUrlDetector;
+
+impl LinuxUrlDetector {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl BrowserUrlDetector for LinuxUrlDetector {
+    fn get_active_url(&self, app_name: &str, process_id: i32) -> Result<Option<String>> {
+        let Some(browser) = Browser::from_app_name(app_name) else {
+            return Ok(None);
+        };
+
+        let mut cache = URL_CACHE.lock().unwrap();
+        if let Some(cached) = cache.get(&process_id) {
+            if cached.checked_at.elapsed() < URL_CACHE_TTL {
+                return Ok(cached.url.clone());
+            }
+        }
+
+        let args = process_args(process_id);
+        let Some((session_file, modified)) = find_session_file(browser, &args) else {
+            debug!("no session file found for {} ({})", app_name, process_id);
+            return Ok(None);
+        };
+
+        let url = match cache.get(&process_id) {
+            Some(cached) if cached.session_file == session_file && cached.modified == modified => {
+                cached.url.clone()
+            }
+            _ => {
+                let bytes = std::fs::read(&session_file)?;
+                match browser {
+                    Browser::Firefox => {
+                        let json = decode_mozlz4(&bytes)?;
+                        firefox_session_url(&serde_json::from_slice(&json)?)
+                    }
+                    Browser::Chromium(_) => chromium_session_url(&bytes),
+                }
+            }
+        };
+
+        // forget browsers that exited
+        cache.retain(|pid, _| Path::new(&format!("/proc/{}", pid)).exists());
+        cache.insert(
+            process_id,
+            CachedUrl {
+                checked_at: Instant::now(),
+                session_file,
+                modified,
+                url: url.clone(),
+            },
+        );
+        Ok(url)
+    }
+}
+
+fn process_args(process_id: i32) -> Vec<String> {
+    std::fs::read(format!("/proc/{}/cmdline", process_id))
+        .map(|cmdline| {
+            cmdline
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+/// Session file the browser process writes to, with its modification time. Without an
+/// explicit profile in the command line, the most recently written profile is used.
+fn find_session_file(browser: Browser, args: &[String]) -> Option<(PathBuf, SystemTime)> {
+    let candidates: Vec<PathBuf> = match browser {
+        Browser::Firefox => {
+            let profiles = match arg_value(args, "-profile") {
+                Some(profile) => vec![PathBuf::from(profile)],
+                None => firefox_profiles(),
+            };
+            profiles
+                .into_iter()
+                .map(|profile| profile.join("sessionstore-backups/recovery.jsonlz4"))
+                .collect()
+        }
+        Browser::Chromium(config_name) => {
+            let user_data_dirs = match arg_value(args, "--user-data-dir") {
+                Some(dir) => vec![PathBuf::from(dir)],
+                None => {
+                    let mut dirs: Vec<PathBuf> = config_dir()
+                        .map(|config| config.join(config_name))
+                        .into_iter()
+                        .collect();
+                    if let Some(home) = home_dir() {
+                        dirs.push(home.join("snap/chromium/common/chromium"));
+                    }
+                    dirs
+                }
+            };
+            user_data_dirs
+                .iter()
+                .flat_map(|dir| subdirectories(dir))
+                .filter(|profile| {
+                    profile.file_name().is_some_and(|name| {
+                        let name = name.to_string_lossy();
+                        name == "Default" || name.starts_with("Profile ")
+                    })
+                })
+                .flat_map(|profile| chromium_session_files(&profile))
+                .collect()
+        }
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+            Some((path, modified))
+        })
+        .max_by_key(|(_, modified)| *modified)
+}
+
+/// Value of `--name=value` or `-name value` arguments
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if let Some(value) = arg.strip_prefix(name).and_then(|v| v.strip_prefix('=')) {
+            Some(value)
+        } else if arg == name {
+            args.get(i + 1).map(String::as_str)
+        } else {
+            None
+        }
+    })
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn firefox_profiles() -> Vec<PathBuf> {
+    let Some(home) = home_dir() else {
+        return vec![];
+    };
+    [
+        ".mozilla/firefox",
+        "snap/firefox/common/.mozilla/firefox",
+        ".var/app/org.mozilla.firefox/.mozilla/firefox",
+    ]
+    .iter()
+    .flat_map(|dir| subdirectories(&home.join(dir)))
+    .collect()
+}
+
+/// Recent chromium versions keep timestamped files in `Sessions`, older ones a single
+/// `Current Session` file
+fn chromium_session_files(profile: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(profile.join("Sessions"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with("Session_"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.push(profile.join("Current Session"));
+    files
+}
+
+/// Decompresses firefox's mozlz4 files: a magic number, the decompressed size and an lz4 block
+pub fn decode_mozlz4(bytes: &[u8]) -> Result<Vec<u8>> {
+    let header = bytes
+        .get(..12)
+        .filter(|header| header.starts_with(MOZLZ4_MAGIC))
+        .ok_or_else(|| anyhow!("not a mozlz4 file"))?;
+    let size = u32::from_le_bytes(header[8..12].try_into()?) as usize;
+    decode_lz4_block(&bytes[12..], size)
+}
+
+fn decode_lz4_block(input: &[u8], size: usize) -> Result<Vec<u8>> {
+    let truncated = || anyhow!("truncated lz4 block");
+    let read_length = |i: &mut usize, mut length: usize| -> Result<usize> {
+        if length == 15 {
+            loop {
+                let byte = *input.get(*i).ok_or_else(truncated)?;
+                *i += 1;
+                length += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+        }
+        Ok(length)
+    };
+
+    let mut output = Vec::with_capacity(size);
+    let mut i = 0;
+    while i < input.len() {
+        let token = input[i];
+        i += 1;
+
+        let literals = read_length(&mut i, (token >> 4) as usize)?;
+        output.extend_from_slice(input.get(i..i + literals).ok_or_else(truncated)?);
+        i += literals;
+        // the last sequence only has literals
+        if i >= input.len() {
+            break;
+        }
+
+        let offset = input
+            .get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(truncated)?;
+        i += 2;
+        if offset == 0 || offset > output.len() {
+            return Err(anyhow!("invalid lz4 match offset {}", offset));
+        }
+        let length = read_length(&mut i, (token & 15) as usize)? + 4;
+        let start = output.len() - offset;
+        // matches can overlap the bytes they produce
+        for k in 0..length {
+            output.push(output[start + k]);
+        }
+    }
+    Ok(output)
+}
+
+/// Url of the selected tab of the selected window of a firefox session store
+pub fn firefox_session_url(session: &serde_json::Value) -> Option<String> {
+    // firefox indices are 1-based
+    let index = |value: &serde_json::Value, key: &str| {
+        value.get(key).and_then(|v| v.as_u64()).unwrap_or(1).max(1) as usize - 1
+    };
+
+    let windows = session.get("windows")?.as_array()?;
+    let window = windows
+        .get(index(session, "selectedWindow"))
+        .or(windows.first())?;
+    let tabs = window.get("tabs")?.as_array()?;
+    let tab = tabs.get(index(window, "selected"))?;
+    let entries = tab.get("entries")?.as_array()?;
+    let entry = entries.get(index(tab, "index")).or(entries.last())?;
+    web_url(entry.get("url")?.as_str()?)
+}
+
+/// Url of the active tab of a chromium session file (SNSS): the selected navigation of the
+/// selected tab of the active window. Falls back to the last navigation in the file.
+pub fn chromium_session_url(bytes: &[u8]) -> Option<String> {
+    if !bytes.starts_with(SNSS_MAGIC) {
+        return None;
+    }
+
+    let mut tab_windows: HashMap<i32, i32> = HashMap::new();
+    let mut tab_indices: HashMap<i32, i32> = HashMap::new();
+    let mut selected_navigations: HashMap<i32, i32> = HashMap::new();
+    let mut selected_tabs: HashMap<i32, i32> = HashMap::new();
+    let mut navigations: HashMap<(i32, i32), String> = HashMap::new();
+    let mut active_window = None;
+    let mut last_url = None;
+
+    // magic then a 4 bytes version
+    let mut i = 8;
+    while let Some(size) = bytes.get(i..i + 2) {
+        let size = u16::from_le_bytes([size[0], size[1]]) as usize;
+        i += 2;
+        let Some(command) = bytes.get(i..i + size) else {
+            break;
+        };
+        i += size;
+        let Some((&id, payload)) = command.split_first() else {
+            continue;
+        };
+        let int = |offset: usize| {
+            payload
+                .get(offset..offset + 4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        match id {
+            SET_TAB_WINDOW => {
+                if let (Some(window), Some(tab)) = (int(0), int(4)) {
+                    tab_windows.insert(tab, window);
+                }
+            }
+            SET_TAB_INDEX_IN_WINDOW => {
+                if let (Some(tab), Some(index)) = (int(0), int(4)) {
+                    tab_indices.insert(tab, index);
+                }
+            }
+            SET_SELECTED_NAVIGATION_INDEX => {
+                if let (Some(tab), Some(index)) = (int(0), int(4)) {
+                    selected_navigations.insert(tab, index);
+                }
+            }
+            SET_SELECTED_TAB_IN_INDEX => {
+                if let (Some(window), Some(index)) = (int(0), int(4)) {
+                    selected_tabs.insert(window, index);
+                }
+            }
+            SET_ACTIVE_WINDOW => active_window = int(0),
+            UPDATE_TAB_NAVIGATION => {
+                // pickle: payload size, tab id, navigation index, then the url as a length
+                // prefixed string
+                let (Some(tab), Some(index), Some(length)) = (int(4), int(8), int(12)) else {
+                    continue;
+                };
+                let Some(url) = payload.get(16..16 + length.max(0) as usize) else {
+                    continue;
+                };
+                let url = String::from_utf8_lossy(url).into_owned();
+                last_url = Some(url.clone());
+                navigations.insert((tab, index), url);
+            }
+            _ => {}
+        }
+    }
+
+    let active_url = (|| {
+        let window = active_window.or_else(|| selected_tabs.keys().next().copied())?;
+        let selected = selected_tabs.get(&window)?;
+        let (&tab, _) = tab_windows
+            .iter()
+            .find(|(tab, &w)| w == window && tab_indices.get(tab) == Some(selected))?;
+        let navigation = selected_navigations.get(&tab)?;
+        navigations.get(&(tab, *navigation)).cloned()
+    })();
+
+    web_url(&active_url.or(last_url)?)
+}
+
+/// Only web pages are kept, not the browsers' internal pages
+fn web_url(url: &str) -> Option<String> {
+    (url.starts_with("http://") || url.starts_with("https://")).then(|| url.to_string())
+}
@@ -14,7 +14,7 @@ pub fn create_url_detector() -> Box<dyn BrowserUrlDetector> {
     return Box::new(WindowsUrlDetector::new());
     
     #[cfg(target_os = "linux")]
-    return Box::new(UnsupportedUrlDetector::new());
+    return Box::new(LinuxUrlDetector::new());
 }
 
 // Unsupported implementation
@@ -43,3 +43,7 @@ mod windows;
 #[cfg(target_os = "windows")]
 pub use windows::WindowsUrlDetector; 
 
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::{chromium_session_url, decode_mozlz4, firefox_session_url, LinuxUrlDetector};
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Share of pixels that must change for a frame to count as activity and be processed
+pub const DEFAULT_DIFF_THRESHOLD: f64 = 0.006;
+/// Rate multiplier applied for each frame that changed
+const RAMP_UP: f64 = 2.0;
+/// Rate multiplier applied for each frame that did not change
+const IDLE_DECAY: f64 = 0.85;
+
+static HIGH_CPU: AtomicBool = AtomicBool::new(false);
+static HIGH_MEMORY: AtomicBool = AtomicBool::new(false);
+static ON_BATTERY: AtomicBool = AtomicBool::new(false);
+
+// Effective capture rate per device, for the health endpoint
+static CAPTURE_RATES: Lazy<Mutex<HashMap<String, f64>>> = Lazy::new(Default::default);
+
+/// Bounds of the adaptive capture rate and the change a frame needs to be processed
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureRateConfig {
+    pub min_fps: f64,
+    pub max_fps: f64,
+    pub diff_threshold: f64,
+}
+
+impl CaptureRateConfig {
+    /// Captures at a constant rate, as with a plain `--fps`
+    pub fn fixed(fps: f64) -> Self {
+        Self {
+            min_fps: fps,
+            max_fps: fps,
+            diff_threshold: DEFAULT_DIFF_THRESHOLD,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(self.min_fps.is_finite() && self.min_fps > 0.0) {
+            return Err(anyhow!("min fps must be a positive number"));
+        }
+        if !self.max_fps.is_finite() || self.max_fps < self.min_fps {
+            return Err(anyhow!("max fps must not be below the min fps"));
+        }
+        if !(0.0..=1.0).contains(&self.diff_threshold) {
+            return Err(anyhow!("frame diff threshold must be between 0 and 1"));
+        }
+        Ok(())
+    }
+}
+
+/// Conditions under which capture backs off, reported by the server's resource monitor
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SystemPressure {
+    pub high_cpu: bool,
+    pub high_memory: bool,
+    pub on_battery: bool,
+}
+
+impl SystemPressure {
+    /// Each active condition halves the capture rate
+    pub fn rate_factor(&self) -> f64 {
+        let active = [self.high_cpu, self.high_memory, self.on_battery]
+            .iter()
+            .filter(|&&active| active)
+            .count();
+        0.5f64.powi(active as i32)
+    }
+}
+
+pub fn set_system_pressure(pressure: SystemPressure) {
+    HIGH_CPU.store(pressure.high_cpu, Ordering::Relaxed);
+    HIGH_MEMORY.store(pressure.high_memory, Ordering::Relaxed);
+    ON_BATTERY.store(pressure.on_battery, Ordering::Relaxed);
+}
+
+pub fn system_pressure() -> SystemPressure {
+    SystemPressure {
+        high_cpu: HIGH_CPU.load(Ordering::Relaxed),
+        high_memory: HIGH_MEMORY.load(Ordering::Relaxed),
+        on_battery: ON_BATTERY.load(Ordering::Relaxed),
+    }
+}
+
+/// Current effective capture rate of every device that is capturing
+pub fn capture_rates() -> HashMap<String, f64> {
+    CAPTURE_RATES
+        .lock()
+        .map(|rates| rates.clone())
+        .unwrap_or_default()
+}
+
+/// Capture rate that ramps toward the max while the screen changes and decays to the min while
+/// it is idle, scaled down under system pressure
+pub struct AdaptiveCaptureRate {
+    config: CaptureRateConfig,
+    device_name: Option<String>,
+    // rate driven by screen activity alone
+    activity_fps: f64,
+    effective_fps: f64,
+}
+
+impl AdaptiveCaptureRate {
+    pub fn new(config: CaptureRateConfig) -> Self {
+        let fps = config.max_fps;
+        Self {
+            config,
+            device_name: None,
+            activity_fps: fps,
+            effective_fps: fps,
+        }
+    }
+
+    /// Publishes the effective rate under this device name
+    pub fn with_device_name(mut self, device_name: &str) -> Self {
+        self.device_name = Some(device_name.to_string());
+        self.publish();
+        self
+    }
+
+    pub fn diff_threshold(&self) -> f64 {
+        self.config.diff_threshold
+    }
+
+    pub fn effective_fps(&self) -> f64 {
+        self.effective_fps
+    }
+
+    /// Updates the rate from the difference of the last frame and returns the delay until
+    /// the next capture
+    pub fn update(&mut self, frame_diff: f64, pressure: SystemPressure) -> Duration {
+        let CaptureRateConfig {
+            min_fps, max_fps, ..
+        } = self.config;
+
+        self.activity_fps = if frame_diff >= self.config.diff_threshold {
+            (self.activity_fps * RAMP_UP).min(max_fps)
+        } else {
+            (self.activity_fps * IDLE_DECAY).max(min_fps)
+        };
+        self.effective_fps = (self.activity_fps * pressure.rate_factor()).clamp(min_fps, max_fps);
+        self.publish();
+
+        Duration::from_secs_f64(1.0 / self.effective_fps)
+    }
+
+    fn publish(&self) {
+        if let Some(device_name) = &self.device_name {
+            if let Ok(mut rates) = CAPTURE_RATES.lock() {
+                rates.insert(device_name.clone(), self.effective_fps);
+            }
+        }
+    }
+}
+
+impl Drop for AdaptiveCaptureRate {
+    fn drop(&mut self) {
+        if let Some(device_name) = &self.device_name {
+            if let Ok(mut rates) = CAPTURE_RATES.lock() {
+                rates.remove(device_name);
+            }
+        }
+    }
+}
@@ -1,14 +1,19 @@
 #[cfg(target_os = "macos")]
 use crate::apple::perform_ocr_apple;
+use crate::capture_rate::{system_pressure, AdaptiveCaptureRate, CaptureRateConfig};
 use crate::capture_screenshot_by_window::CapturedWindow;
 use crate::capture_screenshot_by_window::WindowFilters;
 use crate::custom_ocr::perform_ocr_custom;
+use crate::frame_source::{Frame, FrameSource, MonitorFrameSource};
+use crate::layout::{words_from_normalized_boxes, OcrLayout};
 #[cfg(target_os = "windows")]
 use crate::microsoft::perform_ocr_windows;
 use crate::monitor::get_monitor_by_id;
-use crate::tesseract::perform_ocr_tesseract;
+use crate::ocr_cache::{CachedWindowOcr, WindowOcrCache};
+use crate::ocr_pool::{OcrPool, OcrSource};
+use crate::tesseract::perform_ocr_tesseract_with_layout;
 use crate::utils::OcrEngine;
-use crate::utils::{capture_screenshot, compare_with_previous_image};
+use crate::utils::{calculate_hash, compare_with_previous_image};
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
 use image::codecs::jpeg::JpegEncoder;
@@ -116,6 +121,9 @@ pub struct WindowOcrResult {
     pub focused: bool,
     pub confidence: f64,
     pub browser_url: Option<String>,
+    /// Engine whose result was kept
+    pub ocr_engine: OcrEngine,
+    pub layout: OcrLayout,
 }
 
 pub struct OcrTaskData {
@@ -148,16 +156,12 @@ pub async fn continuous_capture(
     result_tx: Sender<CaptureResult>,
     interval: Duration,
     ocr_engine: OcrEngine,
+    ocr_pool: Arc<OcrPool>,
     monitor_id: u32,
     window_filters: Arc<WindowFilters>,
     languages: Vec<Language>,
     capture_unfocused_windows: bool,
 ) -> Result<(), ContinuousCaptureError> {
-    let mut frame_counter: u64 = 0;
-    let mut previous_image: Option<DynamicImage> = None;
-    let mut max_average: Option<MaxAverageFrame> = None;
-    let mut max_avg_value = 0.0;
-
     debug!(
         "continuous_capture: Starting using monitor: {:?}",
         monitor_id
@@ -171,23 +175,50 @@ pub async fn continuous_capture(
         }
     };
 
+    let source = MonitorFrameSource::new(monitor, window_filters, capture_unfocused_windows);
+    let capture_rate =
+        AdaptiveCaptureRate::new(CaptureRateConfig::fixed(1.0 / interval.as_secs_f64()))
+            .with_device_name(&format!("monitor_{}", monitor_id));
+    let ocr = ocr_pool.source(&format!("monitor_{}", monitor_id), ocr_engine, languages);
+    continuous_capture_from_source(Box::new(source), result_tx, capture_rate, ocr).await
+}
+
+/// Runs the capture pipeline on the frames of any source, until a finite source runs out
+pub async fn continuous_capture_from_source(
+    mut source: Box<dyn FrameSource>,
+    result_tx: Sender<CaptureResult>,
+    mut capture_rate: AdaptiveCaptureRate,
+    ocr: OcrSource,
+) -> Result<(), ContinuousCaptureError> {
+    let mut frame_counter: u64 = 0;
+    let mut previous_image: Option<DynamicImage> = None;
+    let mut max_average: Option<MaxAverageFrame> = None;
+    let mut max_avg_value = 0.0;
+
     loop {
-        // 3. Capture screenshot
-        let capture_result =
-            match capture_screenshot(&monitor, &window_filters, capture_unfocused_windows).await {
-                Ok(result) => result,
-                Err(e) => {
-                    debug!("error capturing screenshot: {}", e);
-                    return Err(ContinuousCaptureError::ErrorCapturingScreenshot(
-                        e.to_string(),
-                    ));
-                }
-            };
+        // 3. Capture frame
+        let frame = match source.next_frame().await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => {
+                debug!("frame source exhausted after {} frames", frame_counter);
+                return Ok(());
+            }
+            Err(e) => {
+                debug!("error capturing frame: {}", e);
+                return Err(ContinuousCaptureError::ErrorCapturingScreenshot(
+                    e.to_string(),
+                ));
+            }
+        };
 
         // 4. Process captured image
-        let (image, window_images, image_hash, _capture_duration) = capture_result;
+        let Frame {
+            image,
+            window_images,
+            image_hash,
+        } = frame;
 
-        let should_skip = should_skip_frame(
+        let (should_skip, frame_diff) = should_skip_frame(
             &previous_image,
             &image,
             &mut max_average,
@@ -196,8 +227,10 @@ pub async fn continuous_capture(
             &window_images,
             image_hash,
             result_tx.clone(),
+            capture_rate.diff_threshold(),
         )
         .await;
+        let interval = capture_rate.update(frame_diff, system_pressure());
 
         if should_skip {
             frame_counter += 1;
@@ -207,13 +240,9 @@ pub async fn continuous_capture(
 
         previous_image = Some(image);
 
-        // 5. Process max average frame if available
+        // 5. Queue max average frame for OCR if available
         if let Some(max_avg_frame) = max_average.take() {
-            if let Err(e) =
-                process_max_average_frame(max_avg_frame, &ocr_engine, languages.clone()).await
-            {
-                error!("Error processing max average frame: {}", e);
-            }
+            queue_max_average_frame(max_avg_frame, &ocr);
             frame_counter = 0;
             max_avg_value = 0.0;
         }
@@ -223,6 +252,8 @@ pub async fn continuous_capture(
     }
 }
 
+/// Returns whether the frame changed too little to be processed, and how much it changed
+#[allow(clippy::too_many_arguments)]
 async fn should_skip_frame(
     previous_image: &Option<DynamicImage>,
     current_image: &DynamicImage,
@@ -232,7 +263,8 @@ async fn should_skip_frame(
     window_images: &Vec<CapturedWindow>,
     image_hash: u64,
     result_tx: Sender<CaptureResult>,
-) -> bool {
+    diff_threshold: f64,
+) -> (bool, f64) {
     let current_average = match compare_with_previous_image(
         previous_image.as_ref(),
         current_image,
@@ -255,12 +287,12 @@ async fn should_skip_frame(
         current_average
     };
 
-    if current_average < 0.006 {
+    if current_average < diff_threshold {
         debug!(
             "Skipping frame {} due to low average difference: {:.3}",
             frame_counter, current_average
         );
-        true
+        (true, current_average)
     } else {
         if current_average > *max_avg_value {
             *max_average = Some(MaxAverageFrame {
@@ -274,15 +306,11 @@ async fn should_skip_frame(
             });
             *max_avg_value = current_average;
         }
-        false
+        (false, current_average)
     }
 }
 
-async fn process_max_average_frame(
-    max_avg_frame: MaxAverageFrame,
-    ocr_engine: &OcrEngine,
-    languages: Vec<Language>,
-) -> Result<(), ContinuousCaptureError> {
+fn queue_max_average_frame(max_avg_frame: MaxAverageFrame, ocr: &OcrSource) {
     let ocr_task_data = OcrTaskData {
         image: max_avg_frame.image,
         window_images: max_avg_frame.window_images,
@@ -291,12 +319,7 @@ async fn process_max_average_frame(
         result_tx: max_avg_frame.result_tx,
     };
 
-    if let Err(e) = process_ocr_task(ocr_task_data, ocr_engine, languages).await {
-        error!("Error processing OCR task: {}", e);
-        return Err(ContinuousCaptureError::ErrorProcessingOcr(e.to_string()));
-    }
-
-    Ok(())
+    ocr.submit(ocr_task_data);
 }
 
 pub struct MaxAverageFrame {
@@ -313,6 +336,7 @@ pub async fn process_ocr_task(
     ocr_task_data: OcrTaskData,
     ocr_engine: &OcrEngine,
     languages: Vec<Language>,
+    ocr_cache: &mut WindowOcrCache,
 ) -> Result<(), ContinuousCaptureError> {
     let OcrTaskData {
         image,
@@ -332,11 +356,13 @@ pub async fn process_ocr_task(
     let mut total_confidence = 0.0;
     let mut window_count = 0;
 
+    ocr_cache.begin_pass();
     for captured_window in window_images {
         let ocr_result = process_window_ocr(
             captured_window,
             ocr_engine,
             &languages,
+            ocr_cache,
             &mut total_confidence,
             &mut window_count,
         )
@@ -345,6 +371,7 @@ pub async fn process_ocr_task(
 
         window_ocr_results.push(ocr_result);
     }
+    ocr_cache.end_pass();
 
     // Create and send the result
     let capture_result = CaptureResult {
@@ -359,7 +386,13 @@ pub async fn process_ocr_task(
         .map_err(|e| ContinuousCaptureError::ErrorSendingOcrResult(e.to_string()))?;
 
     // Log performance metrics
-    log_ocr_performance(start_time, window_count, total_confidence, frame_number);
+    log_ocr_performance(
+        start_time,
+        window_count,
+        total_confidence,
+        frame_number,
+        ocr_cache,
+    );
 
     Ok(())
 }
@@ -368,6 +401,7 @@ async fn process_window_ocr(
     captured_window: CapturedWindow,
     ocr_engine: &OcrEngine,
     languages: &[Language],
+    ocr_cache: &mut WindowOcrCache,
     total_confidence: &mut f64,
     window_count: &mut u32,
 ) -> Result<WindowOcrResult, ContinuousCaptureError> {
@@ -381,14 +415,25 @@ async fn process_window_ocr(
     )
     .await;
 
-    // Perform OCR based on the selected engine
-    let (window_text, window_json_output, confidence) =
-        perform_ocr_with_engine(ocr_engine, &captured_window.image, languages.to_vec())
-            .await
-            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
+    // Reuse the previous OCR of windows that did not change, otherwise perform OCR based on
+    // the selected engine
+    let image_hash = calculate_hash(&captured_window.image);
+    let ocr = match ocr_cache.get(&app_name, &captured_window.window_name, image_hash) {
+        Some(ocr) => ocr,
+        None => {
+            let ocr = perform_window_ocr(ocr_engine, &captured_window.image, languages).await?;
+            ocr_cache.insert(
+                &app_name,
+                &captured_window.window_name,
+                image_hash,
+                ocr.clone(),
+            );
+            ocr
+        }
+    };
 
     // Update confidence metrics
-    if let Some(conf) = confidence {
+    if let Some(conf) = ocr.confidence {
         *total_confidence += conf;
         *window_count += 1;
     }
@@ -397,11 +442,70 @@ async fn process_window_ocr(
         image: captured_window.image,
         window_name: captured_window.window_name,
         app_name: captured_window.app_name,
-        text: window_text,
-        text_json: parse_json_output(&window_json_output),
+        text: ocr.text,
+        text_json: ocr.text_json,
         focused: captured_window.is_focused,
-        confidence: confidence.unwrap_or(0.0),
+        confidence: ocr.confidence.unwrap_or(0.0),
         browser_url,
+        ocr_engine: ocr.engine,
+        layout: ocr.layout,
+    })
+}
+
+/// OCR of a window with the selected engine. An ensemble OCRs the window again with its
+/// secondary engine when the primary one is not confident enough.
+async fn perform_window_ocr(
+    ocr_engine: &OcrEngine,
+    image: &DynamicImage,
+    languages: &[Language],
+) -> Result<CachedWindowOcr, ContinuousCaptureError> {
+    let OcrEngine::Ensemble(ensemble) = ocr_engine else {
+        return perform_single_engine_ocr(ocr_engine, image, languages).await;
+    };
+
+    let primary = perform_single_engine_ocr(&ensemble.primary, image, languages).await?;
+    if !ensemble.needs_second_opinion(&primary) {
+        return Ok(primary);
+    }
+
+    match perform_single_engine_ocr(&ensemble.secondary, image, languages).await {
+        Ok(secondary) => Ok(ensemble.pick(primary, secondary)),
+        Err(e) => {
+            warn!("secondary ocr failed, keeping the primary result: {}", e);
+            Ok(primary)
+        }
+    }
+}
+
+async fn perform_single_engine_ocr(
+    ocr_engine: &OcrEngine,
+    image: &DynamicImage,
+    languages: &[Language],
+) -> Result<CachedWindowOcr, ContinuousCaptureError> {
+    let (text, json_output, confidence, layout) =
+        perform_ocr_with_engine(ocr_engine, image, languages.to_vec())
+            .await
+            .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string()))?;
+    let text_json = parse_json_output(&json_output);
+
+    // engines that only report text get a layout of plain lines
+    let (width, height) = (image.width(), image.height());
+    let layout = match (layout, ocr_engine) {
+        (Some(layout), _) => layout,
+        (None, OcrEngine::AppleNative) => OcrLayout::from_words(
+            width,
+            height,
+            words_from_normalized_boxes(&text_json, width, height),
+        ),
+        (None, _) => OcrLayout::from_text(width, height, &text),
+    };
+
+    Ok(CachedWindowOcr {
+        text,
+        text_json,
+        confidence,
+        layout,
+        engine: ocr_engine.clone(),
     })
 }
 
@@ -410,8 +514,7 @@ async fn get_browser_url_if_needed(
     is_focused: bool,
     process_id: i32,
 ) -> Option<String> {
-    if cfg!(not(target_os = "linux"))
-        && is_focused
+    if is_focused
         && BROWSER_NAMES
             .iter()
             .any(|&browser| app_name.to_lowercase().contains(browser))
@@ -434,24 +537,35 @@ async fn get_browser_url_if_needed(
     }
 }
 
+/// Text, json output and confidence of the engine, with the layout when the engine builds
+/// one itself
 async fn perform_ocr_with_engine(
     ocr_engine: &OcrEngine,
     image: &DynamicImage,
     languages: Vec<Language>,
-) -> Result<(String, String, Option<f64>), ContinuousCaptureError> {
+) -> Result<(String, String, Option<f64>, Option<OcrLayout>), ContinuousCaptureError> {
+    let without_layout =
+        |(text, json, confidence): (String, String, Option<f64>)| (text, json, confidence, None);
     match ocr_engine {
         OcrEngine::Unstructured => perform_ocr_cloud(image, languages)
             .await
+            .map(without_layout)
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
-        OcrEngine::Tesseract => Ok(perform_ocr_tesseract(image, languages)),
+        OcrEngine::Tesseract => {
+            let (text, json, confidence, layout) =
+                perform_ocr_tesseract_with_layout(image, languages);
+            Ok((text, json, confidence, Some(layout)))
+        }
         #[cfg(target_os = "windows")]
         OcrEngine::WindowsNative => perform_ocr_windows(image)
             .await
+            .map(without_layout)
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
         #[cfg(target_os = "macos")]
-        OcrEngine::AppleNative => Ok(perform_ocr_apple(image, &languages)),
+        OcrEngine::AppleNative => Ok(without_layout(perform_ocr_apple(image, &languages))),
         OcrEngine::Custom(config) => perform_ocr_custom(image, languages, config)
             .await
+            .map(without_layout)
             .map_err(|e| ContinuousCaptureError::ErrorProcessingOcr(e.to_string())),
         _ => Err(ContinuousCaptureError::ErrorProcessingOcr(
             "Unsupported OCR engine".to_string(),
@@ -491,6 +605,7 @@ fn log_ocr_performance(
     window_count: u32,
     total_confidence: f64,
     frame_number: u64,
+    ocr_cache: &WindowOcrCache,
 ) {
     let duration = start_time.elapsed();
     let avg_confidence = if window_count > 0 {
@@ -498,9 +613,16 @@ fn log_ocr_performance(
     } else {
         0.0
     };
+    let (cache_hits, cache_lookups) = ocr_cache.pass_stats();
     debug!(
-        "OCR task processed frame {} with {} windows in {:?}, average confidence: {:.2}",
-        frame_number, window_count, duration, avg_confidence
+        "OCR task processed frame {} with {} windows in {:?}, average confidence: {:.2}, cache hits: {}/{}, cache hit rate: {:.1}%",
+        frame_number,
+        window_count,
+        duration,
+        avg_confidence,
+        cache_hits,
+        cache_lookups,
+        ocr_cache.hit_rate() * 100.0
     );
 }
 
@@ -0,0 +1,401 @@
+use crate::capture_screenshot_by_window::{CapturedWindow, WindowFilters};
+use crate::monitor::{get_monitor_by_id, SafeMonitor};
+use crate::utils::{calculate_hash, capture_screenshot};
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, ImageFormat};
+use screenpipe_core::find_ffmpeg_path;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, ChildStdout, Command};
+use tracing::{debug, warn};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// Larger chunks are taken as a corrupt stream rather than allocated
+const MAX_PNG_CHUNK: usize = 256 * 1024 * 1024;
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "bmp", "webp"];
+
+/// A captured frame: the full image that goes into the video, and the windows that are OCR'd
+pub struct Frame {
+    pub image: DynamicImage,
+    pub window_images: Vec<CapturedWindow>,
+    pub image_hash: u64,
+}
+
+impl Frame {
+    /// Frame of a source without windows, OCR'd as a single window covering the whole image
+    fn whole_image(image: DynamicImage, app_name: &str, window_name: &str) -> Self {
+        let image_hash = calculate_hash(&image);
+        Self {
+            window_images: vec![CapturedWindow {
+                image: image.clone(),
+                app_name: app_name.to_string(),
+                window_name: window_name.to_string(),
+                process_id: 0,
+                is_focused: true,
+            }],
+            image,
+            image_hash,
+        }
+    }
+}
+
+pub type FrameFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Frame>>> + Send + 'a>>;
+
+/// Something `continuous_capture` can pull frames from. Frames go through the same diffing,
+/// OCR and video encoding whatever their source.
+pub trait FrameSource: Send {
+    /// Next frame, or `None` once a finite source is exhausted
+    fn next_frame(&mut self) -> FrameFuture<'_>;
+}
+
+/// Where frames come from, parsed from `monitor:<id>`, `dir:<path>[?loop=true]`,
+/// `video:<path>` or `stdin`
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameSourceConfig {
+    /// Screenshots of a local monitor and its windows
+    Monitor(u32),
+    /// Images of a directory in file name order
+    ImageDirectory { path: PathBuf, looping: bool },
+    /// Frames of a video file or any other input ffmpeg can read, sampled at the capture fps
+    Video { path: PathBuf },
+    /// A stream of concatenated PNG images on stdin, e.g. from
+    /// `ffmpeg -f v4l2 -i /dev/video0 -f image2pipe -c:v png -`
+    Stdin,
+}
+
+impl FrameSourceConfig {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec == "stdin" || spec == "-" {
+            return Ok(Self::Stdin);
+        }
+
+        let (scheme, rest) = spec
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid frame source {:?}", spec))?;
+        if rest.is_empty() {
+            return Err(anyhow!("frame source {:?} is missing its target", spec));
+        }
+
+        match scheme {
+            "monitor" => Ok(Self::Monitor(
+                rest.parse()
+                    .map_err(|_| anyhow!("invalid monitor id {:?}", rest))?,
+            )),
+            "dir" => {
+                let (path, looping) = match rest.split_once('?') {
+                    Some((path, "loop=true")) => (path, true),
+                    Some((path, "loop=false")) => (path, false),
+                    Some((_, query)) => {
+                        return Err(anyhow!("unknown frame source option {:?}", query))
+                    }
+                    None => (rest, false),
+                };
+                Ok(Self::ImageDirectory {
+                    path: PathBuf::from(path),
+                    looping,
+                })
+            }
+            "video" => Ok(Self::Video {
+                path: PathBuf::from(rest),
+            }),
+            _ => Err(anyhow!("unknown frame source scheme {:?}", scheme)),
+        }
+    }
+
+    pub fn monitor_id(&self) -> Option<u32> {
+        match self {
+            Self::Monitor(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether the source runs out of frames, as opposed to live capture
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Self::Monitor(_) => false,
+            Self::ImageDirectory { looping, .. } => !looping,
+            Self::Video { .. } | Self::Stdin => true,
+        }
+    }
+
+    /// Name recorded as the device of the frames, `monitor_<id>` for monitors
+    pub fn device_name(&self) -> String {
+        match self {
+            Self::Monitor(id) => format!("monitor_{}", id),
+            Self::ImageDirectory { path, .. } => format!("dir_{}", file_label(path)),
+            Self::Video { path } => format!("video_{}", file_label(path)),
+            Self::Stdin => "stdin".to_string(),
+        }
+    }
+
+    pub async fn open(
+        &self,
+        window_filters: Arc<WindowFilters>,
+        capture_unfocused_windows: bool,
+        fps: f64,
+    ) -> Result<Box<dyn FrameSource>> {
+        Ok(match self {
+            Self::Monitor(id) => {
+                let monitor = get_monitor_by_id(*id)
+                    .await
+                    .ok_or_else(|| anyhow!("monitor {} not found", id))?;
+                Box::new(MonitorFrameSource::new(
+                    monitor,
+                    window_filters,
+                    capture_unfocused_windows,
+                ))
+            }
+            Self::ImageDirectory { path, looping } => {
+                Box::new(ImageDirectorySource::new(path, *looping)?)
+            }
+            Self::Video { path } => Box::new(VideoFileSource::new(path, fps)?),
+            Self::Stdin => Box::new(PngStreamSource::new(tokio::io::stdin(), "stdin")),
+        })
+    }
+}
+
+impl fmt::Display for FrameSourceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Monitor(id) => write!(f, "monitor {}", id),
+            Self::ImageDirectory { path, .. } => write!(f, "directory {}", path.display()),
+            Self::Video { path } => write!(f, "video {}", path.display()),
+            Self::Stdin => write!(f, "stdin"),
+        }
+    }
+}
+
+fn file_label(path: &Path) -> String {
+    path.file_stem()
+        .or_else(|| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Screenshots of a monitor and its visible windows
+pub struct MonitorFrameSource {
+    monitor: SafeMonitor,
+    window_filters: Arc<WindowFilters>,
+    capture_unfocused_windows: bool,
+}
+
+impl MonitorFrameSource {
+    pub fn new(
+        monitor: SafeMonitor,
+        window_filters: Arc<WindowFilters>,
+        capture_unfocused_windows: bool,
+    ) -> Self {
+        Self {
+            monitor,
+            window_filters,
+            capture_unfocused_windows,
+        }
+    }
+}
+
+impl FrameSource for MonitorFrameSource {
+    fn next_frame(&mut self) -> FrameFuture<'_> {
+        Box::pin(async move {
+            let (image, window_images, image_hash, _capture_duration) = capture_screenshot(
+                &self.monitor,
+                &self.window_filters,
+                self.capture_unfocused_windows,
+            )
+            .await?;
+            Ok(Some(Frame {
+                image,
+                window_images,
+                image_hash,
+            }))
+        })
+    }
+}
+
+/// Images of a directory, in file name order
+pub struct ImageDirectorySource {
+    dir: PathBuf,
+    app_name: String,
+    files: Vec<PathBuf>,
+    pending: VecDeque<PathBuf>,
+    looping: bool,
+}
+
+impl ImageDirectorySource {
+    pub fn new(dir: &Path, looping: bool) -> Result<Self> {
+        let mut files = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| is_image(path))
+            .collect::<Vec<_>>();
+        files.sort();
+        if files.is_empty() {
+            return Err(anyhow!("no images found in {}", dir.display()));
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            app_name: file_label(dir),
+            pending: files.iter().cloned().collect(),
+            files,
+            looping,
+        })
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+impl FrameSource for ImageDirectorySource {
+    fn next_frame(&mut self) -> FrameFuture<'_> {
+        Box::pin(async move {
+            loop {
+                if self.pending.is_empty() {
+                    if !self.looping {
+                        return Ok(None);
+                    }
+                    debug!("restarting image directory {}", self.dir.display());
+                    self.pending = self.files.iter().cloned().collect();
+                }
+
+                let path = self.pending.pop_front().unwrap();
+                let load_path = path.clone();
+                match tokio::task::spawn_blocking(move || image::open(load_path)).await? {
+                    Ok(image) => {
+                        let window_name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        return Ok(Some(Frame::whole_image(
+                            image,
+                            &self.app_name,
+                            &window_name,
+                        )));
+                    }
+                    // skip unreadable files instead of stopping the whole directory
+                    Err(e) => warn!("failed to load image {}: {}", path.display(), e),
+                }
+            }
+        })
+    }
+}
+
+/// Frames decoded by ffmpeg from a video file, or any input ffmpeg can open
+pub struct VideoFileSource {
+    // killed when the source is dropped
+    _child: Child,
+    frames: PngStreamSource<ChildStdout>,
+}
+
+impl VideoFileSource {
+    pub fn new(path: &Path, fps: f64) -> Result<Self> {
+        if !path.exists() {
+            return Err(anyhow!("video {} does not exist", path.display()));
+        }
+        let ffmpeg_path = find_ffmpeg_path().ok_or_else(|| anyhow!("ffmpeg not found"))?;
+
+        let mut child = Command::new(ffmpeg_path)
+            .args(["-v", "error", "-i"])
+            .arg(path)
+            .args([
+                "-vf",
+                &format!("fps={}", fps),
+                "-f",
+                "image2pipe",
+                "-c:v",
+                "png",
+                "-",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("failed to open ffmpeg output"))?;
+
+        Ok(Self {
+            _child: child,
+            frames: PngStreamSource::new(stdout, &file_label(path)),
+        })
+    }
+}
+
+impl FrameSource for VideoFileSource {
+    fn next_frame(&mut self) -> FrameFuture<'_> {
+        self.frames.next_frame()
+    }
+}
+
+/// Frames from a stream of concatenated PNG images, as written by ffmpeg's `image2pipe`
+pub struct PngStreamSource<R> {
+    reader: R,
+    name: String,
+}
+
+impl<R: AsyncRead + Unpin + Send> PngStreamSource<R> {
+    pub fn new(reader: R, name: &str) -> Self {
+        Self {
+            reader,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send> FrameSource for PngStreamSource<R> {
+    fn next_frame(&mut self) -> FrameFuture<'_> {
+        Box::pin(async move {
+            let Some(png) = read_png(&mut self.reader).await? else {
+                return Ok(None);
+            };
+            let image = tokio::task::spawn_blocking(move || {
+                image::load_from_memory_with_format(&png, ImageFormat::Png)
+            })
+            .await??;
+            Ok(Some(Frame::whole_image(image, &self.name, &self.name)))
+        })
+    }
+}
+
+/// Reads the next PNG image of a stream of concatenated PNGs, `None` at the end of the stream
+pub async fn read_png<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut signature = [0u8; 8];
+    let read = reader.read(&mut signature).await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut signature[read..]).await?;
+    if signature != PNG_SIGNATURE {
+        return Err(anyhow!("frame stream is not a PNG stream"));
+    }
+
+    let mut png = signature.to_vec();
+    loop {
+        // chunk length and type, then data and crc
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).await?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        if length > MAX_PNG_CHUNK {
+            return Err(anyhow!("PNG chunk of {} bytes in frame stream", length));
+        }
+        png.extend_from_slice(&header);
+
+        let start = png.len();
+        png.resize(start + length + 4, 0);
+        reader.read_exact(&mut png[start..]).await?;
+
+        if &header[4..] == b"IEND" {
+            return Ok(Some(png));
+        }
+    }
+}
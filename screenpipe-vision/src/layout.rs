@@ -0,0 +1,598 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Gap between two words of a row, in row heights, above which they belong to different
+/// columns or table cells
+const COLUMN_GAP: f64 = 2.0;
+/// Gap between two lines, in line heights, above which they start a new paragraph
+const PARAGRAPH_GAP: f64 = 0.8;
+/// Gap between two lines, in line heights, above which they start a new block
+const BLOCK_GAP: f64 = 1.5;
+/// Cells of a table are short, rows of longer segments are taken as text columns
+const MAX_TABLE_CELL_WORDS: f64 = 4.0;
+
+/// Box in pixels of the OCR'd image, from its top left corner
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl BoundingBox {
+    pub fn new(left: f64, top: f64, width: f64, height: f64) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.top + self.height
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        BoundingBox::new(
+            left,
+            top,
+            self.right().max(other.right()) - left,
+            self.bottom().max(other.bottom()) - top,
+        )
+    }
+
+    fn vertical_overlap(&self, other: &BoundingBox) -> f64 {
+        self.bottom().min(other.bottom()) - self.top.max(other.top)
+    }
+
+    fn horizontal_overlap(&self, other: &BoundingBox) -> f64 {
+        self.right().min(other.right()) - self.left.max(other.left)
+    }
+
+    fn center_y(&self) -> f64 {
+        self.top + self.height / 2.0
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub bbox: Option<BoundingBox>,
+    /// From 0 to 1
+    pub confidence: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub bbox: Option<BoundingBox>,
+    pub words: Vec<OcrWord>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrParagraph {
+    pub bbox: Option<BoundingBox>,
+    pub lines: Vec<OcrLine>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrBlock {
+    pub bbox: Option<BoundingBox>,
+    pub paragraphs: Vec<OcrParagraph>,
+}
+
+/// Cells found aligned in rows and columns, e.g. a spreadsheet. Missing cells are empty.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrTable {
+    pub bbox: BoundingBox,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Layout of the text of an OCR'd image, the same whatever engine produced it.
+///
+/// Blocks are in reading order: top to bottom, and columns left to right. Engines that do not
+/// report positions produce a single block of lines without boxes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrLayout {
+    pub width: u32,
+    pub height: u32,
+    pub blocks: Vec<OcrBlock>,
+    pub tables: Vec<OcrTable>,
+}
+
+impl OcrLayout {
+    /// Builds the layout of positioned words. Words without a box end up in a last block.
+    pub fn from_words(width: u32, height: u32, words: Vec<OcrWord>) -> Self {
+        let (boxed, unboxed): (Vec<_>, Vec<_>) = words
+            .into_iter()
+            .filter(|word| !word.text.trim().is_empty())
+            .partition(|word| word.bbox.is_some());
+
+        let lines = group_lines(boxed);
+        let tables = detect_tables(&lines);
+        let mut blocks = reading_order(group_blocks(lines));
+        if !unboxed.is_empty() {
+            blocks.push(unpositioned_block(unboxed));
+        }
+
+        Self {
+            width,
+            height,
+            blocks,
+            tables,
+        }
+    }
+
+    /// Layout of plain text: blank lines separate paragraphs
+    pub fn from_text(width: u32, height: u32, text: &str) -> Self {
+        let mut paragraphs = Vec::new();
+        let mut lines = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() {
+                if !lines.is_empty() {
+                    paragraphs.push(OcrParagraph {
+                        bbox: None,
+                        lines: std::mem::take(&mut lines),
+                    });
+                }
+                continue;
+            }
+            lines.push(OcrLine {
+                text: line.to_string(),
+                bbox: None,
+                words: line
+                    .split_whitespace()
+                    .map(|word| OcrWord {
+                        text: word.to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            });
+        }
+        if !lines.is_empty() {
+            paragraphs.push(OcrParagraph { bbox: None, lines });
+        }
+
+        let blocks = if paragraphs.is_empty() {
+            vec![]
+        } else {
+            vec![OcrBlock {
+                bbox: None,
+                paragraphs,
+            }]
+        };
+        Self {
+            width,
+            height,
+            blocks,
+            tables: vec![],
+        }
+    }
+
+    /// Lines in reading order, paragraphs separated by a blank line
+    pub fn text(&self) -> String {
+        self.blocks
+            .iter()
+            .flat_map(|block| &block.paragraphs)
+            .map(|paragraph| {
+                paragraph
+                    .lines
+                    .iter()
+                    .map(|line| line.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Rewrites the text of every line and table cell, e.g. to remove PII. A changed line
+    /// keeps a single word covering the whole line, so no original word survives.
+    pub fn redact(&mut self, redact: impl Fn(&str) -> String) {
+        for line in self
+            .blocks
+            .iter_mut()
+            .flat_map(|block| block.paragraphs.iter_mut())
+            .flat_map(|paragraph| paragraph.lines.iter_mut())
+        {
+            let redacted = redact(&line.text);
+            if redacted != line.text {
+                line.words = vec![OcrWord {
+                    text: redacted.clone(),
+                    bbox: line.bbox,
+                    confidence: None,
+                }];
+                line.text = redacted;
+            }
+        }
+        for cell in self
+            .tables
+            .iter_mut()
+            .flat_map(|table| table.rows.iter_mut())
+            .flatten()
+        {
+            *cell = redact(cell);
+        }
+    }
+}
+
+/// Words from boxes normalized to 0-1 with a bottom left origin, as apple's vision framework
+/// reports them in `text_json`
+pub fn words_from_normalized_boxes(
+    text_json: &[HashMap<String, String>],
+    width: u32,
+    height: u32,
+) -> Vec<OcrWord> {
+    let (width, height) = (width as f64, height as f64);
+    text_json
+        .iter()
+        .filter_map(|entry| {
+            let value = |key: &str| entry.get(key)?.parse::<f64>().ok();
+            let (left, bottom, w, h) = (
+                value("left")?,
+                value("top")?,
+                value("width")?,
+                value("height")?,
+            );
+            Some(OcrWord {
+                text: entry.get("text")?.clone(),
+                bbox: Some(BoundingBox::new(
+                    left * width,
+                    (1.0 - bottom - h) * height,
+                    w * width,
+                    h * height,
+                )),
+                confidence: value("conf"),
+            })
+        })
+        .collect()
+}
+
+fn bbox_of(word: &OcrWord) -> BoundingBox {
+    word.bbox.unwrap_or_default()
+}
+
+fn line_bbox(line: &OcrLine) -> BoundingBox {
+    line.bbox.unwrap_or_default()
+}
+
+fn union_all<'a>(boxes: impl Iterator<Item = &'a Option<BoundingBox>>) -> Option<BoundingBox> {
+    boxes
+        .flatten()
+        .fold(None, |acc: Option<BoundingBox>, b| match acc {
+            Some(acc) => Some(acc.union(b)),
+            None => Some(*b),
+        })
+}
+
+/// Groups words into rows by vertical overlap, then splits rows at wide gaps so that
+/// separate columns or cells become separate lines
+fn group_lines(mut words: Vec<OcrWord>) -> Vec<OcrLine> {
+    words.sort_by(|a, b| bbox_of(a).center_y().total_cmp(&bbox_of(b).center_y()));
+
+    let mut rows: Vec<(BoundingBox, Vec<OcrWord>)> = Vec::new();
+    for word in words {
+        let bbox = bbox_of(&word);
+        match rows.last_mut() {
+            Some((row_bbox, row_words))
+                if row_bbox.vertical_overlap(&bbox) > 0.5 * row_bbox.height.min(bbox.height) =>
+            {
+                *row_bbox = row_bbox.union(&bbox);
+                row_words.push(word);
+            }
+            _ => rows.push((bbox, vec![word])),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (_, mut row_words) in rows {
+        row_words.sort_by(|a, b| bbox_of(a).left.total_cmp(&bbox_of(b).left));
+        let row_height = median(row_words.iter().map(|w| bbox_of(w).height));
+
+        let mut segment: Vec<OcrWord> = Vec::new();
+        for word in row_words {
+            if let Some(previous) = segment.last() {
+                if bbox_of(&word).left - bbox_of(previous).right() > COLUMN_GAP * row_height {
+                    lines.push(make_line(std::mem::take(&mut segment)));
+                }
+            }
+            segment.push(word);
+        }
+        if !segment.is_empty() {
+            lines.push(make_line(segment));
+        }
+    }
+    lines
+}
+
+fn make_line(words: Vec<OcrWord>) -> OcrLine {
+    OcrLine {
+        text: words
+            .iter()
+            .map(|word| word.text.trim())
+            .collect::<Vec<_>>()
+            .join(" "),
+        bbox: union_all(words.iter().map(|word| &word.bbox)),
+        words,
+    }
+}
+
+/// Stacks lines that are close and horizontally overlapping into blocks, and splits blocks
+/// into paragraphs at larger vertical gaps
+fn group_blocks(mut lines: Vec<OcrLine>) -> Vec<OcrBlock> {
+    lines.sort_by(|a, b| line_bbox(a).top.total_cmp(&line_bbox(b).top));
+
+    let mut blocks: Vec<Vec<Vec<OcrLine>>> = Vec::new();
+    for line in lines {
+        let bbox = line_bbox(&line);
+        let block = blocks.iter_mut().rev().find(|paragraphs| {
+            let last = line_bbox(paragraphs.last().and_then(|p| p.last()).unwrap());
+            let gap = bbox.top - last.bottom();
+            last.horizontal_overlap(&bbox) > 0.0
+                && gap > -0.5 * bbox.height
+                && gap < BLOCK_GAP * bbox.height.max(last.height)
+        });
+
+        match block {
+            Some(paragraphs) => {
+                let last = line_bbox(paragraphs.last().and_then(|p| p.last()).unwrap());
+                let gap = bbox.top - last.bottom();
+                if gap > PARAGRAPH_GAP * (bbox.height + last.height) / 2.0 {
+                    paragraphs.push(vec![line]);
+                } else {
+                    paragraphs.last_mut().unwrap().push(line);
+                }
+            }
+            None => blocks.push(vec![vec![line]]),
+        }
+    }
+
+    blocks
+        .into_iter()
+        .map(|paragraphs| {
+            let paragraphs: Vec<OcrParagraph> = paragraphs
+                .into_iter()
+                .map(|lines| OcrParagraph {
+                    bbox: union_all(lines.iter().map(|line| &line.bbox)),
+                    lines,
+                })
+                .collect();
+            OcrBlock {
+                bbox: union_all(paragraphs.iter().map(|paragraph| &paragraph.bbox)),
+                paragraphs,
+            }
+        })
+        .collect()
+}
+
+/// Orders blocks with a recursive XY cut: split at horizontal whitespace bands first, top to
+/// bottom, then at vertical bands, left to right
+fn reading_order(blocks: Vec<OcrBlock>) -> Vec<OcrBlock> {
+    let boxes: Vec<BoundingBox> = blocks.iter().map(|b| b.bbox.unwrap_or_default()).collect();
+    let mut order = Vec::with_capacity(blocks.len());
+    xy_cut((0..blocks.len()).collect(), &boxes, &mut order);
+
+    let mut blocks: Vec<Option<OcrBlock>> = blocks.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|index| blocks[index].take())
+        .collect()
+}
+
+fn xy_cut(indices: Vec<usize>, boxes: &[BoundingBox], order: &mut Vec<usize>) {
+    if indices.len() <= 1 {
+        order.extend(indices);
+        return;
+    }
+
+    for horizontal in [true, false] {
+        let span = |i: usize| {
+            if horizontal {
+                (boxes[i].top, boxes[i].bottom())
+            } else {
+                (boxes[i].left, boxes[i].right())
+            }
+        };
+        let groups = split_at_gaps(&indices, span);
+        if groups.len() > 1 {
+            for group in groups {
+                xy_cut(group, boxes, order);
+            }
+            return;
+        }
+    }
+
+    // no whitespace band separates these blocks, fall back to top to bottom
+    let mut indices = indices;
+    indices.sort_by(|&a, &b| {
+        boxes[a]
+            .top
+            .total_cmp(&boxes[b].top)
+            .then(boxes[a].left.total_cmp(&boxes[b].left))
+    });
+    order.extend(indices);
+}
+
+/// Splits items into groups whose projections on one axis do not overlap
+fn split_at_gaps(indices: &[usize], span: impl Fn(usize) -> (f64, f64)) -> Vec<Vec<usize>> {
+    let mut sorted = indices.to_vec();
+    sorted.sort_by(|&a, &b| span(a).0.total_cmp(&span(b).0));
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_end = f64::NEG_INFINITY;
+    for index in sorted {
+        let (start, end) = span(index);
+        match groups.last_mut() {
+            Some(group) if start < group_end => group.push(index),
+            _ => groups.push(vec![index]),
+        }
+        group_end = group_end.max(end);
+    }
+    groups
+}
+
+/// Finds runs of rows whose short segments line up in the same columns
+fn detect_tables(lines: &[OcrLine]) -> Vec<OcrTable> {
+    let mut sorted: Vec<&OcrLine> = lines.iter().collect();
+    sorted.sort_by(|a, b| line_bbox(a).center_y().total_cmp(&line_bbox(b).center_y()));
+
+    let mut rows: Vec<(BoundingBox, Vec<&OcrLine>)> = Vec::new();
+    for line in sorted {
+        let bbox = line_bbox(line);
+        match rows.last_mut() {
+            Some((row_bbox, row_lines))
+                if row_bbox.vertical_overlap(&bbox) > 0.5 * row_bbox.height.min(bbox.height) =>
+            {
+                *row_bbox = row_bbox.union(&bbox);
+                row_lines.push(line);
+            }
+            _ => rows.push((bbox, vec![line])),
+        }
+    }
+
+    let mut tables = Vec::new();
+    let mut candidate = TableCandidate::default();
+    for (row_bbox, mut cells) in rows {
+        cells.sort_by(|a, b| line_bbox(a).left.total_cmp(&line_bbox(b).left));
+        if cells.len() >= 2 && candidate.accepts(&row_bbox, &cells) {
+            candidate.push(row_bbox, cells);
+            continue;
+        }
+
+        if let Some(table) = std::mem::take(&mut candidate).finish() {
+            tables.push(table);
+        }
+        if cells.len() >= 2 {
+            candidate.push(row_bbox, cells);
+        }
+    }
+    if let Some(table) = candidate.finish() {
+        tables.push(table);
+    }
+    tables
+}
+
+#[derive(Default)]
+struct TableCandidate<'a> {
+    bbox: Option<BoundingBox>,
+    // horizontal extent of each column
+    columns: Vec<(f64, f64)>,
+    rows: Vec<Vec<&'a OcrLine>>,
+}
+
+impl<'a> TableCandidate<'a> {
+    fn column_of(&self, bbox: &BoundingBox) -> Option<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, (left, right))| (i, right.min(bbox.right()) - left.max(bbox.left)))
+            .filter(|(_, overlap)| *overlap > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// A row continues the table when it is right below it and at least two of its cells
+    /// fall in distinct existing columns
+    fn accepts(&self, row_bbox: &BoundingBox, cells: &[&OcrLine]) -> bool {
+        let Some(bbox) = self.bbox else {
+            return true;
+        };
+        if row_bbox.top - bbox.bottom() > BLOCK_GAP * 2.0 * row_bbox.height {
+            return false;
+        }
+        let mut matched: Vec<usize> = cells
+            .iter()
+            .filter_map(|cell| self.column_of(&line_bbox(cell)))
+            .collect();
+        matched.sort_unstable();
+        matched.dedup();
+        matched.len() >= 2
+    }
+
+    fn push(&mut self, row_bbox: BoundingBox, cells: Vec<&'a OcrLine>) {
+        for cell in &cells {
+            let cell_bbox = line_bbox(cell);
+            match self.column_of(&cell_bbox) {
+                Some(i) => {
+                    let (left, right) = &mut self.columns[i];
+                    *left = left.min(cell_bbox.left);
+                    *right = right.max(cell_bbox.right());
+                }
+                None => self.columns.push((cell_bbox.left, cell_bbox.right())),
+            }
+        }
+        self.bbox = Some(match self.bbox {
+            Some(bbox) => bbox.union(&row_bbox),
+            None => row_bbox,
+        });
+        self.rows.push(cells);
+    }
+
+    fn finish(mut self) -> Option<OcrTable> {
+        let cells: Vec<&&OcrLine> = self.rows.iter().flatten().collect();
+        let words_per_cell =
+            cells.iter().map(|cell| cell.words.len()).sum::<usize>() as f64 / cells.len() as f64;
+        if self.rows.len() < 2 || self.columns.len() < 2 || words_per_cell > MAX_TABLE_CELL_WORDS {
+            return None;
+        }
+
+        self.columns.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let rows = self
+            .rows
+            .iter()
+            .map(|cells| {
+                let mut row = vec![String::new(); self.columns.len()];
+                for cell in cells {
+                    if let Some(i) = self.column_of(&line_bbox(cell)) {
+                        if !row[i].is_empty() {
+                            row[i].push(' ');
+                        }
+                        row[i].push_str(&cell.text);
+                    }
+                }
+                row
+            })
+            .collect();
+
+        Some(OcrTable {
+            bbox: self.bbox?,
+            rows,
+        })
+    }
+}
+
+fn unpositioned_block(words: Vec<OcrWord>) -> OcrBlock {
+    let line = OcrLine {
+        text: words
+            .iter()
+            .map(|word| word.text.trim())
+            .collect::<Vec<_>>()
+            .join(" "),
+        bbox: None,
+        words,
+    };
+    OcrBlock {
+        bbox: None,
+        paragraphs: vec![OcrParagraph {
+            bbox: None,
+            lines: vec![line],
+        }],
+    }
+}
+
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
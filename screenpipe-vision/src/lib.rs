@@ -2,22 +2,35 @@
 pub mod apple;
 pub mod core;
 pub mod custom_ocr;
+pub mod frame_source;
+pub mod layout;
 #[cfg(target_os = "windows")]
 pub mod microsoft;
 pub mod monitor;
+pub mod ocr_cache;
+pub mod ocr_ensemble;
+pub mod ocr_pool;
+#[cfg(target_os = "linux")]
+pub mod run_ui_monitoring_linux;
 #[cfg(target_os = "macos")]
 pub mod run_ui_monitoring_macos;
 pub mod tesseract;
 pub mod utils;
 #[cfg(target_os = "macos")]
 pub use apple::perform_ocr_apple;
-pub use core::{continuous_capture, process_ocr_task, CaptureResult, RealtimeVisionEvent, UIFrame};
+pub use core::{
+    continuous_capture, continuous_capture_from_source, process_ocr_task, CaptureResult,
+    RealtimeVisionEvent, UIFrame,
+};
 // pub use types::CaptureResult;
 pub use utils::OcrEngine;
+pub mod capture_rate;
 pub mod capture_screenshot_by_window;
 pub use custom_ocr::perform_ocr_custom;
 #[cfg(target_os = "windows")]
 pub use microsoft::perform_ocr_windows;
+#[cfg(target_os = "linux")]
+pub use run_ui_monitoring_linux::run_ui;
 #[cfg(target_os = "macos")]
 pub use run_ui_monitoring_macos::run_ui;
 pub use tesseract::perform_ocr_tesseract;
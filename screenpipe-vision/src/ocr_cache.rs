@@ -0,0 +1,119 @@
+use crate::layout::OcrLayout;
+use crate::utils::OcrEngine;
+use std::collections::HashMap;
+
+/// Passes a window can stay out of view before its cached OCR is dropped
+const MAX_IDLE_PASSES: u64 = 30;
+
+/// OCR output of a window, reused while the window image stays the same
+#[derive(Clone, Debug)]
+pub struct CachedWindowOcr {
+    pub text: String,
+    pub text_json: Vec<HashMap<String, String>>,
+    pub confidence: Option<f64>,
+    pub layout: OcrLayout,
+    /// Engine that produced the result
+    pub engine: OcrEngine,
+}
+
+struct CacheEntry {
+    image_hash: u64,
+    ocr: CachedWindowOcr,
+    last_pass: u64,
+}
+
+/// Cache of per-window OCR results, keyed by app, window title and image hash.
+///
+/// The whole-monitor diff decides whether a frame is OCR'd at all, but once it is, windows that
+/// did not change since the previous pass reuse their previous result instead of being OCR'd
+/// again. Only the latest image of each window is kept.
+#[derive(Default)]
+pub struct WindowOcrCache {
+    entries: HashMap<(String, String), CacheEntry>,
+    pass: u64,
+    pass_hits: u64,
+    pass_lookups: u64,
+    total_hits: u64,
+    total_lookups: u64,
+}
+
+impl WindowOcrCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts an OCR pass over the windows of a frame, resetting the pass statistics
+    pub fn begin_pass(&mut self) {
+        self.pass += 1;
+        self.pass_hits = 0;
+        self.pass_lookups = 0;
+    }
+
+    /// Returns the cached OCR of the window if its image did not change
+    pub fn get(
+        &mut self,
+        app_name: &str,
+        window_name: &str,
+        image_hash: u64,
+    ) -> Option<CachedWindowOcr> {
+        self.pass_lookups += 1;
+        self.total_lookups += 1;
+
+        let pass = self.pass;
+        let entry = self
+            .entries
+            .get_mut(&(app_name.to_string(), window_name.to_string()))
+            .filter(|entry| entry.image_hash == image_hash)?;
+        entry.last_pass = pass;
+
+        self.pass_hits += 1;
+        self.total_hits += 1;
+        Some(entry.ocr.clone())
+    }
+
+    pub fn insert(
+        &mut self,
+        app_name: &str,
+        window_name: &str,
+        image_hash: u64,
+        ocr: CachedWindowOcr,
+    ) {
+        self.entries.insert(
+            (app_name.to_string(), window_name.to_string()),
+            CacheEntry {
+                image_hash,
+                ocr,
+                last_pass: self.pass,
+            },
+        );
+    }
+
+    /// Drops windows that have not been seen for a while
+    pub fn end_pass(&mut self) {
+        let pass = self.pass;
+        self.entries
+            .retain(|_, entry| pass - entry.last_pass <= MAX_IDLE_PASSES);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hits and lookups of the current pass
+    pub fn pass_stats(&self) -> (u64, u64) {
+        (self.pass_hits, self.pass_lookups)
+    }
+
+    /// Share of lookups served from the cache since it was created
+    pub fn hit_rate(&self) -> f64 {
+        if self.total_lookups == 0 {
+            0.0
+        } else {
+            self.total_hits as f64 / self.total_lookups as f64
+        }
+    }
+}
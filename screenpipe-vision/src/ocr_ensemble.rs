@@ -0,0 +1,54 @@
+use crate::ocr_cache::CachedWindowOcr;
+use crate::utils::OcrEngine;
+
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.6;
+
+/// Two engines used together: every window goes through the fast primary engine, and windows
+/// it is not confident about are OCR'd again by the secondary engine. The most confident
+/// result is kept.
+#[derive(Clone, Debug)]
+pub struct OcrEnsemble {
+    pub primary: Box<OcrEngine>,
+    pub secondary: Box<OcrEngine>,
+    /// Normalized confidence, from 0 to 1, below which a window is OCR'd again
+    pub min_confidence: f64,
+}
+
+impl OcrEnsemble {
+    pub fn new(primary: OcrEngine, secondary: OcrEngine, min_confidence: f64) -> Self {
+        Self {
+            primary: Box::new(primary),
+            secondary: Box::new(secondary),
+            min_confidence,
+        }
+    }
+
+    /// Whether the primary result is not confident enough to keep without a second opinion.
+    /// Results without a confidence are always OCR'd again.
+    pub fn needs_second_opinion(&self, primary: &CachedWindowOcr) -> bool {
+        !matches!(normalized_confidence(primary), Some(c) if c >= self.min_confidence)
+    }
+
+    /// Keeps the secondary result only when it is more confident than the primary one
+    pub fn pick(&self, primary: CachedWindowOcr, secondary: CachedWindowOcr) -> CachedWindowOcr {
+        let primary_confidence = normalized_confidence(&primary).unwrap_or(-1.0);
+        let secondary_confidence = normalized_confidence(&secondary).unwrap_or(-1.0);
+        if secondary_confidence > primary_confidence {
+            secondary
+        } else {
+            primary
+        }
+    }
+}
+
+/// Confidence of a result on a 0 to 1 scale, whatever engine produced it. Tesseract reports
+/// 0 to 100 and apple the sum over its text observations.
+pub fn normalized_confidence(ocr: &CachedWindowOcr) -> Option<f64> {
+    let confidence = ocr.confidence?;
+    let normalized = match ocr.engine {
+        OcrEngine::Tesseract => confidence / 100.0,
+        OcrEngine::AppleNative => confidence / ocr.text_json.len().max(1) as f64,
+        _ => confidence,
+    };
+    Some(normalized.clamp(0.0, 1.0))
+}
@@ -0,0 +1,361 @@
+use crate::core::{process_ocr_task, OcrTaskData};
+use crate::ocr_cache::WindowOcrCache;
+use crate::utils::OcrEngine;
+use screenpipe_core::Language;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{debug, error, info, warn};
+
+pub const DEFAULT_OCR_QUEUE_SIZE: usize = 8;
+const METRICS_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What happened to a job pushed on a full or busy queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueOutcome {
+    Queued,
+    /// Replaced the job its source already had waiting
+    Coalesced,
+    /// The queue was full and its oldest job was dropped
+    DroppedOldest,
+}
+
+/// Bounded FIFO of jobs from several sources.
+///
+/// A source has at most one job waiting: a newer frame replaces the queued one in place, since
+/// only the latest state of a screen is worth OCR'ing. When the queue is full the oldest job
+/// is dropped.
+pub struct CoalescingQueue<T> {
+    jobs: VecDeque<(String, Instant, T)>,
+    capacity: usize,
+}
+
+impl<T> CoalescingQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            jobs: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, source: &str, job: T) -> QueueOutcome {
+        if let Some(queued) = self.jobs.iter_mut().find(|(s, _, _)| s == source) {
+            // the frame keeps its place and enqueue time, so waits count from the first frame
+            queued.2 = job;
+            return QueueOutcome::Coalesced;
+        }
+
+        let outcome = if self.jobs.len() >= self.capacity {
+            self.jobs.pop_front();
+            QueueOutcome::DroppedOldest
+        } else {
+            QueueOutcome::Queued
+        };
+        self.jobs
+            .push_back((source.to_string(), Instant::now(), job));
+        outcome
+    }
+
+    /// Oldest job with the time it spent waiting
+    pub fn pop(&mut self) -> Option<(String, Duration, T)> {
+        self.jobs
+            .pop_front()
+            .map(|(source, queued_at, job)| (source, queued_at.elapsed(), job))
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Frames OCR'd with one engine and how long it took
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OcrEngineLatency {
+    pub engine: String,
+    pub frames: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OcrPoolMetrics {
+    pub workers: usize,
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub submitted: u64,
+    pub coalesced: u64,
+    pub dropped: u64,
+    pub completed: u64,
+    pub failed: u64,
+    /// Time frames waited in the queue before a worker picked them up
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+    pub engines: Vec<OcrEngineLatency>,
+}
+
+#[derive(Default)]
+struct PoolStats {
+    submitted: u64,
+    coalesced: u64,
+    dropped: u64,
+    completed: u64,
+    failed: u64,
+    waited: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+    // engine -> (frames, total time, max time)
+    engines: HashMap<&'static str, (u64, Duration, Duration)>,
+}
+
+struct OcrJob {
+    data: OcrTaskData,
+    engine: OcrEngine,
+    languages: Vec<Language>,
+    cache: Arc<tokio::sync::Mutex<WindowOcrCache>>,
+}
+
+/// OCR workers shared by all capture sources, so a slow engine delays OCR results instead
+/// of the captures themselves
+pub struct OcrPool {
+    queue: Mutex<CoalescingQueue<OcrJob>>,
+    job_ready: Notify,
+    closed: AtomicBool,
+    closed_signal: Notify,
+    workers: usize,
+    stats: Mutex<PoolStats>,
+}
+
+impl OcrPool {
+    /// Starts the workers on the current runtime
+    pub fn new(workers: usize, queue_size: usize) -> Arc<Self> {
+        let workers = workers.max(1);
+        let pool = Arc::new(Self {
+            queue: Mutex::new(CoalescingQueue::new(queue_size)),
+            job_ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            closed_signal: Notify::new(),
+            workers,
+            stats: Mutex::new(PoolStats::default()),
+        });
+        info!(
+            "starting ocr pool with {} workers, queue size {}",
+            workers,
+            queue_size.max(1)
+        );
+
+        for worker in 0..workers {
+            tokio::spawn(pool.clone().run_worker(worker));
+        }
+        tokio::spawn(pool.clone().log_metrics());
+        pool
+    }
+
+    /// Access to the pool for one capture source, with its own window cache
+    pub fn source(
+        self: &Arc<Self>,
+        name: &str,
+        engine: OcrEngine,
+        languages: Vec<Language>,
+    ) -> OcrSource {
+        OcrSource {
+            pool: self.clone(),
+            name: name.to_string(),
+            engine,
+            languages,
+            cache: Arc::new(tokio::sync::Mutex::new(WindowOcrCache::new())),
+        }
+    }
+
+    /// Lets the workers finish the queued jobs and exit
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.job_ready.notify_waiters();
+        self.closed_signal.notify_waiters();
+    }
+
+    pub fn metrics(&self) -> OcrPoolMetrics {
+        let (queue_depth, queue_capacity) = {
+            let queue = self.queue.lock().unwrap();
+            (queue.len(), queue.capacity())
+        };
+        let stats = self.stats.lock().unwrap();
+
+        let mut engines: Vec<OcrEngineLatency> = stats
+            .engines
+            .iter()
+            .map(|(engine, (frames, total, max))| OcrEngineLatency {
+                engine: engine.to_string(),
+                frames: *frames,
+                avg_ms: total.as_secs_f64() * 1000.0 / (*frames).max(1) as f64,
+                max_ms: max.as_secs_f64() * 1000.0,
+            })
+            .collect();
+        engines.sort_by(|a, b| a.engine.cmp(&b.engine));
+
+        OcrPoolMetrics {
+            workers: self.workers,
+            queue_depth,
+            queue_capacity,
+            submitted: stats.submitted,
+            coalesced: stats.coalesced,
+            dropped: stats.dropped,
+            completed: stats.completed,
+            failed: stats.failed,
+            avg_wait_ms: stats.total_wait.as_secs_f64() * 1000.0 / stats.waited.max(1) as f64,
+            max_wait_ms: stats.max_wait.as_secs_f64() * 1000.0,
+            engines,
+        }
+    }
+
+    fn submit(&self, source: &str, job: OcrJob) -> QueueOutcome {
+        let outcome = self.queue.lock().unwrap().push(source, job);
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.submitted += 1;
+            match outcome {
+                QueueOutcome::Queued => {}
+                QueueOutcome::Coalesced => stats.coalesced += 1,
+                QueueOutcome::DroppedOldest => stats.dropped += 1,
+            }
+        }
+        match outcome {
+            QueueOutcome::Coalesced => {
+                debug!("ocr still busy, replaced the queued frame of {}", source)
+            }
+            QueueOutcome::DroppedOldest => warn!("ocr queue is full, dropped the oldest frame"),
+            QueueOutcome::Queued => {}
+        }
+        self.job_ready.notify_one();
+        outcome
+    }
+
+    async fn run_worker(self: Arc<Self>, worker: usize) {
+        debug!("ocr worker {} started", worker);
+        loop {
+            // register before checking the queue so a close or a new job is not missed
+            let job_ready = self.job_ready.notified();
+            tokio::pin!(job_ready);
+            job_ready.as_mut().enable();
+
+            let next = self.queue.lock().unwrap().pop();
+            let Some((source, waited, job)) = next else {
+                if self.closed.load(Ordering::SeqCst) {
+                    debug!("ocr worker {} stopped", worker);
+                    return;
+                }
+                job_ready.await;
+                continue;
+            };
+
+            let engine = engine_name(&job.engine);
+            let start = Instant::now();
+            let result = {
+                // frames of one source are OCR'd in order, one at a time
+                let mut cache = job.cache.lock().await;
+                process_ocr_task(job.data, &job.engine, job.languages, &mut cache).await
+            };
+            if let Err(e) = &result {
+                error!("ocr of a frame from {} failed: {}", source, e);
+            }
+            self.record_job(engine, waited, start.elapsed(), result.is_ok());
+        }
+    }
+
+    fn record_job(&self, engine: &'static str, waited: Duration, elapsed: Duration, ok: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.waited += 1;
+        stats.total_wait += waited;
+        stats.max_wait = stats.max_wait.max(waited);
+        let (frames, total, max) = stats.engines.entry(engine).or_default();
+        *frames += 1;
+        *total += elapsed;
+        *max = (*max).max(elapsed);
+        if ok {
+            stats.completed += 1;
+        } else {
+            stats.failed += 1;
+        }
+    }
+
+    async fn log_metrics(self: Arc<Self>) {
+        loop {
+            let closed = self.closed_signal.notified();
+            tokio::pin!(closed);
+            closed.as_mut().enable();
+            if self.closed.load(Ordering::SeqCst) {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(METRICS_LOG_INTERVAL) => {}
+                _ = closed => return,
+            }
+
+            let metrics = self.metrics();
+            let engines = metrics
+                .engines
+                .iter()
+                .map(|e| format!("{} {:.0}ms avg/{:.0}ms max", e.engine, e.avg_ms, e.max_ms))
+                .collect::<Vec<_>>()
+                .join(", ");
+            info!(
+                "ocr pool: queue {}/{}, {} frames done, {} failed, {} coalesced, {} dropped, wait {:.0}ms avg/{:.0}ms max, {}",
+                metrics.queue_depth,
+                metrics.queue_capacity,
+                metrics.completed,
+                metrics.failed,
+                metrics.coalesced,
+                metrics.dropped,
+                metrics.avg_wait_ms,
+                metrics.max_wait_ms,
+                engines
+            );
+        }
+    }
+}
+
+/// A capture source's handle on the pool: where its frames go, with its OCR settings
+pub struct OcrSource {
+    pool: Arc<OcrPool>,
+    name: String,
+    engine: OcrEngine,
+    languages: Vec<Language>,
+    cache: Arc<tokio::sync::Mutex<WindowOcrCache>>,
+}
+
+impl OcrSource {
+    /// Queues a frame for OCR without waiting for it
+    pub fn submit(&self, data: OcrTaskData) -> QueueOutcome {
+        self.pool.submit(
+            &self.name,
+            OcrJob {
+                data,
+                engine: self.engine.clone(),
+                languages: self.languages.clone(),
+                cache: self.cache.clone(),
+            },
+        )
+    }
+}
+
+fn engine_name(engine: &OcrEngine) -> &'static str {
+    match engine {
+        OcrEngine::Unstructured => "unstructured",
+        OcrEngine::Tesseract => "tesseract",
+        OcrEngine::WindowsNative => "windows-native",
+        OcrEngine::AppleNative => "apple-native",
+        OcrEngine::Custom(_) => "custom",
+        OcrEngine::Ensemble(_) => "ensemble",
+    }
+}
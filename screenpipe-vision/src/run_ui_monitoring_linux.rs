@@ -0,0 +1,99 @@
+use crate::UIFrame;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use screenpipe_core::{Desktop, UIElement};
+use screenpipe_db::DatabaseManager;
+use screenpipe_events::send_event;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How deep below the window the accessibility tree is read for text
+const MAX_TEXT_DEPTH: usize = 30;
+
+struct FocusedWindow {
+    app: String,
+    window: String,
+    text_output: String,
+}
+
+/// Climbs from the focused element to its top-level window and reads the window text
+fn read_focused_window(desktop: &Desktop) -> Result<FocusedWindow> {
+    let mut window: UIElement = desktop.focused_element()?;
+    let app = loop {
+        match window.parent()? {
+            Some(parent) if parent.role() == "application" => break parent,
+            Some(parent) => window = parent,
+            None => break window.clone(),
+        }
+    };
+
+    Ok(FocusedWindow {
+        app: app.attributes().label.unwrap_or_default(),
+        window: window.attributes().label.unwrap_or_default(),
+        text_output: window.text(MAX_TEXT_DEPTH)?,
+    })
+}
+
+/// Polls the focused window through AT-SPI, stores its text in `ui_monitoring`
+/// and emits `ui_frame` events like the macOS ui_monitor does
+pub async fn run_ui(db: Arc<DatabaseManager>) -> Result<()> {
+    info!("starting ui monitoring service...");
+
+    let desktop = Arc::new(tokio::task::spawn_blocking(|| Desktop::new(false, false)).await??);
+    let mut last: Option<(String, String, String)> = None;
+    let mut initial_traversal_at: DateTime<Utc> = Utc::now();
+
+    loop {
+        let desktop_clone = desktop.clone();
+        let focused =
+            tokio::task::spawn_blocking(move || read_focused_window(&desktop_clone)).await?;
+
+        match focused {
+            Ok(focused) if !focused.text_output.is_empty() => {
+                let same_window = matches!(&last, Some((app, window, _)) if *app == focused.app && *window == focused.window);
+                let unchanged = same_window
+                    && matches!(&last, Some((_, _, text)) if *text == focused.text_output);
+                if !same_window {
+                    initial_traversal_at = Utc::now();
+                }
+
+                if !unchanged {
+                    debug!(
+                        "ui frame for {} / {}: {} chars",
+                        focused.app,
+                        focused.window,
+                        focused.text_output.len()
+                    );
+                    if let Err(e) = db
+                        .insert_ui_monitoring(
+                            &focused.app,
+                            &focused.window,
+                            &focused.text_output,
+                            initial_traversal_at,
+                        )
+                        .await
+                    {
+                        error!("failed to insert ui monitoring text: {}", e);
+                    }
+
+                    let _ = send_event(
+                        "ui_frame",
+                        UIFrame {
+                            window: focused.window.clone(),
+                            app: focused.app.clone(),
+                            text_output: focused.text_output.clone(),
+                            initial_traversal_at: initial_traversal_at.to_rfc3339(),
+                        },
+                    );
+                    last = Some((focused.app, focused.window, focused.text_output));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => debug!("no focused window to monitor: {}", e),
+        }
+
+        sleep(POLL_INTERVAL).await;
+    }
+}
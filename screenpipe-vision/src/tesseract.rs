@@ -1,12 +1,25 @@
+use crate::layout::{BoundingBox, OcrLayout, OcrWord};
 use image::DynamicImage;
 use rusty_tesseract::{Args, DataOutput, Image};
 use screenpipe_core::{Language, TESSERACT_LANGUAGES};
 use std::collections::HashMap;
 
+/// Tesseract level of the records describing single words
+const WORD_LEVEL: i32 = 5;
+
 pub fn perform_ocr_tesseract(
     image: &DynamicImage,
     languages: Vec<Language>,
 ) -> (String, String, Option<f64>) {
+    let (text, json_output, confidence, _) = perform_ocr_tesseract_with_layout(image, languages);
+    (text, json_output, confidence)
+}
+
+/// Same as `perform_ocr_tesseract`, also returning the layout built from the word boxes
+pub fn perform_ocr_tesseract_with_layout(
+    image: &DynamicImage,
+    languages: Vec<Language>,
+) -> (String, String, Option<f64>, OcrLayout) {
     let language_string = match languages.is_empty() {
         true => "eng".to_string(),
         _ => TESSERACT_LANGUAGES
@@ -40,8 +53,29 @@ pub fn perform_ocr_tesseract(
     let json_output = data_output_to_json(&data_output);
 
     let overall_confidence = calculate_overall_confidence(&data_output);
+    let layout = data_output_to_layout(&data_output, image.width(), image.height());
+
+    (text, json_output, Some(overall_confidence), layout)
+}
 
-    (text, json_output, Some(overall_confidence))
+fn data_output_to_layout(data_output: &DataOutput, width: u32, height: u32) -> OcrLayout {
+    let words = data_output
+        .data
+        .iter()
+        .filter(|record| record.level == WORD_LEVEL && !record.text.trim().is_empty())
+        .map(|record| OcrWord {
+            text: record.text.clone(),
+            bbox: Some(BoundingBox::new(
+                record.left as f64,
+                record.top as f64,
+                record.width as f64,
+                record.height as f64,
+            )),
+            // tesseract reports -1 for words it did not recognize
+            confidence: (record.conf >= 0.0).then_some(record.conf as f64 / 100.0),
+        })
+        .collect();
+    OcrLayout::from_words(width, height, words)
 }
 
 fn data_output_to_text(data_output: &DataOutput) -> String {
@@ -4,6 +4,7 @@ use crate::capture_screenshot_by_window::{
 use crate::core::MaxAverageFrame;
 use crate::custom_ocr::CustomOcrConfig;
 use crate::monitor::SafeMonitor;
+use crate::ocr_ensemble::OcrEnsemble;
 use image::DynamicImage;
 use image_compare::{Algorithm, Metric, Similarity};
 use tracing::{debug, warn};
@@ -19,6 +20,8 @@ pub enum OcrEngine {
     WindowsNative,
     AppleNative,
     Custom(CustomOcrConfig),
+    /// A primary engine with low confidence windows OCR'd again by a secondary engine
+    Ensemble(OcrEnsemble),
 }
 
 impl From<OcrEngine> for screenpipe_db::OcrEngine {
@@ -31,6 +34,8 @@ impl From<OcrEngine> for screenpipe_db::OcrEngine {
             OcrEngine::Custom(config) => {
                 screenpipe_db::OcrEngine::Custom(DBCustomOcrConfig::from(config))
             }
+            // results record the engine that produced them, this is only a fallback
+            OcrEngine::Ensemble(ensemble) => (*ensemble.primary).into(),
         }
     }
 }
@@ -0,0 +1,109 @@
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod tests {
+    use screenpipe_vision::browser_utils::{
+        chromium_session_url, decode_mozlz4, firefox_session_url,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_decode_mozlz4() {
+        let mut file = b"mozLz40\0".to_vec();
+        file.extend_from_slice(&15u32.to_le_bytes());
+        // "hello abc" as literals, then "abc" repeated through an overlapping match
+        file.extend_from_slice(&[0x92]);
+        file.extend_from_slice(b"hello abc");
+        file.extend_from_slice(&[3, 0]);
+        assert_eq!(decode_mozlz4(&file).unwrap(), b"hello abcabcabc");
+
+        assert!(decode_mozlz4(b"not lz4 at all").is_err());
+        let mut truncated = b"mozLz40\0".to_vec();
+        truncated.extend_from_slice(&15u32.to_le_bytes());
+        truncated.extend_from_slice(&[0x92, b'h']);
+        assert!(decode_mozlz4(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_firefox_session_url() {
+        let session = json!({
+            "selectedWindow": 2,
+            "windows": [
+                {"selected": 1, "tabs": [{"index": 1, "entries": [{"url": "https://one.example"}]}]},
+                {"selected": 2, "tabs": [
+                    {"index": 1, "entries": [{"url": "https://two.example"}]},
+                    {"index": 2, "entries": [
+                        {"url": "https://back.example"},
+                        {"url": "https://active.example/page"},
+                        {"url": "https://forward.example"}
+                    ]}
+                ]}
+            ]
+        });
+        assert_eq!(
+            firefox_session_url(&session).as_deref(),
+            Some("https://active.example/page")
+        );
+
+        let internal = json!({"windows": [{"tabs": [{"entries": [{"url": "about:newtab"}]}]}]});
+        assert_eq!(firefox_session_url(&internal), None);
+    }
+
+    fn command(id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = ((payload.len() + 1) as u16).to_le_bytes().to_vec();
+        bytes.push(id);
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn navigation(tab: i32, index: i32, url: &str) -> Vec<u8> {
+        let mut payload = ints(&[0, tab, index, url.len() as i32]);
+        payload.extend_from_slice(url.as_bytes());
+        // padding and the rest of the navigation are ignored
+        payload.extend_from_slice(&[0; 8]);
+        command(6, &payload)
+    }
+
+    #[test]
+    fn test_chromium_session_url() {
+        let mut session = b"SNSS".to_vec();
+        session.extend_from_slice(&3i32.to_le_bytes());
+        // window 1 with tabs 10 and 11, window 2 with tab 20
+        session.extend(command(0, &ints(&[1, 10])));
+        session.extend(command(2, &ints(&[10, 0])));
+        session.extend(command(0, &ints(&[1, 11])));
+        session.extend(command(2, &ints(&[11, 1])));
+        session.extend(command(0, &ints(&[2, 20])));
+        session.extend(command(2, &ints(&[20, 0])));
+        session.extend(navigation(10, 0, "https://first.example"));
+        session.extend(navigation(11, 0, "https://old.example"));
+        session.extend(navigation(11, 1, "https://active.example/"));
+        session.extend(navigation(20, 0, "https://other-window.example"));
+        session.extend(command(7, &ints(&[11, 1])));
+        session.extend(command(8, &ints(&[1, 1])));
+        session.extend(command(8, &ints(&[2, 0])));
+        session.extend(command(20, &ints(&[1])));
+
+        assert_eq!(
+            chromium_session_url(&session).as_deref(),
+            Some("https://active.example/")
+        );
+
+        // without selection commands the last navigation is used
+        let mut partial = b"SNSS".to_vec();
+        partial.extend_from_slice(&3i32.to_le_bytes());
+        partial.extend(navigation(10, 0, "https://first.example"));
+        partial.extend(navigation(10, 1, "https://last.example"));
+        // a truncated command at the end is ignored
+        partial.extend_from_slice(&[40, 0, 6]);
+        assert_eq!(
+            chromium_session_url(&partial).as_deref(),
+            Some("https://last.example")
+        );
+
+        assert_eq!(chromium_session_url(b"garbage"), None);
+    }
+}
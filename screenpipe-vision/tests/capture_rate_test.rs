@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use screenpipe_vision::capture_rate::{
+        capture_rates, AdaptiveCaptureRate, CaptureRateConfig, SystemPressure,
+    };
+    use std::time::Duration;
+
+    fn config() -> CaptureRateConfig {
+        CaptureRateConfig {
+            min_fps: 0.2,
+            max_fps: 2.0,
+            diff_threshold: 0.01,
+        }
+    }
+
+    #[test]
+    fn test_capture_rate_follows_activity() {
+        let mut rate = AdaptiveCaptureRate::new(config());
+        let idle = SystemPressure::default();
+
+        for _ in 0..50 {
+            rate.update(0.0, idle);
+        }
+        assert_eq!(rate.effective_fps(), 0.2);
+
+        // a burst of changes ramps up quickly
+        rate.update(0.5, idle);
+        rate.update(0.5, idle);
+        assert_eq!(rate.effective_fps(), 0.8);
+        for _ in 0..3 {
+            rate.update(0.5, idle);
+        }
+        assert_eq!(rate.update(0.5, idle), Duration::from_millis(500));
+
+        // one quiet frame only slows down a little
+        rate.update(0.001, idle);
+        assert!(rate.effective_fps() > 1.5 && rate.effective_fps() < 2.0);
+    }
+
+    #[test]
+    fn test_capture_rate_backs_off_under_pressure() {
+        let mut rate = AdaptiveCaptureRate::new(config());
+
+        let on_battery = SystemPressure {
+            on_battery: true,
+            ..Default::default()
+        };
+        rate.update(0.5, on_battery);
+        assert_eq!(rate.effective_fps(), 1.0);
+
+        let overloaded = SystemPressure {
+            high_cpu: true,
+            high_memory: true,
+            on_battery: true,
+        };
+        rate.update(0.5, overloaded);
+        assert_eq!(rate.effective_fps(), 0.25);
+
+        // never below the floor
+        for _ in 0..10 {
+            rate.update(0.0, overloaded);
+        }
+        assert_eq!(rate.effective_fps(), 0.2);
+
+        // the screen activity alone drives the rate once the pressure is gone
+        rate.update(0.5, SystemPressure::default());
+        assert!(rate.effective_fps() > 0.5);
+    }
+
+    #[test]
+    fn test_capture_rate_is_published_per_device() {
+        let rate =
+            AdaptiveCaptureRate::new(CaptureRateConfig::fixed(1.0)).with_device_name("test_rates");
+        assert_eq!(capture_rates().get("test_rates"), Some(&1.0));
+
+        drop(rate);
+        assert!(!capture_rates().contains_key("test_rates"));
+    }
+
+    #[test]
+    fn test_capture_rate_config_validation() {
+        assert!(config().validate().is_ok());
+        assert!(CaptureRateConfig::fixed(0.5).validate().is_ok());
+
+        let inverted = CaptureRateConfig {
+            min_fps: 2.0,
+            max_fps: 1.0,
+            ..config()
+        };
+        assert!(inverted.validate().is_err());
+        assert!(CaptureRateConfig::fixed(0.0).validate().is_err());
+        let threshold = CaptureRateConfig {
+            diff_threshold: 1.5,
+            ..config()
+        };
+        assert!(threshold.validate().is_err());
+    }
+}
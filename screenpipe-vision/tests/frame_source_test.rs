@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+    use screenpipe_vision::frame_source::{
+        read_png, FrameSource, FrameSourceConfig, ImageDirectorySource, PngStreamSource,
+    };
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn png(shade: u8) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 4, Rgb([shade; 3])));
+        let mut buf = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_parse_frame_source() {
+        assert_eq!(
+            FrameSourceConfig::parse("monitor:2").unwrap(),
+            FrameSourceConfig::Monitor(2)
+        );
+        assert_eq!(
+            FrameSourceConfig::parse("dir:/tmp/frames?loop=true").unwrap(),
+            FrameSourceConfig::ImageDirectory {
+                path: PathBuf::from("/tmp/frames"),
+                looping: true
+            }
+        );
+        assert_eq!(
+            FrameSourceConfig::parse("video:/tmp/meeting.mp4").unwrap(),
+            FrameSourceConfig::Video {
+                path: PathBuf::from("/tmp/meeting.mp4")
+            }
+        );
+        assert_eq!(
+            FrameSourceConfig::parse("-").unwrap(),
+            FrameSourceConfig::Stdin
+        );
+
+        assert!(FrameSourceConfig::parse("webcam:0").is_err());
+        assert!(FrameSourceConfig::parse("video:").is_err());
+        assert!(FrameSourceConfig::parse("dir:/tmp/frames?repeat").is_err());
+    }
+
+    #[test]
+    fn test_frame_source_properties() {
+        let monitor = FrameSourceConfig::Monitor(1);
+        assert!(!monitor.is_finite());
+        assert_eq!(monitor.monitor_id(), Some(1));
+        assert_eq!(monitor.device_name(), "monitor_1");
+
+        let looping = FrameSourceConfig::parse("dir:/tmp/frames?loop=true").unwrap();
+        assert!(!looping.is_finite());
+        assert_eq!(looping.device_name(), "dir_frames");
+
+        let video = FrameSourceConfig::parse("video:/tmp/meeting.mp4").unwrap();
+        assert!(video.is_finite());
+        assert_eq!(video.monitor_id(), None);
+        assert_eq!(video.device_name(), "video_meeting");
+    }
+
+    #[tokio::test]
+    async fn test_image_directory_source() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.png"), png(2)).unwrap();
+        std::fs::write(dir.path().join("a.png"), png(1)).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a frame").unwrap();
+
+        let mut source = ImageDirectorySource::new(dir.path(), false).unwrap();
+        let first = source.next_frame().await.unwrap().unwrap();
+        let second = source.next_frame().await.unwrap().unwrap();
+        assert_eq!(first.window_images[0].window_name, "a.png");
+        assert_eq!(second.window_images[0].window_name, "b.png");
+        assert_ne!(first.image_hash, second.image_hash);
+        assert!(source.next_frame().await.unwrap().is_none());
+
+        let mut looping = ImageDirectorySource::new(dir.path(), true).unwrap();
+        for expected in ["a.png", "b.png", "a.png"] {
+            let frame = looping.next_frame().await.unwrap().unwrap();
+            assert_eq!(frame.window_images[0].window_name, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_png_stream_source() {
+        let mut stream = png(1);
+        stream.extend(png(200));
+
+        let mut source = PngStreamSource::new(Cursor::new(stream.clone()), "stdin");
+        let first = source.next_frame().await.unwrap().unwrap();
+        let second = source.next_frame().await.unwrap().unwrap();
+        assert_eq!(first.window_images[0].app_name, "stdin");
+        assert_eq!(first.image.width(), 8);
+        assert_ne!(first.image_hash, second.image_hash);
+        assert!(source.next_frame().await.unwrap().is_none());
+
+        // a stream cut off in the middle of an image is an error, not the end of the stream
+        let mut truncated = Cursor::new(stream[..stream.len() - 5].to_vec());
+        assert!(read_png(&mut truncated).await.unwrap().is_some());
+        assert!(read_png(&mut truncated).await.is_err());
+
+        let mut garbage = Cursor::new(b"definitely not a png".to_vec());
+        assert!(read_png(&mut garbage).await.is_err());
+    }
+}
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use screenpipe_vision::layout::{words_from_normalized_boxes, BoundingBox, OcrLayout, OcrWord};
+    use std::collections::HashMap;
+
+    const HEIGHT: f64 = 10.0;
+
+    /// Words of a line of text starting at the given position, 6px per character
+    fn words(text: &str, left: f64, top: f64) -> Vec<OcrWord> {
+        let mut left = left;
+        text.split_whitespace()
+            .map(|word| {
+                let width = 6.0 * word.len() as f64;
+                let bbox = BoundingBox::new(left, top, width, HEIGHT);
+                left += width + 4.0;
+                OcrWord {
+                    text: word.to_string(),
+                    bbox: Some(bbox),
+                    confidence: Some(0.9),
+                }
+            })
+            .collect()
+    }
+
+    fn block_texts(layout: &OcrLayout) -> Vec<String> {
+        layout
+            .blocks
+            .iter()
+            .map(|block| {
+                block
+                    .paragraphs
+                    .iter()
+                    .flat_map(|paragraph| &paragraph.lines)
+                    .map(|line| line.text.clone())
+                    .collect::<Vec<_>>()
+                    .join(" / ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_reads_columns_in_order() {
+        let mut all = Vec::new();
+        all.extend(words("Quarterly report for the whole team", 0.0, 0.0));
+        // right column first, words arrive in any order
+        all.extend(words("right column starts right here", 300.0, 30.0));
+        all.extend(words("and ends right there now", 300.0, 44.0));
+        all.extend(words("left column starts right here", 0.0, 30.0));
+        all.extend(words("and then continues below it", 0.0, 44.0));
+        all.extend(words("until the very end", 0.0, 58.0));
+
+        let layout = OcrLayout::from_words(600, 100, all);
+        assert_eq!(
+            block_texts(&layout),
+            vec![
+                "Quarterly report for the whole team",
+                "left column starts right here / and then continues below it / until the very end",
+                "right column starts right here / and ends right there now",
+            ]
+        );
+        assert_eq!(
+            layout.blocks[1].bbox,
+            Some(BoundingBox::new(0.0, 30.0, 166.0, 38.0))
+        );
+        assert!(layout.tables.is_empty());
+    }
+
+    #[test]
+    fn test_layout_splits_paragraphs_and_blocks() {
+        let mut all = Vec::new();
+        all.extend(words("first paragraph line one", 0.0, 0.0));
+        all.extend(words("first paragraph line two", 0.0, 13.0));
+        // a gap of one line height starts a paragraph, a larger one a block
+        all.extend(words("second paragraph", 0.0, 33.0));
+        all.extend(words("another block", 0.0, 70.0));
+
+        let layout = OcrLayout::from_words(400, 100, all);
+        assert_eq!(layout.blocks.len(), 2);
+        assert_eq!(layout.blocks[0].paragraphs.len(), 2);
+        assert_eq!(layout.blocks[0].paragraphs[0].lines.len(), 2);
+        assert_eq!(
+            layout.text(),
+            "first paragraph line one\nfirst paragraph line two\n\nsecond paragraph\n\nanother block"
+        );
+        assert_eq!(layout.blocks[0].paragraphs[0].lines[0].words.len(), 4);
+    }
+
+    #[test]
+    fn test_layout_detects_tables() {
+        let rows = [
+            ("Name", "Qty", "Price"),
+            ("Apples", "3", "1.20"),
+            ("Pears", "12", "0.80"),
+            ("Dried figs", "1", "4.50"),
+        ];
+        let mut all = Vec::new();
+        for (i, (name, qty, price)) in rows.iter().enumerate() {
+            let top = i as f64 * 14.0;
+            all.extend(words(name, 0.0, top));
+            all.extend(words(qty, 150.0, top));
+            all.extend(words(price, 250.0, top));
+        }
+        all.extend(words("Total due at the end of the month", 0.0, 100.0));
+
+        let layout = OcrLayout::from_words(400, 200, all);
+        assert_eq!(layout.tables.len(), 1);
+        let table = &layout.tables[0];
+        assert_eq!(table.rows.len(), 4);
+        assert_eq!(table.rows[0], vec!["Name", "Qty", "Price"]);
+        assert_eq!(table.rows[3], vec!["Dried figs", "1", "4.50"]);
+        assert_eq!(table.bbox.top, 0.0);
+        assert_eq!(table.bbox.bottom(), 52.0);
+    }
+
+    #[test]
+    fn test_layout_ignores_text_columns_as_tables() {
+        let mut all = Vec::new();
+        for i in 0..4 {
+            let top = i as f64 * 14.0;
+            all.extend(words("a long line of prose in a column", 0.0, top));
+            all.extend(words("another long line of prose here", 300.0, top));
+        }
+        let layout = OcrLayout::from_words(600, 100, all);
+        assert!(layout.tables.is_empty());
+        assert_eq!(layout.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_layout_from_text_and_redaction() {
+        let mut layout = OcrLayout::from_text(100, 100, "hello world\nmail me at a@b.c\n\nbye");
+        assert_eq!(layout.blocks.len(), 1);
+        assert_eq!(layout.blocks[0].paragraphs.len(), 2);
+        assert_eq!(layout.blocks[0].paragraphs[0].lines[1].words.len(), 4);
+        assert_eq!(layout.blocks[0].bbox, None);
+
+        layout.redact(|text| text.replace("a@b.c", "[EMAIL]"));
+        let line = &layout.blocks[0].paragraphs[0].lines[1];
+        assert_eq!(line.text, "mail me at [EMAIL]");
+        assert_eq!(line.words.len(), 1);
+        assert_eq!(line.words[0].text, "mail me at [EMAIL]");
+        // untouched lines keep their words
+        assert_eq!(layout.blocks[0].paragraphs[0].lines[0].words.len(), 2);
+    }
+
+    #[test]
+    fn test_words_from_normalized_boxes() {
+        let entry: HashMap<String, String> = [
+            ("text", "hello"),
+            ("left", "0.25"),
+            ("top", "0.5"),
+            ("width", "0.5"),
+            ("height", "0.25"),
+            ("conf", "0.8"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let words = words_from_normalized_boxes(&[entry, HashMap::new()], 200, 100);
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].text, "hello");
+        assert_eq!(words[0].confidence, Some(0.8));
+        // the origin moves from the bottom left to the top left corner
+        assert_eq!(
+            words[0].bbox,
+            Some(BoundingBox::new(50.0, 25.0, 100.0, 25.0))
+        );
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgb, RgbImage};
+    use screenpipe_vision::layout::OcrLayout;
+    use screenpipe_vision::ocr_cache::{CachedWindowOcr, WindowOcrCache};
+    use screenpipe_vision::utils::{calculate_hash, OcrEngine};
+
+    fn window_image(shade: u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 32, Rgb([shade, shade, shade])))
+    }
+
+    fn ocr(text: &str) -> CachedWindowOcr {
+        CachedWindowOcr {
+            text: text.to_string(),
+            text_json: vec![],
+            confidence: Some(0.9),
+            layout: OcrLayout::default(),
+            engine: OcrEngine::Tesseract,
+        }
+    }
+
+    #[test]
+    fn test_window_ocr_cache_reuses_unchanged_windows() {
+        let mut cache = WindowOcrCache::new();
+        let editor = calculate_hash(&window_image(10));
+        let editor_changed = calculate_hash(&window_image(20));
+        assert_ne!(editor, editor_changed);
+
+        cache.begin_pass();
+        assert!(cache.get("Code", "main.rs", editor).is_none());
+        cache.insert("Code", "main.rs", editor, ocr("fn main()"));
+        cache.end_pass();
+        assert_eq!(cache.pass_stats(), (0, 1));
+
+        cache.begin_pass();
+        assert_eq!(
+            cache.get("Code", "main.rs", editor).unwrap().text,
+            "fn main()"
+        );
+        // same image under another title or app is a different window
+        assert!(cache.get("Code", "lib.rs", editor).is_none());
+        assert!(cache.get("Terminal", "main.rs", editor).is_none());
+        // a changed window is OCR'd again and replaces the cached result
+        assert!(cache.get("Code", "main.rs", editor_changed).is_none());
+        cache.insert("Code", "main.rs", editor_changed, ocr("fn main() {}"));
+        cache.end_pass();
+
+        assert_eq!(cache.pass_stats(), (1, 4));
+        assert_eq!(cache.hit_rate(), 0.2);
+        assert!(cache.get("Code", "main.rs", editor).is_none());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_window_ocr_cache_drops_windows_out_of_view() {
+        let mut cache = WindowOcrCache::new();
+        let hash = calculate_hash(&window_image(10));
+
+        cache.begin_pass();
+        cache.insert("Slack", "general", hash, ocr("hello"));
+        cache.end_pass();
+
+        for _ in 0..30 {
+            cache.begin_pass();
+            cache.end_pass();
+        }
+        assert_eq!(cache.len(), 1);
+
+        cache.begin_pass();
+        cache.end_pass();
+        assert!(cache.is_empty());
+    }
+}
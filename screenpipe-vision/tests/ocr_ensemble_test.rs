@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use screenpipe_vision::custom_ocr::CustomOcrConfig;
+    use screenpipe_vision::layout::OcrLayout;
+    use screenpipe_vision::ocr_cache::CachedWindowOcr;
+    use screenpipe_vision::ocr_ensemble::{normalized_confidence, OcrEnsemble};
+    use screenpipe_vision::utils::OcrEngine;
+    use std::collections::HashMap;
+
+    fn result(engine: OcrEngine, text: &str, confidence: Option<f64>) -> CachedWindowOcr {
+        CachedWindowOcr {
+            text: text.to_string(),
+            text_json: vec![HashMap::new(); 2],
+            confidence,
+            layout: OcrLayout::default(),
+            engine,
+        }
+    }
+
+    fn ensemble() -> OcrEnsemble {
+        OcrEnsemble::new(
+            OcrEngine::Tesseract,
+            OcrEngine::Custom(CustomOcrConfig::default()),
+            0.6,
+        )
+    }
+
+    #[test]
+    fn test_confidence_is_normalized_per_engine() {
+        let tesseract = result(OcrEngine::Tesseract, "a", Some(87.0));
+        assert_eq!(normalized_confidence(&tesseract), Some(0.87));
+
+        // apple sums the confidence of its observations, one per text_json entry
+        let apple = result(OcrEngine::AppleNative, "a", Some(1.6));
+        assert_eq!(normalized_confidence(&apple), Some(0.8));
+
+        let custom = result(
+            OcrEngine::Custom(CustomOcrConfig::default()),
+            "a",
+            Some(1.4),
+        );
+        assert_eq!(normalized_confidence(&custom), Some(1.0));
+        assert_eq!(
+            normalized_confidence(&result(OcrEngine::Unstructured, "a", None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_low_confidence_windows_get_a_second_opinion() {
+        let ensemble = ensemble();
+        assert!(!ensemble.needs_second_opinion(&result(OcrEngine::Tesseract, "ok", Some(75.0))));
+        assert!(ensemble.needs_second_opinion(&result(OcrEngine::Tesseract, "0k", Some(40.0))));
+        assert!(ensemble.needs_second_opinion(&result(OcrEngine::Tesseract, "", None)));
+    }
+
+    #[test]
+    fn test_most_confident_result_wins() {
+        let ensemble = ensemble();
+        let custom = OcrEngine::Custom(CustomOcrConfig::default());
+
+        let picked = ensemble.pick(
+            result(OcrEngine::Tesseract, "he11o", Some(40.0)),
+            result(custom.clone(), "hello", Some(0.95)),
+        );
+        assert_eq!(picked.text, "hello");
+        assert!(matches!(picked.engine, OcrEngine::Custom(_)));
+
+        let picked = ensemble.pick(
+            result(OcrEngine::Tesseract, "hello", Some(55.0)),
+            result(custom.clone(), "hallo", Some(0.3)),
+        );
+        assert!(matches!(picked.engine, OcrEngine::Tesseract));
+
+        // a secondary result without confidence never replaces a scored one
+        let picked = ensemble.pick(
+            result(OcrEngine::Tesseract, "hello", Some(10.0)),
+            result(custom, "hallo", None),
+        );
+        assert!(matches!(picked.engine, OcrEngine::Tesseract));
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use screenpipe_vision::ocr_pool::{CoalescingQueue, QueueOutcome};
+    use std::time::Duration;
+
+    #[test]
+    fn test_queue_coalesces_frames_of_a_source() {
+        let mut queue = CoalescingQueue::new(4);
+        assert_eq!(queue.push("monitor_1", 1), QueueOutcome::Queued);
+        assert_eq!(queue.push("monitor_2", 2), QueueOutcome::Queued);
+        // the newer frame of monitor 1 takes the place of the waiting one
+        assert_eq!(queue.push("monitor_1", 3), QueueOutcome::Coalesced);
+        assert_eq!(queue.len(), 2);
+
+        let (source, _, frame) = queue.pop().unwrap();
+        assert_eq!((source.as_str(), frame), ("monitor_1", 3));
+        assert_eq!(queue.pop().unwrap().2, 2);
+        assert!(queue.pop().is_none());
+
+        // once picked up, a source can queue again
+        assert_eq!(queue.push("monitor_1", 4), QueueOutcome::Queued);
+    }
+
+    #[test]
+    fn test_coalesced_frame_keeps_enqueue_time() {
+        let mut queue = CoalescingQueue::new(4);
+        queue.push("monitor_1", 1);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(queue.push("monitor_1", 2), QueueOutcome::Coalesced);
+
+        // the wait counts from the first frame, or a busy source would never look backed up
+        let (_, waited, frame) = queue.pop().unwrap();
+        assert_eq!(frame, 2);
+        assert!(waited >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_full_queue_drops_oldest_frame() {
+        let mut queue = CoalescingQueue::new(2);
+        queue.push("monitor_1", 1);
+        queue.push("monitor_2", 2);
+        assert_eq!(queue.push("monitor_3", 3), QueueOutcome::DroppedOldest);
+        assert_eq!(queue.len(), queue.capacity());
+
+        let frames: Vec<u32> = std::iter::from_fn(|| queue.pop().map(|(_, _, f)| f)).collect();
+        assert_eq!(frames, vec![2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_has_room_for_at_least_one_frame() {
+        let mut queue = CoalescingQueue::new(0);
+        assert_eq!(queue.capacity(), 1);
+        assert_eq!(queue.push("monitor_1", 1), QueueOutcome::Queued);
+        assert_eq!(queue.push("monitor_2", 2), QueueOutcome::DroppedOldest);
+    }
+}
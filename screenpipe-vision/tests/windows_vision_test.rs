@@ -4,11 +4,13 @@ mod tests {
     use screenpipe_vision::capture_screenshot_by_window::{CapturedWindow, WindowFilters};
     use screenpipe_vision::core::OcrTaskData;
     use screenpipe_vision::monitor::get_default_monitor;
+    use screenpipe_vision::ocr_cache::WindowOcrCache;
     use screenpipe_vision::{process_ocr_task, OcrEngine};
     use std::sync::Arc;
     use std::{path::PathBuf, time::Instant};
     use tokio::sync::mpsc;
 
+    use screenpipe_vision::ocr_pool::{OcrPool, DEFAULT_OCR_QUEUE_SIZE};
     use screenpipe_vision::{continuous_capture, CaptureResult};
     use std::time::Duration;
     use tokio::time::timeout;
@@ -47,6 +49,7 @@ mod tests {
             },
             &ocr_engine,
             vec![],
+            &mut WindowOcrCache::new(),
         )
         .await;
 
@@ -68,12 +71,14 @@ mod tests {
         let save_text_files_flag = false;
         let ocr_engine = OcrEngine::WindowsNative;
         let window_filters = Arc::new(WindowFilters::new(&[], &[]));
+        let ocr_pool = OcrPool::new(1, DEFAULT_OCR_QUEUE_SIZE);
 
         // Spawn the continuous_capture function with corrected parameter order
         let capture_handle = tokio::spawn(continuous_capture(
             result_tx,
             interval,
             ocr_engine,
+            ocr_pool,
             monitor,
             window_filters, // window filters as empty vec
             vec![],         // languages as empty vec